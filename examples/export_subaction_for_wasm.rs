@@ -1,5 +1,6 @@
 use brawllib_rs::brawl_mod::BrawlMod;
 use brawllib_rs::high_level_fighter::HighLevelFighter;
+use brawllib_rs::script_runner::SimulationContext;
 
 use getopts::Options;
 
@@ -78,7 +79,7 @@ fn main() {
 
     for fighter in fighters {
         if fighter.cased_name.to_lowercase() == fighter_name.to_lowercase() {
-            let hl_fighter = HighLevelFighter::new(&fighter);
+            let hl_fighter = HighLevelFighter::new(&fighter, SimulationContext::default());
             for subaction in hl_fighter.subactions.iter() {
                 if subaction.name.to_lowercase() == subaction_name.to_lowercase() {
                     // this example only exports a single subaction_data.bin file
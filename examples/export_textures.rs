@@ -0,0 +1,137 @@
+//! Exports every texture from every costume of a fighter as a gif.
+//! The textures are written to `<output>/<model file name>/<texture name>.gif`
+
+use brawllib_rs::arc::{Arc, ArcChildData};
+use brawllib_rs::brawl_mod::BrawlMod;
+use brawllib_rs::bres::{BresChild, BresChildData};
+use brawllib_rs::plt0::Plt0;
+use brawllib_rs::tex0::Tex0;
+
+use getopts::Options;
+
+use std::env;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [options]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    env_logger::init();
+
+    let args: Vec<String> = env::args().collect();
+    let program = &args[0];
+
+    let mut opts = Options::new();
+    opts.optopt(
+        "d",
+        "dir",
+        "full path to a brawl directory",
+        "DIRECTORY_NAME",
+    );
+    opts.optopt(
+        "m",
+        "mod",
+        "full path to a mod directory that will overwrite brawl files",
+        "DIRECTORY_NAME",
+    );
+    opts.optopt("f", "fighter", "fighter name", "FIGHTER_NAME");
+    opts.optopt(
+        "o",
+        "output",
+        "directory to write the textures to, defaults to \"textures\"",
+        "DIRECTORY_NAME",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(_) => {
+            print_usage(program, opts);
+            return;
+        }
+    };
+
+    let brawl_path = if let Some(path) = matches.opt_str("d") {
+        PathBuf::from(path)
+    } else {
+        println!("Need to pass a brawl directory\n");
+        print_usage(program, opts);
+        return;
+    };
+    let mod_path = matches.opt_str("m").map(PathBuf::from);
+
+    let fighter_name = if let Some(fighter_name) = matches.opt_str("f") {
+        fighter_name
+    } else {
+        println!("Need to pass a fighter name\n");
+        print_usage(program, opts);
+        return;
+    };
+    let output_path = PathBuf::from(matches.opt_str("o").unwrap_or_else(|| "textures".into()));
+
+    let brawl_mod = BrawlMod::new(&brawl_path, mod_path.as_deref());
+
+    let fighters = match brawl_mod.load_fighters(false) {
+        Ok(fighters) => fighters,
+        Err(err) => {
+            println!("Failed to load brawl mod: {}", err);
+            return;
+        }
+    };
+
+    for fighter in fighters {
+        if fighter.cased_name.to_lowercase() == fighter_name.to_lowercase() {
+            for model in &fighter.models {
+                export_model(model, &output_path.join(&model.name));
+            }
+            return;
+        }
+    }
+    println!("Passed fighter was not found");
+}
+
+fn export_model(model: &Arc, path: &Path) {
+    let mut textures = vec![];
+    let mut palettes = vec![];
+    for child in &model.children {
+        if let ArcChildData::Bres(bres) = &child.data {
+            collect(&bres.children, &mut textures, &mut palettes);
+        }
+    }
+
+    fs::create_dir_all(path).unwrap();
+    for texture in textures {
+        let palette = palettes.iter().find(|x| x.name == texture.name).cloned();
+        let rgba = match texture.decode(0, palette) {
+            Ok(rgba) => rgba,
+            Err(err) => {
+                println!("{}: {}", model.name, err);
+                continue;
+            }
+        };
+
+        let file = File::create(path.join(format!("{}.gif", texture.name))).unwrap();
+        let (width, height) = texture.mipmap_size(0);
+        let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &[]).unwrap();
+        let mut rgba = rgba;
+        let frame = gif::Frame::from_rgba_speed(width as u16, height as u16, &mut rgba, 10);
+        encoder.write_frame(&frame).unwrap();
+    }
+}
+
+fn collect<'a>(
+    children: &'a [BresChild],
+    textures: &mut Vec<&'a Tex0>,
+    palettes: &mut Vec<&'a Plt0>,
+) {
+    for child in children {
+        match &child.data {
+            BresChildData::Bres(children) => collect(children, textures, palettes),
+            BresChildData::Tex0(tex0) => textures.push(tex0),
+            BresChildData::Plt0(plt0) => palettes.push(plt0),
+            _ => {}
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use brawllib_rs::brawl_mod::BrawlMod;
 use brawllib_rs::high_level_fighter::HighLevelFighter;
+use brawllib_rs::script_runner::SimulationContext;
 use getopts::Options;
 use std::collections::HashMap;
 use std::env;
@@ -63,7 +64,7 @@ fn main() {
         if fighter.cased_name.to_lowercase() == "poketrainer" {
             continue;
         }
-        let fighter = HighLevelFighter::new(&fighter);
+        let fighter = HighLevelFighter::new(&fighter, SimulationContext::default());
         let mut subaction_map = HashMap::new();
 
         for subaction in &fighter.subactions {
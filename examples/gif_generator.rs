@@ -2,6 +2,7 @@ use brawllib_rs::brawl_mod::BrawlMod;
 use brawllib_rs::high_level_fighter::HighLevelFighter;
 use brawllib_rs::renderer;
 use brawllib_rs::renderer::WgpuState;
+use brawllib_rs::script_runner::SimulationContext;
 
 use getopts::Options;
 
@@ -34,6 +35,7 @@ fn main() {
     );
     opts.optopt("f", "fighter", "fighter name", "FIGHTER_NAME");
     opts.optopt("a", "subaction", "subaction name", "ACTION_NAME");
+    opts.optflag("", "model", "display the fighters model");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -80,7 +82,11 @@ fn main() {
 
     for fighter in fighters {
         if fighter.cased_name.to_lowercase() == fighter_name.to_lowercase() {
-            let hl_fighter = HighLevelFighter::new(&fighter);
+            let hl_fighter = if matches.opt_present("model") {
+                HighLevelFighter::new_with_model(&fighter, SimulationContext::default())
+            } else {
+                HighLevelFighter::new(&fighter, SimulationContext::default())
+            };
             for (i, subaction) in hl_fighter.subactions.iter().enumerate() {
                 if subaction.name.to_lowercase() == subaction_name.to_lowercase() {
                     let mut wgpu_state = futures::executor::block_on(WgpuState::new_for_gif());
@@ -1,5 +1,6 @@
 use brawllib_rs::brawl_mod::BrawlMod;
 use brawllib_rs::high_level_fighter::HighLevelFighter;
+use brawllib_rs::script_runner::SimulationContext;
 
 use getopts::Options;
 
@@ -83,7 +84,7 @@ fn main() {
                 }
                 println!("Fighter name: {}", fighter.cased_name);
 
-                let hl_fighter = HighLevelFighter::new(&fighter);
+                let hl_fighter = HighLevelFighter::new(&fighter, SimulationContext::default());
                 for subaction in hl_fighter.subactions {
                     if let Some(subaction_filter) = &subaction_filter
                         && subaction.name.to_lowercase() != subaction_filter.to_lowercase()
@@ -111,7 +112,7 @@ fn main() {
                 }
                 println!("Fighter name: {}", fighter.cased_name);
 
-                let hl_fighter = HighLevelFighter::new(&fighter);
+                let hl_fighter = HighLevelFighter::new(&fighter, SimulationContext::default());
                 for mut subaction in hl_fighter.subactions {
                     if let Some(subaction_filter) = &subaction_filter
                         && subaction.name.to_lowercase() != subaction_filter.to_lowercase()
@@ -138,7 +139,7 @@ fn main() {
                     continue;
                 }
 
-                let mut hl_fighter = HighLevelFighter::new(&fighter);
+                let mut hl_fighter = HighLevelFighter::new(&fighter, SimulationContext::default());
 
                 // filter by subaction
                 if let Some(subaction_filter) = &subaction_filter {
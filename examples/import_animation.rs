@@ -0,0 +1,159 @@
+//! Replaces animations of a fighter with the animations in a glTF binary or collada file exported from e.g. Blender.
+//! The modified motion file is written to `<output>/<motion name>.pac`
+//!
+//! Animations are matched to the fighters existing animations by name.
+//! Collada files only contain a single animation so its name needs to be passed with `--name`.
+
+use brawllib_rs::brawl_mod::BrawlMod;
+use brawllib_rs::chr0_import;
+
+use getopts::Options;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [options]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    env_logger::init();
+
+    let args: Vec<String> = env::args().collect();
+    let program = &args[0];
+
+    let mut opts = Options::new();
+    opts.optopt(
+        "d",
+        "dir",
+        "full path to a brawl directory",
+        "DIRECTORY_NAME",
+    );
+    opts.optopt(
+        "m",
+        "mod",
+        "full path to a mod directory that will overwrite brawl files",
+        "DIRECTORY_NAME",
+    );
+    opts.optopt("f", "fighter", "fighter name", "FIGHTER_NAME");
+    opts.optopt(
+        "i",
+        "input",
+        "a .glb or .dae file containing the animations",
+        "FILE_NAME",
+    );
+    opts.optopt(
+        "n",
+        "name",
+        "name of the animation in a .dae file",
+        "ANIMATION_NAME",
+    );
+    opts.optopt(
+        "o",
+        "output",
+        "directory to write the motion file to, defaults to the current directory",
+        "DIRECTORY_NAME",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(_) => {
+            print_usage(program, opts);
+            return;
+        }
+    };
+
+    let brawl_path = if let Some(path) = matches.opt_str("d") {
+        PathBuf::from(path)
+    } else {
+        println!("Need to pass a brawl directory\n");
+        print_usage(program, opts);
+        return;
+    };
+    let mod_path = matches.opt_str("m").map(PathBuf::from);
+
+    let fighter_name = if let Some(fighter_name) = matches.opt_str("f") {
+        fighter_name
+    } else {
+        println!("Need to pass a fighter name\n");
+        print_usage(program, opts);
+        return;
+    };
+
+    let input_path = if let Some(path) = matches.opt_str("i") {
+        PathBuf::from(path)
+    } else {
+        println!("Need to pass an input file\n");
+        print_usage(program, opts);
+        return;
+    };
+    let output_path = PathBuf::from(matches.opt_str("o").unwrap_or_else(|| ".".into()));
+
+    let brawl_mod = BrawlMod::new(&brawl_path, mod_path.as_deref());
+
+    let fighters = match brawl_mod.load_fighters(false) {
+        Ok(fighters) => fighters,
+        Err(err) => {
+            println!("Failed to load brawl mod: {}", err);
+            return;
+        }
+    };
+
+    for mut fighter in fighters {
+        if fighter.cased_name.to_lowercase() == fighter_name.to_lowercase() {
+            let Some(skeleton) = fighter.get_bones() else {
+                println!("The fighter has no model");
+                return;
+            };
+
+            let input = fs::read(&input_path).unwrap();
+            let chr0s = if input_path.extension().is_some_and(|x| x == "dae") {
+                let Some(name) = matches.opt_str("n") else {
+                    println!("Need to pass the name of the animation in the .dae\n");
+                    print_usage(program, opts);
+                    return;
+                };
+                chr0_import::chr0_from_collada(&String::from_utf8_lossy(&input), &name, skeleton)
+                    .map(|x| vec![x])
+            } else {
+                chr0_import::chr0s_from_glb(&input, skeleton)
+            };
+            let chr0s = match chr0s {
+                Ok(chr0s) => chr0s,
+                Err(err) => {
+                    println!("Failed to import animations: {}", err);
+                    return;
+                }
+            };
+
+            for chr0 in &chr0s {
+                if fighter.motion.replace_chr0(chr0) {
+                    println!("Replaced {}", chr0.name);
+                } else {
+                    println!(
+                        "Skipped {} as the fighter has no animation with that name",
+                        chr0.name
+                    );
+                }
+            }
+
+            fs::create_dir_all(&output_path).unwrap();
+            let compiled = match fighter.motion.compile() {
+                Ok(compiled) => compiled,
+                Err(err) => {
+                    println!("Failed to compile {}: {}", fighter.motion.name, err);
+                    return;
+                }
+            };
+            fs::write(
+                output_path.join(format!("{}.pac", fighter.motion.name)),
+                compiled,
+            )
+            .unwrap();
+            return;
+        }
+    }
+    println!("Passed fighter was not found");
+}
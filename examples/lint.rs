@@ -2,7 +2,8 @@ use brawllib_rs::brawl_mod::BrawlMod;
 use brawllib_rs::high_level_fighter::{
     CollisionBoxValues, GrabBoxValues, HighLevelFighter, HighLevelHitBox,
 };
-use brawllib_rs::script_ast::GrabTarget;
+use brawllib_rs::script_ast::{EventAst, GrabTarget};
+use brawllib_rs::script_runner::SimulationContext;
 
 use getopts::Options;
 
@@ -73,7 +74,7 @@ fn main() {
         "This is bad because sometimes it doesnt work correctly, resulting in a hitbox hitting that should never be possible e.g. https://twitter.com/ShaydonJohn/status/1147308339753127936"
     );
     for fighter in fighters.iter() {
-        let fighter = HighLevelFighter::new(fighter);
+        let fighter = HighLevelFighter::new(fighter, SimulationContext::default());
         'subactions: for subaction in &fighter.subactions {
             for frame in &subaction.frames {
                 // group hitboxes together that have a matching size and position
@@ -118,9 +119,17 @@ fn main() {
         "All of these cases should be replaced with action overrides so that move staling is properly handled."
     );
     for fighter in fighters {
-        let fighter = HighLevelFighter::new(fighter);
+        let fighter = HighLevelFighter::new(fighter, SimulationContext::default());
         for subaction in &fighter.subactions {
-            if subaction.bad_interrupts {
+            let creates_interrupt = matches!(
+                subaction.scripts.script_main.block.events.first(),
+                Some(EventAst::CreateInterrupt(_))
+            );
+            let interrupt_met = subaction
+                .frames
+                .first()
+                .is_some_and(|frame| frame.interrupts.iter().any(|x| x.requirement_met));
+            if creates_interrupt && interrupt_met {
                 println!("{} {}", fighter.name, subaction.name);
             }
         }
@@ -68,7 +68,7 @@ fn main() {
 
     for fighter in fighters {
         if fighter.cased_name.to_lowercase() == fighter_name.to_lowercase() {
-            let model = fighter.models.first().unwrap().compile();
+            let model = fighter.models.first().unwrap().compile().unwrap();
             std::fs::write("modifier_output.pac", model).unwrap();
 
             return;
@@ -0,0 +1,221 @@
+//! Checks that parse -> compile -> parse of every fighters moveset is lossless.
+//! Also checks that edits to the moveset survive the round trip.
+//! Models are checked by compiling their BRES twice, the second compile must give identical bytes.
+
+use brawllib_rs::arc::{self, Arc, ArcChildData};
+use brawllib_rs::brawl_mod::BrawlMod;
+use brawllib_rs::bres::{self, BresChild, BresChildData};
+use brawllib_rs::sakurai::{ArcSakurai, SectionData};
+use brawllib_rs::script::{Argument, Event};
+use brawllib_rs::wii_memory::WiiMemory;
+
+use fancy_slice::FancySlice;
+use getopts::Options;
+
+use std::env;
+use std::path::PathBuf;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [options]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    env_logger::init();
+
+    let args: Vec<String> = env::args().collect();
+    let program = &args[0];
+
+    let mut opts = Options::new();
+    opts.optopt(
+        "d",
+        "dir",
+        "full path to a brawl directory",
+        "DIRECTORY_NAME",
+    );
+    opts.optopt(
+        "m",
+        "mod",
+        "full path to a mod directory that will overwrite brawl files",
+        "DIRECTORY_NAME",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(_) => {
+            print_usage(program, opts);
+            return;
+        }
+    };
+
+    let brawl_path = if let Some(path) = matches.opt_str("d") {
+        PathBuf::from(path)
+    } else {
+        println!("Need to pass a brawl directory\n");
+        print_usage(program, opts);
+        return;
+    };
+    let mod_path = matches.opt_str("m").map(PathBuf::from);
+
+    let brawl_mod = BrawlMod::new(&brawl_path, mod_path.as_deref());
+
+    let fighters = match brawl_mod.load_fighters(true) {
+        Ok(fighters) => fighters,
+        Err(err) => {
+            println!("Failed to load brawl mod: {}", err);
+            return;
+        }
+    };
+
+    let wii_memory = WiiMemory::new();
+    let mut failures = 0;
+    for fighter in fighters {
+        let name = &fighter.cased_name;
+
+        let moveset = &fighter.moveset;
+
+        // unmodified round trip
+        let compiled = moveset.compile().unwrap();
+        let reparsed = arc::arc(FancySlice::new(&compiled), &wii_memory, false);
+        if format!("{:?}", sakurai(moveset)) != format!("{:?}", sakurai(&reparsed)) {
+            println!("{name}: moveset differs after round trip");
+            failures += 1;
+            continue;
+        }
+        if reparsed.compile().unwrap() != compiled {
+            println!("{name}: compiling the round tripped moveset gave different bytes");
+            failures += 1;
+            continue;
+        }
+
+        // modified round trip
+        let mut modified = moveset.clone();
+        let mut expected_events = None;
+        for child in &mut modified.children {
+            if let ArcChildData::Sakurai(sakurai) = &mut child.data {
+                for section in &mut sakurai.sections {
+                    if let SectionData::FighterData(data) = &mut section.data {
+                        data.attributes.weight += 1.0;
+                        data.attributes.gravity *= 2.0;
+                        if let Some(script) = data
+                            .subaction_main
+                            .iter_mut()
+                            .find(|x| !x.events.is_empty())
+                        {
+                            let event = script.events[0].clone();
+                            script.events.push(event);
+                            expected_events = Some(summarize_events(&script.events));
+                        }
+                    }
+                }
+            }
+        }
+
+        let compiled = modified.compile().unwrap();
+        let reparsed = arc::arc(FancySlice::new(&compiled), &wii_memory, false);
+        let mut success = false;
+        for section in &sakurai(&reparsed).sections {
+            if let SectionData::FighterData(data) = &section.data {
+                let events = data
+                    .subaction_main
+                    .iter()
+                    .find(|x| !x.events.is_empty())
+                    .map(|x| summarize_events(&x.events));
+                success = format!("{:?}", data.attributes) == modified_attributes(&modified)
+                    && events == expected_events;
+            }
+        }
+        if !success {
+            println!("{name}: modifications were lost after round trip");
+            failures += 1;
+            continue;
+        }
+
+        if let Some(Err(err)) = fighter.models.first().map(check_model) {
+            println!("{name}: model {err}");
+            failures += 1;
+            continue;
+        }
+
+        println!("{name}: success");
+    }
+
+    if failures != 0 {
+        println!("{failures} fighters failed the round trip");
+        std::process::exit(1);
+    }
+}
+
+fn check_model(model: &Arc) -> Result<(), String> {
+    for child in &model.children {
+        if let ArcChildData::Bres(bres) = &child.data {
+            let compiled = bres
+                .compile()
+                .map_err(|err| format!("failed to compile: {err}"))?;
+            let reparsed = bres::try_bres(FancySlice::new(&compiled))
+                .map_err(|err| format!("failed to reparse: {err}"))?;
+            if format!("{:?}", leaf_names(&bres.children))
+                != format!("{:?}", leaf_names(&reparsed.children))
+            {
+                return Err(String::from("BRES names differ after round trip"));
+            }
+            if reparsed.compile().ok() != Some(compiled) {
+                return Err(String::from(
+                    "compiling the round tripped BRES gave different bytes",
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn leaf_names(children: &[BresChild]) -> Vec<String> {
+    let mut names = vec![];
+    for child in children {
+        match &child.data {
+            BresChildData::Mdl0(mdl0) => names.push(format!("{} {}", child.name, mdl0.name)),
+            BresChildData::Bres(children) => names.extend(leaf_names(children)),
+            _ => names.push(child.name.clone()),
+        }
+    }
+    names
+}
+
+fn sakurai(arc: &Arc) -> &ArcSakurai {
+    for child in &arc.children {
+        if let ArcChildData::Sakurai(sakurai) = &child.data {
+            return sakurai;
+        }
+    }
+    panic!("moveset has no sakurai data")
+}
+
+/// Offset arguments store where they were read from, which changes when a script is moved, so leave that out.
+fn summarize_events(events: &[Event]) -> Vec<String> {
+    events
+        .iter()
+        .map(|event| {
+            let arguments: Vec<String> = event
+                .arguments
+                .iter()
+                .map(|argument| match argument {
+                    Argument::Offset(offset) => format!("Offset({})", offset.offset),
+                    argument => format!("{:?}", argument),
+                })
+                .collect();
+            format!(
+                "{:x} {:x} {:x} {:?}",
+                event.namespace, event.code, event.unk1, arguments
+            )
+        })
+        .collect()
+}
+
+fn modified_attributes(arc: &Arc) -> String {
+    for section in &sakurai(arc).sections {
+        if let SectionData::FighterData(data) = &section.data {
+            return format!("{:?}", data.attributes);
+        }
+    }
+    String::new()
+}
@@ -0,0 +1,75 @@
+use brawllib_rs::brawl_mod::BrawlMod;
+use getopts::Options;
+use std::env;
+use std::path::PathBuf;
+
+fn print_usage(program: &str, opts: Options) {
+    let brief = format!("Usage: {} [options]", program);
+    print!("{}", opts.usage(&brief));
+}
+
+fn main() {
+    env_logger::init();
+
+    let args: Vec<String> = env::args().collect();
+    let program = &args[0];
+
+    let mut opts = Options::new();
+    opts.optopt(
+        "d",
+        "dir",
+        "full path to a brawl directory",
+        "DIRECTORY_NAME",
+    );
+    opts.optopt(
+        "m",
+        "mod",
+        "full path to a mod directory that will overwrite brawl files",
+        "DIRECTORY_NAME",
+    );
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(m) => m,
+        Err(_) => {
+            print_usage(program, opts);
+            return;
+        }
+    };
+
+    let brawl_path = if let Some(path) = matches.opt_str("d") {
+        PathBuf::from(path)
+    } else {
+        println!("Need to pass a brawl directory\n");
+        print_usage(program, opts);
+        return;
+    };
+    let mod_path = matches.opt_str("m").map(PathBuf::from);
+
+    let brawl_mod = BrawlMod::new(&brawl_path, mod_path.as_deref());
+
+    let stages = match brawl_mod.load_stages() {
+        Ok(stages) => stages,
+        Err(err) => {
+            println!("Failed to load brawl mod: {}", err);
+            return;
+        }
+    };
+
+    println!("stage,module,left,right,top,bottom");
+    for stage in stages {
+        let module = stage.module_name.as_deref().unwrap_or_default();
+        if let Some(blast_zone) = stage.get_positions().blast_zone {
+            println!(
+                "{},{},{},{},{},{}",
+                stage.name,
+                module,
+                blast_zone.left,
+                blast_zone.right,
+                blast_zone.top,
+                blast_zone.bottom
+            );
+        } else {
+            println!("{},{},,,,", stage.name, module);
+        }
+    }
+}
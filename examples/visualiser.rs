@@ -1,6 +1,7 @@
 use brawllib_rs::brawl_mod::BrawlMod;
 use brawllib_rs::high_level_fighter::HighLevelFighter;
 use brawllib_rs::renderer::app::App;
+use brawllib_rs::script_runner::SimulationContext;
 
 use getopts::Options;
 
@@ -33,6 +34,7 @@ fn main() {
     );
     opts.optopt("f", "fighter", "fighter name", "FIGHTER_NAME");
     opts.optopt("a", "subaction", "subaction name", "ACTION_NAME");
+    opts.optflag("", "model", "display the fighters model");
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -79,11 +81,16 @@ fn main() {
 
     for fighter in fighters {
         if fighter.cased_name.to_lowercase() == fighter_name.to_lowercase() {
-            let hl_fighter = HighLevelFighter::new(&fighter);
+            let hl_fighter = if matches.opt_present("model") {
+                HighLevelFighter::new_with_model(&fighter, SimulationContext::default())
+            } else {
+                HighLevelFighter::new(&fighter, SimulationContext::default())
+            };
             for (i, subaction) in hl_fighter.subactions.iter().enumerate() {
                 if subaction.name.to_lowercase() == subaction_name.to_lowercase() {
                     let subaction = hl_fighter.subactions[i].clone();
-                    let app = futures::executor::block_on(App::new(subaction));
+                    let mut app = futures::executor::block_on(App::new(subaction));
+                    app.set_model(hl_fighter.model.clone());
 
                     app.run();
                     return;
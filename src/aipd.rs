@@ -0,0 +1,39 @@
+use fancy_slice::FancySlice;
+
+use crate::parse_error::{ParseError, check_len};
+
+/// Parses an AIPD, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_aipd(data: FancySlice) -> Result<Aipd, ParseError> {
+    check_len(data, AIPD_HEADER_SIZE, "AIPD")?;
+    let unk0 = data.i32_be(0x4);
+    let unk1 = data.i32_be(0x8);
+    let unk2 = data.i32_be(0xc);
+
+    // TODO: Split this up into the individual parameters once they are understood
+    let parameters = data.relative_slice(AIPD_HEADER_SIZE ..).to_vec();
+
+    Ok(Aipd { unk0, unk1, unk2, parameters })
+}
+
+const AIPD_HEADER_SIZE: usize = 0x10;
+/// Parameters that control how the CPU plays the fighter
+#[derive(Clone, Debug)]
+pub struct Aipd {
+    pub unk0: i32,
+    pub unk1: i32,
+    pub unk2: i32,
+    pub parameters: Vec<u8>,
+}
+
+impl Aipd {
+    pub fn compile(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(AIPD_HEADER_SIZE + self.parameters.len());
+        output.extend("AIPD".chars().map(|x| x as u8));
+        output.extend(i32::to_be_bytes(self.unk0));
+        output.extend(i32::to_be_bytes(self.unk1));
+        output.extend(i32::to_be_bytes(self.unk2));
+        output.extend(&self.parameters);
+        output
+    }
+}
@@ -1,5 +1,13 @@
+use crate::aipd::{Aipd, try_aipd};
+use crate::atkd::{Atkd, try_atkd};
 use crate::bres::*;
-use crate::compression::decompress;
+use crate::chr0::Chr0;
+use crate::coll::{Coll, is_coll, try_coll};
+use crate::compression::{compress, try_decompress};
+use crate::efls::{Efls, try_efls};
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_str};
+use crate::reff::{Reff, try_reff};
+use crate::reft::{Reft, try_reft};
 use crate::sakurai;
 use crate::sakurai::ArcSakurai;
 use crate::util;
@@ -8,7 +16,15 @@ use crate::wii_memory::WiiMemory;
 use fancy_slice::FancySlice;
 
 pub fn arc(data: FancySlice, wii_memory: &WiiMemory, item: bool) -> Arc {
+    try_arc(data, wii_memory, item).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Parses an ARC, returning an error instead of panicking on malformed data.
+///
+/// Use `ParseError::with_file` to record which file failed.
+pub fn try_arc(data: FancySlice, wii_memory: &WiiMemory, item: bool) -> Result<Arc, ParseError> {
     // read the main header
+    check_len(data, 4, "ARC")?;
     let tag = util::parse_tag(data.relative_slice(0..3));
     let decompressed;
     let data = if tag == "ARC" {
@@ -16,50 +32,101 @@ pub fn arc(data: FancySlice, wii_memory: &WiiMemory, item: bool) -> Arc {
         data
     } else {
         // compressed arc
-        decompressed = decompress(data);
+        decompressed = try_decompress(data)?;
         FancySlice::new(&decompressed)
     };
+    check_len(data, ARC_HEADER_SIZE, "ARC")?;
     let num_sub_headers = data.u16_be(6);
 
-    let name = data.str(0x10).unwrap().to_string();
+    let name = try_str(&data, 0x10, "ARC name")
+        .map_err(|err| err.offset_by(0x10))?
+        .to_string();
 
     // read the sub headers
     let mut children = vec![];
     let mut header_index = ARC_HEADER_SIZE;
     for i in 0..num_sub_headers {
+        check_len(
+            data,
+            header_index + ARC_CHILD_HEADER_SIZE,
+            "ARC child header",
+        )
+        .map_err(|err| err.offset_by(header_index))?;
         let mut arc_child = arc_child(data.relative_fancy_slice(header_index..));
-        if arc_child.redirect_index == -1 {
-            let tag = util::parse_tag(data.relative_slice(header_index + ARC_CHILD_HEADER_SIZE..));
-            let child_data = data.relative_fancy_slice(header_index + ARC_CHILD_HEADER_SIZE..);
-            arc_child.data = match tag.as_ref() {
-                "ARC" => ArcChildData::Arc(arc(child_data, wii_memory, item)),
-                "EFLS" => ArcChildData::Efls,
-                "bres" => ArcChildData::Bres(bres(child_data)),
-                "ATKD" => ArcChildData::Atkd,
-                "REFF" => ArcChildData::Reff,
-                "REFT" => ArcChildData::Reft,
-                "AIPD" => ArcChildData::Aipd,
-                "W" => ArcChildData::W,
-                "" if i == 0 => ArcChildData::Sakurai(sakurai::arc_sakurai(
-                    data.relative_fancy_slice(header_index + ARC_CHILD_HEADER_SIZE..),
-                    wii_memory,
-                    item,
-                )),
-                _ => ArcChildData::Unknown,
-            };
+        let child_offset = header_index + ARC_CHILD_HEADER_SIZE;
+        if arc_child.size < 0 || data.len() < child_offset + arc_child.size as usize {
+            return Err(ParseError::new(
+                "ARC child",
+                child_offset,
+                ParseErrorKind::UnexpectedEnd {
+                    needed: arc_child.size.max(0) as usize,
+                    len: data.len() - child_offset,
+                },
+            ));
+        }
+        let tag = util::parse_tag(data.relative_slice(child_offset..));
+        let child_data = data.relative_fancy_slice(child_offset..);
+        let child_data_sized =
+            data.relative_fancy_slice(child_offset..child_offset + arc_child.size as usize);
+        let raw = || child_data_sized.relative_slice(..).to_vec();
+        arc_child.data = if arc_child.redirect_index != -1 {
+            // The data is read from a different file, so there is nothing to parse.
+            ArcChildData::Unknown(raw())
+        } else {
+            match tag.as_ref() {
+                "ARC" => ArcChildData::Arc(
+                    try_arc(child_data, wii_memory, item)
+                        .map_err(|err| err.offset_by(child_offset))?,
+                ),
+                "EFLS" => ArcChildData::Efls(
+                    try_efls(child_data_sized).map_err(|err| err.offset_by(child_offset))?,
+                ),
+                "bres" => ArcChildData::Bres(
+                    try_bres(child_data).map_err(|err| err.offset_by(child_offset))?,
+                ),
+                "ATKD" => ArcChildData::Atkd(
+                    try_atkd(child_data_sized).map_err(|err| err.offset_by(child_offset))?,
+                ),
+                "REFF" => ArcChildData::Reff(
+                    try_reff(child_data_sized).map_err(|err| err.offset_by(child_offset))?,
+                ),
+                "REFT" => ArcChildData::Reft(
+                    try_reft(child_data_sized).map_err(|err| err.offset_by(child_offset))?,
+                ),
+                "AIPD" => ArcChildData::Aipd(
+                    try_aipd(child_data_sized).map_err(|err| err.offset_by(child_offset))?,
+                ),
+                "W" => ArcChildData::W(raw()),
+                // The COLL check is only a heuristic, so data that fails to parse is kept as is
+                "" if is_coll(child_data_sized) => match try_coll(child_data_sized) {
+                    Ok(coll) => ArcChildData::Coll(coll),
+                    Err(err) => {
+                        warn!(
+                            "Kept ARC child as unknown data: {}",
+                            err.offset_by(child_offset)
+                        );
+                        ArcChildData::Unknown(raw())
+                    }
+                },
+                "" if i == 0 => ArcChildData::Sakurai(
+                    sakurai::try_arc_sakurai(child_data, wii_memory, item)
+                        .map_err(|err| err.offset_by(child_offset))?,
+                ),
+                _ => ArcChildData::Unknown(raw()),
+            }
+        };
 
-            header_index += ARC_CHILD_HEADER_SIZE + arc_child.size as usize;
+        header_index += ARC_CHILD_HEADER_SIZE + arc_child.size as usize;
 
-            // align to the next ARC_CHILD_HEADER_SIZE
-            let offset = header_index % ARC_CHILD_HEADER_SIZE;
-            if offset != 0 {
-                header_index += ARC_CHILD_HEADER_SIZE - offset;
-            }
-            children.push(arc_child);
+        // align to the next ARC_CHILD_HEADER_SIZE
+        let offset = header_index % ARC_CHILD_HEADER_SIZE;
+        if offset != 0 {
+            header_index += ARC_CHILD_HEADER_SIZE - offset;
         }
+        children.push(arc_child);
     }
 
-    Arc { name, children }
+    Ok(Arc { name, children })
 }
 
 #[rustfmt::skip]
@@ -70,12 +137,13 @@ fn arc_child(data: FancySlice) -> ArcChild {
         size:           data.i32_be(4),
         group_index:    data.u8(8),
         redirect_index: data.i16_be(10),
-        data:           ArcChildData::Unknown,
+        data:           ArcChildData::Unknown(vec!()),
     }
 }
 
 impl Arc {
-    pub fn compile(&self) -> Vec<u8> {
+    /// Returns an error if a BRES within the ARC is too large to compile
+    pub fn compile(&self) -> Result<Vec<u8>, ParseError> {
         // TODO: Would be more efficient to allocate once, then overwrite the bytes at specific offsets.
         // However, for now, having each section create its own vec which get `extend`ed together makes for a cleaner implementation.
         let mut output = Vec::with_capacity(1024 * 1024); // Preallocate 1MB, we will likely need more, but dont want to overdo it as we have arcs in arcs.
@@ -91,11 +159,24 @@ impl Arc {
         }
 
         for child in &self.children {
+            let child_output = match &child.data {
+                ArcChildData::Arc(arc) => arc.compile()?,
+                ArcChildData::Sakurai(sakurai) => sakurai.compile(),
+                ArcChildData::Bres(bres) => bres.compile()?,
+                ArcChildData::Efls(efls) => efls.compile(),
+                ArcChildData::Atkd(atkd) => atkd.compile(),
+                ArcChildData::Reff(reff) => reff.compile(),
+                ArcChildData::Reft(reft) => reft.compile(),
+                ArcChildData::Aipd(aipd) => aipd.compile(),
+                ArcChildData::Coll(coll) => coll.compile(),
+                ArcChildData::W(data) | ArcChildData::Unknown(data) => data.clone(),
+            };
+
             // create arc child header
             let start = output.len();
             output.extend(i16::to_be_bytes(child.ty));
             output.extend(i16::to_be_bytes(child.index));
-            output.extend(i32::to_be_bytes(child.size)); // TODO: remove this field and calculate it instead
+            output.extend(i32::to_be_bytes(child_output.len() as i32));
             output.push(child.group_index);
             output.push(0x00);
             output.extend(i16::to_be_bytes(child.redirect_index));
@@ -103,18 +184,44 @@ impl Arc {
                 output.push(0x00);
             }
 
-            match &child.data {
-                ArcChildData::Arc(arc) => output.extend(arc.compile()),
-                ArcChildData::Bres(bres) => output.extend(bres.compile()),
-                // TODO
-                _ => {}
+            output.extend(child_output);
+
+            // align to the next ARC_CHILD_HEADER_SIZE
+            while output.len() % ARC_CHILD_HEADER_SIZE != 0 {
+                output.push(0x00);
             }
         }
 
-        output
+        Ok(output)
+    }
+
+    /// Same as `Arc::compile` but the result is compressed the same way brawl compresses its archives.
+    pub fn compile_compressed(&self) -> Result<Vec<u8>, ParseError> {
+        Ok(compress(&self.compile()?))
+    }
+
+    /// Replaces the CHR0 with the same name anywhere in this ARC, e.g. to replace an animation in a Fit{}Motion.pac.
+    /// Returns false if there is no CHR0 with the same name.
+    pub fn replace_chr0(&mut self, chr0: &Chr0) -> bool {
+        self.children.iter_mut().any(|child| match &mut child.data {
+            ArcChildData::Arc(arc) => arc.replace_chr0(chr0),
+            ArcChildData::Bres(bres) => replace_bres_chr0(&mut bres.children, chr0),
+            _ => false,
+        })
     }
 }
 
+fn replace_bres_chr0(children: &mut [BresChild], chr0: &Chr0) -> bool {
+    children.iter_mut().any(|child| match &mut child.data {
+        BresChildData::Bres(children) => replace_bres_chr0(children, chr0),
+        BresChildData::Chr0(existing) if existing.name == chr0.name => {
+            *existing = chr0.clone();
+            true
+        }
+        _ => false,
+    })
+}
+
 const ARC_HEADER_SIZE: usize = 0x40;
 /// Arc is for archive not to be confused with an atomic reference count
 #[derive(Clone, Debug)]
@@ -134,16 +241,50 @@ pub struct ArcChild {
     pub data: ArcChildData,
 }
 
+impl ArcChild {
+    /// Children are grouped e.g. an articles model and animations share a group
+    pub fn group_index(&self) -> u8 {
+        self.group_index
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum ArcChildData {
     Arc(Arc),
     Sakurai(ArcSakurai),
-    Efls,
+    Efls(Efls),
     Bres(Bres),
-    Atkd,
-    Reff,
-    Reft,
-    Aipd,
-    W,
-    Unknown,
+    Atkd(Atkd),
+    Reff(Reff),
+    Reft(Reft),
+    Aipd(Aipd),
+    Coll(Coll),
+    /// The raw bytes, written back unchanged when compiling
+    W(Vec<u8>),
+    /// The raw bytes, written back unchanged when compiling
+    Unknown(Vec<u8>),
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    /// An uncompressed ARC named `name` containing `children`
+    pub(crate) fn arc_fixture(name: &str, children: &[&[u8]]) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend(b"ARC\0\x01\x01");
+        data.extend(u16::to_be_bytes(children.len() as u16));
+        data.resize(0x10, 0);
+        data.extend(name.as_bytes());
+        data.resize(0x40, 0);
+        for (index, child) in children.iter().enumerate() {
+            data.extend(i16::to_be_bytes(0)); // type
+            data.extend(i16::to_be_bytes(index as i16));
+            data.extend(i32::to_be_bytes(child.len() as i32));
+            data.extend([0, 0]); // group index
+            data.extend(i16::to_be_bytes(-1)); // redirect index
+            data.resize(data.len() + 0x14, 0);
+            data.extend(*child);
+            data.resize(data.len().next_multiple_of(0x20), 0);
+        }
+        data
+    }
 }
@@ -0,0 +1,79 @@
+use fancy_slice::FancySlice;
+
+use crate::parse_error::{ParseError, check_len};
+
+/// Parses an ATKD, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_atkd(data: FancySlice) -> Result<Atkd, ParseError> {
+    check_len(data, ATKD_HEADER_SIZE, "ATKD")?;
+    let num_entries = data.i32_be(0x4);
+    let unk0        = data.i32_be(0x8);
+    let unk1        = data.i32_be(0xc);
+
+    check_len(data, ATKD_HEADER_SIZE + ATKD_ENTRY_SIZE * num_entries.max(0) as usize, "ATKD")?;
+    let mut entries = vec!();
+    for i in 0..num_entries.max(0) as usize {
+        let entry_data = data.relative_fancy_slice(ATKD_HEADER_SIZE + ATKD_ENTRY_SIZE * i ..);
+        entries.push(AtkdEntry {
+            subaction_index: entry_data.u16_be(0x0),
+            unk:             entry_data.u16_be(0x2),
+            start_frame:     entry_data.u16_be(0x4),
+            end_frame:       entry_data.u16_be(0x6),
+            x_min:           entry_data.f32_be(0x8),
+            x_max:           entry_data.f32_be(0xc),
+            y_min:           entry_data.f32_be(0x10),
+            y_max:           entry_data.f32_be(0x14),
+        });
+    }
+
+    Ok(Atkd { unk0, unk1, entries })
+}
+
+const ATKD_HEADER_SIZE: usize = 0x10;
+/// Describes the attacks of a fighter, the CPU uses this instead of the hitboxes in the moveset to decide which attack to use.
+#[derive(Clone, Debug)]
+pub struct Atkd {
+    pub unk0: i32,
+    pub unk1: i32,
+    pub entries: Vec<AtkdEntry>,
+}
+
+impl Atkd {
+    pub fn compile(&self) -> Vec<u8> {
+        let mut output =
+            Vec::with_capacity(ATKD_HEADER_SIZE + ATKD_ENTRY_SIZE * self.entries.len());
+        output.extend("ATKD".chars().map(|x| x as u8));
+        output.extend(i32::to_be_bytes(self.entries.len() as i32));
+        output.extend(i32::to_be_bytes(self.unk0));
+        output.extend(i32::to_be_bytes(self.unk1));
+
+        for entry in &self.entries {
+            output.extend(u16::to_be_bytes(entry.subaction_index));
+            output.extend(u16::to_be_bytes(entry.unk));
+            output.extend(u16::to_be_bytes(entry.start_frame));
+            output.extend(u16::to_be_bytes(entry.end_frame));
+            output.extend(f32::to_be_bytes(entry.x_min));
+            output.extend(f32::to_be_bytes(entry.x_max));
+            output.extend(f32::to_be_bytes(entry.y_min));
+            output.extend(f32::to_be_bytes(entry.y_max));
+        }
+
+        output
+    }
+}
+
+const ATKD_ENTRY_SIZE: usize = 0x18;
+#[derive(Clone, Debug)]
+pub struct AtkdEntry {
+    pub subaction_index: u16,
+    pub unk: u16,
+    /// First frame the CPU expects the attack to hit on
+    pub start_frame: u16,
+    /// Last frame the CPU expects the attack to hit on
+    pub end_frame: u16,
+    /// The area the CPU expects the attack to hit, relative to the fighter facing right
+    pub x_min: f32,
+    pub x_max: f32,
+    pub y_min: f32,
+    pub y_max: f32,
+}
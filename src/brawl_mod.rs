@@ -3,6 +3,7 @@ use std::path::{Path, PathBuf};
 
 use crate::arc;
 use crate::fighter::Fighter;
+use crate::stage::Stage;
 use crate::wii_memory::WiiMemory;
 use crate::wiird_runner;
 
@@ -93,6 +94,42 @@ impl BrawlMod {
         ))
     }
 
+    /// Returns Err(..) on failure to read required directories from disk.
+    /// Stage specific errors encountered when parsing data are reported via the `error!()` macro from the log crate.
+    pub fn load_stages(&self) -> Result<Vec<Stage>, Error> {
+        let brawl_stage_path = self.brawl_path.join("stage/melee");
+        if let Err(err) = fs::read_dir(&brawl_stage_path) {
+            bail!(
+                "Cannot read stage/melee directory in the brawl dump: {}",
+                err
+            );
+        }
+
+        let mut module_dirs = vec![self.brawl_path.join("module")];
+        let mut mod_stage_path = None;
+        if let Some(mod_path) = &self.mod_path {
+            let dir_reader = match fs::read_dir(mod_path) {
+                Ok(dir) => dir,
+                Err(err) => bail!("Cannot read brawl mod directory: {}", err),
+            };
+
+            for dir in dir_reader.flatten() {
+                let path = dir.path().join("pf/stage/melee");
+                if path.exists() {
+                    module_dirs.push(dir.path().join("pf/module"));
+                    mod_stage_path = Some(path);
+                    break;
+                }
+            }
+        }
+
+        Ok(Stage::load(
+            &brawl_stage_path,
+            mod_stage_path.as_deref(),
+            &module_dirs,
+        ))
+    }
+
     pub fn load_wiird_codeset_raw(&self) -> Result<Vec<u8>, Error> {
         // RSBE01.gct is usually located in the codes folder but can also be in the main sub folder e.g. LXP 2.1
         // Additionally P+ now has a second codeset file called BOOST.GCT
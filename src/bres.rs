@@ -1,47 +1,100 @@
 use fancy_slice::FancySlice;
 
 use crate::chr0::*;
+use crate::clr0::*;
 use crate::mdl0::*;
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
+use crate::pat0::*;
 use crate::plt0::*;
 use crate::resources;
+use crate::resources::StringTable;
+use crate::shp0::*;
+use crate::srt0::*;
+use crate::tex0::*;
 use crate::util;
+use crate::vis0::*;
 
+/// Parses a BRES, returning an error instead of panicking on malformed data.
 #[rustfmt::skip]
-pub(crate) fn bres(data: FancySlice) -> Bres {
+pub fn try_bres(data: FancySlice) -> Result<Bres, ParseError> {
+    check_len(data, BRES_HEADER_SIZE, "BRES")?;
     let endian         = data.u16_be(0x4);
     let version        = data.u16_be(0x6);
     //let size         = data.u32_be(0x8);
     let root_offset    = data.u16_be(0xc);
     //let num_sections = data.u16_be(0xe);
 
-    let children = bres_group(data.relative_fancy_slice(root_offset as usize ..));
-    Bres { endian, version, children }
+    check_len(data, root_offset as usize, "BRES")?;
+    let children = bres_group(data.relative_fancy_slice(root_offset as usize ..))
+        .map_err(|err| err.offset_by(root_offset as usize))?;
+    Ok(Bres { endian, version, children })
 }
 
-fn bres_group(data: FancySlice) -> Vec<BresChild> {
-    resources::resources(data.relative_fancy_slice(ROOT_HEADER_SIZE..))
-        .into_iter()
-        .map(|resource| {
-            let child_data =
-                data.relative_fancy_slice(ROOT_HEADER_SIZE + resource.data_offset as usize..);
-
-            let tag = util::parse_tag(child_data.relative_slice(..));
-            let child_data = match tag.as_ref() {
-                "CHR0" => BresChildData::Chr0(chr0(child_data)),
-                "MDL0" => BresChildData::Mdl0(mdl0(child_data)),
-                "PLT0" => BresChildData::Plt0(plt0(child_data)),
-                "" => BresChildData::Bres(bres_group(
-                    data.relative_fancy_slice(resource.data_offset as usize..),
-                )), // TODO: I suspect the match on "" is succeeding by accident
-                _ => BresChildData::Unknown(tag),
-            };
-
-            BresChild {
-                name: resource.string,
-                data: child_data,
-            }
-        })
-        .collect()
+fn bres_group(data: FancySlice) -> Result<Vec<BresChild>, ParseError> {
+    check_len(data, ROOT_HEADER_SIZE, "BRES group")?;
+    let resources = resources::try_resources(data.relative_fancy_slice(ROOT_HEADER_SIZE..))
+        .map_err(|err| err.offset_by(ROOT_HEADER_SIZE))?;
+
+    let mut children = vec![];
+    for resource in resources {
+        if resource.data_offset < 0 {
+            return Err(ParseError::new(
+                "BRES group",
+                ROOT_HEADER_SIZE,
+                ParseErrorKind::Malformed(format!(
+                    "resource {} has negative data offset {}",
+                    resource.string, resource.data_offset
+                )),
+            ));
+        }
+        let child_offset = ROOT_HEADER_SIZE + resource.data_offset as usize;
+        check_len(data, child_offset, "BRES group")?;
+        let child_data = data.relative_fancy_slice(child_offset..);
+
+        let tag = util::parse_tag(child_data.relative_slice(..));
+        let child_data = match tag.as_ref() {
+            "CHR0" => BresChildData::Chr0(
+                try_chr0(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+            "CLR0" => BresChildData::Clr0(
+                try_clr0(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+            "PAT0" => BresChildData::Pat0(
+                try_pat0(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+            "SHP0" => BresChildData::Shp0(
+                try_shp0(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+            "SRT0" => BresChildData::Srt0(
+                try_srt0(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+            "VIS0" => BresChildData::Vis0(
+                try_vis0(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+            "MDL0" => BresChildData::Mdl0(
+                try_mdl0(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+            "PLT0" => BresChildData::Plt0(
+                try_plt0(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+            "TEX0" => BresChildData::Tex0(
+                try_tex0(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+            "" => BresChildData::Bres(
+                bres_group(data.relative_fancy_slice(resource.data_offset as usize..))
+                    .map_err(|err| err.offset_by(resource.data_offset as usize))?,
+            ), // TODO: I suspect the match on "" is succeeding by accident
+            _ => BresChildData::Unknown(
+                RawBresChild::unknown(child_data).map_err(|err| err.offset_by(child_offset))?,
+            ),
+        };
+
+        children.push(BresChild {
+            name: resource.string,
+            data: child_data,
+        });
+    }
+    Ok(children)
 }
 
 // Brawlbox has this split into three structs: BRESHeader, BRESEntry and ROOTHeader
@@ -56,9 +109,10 @@ pub struct Bres {
 }
 
 impl Bres {
-    pub fn compile(&self) -> Vec<u8> {
+    /// Returns an error if the BRES would be too large for its size field
+    pub fn compile(&self) -> Result<Vec<u8>, ParseError> {
         let mut output = vec![];
-        let mut root_output: Vec<u8> = vec![];
+        let mut strings = StringTable::new();
 
         let root_size = ROOT_HEADER_SIZE
             + resources::RESOURCE_HEADER_SIZE
@@ -72,130 +126,147 @@ impl Bres {
             bres_size_leafless_buffered += 1; // TODO: arithmeticize the loop
         }
 
-        // compile children
+        let leaf_count: usize = self.children.iter().map(|x| x.count_leaves()).sum();
+
+        // create bres header
+        output.extend("bres".chars().map(|x| x as u8));
+        output.extend(u16::to_be_bytes(self.endian));
+        output.extend(u16::to_be_bytes(self.version));
+        output.extend(u32::to_be_bytes(0)); // size, filled in at the end
+        output.extend(u16::to_be_bytes(0x10)); // root_offset
+        output.extend(u16::to_be_bytes(leaf_count as u16 + 1)); // +1 for the root entry
+
+        // create bres root child header
+        output.extend("root".chars().map(|x| x as u8));
+        output.extend(i32::to_be_bytes(root_size as i32));
+
+        // create the resource groups and compile the leaf children
         let mut leaf_children_output: Vec<Vec<u8>> = vec![];
-        let mut leaf_children_size = 0;
+        let mut leaf_children_size: usize = 0;
+        let mut next_group_offset = BRES_HEADER_SIZE
+            + ROOT_HEADER_SIZE
+            + resources::RESOURCE_HEADER_SIZE
+            + resources::RESOURCE_SIZE * (self.children.len() + 1);
         let mut to_process = vec![&self.children];
         while !to_process.is_empty() {
             let children = to_process.remove(0);
-            let resource_header_offset = BRES_HEADER_SIZE + ROOT_HEADER_SIZE + root_output.len();
-
-            // create resources header
-            let resources_size =
-                (children.len() + 1) * resources::RESOURCE_SIZE + resources::RESOURCE_HEADER_SIZE; // includes the dummy child
-            root_output.extend(i32::to_be_bytes(resources_size as i32));
-            root_output.extend(i32::to_be_bytes(children.len() as i32)); // num_children
-
-            // insert the dummy resource
-            let root_resource = 1;
-            root_output.extend([0xff, 0xff]); // id
-            root_output.extend(u16::to_be_bytes(0)); // flag
-            root_output.extend(u16::to_be_bytes(root_resource)); // left_index
-            root_output.extend(u16::to_be_bytes(0)); // right_index
-            root_output.extend(i32::to_be_bytes(0)); // string_offset
-            root_output.extend(i32::to_be_bytes(0)); // data_offset
-
-            let mut data_offset_current = resources_size;
+
+            let names: Vec<&str> = children.iter().map(|x| x.name.as_str()).collect();
+            let group = resources::compile_resources(&mut output, &mut strings, &names);
+
             for (i, child) in children.iter().enumerate() {
                 let data_offset = match child.data {
-                    BresChildData::Bres(_) => data_offset_current as i32,
-                    _ => {
-                        bres_size_leafless_buffered as i32 - resource_header_offset as i32
-                            + leaf_children_size
-                    }
-                };
-
-                match child.data {
                     BresChildData::Bres(ref children) => {
+                        // groups are written in the order they are processed
                         to_process.push(children);
-                        data_offset_current += (children.len() + 1) * resources::RESOURCE_SIZE
+                        let group_offset = next_group_offset;
+                        next_group_offset += (children.len() + 1) * resources::RESOURCE_SIZE
                             + resources::RESOURCE_HEADER_SIZE;
+                        group_offset
                     }
                     _ => {
-                        // calculate offset to child
-                        let mut child_offset = bres_size_leafless as i32;
-                        while child_offset % 0x20 != 0 {
-                            child_offset += 1; // TODO: arithmeticize the loop
+                        let child_offset = bres_size_leafless_buffered + leaf_children_size;
+                        let mut child_output = child.compile(-(child_offset as i32));
+                        while child_output.len() % 0x20 != 0 {
+                            child_output.push(0x00);
                         }
-                        child_offset += leaf_children_size;
-
-                        let child_output = child.compile(-child_offset);
 
-                        leaf_children_size = if let Some(result) =
-                            leaf_children_size.checked_add(child_output.len() as i32)
-                        {
-                            result
-                        } else {
-                            panic!("BRES over 2 ^ 32 bytes"); // TODO: Make this an Err(_)
-                        };
+                        leaf_children_size += child_output.len();
 
                         leaf_children_output.push(child_output);
+                        child_offset
                     }
-                }
-
-                let mut name = child.name.clone();
-                let index_component = (name.len() as u16 - 1) << 3;
-                let char_component =
-                    most_significant_different_bit(name.pop().unwrap() as u8, 0) as u16;
-                let id = index_component | char_component;
-                let left_index = (i + 1) as u16;
-                let right_index = (i + 1) as u16;
-
-                // create each resource
-                root_output.extend(u16::to_be_bytes(id));
-                root_output.extend(u16::to_be_bytes(0));
-                root_output.extend(u16::to_be_bytes(left_index));
-                root_output.extend(u16::to_be_bytes(right_index));
-                root_output.extend(i32::to_be_bytes(0)); // TODO: string_offset
-                root_output.extend(i32::to_be_bytes(data_offset));
+                };
+                resources::set_resource_data_offset(&mut output, group, i, data_offset);
             }
         }
 
-        let bres_size = bres_size_leafless as u32 + leaf_children_size as u32;
-        let leaf_count: usize = self.children.iter().map(|x| x.count_leaves()).sum();
-
-        // create bres header
-        output.extend("bres".chars().map(|x| x as u8));
-        output.extend(u16::to_be_bytes(self.endian));
-        output.extend(u16::to_be_bytes(self.version));
-        output.extend(u32::to_be_bytes(bres_size));
-        output.extend(u16::to_be_bytes(0x10)); // root_offset
-        output.extend(u16::to_be_bytes(leaf_count as u16 + 1)); // +1 for the root entry
-
-        // create bres root child header
-        output.extend("root".chars().map(|x| x as u8));
-        output.extend(i32::to_be_bytes(root_size as i32));
-
-        // create bres root child contents
-        output.extend(root_output);
         while output.len() % 0x20 != 0 {
             output.push(0x00);
         }
 
         // create bres leaf children
-        let mut size: u32 = 0;
         for child_output in leaf_children_output {
-            size = if let Some(result) = size.checked_add(child_output.len() as u32) {
-                result
-            } else {
-                panic!("BRES over 2 ^ 32 bytes"); // TODO: Make this an Err(_)
-            };
-
             output.extend(child_output);
         }
 
-        output
+        strings.compile(&mut output);
+        while output.len() % 0x20 != 0 {
+            output.push(0x00);
+        }
+
+        let bres_size = u32::try_from(output.len()).map_err(|_| {
+            ParseError::new(
+                "BRES",
+                0,
+                ParseErrorKind::Malformed(format!(
+                    "the compiled BRES is 0x{:x} bytes, it cannot be over 2 ^ 32 bytes",
+                    output.len()
+                )),
+            )
+        })?;
+        output[0x8..0xc].copy_from_slice(&u32::to_be_bytes(bres_size));
+
+        Ok(output)
+    }
+}
+
+impl Bres {
+    /// Adds the texture to the textures group and its palette to the palettes group.
+    /// Any existing texture or palette with the same name is replaced.
+    pub fn insert_texture(&mut self, tex0: Tex0, plt0: Option<Plt0>) {
+        insert_child(
+            &mut self.children,
+            "Textures(NW4R)",
+            BresChild {
+                name: tex0.name.clone(),
+                data: BresChildData::Tex0(tex0),
+            },
+        );
+        if let Some(plt0) = plt0 {
+            insert_child(
+                &mut self.children,
+                "Palettes(NW4R)",
+                BresChild {
+                    name: plt0.name.clone(),
+                    data: BresChildData::Plt0(plt0),
+                },
+            );
+        }
+    }
+
+    /// Adds the animation to the animations group.
+    /// Any existing animation with the same name is replaced.
+    pub fn insert_chr0(&mut self, chr0: Chr0) {
+        insert_child(
+            &mut self.children,
+            "AnimChr(NW4R)",
+            BresChild {
+                name: chr0.name.clone(),
+                data: BresChildData::Chr0(chr0),
+            },
+        );
     }
 }
 
-fn most_significant_different_bit(b0: u8, b1: u8) -> u8 {
-    for i in 0..8 {
-        let bit = 0x80 >> i;
-        if b0 & bit != b1 & bit {
-            return 7 - i;
+fn insert_child(children: &mut Vec<BresChild>, group_name: &str, child: BresChild) {
+    let group = match children.iter().position(|x| x.name == group_name) {
+        Some(index) => &mut children[index],
+        None => {
+            children.push(BresChild {
+                name: group_name.to_string(),
+                data: BresChildData::Bres(vec![]),
+            });
+            children.last_mut().unwrap()
+        }
+    };
+
+    if let BresChildData::Bres(group) = &mut group.data {
+        match group.iter_mut().find(|x| x.name == child.name) {
+            Some(existing) => *existing = child,
+            None => group.push(child),
         }
     }
-    0
 }
 
 impl BresChild {
@@ -210,9 +281,16 @@ impl BresChild {
 
                 output
             }
+            BresChildData::Chr0(child) => child.compile(bres_offset),
             BresChildData::Mdl0(child) => child.compile(bres_offset),
             BresChildData::Plt0(child) => child.compile(bres_offset),
-            _ => vec![],
+            BresChildData::Tex0(child) => child.compile(bres_offset),
+            BresChildData::Clr0(child) => child.compile(bres_offset),
+            BresChildData::Pat0(child) => child.compile(bres_offset),
+            BresChildData::Shp0(child) => child.compile(bres_offset),
+            BresChildData::Srt0(child) => child.compile(bres_offset),
+            BresChildData::Vis0(child) => child.compile(bres_offset),
+            BresChildData::Unknown(child) => child.compile(bres_offset),
         }
     }
 
@@ -252,8 +330,266 @@ pub struct BresChild {
 #[derive(Clone, Debug)]
 pub enum BresChildData {
     Chr0(Chr0),
+    Clr0(Clr0),
     Mdl0(Mdl0),
+    Pat0(Pat0),
     Plt0(Plt0),
+    Shp0(Shp0),
+    Srt0(Srt0),
+    Tex0(Tex0),
+    Vis0(Vis0),
     Bres(Vec<BresChild>),
-    Unknown(String),
+    Unknown(RawBresChild),
+}
+
+/// Every BRES child starts with its tag, size, version and the offset to the start of the BRES
+const BRES_CHILD_HEADER_SIZE: usize = 0x10;
+
+/// The original bytes of a BRES child that is written back as is, rather than compiled from its parsed fields.
+///
+/// The names used by the child are stored in the string table of the BRES, outside of the child.
+/// So the fields pointing to them are recorded and pointed at the new string table when compiled.
+#[derive(Clone, Debug)]
+pub struct RawBresChild {
+    data: Vec<u8>,
+    strings: Vec<RawString>,
+}
+
+#[derive(Clone, Debug)]
+struct RawString {
+    /// Offset of the i32 pointing to the string
+    field: usize,
+    /// The string offset is relative to this offset
+    base: usize,
+    string: String,
+}
+
+impl RawBresChild {
+    /// Keeps the bytes of the child at the start of `data`, the size is read from the childs header
+    pub(crate) fn new(
+        data: FancySlice,
+        structure: &'static str,
+    ) -> Result<RawBresChild, ParseError> {
+        check_len(data, BRES_CHILD_HEADER_SIZE, structure)?;
+        let size = data.i32_be(0x4);
+        if size < BRES_CHILD_HEADER_SIZE as i32 {
+            return Err(ParseError::new(
+                structure,
+                0,
+                ParseErrorKind::Malformed(format!("size {size} is smaller than the header")),
+            ));
+        }
+        check_len(data, size as usize, structure)?;
+        Ok(RawBresChild {
+            data: data.relative_slice(..size as usize).to_vec(),
+            strings: vec![],
+        })
+    }
+
+    /// Keeps the bytes of a child we know nothing about, they are written back verbatim except for the offset to the BRES.
+    ///
+    /// Its names are stored in the string table of the BRES, but there is no way to tell which fields point to them.
+    /// So when compiled into a BRES with a different layout the names are lost.
+    fn unknown(data: FancySlice) -> Result<RawBresChild, ParseError> {
+        RawBresChild::new(data, "BRES child")
+    }
+
+    /// Records that the i32 at `field` is the offset of a string relative to `base`, an offset of 0 means there is no string.
+    pub(crate) fn string(
+        &mut self,
+        data: FancySlice,
+        field: usize,
+        base: usize,
+        structure: &'static str,
+    ) -> Result<(), ParseError> {
+        check_len(data, field + 4, structure)?;
+        let offset = data.i32_be(field);
+        if offset != 0 {
+            let string_data = try_offset(&data, base as i64 + offset as i64, 0, structure)?;
+            let string = try_str(&string_data, 0, structure)
+                .map_err(|err| err.offset_by(base.saturating_add_signed(offset as isize)))?;
+            self.strings.push(RawString {
+                field,
+                base,
+                string: string.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Records the names of every entry in the resource group at `group`
+    pub(crate) fn resource_group_strings(
+        &mut self,
+        data: FancySlice,
+        group: usize,
+        structure: &'static str,
+    ) -> Result<(), ParseError> {
+        check_len(data, group + resources::RESOURCE_HEADER_SIZE, structure)?;
+        let num_children = data.i32_be(group + 4).max(0) as usize;
+        // the first child is a dummy without a name
+        for i in 1..=num_children {
+            let field = group + resources::RESOURCE_HEADER_SIZE + resources::RESOURCE_SIZE * i + 8;
+            self.string(data, field, group, structure)?;
+        }
+        Ok(())
+    }
+
+    /// Keeps the bytes of an animation with a resource group of entries at `resources_offset` and its name and original path at `names_offset`.
+    /// Each entry has an offset to its name at `entry_name_offset`, relative to the entry.
+    /// Versions with user data have its offset directly before `names_offset`.
+    pub(crate) fn animation(
+        data: FancySlice,
+        resources_offset: usize,
+        names_offset: usize,
+        entry_name_offset: usize,
+        has_user_data: bool,
+        structure: &'static str,
+    ) -> Result<RawBresChild, ParseError> {
+        let mut raw = RawBresChild::new(data, structure)?;
+        if has_user_data && data.i32_be(names_offset - 4) != 0 {
+            warn!(
+                "{structure} has user data, the names used by the user data will be lost when compiled"
+            );
+        }
+        raw.string(data, names_offset, 0, structure)?;
+        // Tools dont agree on what the original path points to, keep it only when it is a string
+        let mut orig_path = raw.clone();
+        if orig_path
+            .string(data, names_offset + 0x4, 0, structure)
+            .is_ok()
+        {
+            raw = orig_path;
+        }
+        raw.resource_group_strings(data, resources_offset, structure)?;
+
+        let resources_data = data.relative_fancy_slice(resources_offset..);
+        for resource in resources::try_resources(resources_data)? {
+            let entry = resources_offset.saturating_add_signed(resource.data_offset as isize);
+            raw.string(data, entry + entry_name_offset, entry, structure)?;
+        }
+        Ok(raw)
+    }
+
+    /// The tag at the start of the child e.g. "SCN0"
+    pub fn tag(&self) -> String {
+        util::parse_tag(&self.data)
+    }
+
+    pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
+        let mut output = self.data.clone();
+        output[0xc..0x10].copy_from_slice(&i32::to_be_bytes(bres_offset));
+
+        // The strings are not included in the size, same as the children we can compile
+        let mut strings = StringTable::new();
+        for string in &self.strings {
+            strings.reference(string.field, string.base, &string.string);
+        }
+        strings.compile(&mut output);
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A VIS0 v3 named "anim" with a single bone named "bone" that is always visible, followed by its string table
+    fn vis0_fixture(tag: &[u8; 4]) -> Vec<u8> {
+        let mut output = vec![];
+        output.extend(tag);
+        output.extend(i32::to_be_bytes(0x54)); // size
+        output.extend(i32::to_be_bytes(3)); // version
+        output.extend(i32::to_be_bytes(0)); // bres_offset
+        output.extend(i32::to_be_bytes(0x24)); // resources_offset
+        output.extend(i32::to_be_bytes(0x58)); // string_offset
+        output.extend(i32::to_be_bytes(0)); // orig_path_offset
+        output.extend(u16::to_be_bytes(10)); // num_frames
+        output.extend(u16::to_be_bytes(1)); // num_children
+        output.extend(i32::to_be_bytes(1)); // loop_value
+
+        // resource group containing the dummy child and the bone
+        output.extend(i32::to_be_bytes(0x28));
+        output.extend(i32::to_be_bytes(1));
+        output.extend([0; 0x10]);
+        output.extend([0; 8]);
+        output.extend(i32::to_be_bytes(0x64 - 0x24)); // string offset
+        output.extend(i32::to_be_bytes(0x4c - 0x24)); // data offset
+
+        // bone
+        output.extend(i32::to_be_bytes(0x64 - 0x4c)); // string offset
+        output.extend(u32::to_be_bytes(0b11)); // enabled and constant
+
+        for string in ["anim", "bone"] {
+            output.extend(u32::to_be_bytes(string.len() as u32));
+            output.extend(string.as_bytes());
+            output.extend([0; 4]);
+        }
+        output
+    }
+
+    fn bres(data: BresChildData) -> Bres {
+        Bres {
+            endian: 0xfeff,
+            version: 0,
+            children: vec![BresChild {
+                name: String::from("AnmVis(NW4R)"),
+                data: BresChildData::Bres(vec![BresChild {
+                    name: String::from("anim"),
+                    data,
+                }]),
+            }],
+        }
+    }
+
+    fn leaf(bres: &Bres) -> &BresChildData {
+        match &bres.children[0].data {
+            BresChildData::Bres(children) => &children[0].data,
+            _ => panic!("expected a BRES group"),
+        }
+    }
+
+    #[test]
+    fn compile_vis0() {
+        let vis0 = try_vis0(FancySlice::new(&vis0_fixture(b"VIS0"))).unwrap();
+        let bres = bres(BresChildData::Vis0(vis0));
+
+        let compiled = bres.compile().unwrap();
+        let reparsed = try_bres(FancySlice::new(&compiled)).unwrap();
+        let BresChildData::Vis0(vis0) = leaf(&reparsed) else {
+            panic!("expected a VIS0");
+        };
+        assert_eq!(vis0.name(), "anim");
+        assert_eq!(vis0.num_frames(), 10);
+        assert_eq!(vis0.bones()[0].name, "bone");
+        assert!(vis0.bones()[0].is_visible(vis0.loop_value(), 5));
+        assert_eq!(reparsed.compile().unwrap(), compiled);
+    }
+
+    #[test]
+    fn compile_unknown() {
+        let data = vis0_fixture(b"XYZ0");
+        let unknown = RawBresChild::unknown(FancySlice::new(&data)).unwrap();
+        // the string table after the child is not part of it
+        let fixture = &data[..0x54];
+        assert_eq!(unknown.tag(), "XYZ0");
+        assert_eq!(unknown.compile(0), fixture);
+
+        let compiled = bres(BresChildData::Unknown(unknown)).compile().unwrap();
+        // the child is copied as is, apart from the offset to the start of the BRES
+        let child_offset = compiled.windows(4).position(|x| x == b"XYZ0").unwrap();
+        let mut expected = fixture.to_vec();
+        expected[0xc..0x10].copy_from_slice(&i32::to_be_bytes(-(child_offset as i32)));
+        assert_eq!(
+            compiled[child_offset..child_offset + fixture.len()],
+            expected
+        );
+
+        let reparsed = try_bres(FancySlice::new(&compiled)).unwrap();
+        let BresChildData::Unknown(unknown) = leaf(&reparsed) else {
+            panic!("expected an unknown child");
+        };
+        assert_eq!(unknown.data, expected);
+        assert_eq!(reparsed.compile().unwrap(), compiled);
+    }
 }
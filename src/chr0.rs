@@ -3,10 +3,14 @@ use fancy_slice::FancySlice;
 use std::iter::Iterator;
 
 use crate::math;
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
 use crate::resources;
+use crate::resources::StringTable;
 
+/// Parses a CHR0, returning an error instead of panicking on malformed data.
 #[rustfmt::skip]
-pub(crate) fn chr0(data: FancySlice) -> Chr0 {
+pub fn try_chr0(data: FancySlice) -> Result<Chr0, ParseError> {
+    check_len(data, CHR0_HEADER_SIZE, "CHR0")?;
     let _size             = data.i32_be(0x4);
     let version           = data.i32_be(0x8);
     let _bres_offset      = data.i32_be(0xc);
@@ -16,45 +20,49 @@ pub(crate) fn chr0(data: FancySlice) -> Chr0 {
     let num_frames        = data.u16_be(0x1c);
     let _num_children     = data.u16_be(0x1e);
     let loop_value        = data.i32_be(0x20);
-    let _scaling_rule     = data.i32_be(0x24);
-    if version == 0 || version == 4 {
-        // Current implementation only handles version 4
-        // version 0 seems to be the same as version 4
-    }
-    else if version == 5 {
-        unimplemented!("Need to implement handling for chr0 v5, refer to brawlbox CHR0Node.cs");
-    }
-    else {
-        panic!("Unknown chr0 version: {}", version);
+    let scaling_rule      = data.i32_be(0x24);
+    // Current implementation only handles version 4
+    // version 0 seems to be the same as version 4
+    // TODO: version 5, refer to brawlbox CHR0Node.cs
+    if version != 0 && version != 4 {
+        return Err(ParseError::new("CHR0", 0, ParseErrorKind::UnknownVersion(version)));
     }
 
-    let name = data.str(string_offset as usize).unwrap().to_string();
+    let name = try_str(&data, string_offset as usize, "CHR0 name")
+        .map_err(|err| err.offset_by(string_offset as usize))?
+        .to_string();
 
-    let mut children = vec!();
-    for resource in resources::resources(data.relative_fancy_slice(resources_offset as usize ..)) {
-        let child_data = data.relative_fancy_slice(resources_offset as usize + resource.data_offset as usize .. );
+    let resources_data = try_offset(&data, resources_offset as i64, 0, "CHR0")?;
+    let resources = resources::try_resources(resources_data)
+        .map_err(|err| err.offset_by(resources_offset as usize))?;
 
-        let string_offset = child_data.i32_be(0);
-        let _string = child_data.str(string_offset as usize).unwrap(); // same as resource.string
+    let mut children = vec!();
+    for resource in resources {
+        let child_offset = resources_offset as i64 + resource.data_offset as i64;
+        let child_data = try_offset(&data, child_offset, CHR0_CHILD_SIZE, "CHR0 child")?;
+        let child_error = |err: ParseError| err.offset_by(child_offset as usize);
 
         let code = Chr0ChildCode::new(child_data.u32_be(4));
+        if (code.value >> 27) & 0b111 == 0b111 {
+            return Err(ParseError::new("CHR0 child", child_offset as usize, ParseErrorKind::Malformed(String::from("unknown rotation format 7"))));
+        }
 
         let mut data_offset = CHR0_CHILD_SIZE;
 
-        let scale = keyframe_holder(child_data.relative_fancy_slice(..), &mut data_offset, code.scale_exists(), code.scale_isotropic(), code.scale_fixed_x(), code.scale_fixed_y(), code.scale_fixed_z(), code.scale_format(), num_frames);
-        let rot = keyframe_holder(child_data.relative_fancy_slice(..), &mut data_offset, code.rot_exists(), code.rot_isotropic(), code.rot_fixed_x(), code.rot_fixed_y(), code.rot_fixed_z(), code.rot_format(), num_frames);
-        let translation = keyframe_holder(child_data.relative_fancy_slice(..), &mut data_offset, code.translation_exists(), code.translation_isotropic(), code.translation_fixed_x(), code.translation_fixed_y(), code.translation_fixed_z(), code.translation_format(), num_frames);
+        let scale = keyframe_holder(child_data, &mut data_offset, code.scale_exists(), code.scale_isotropic(), code.scale_fixed_x(), code.scale_fixed_y(), code.scale_fixed_z(), code.scale_format(), num_frames).map_err(child_error)?;
+        let rot = keyframe_holder(child_data, &mut data_offset, code.rot_exists(), code.rot_isotropic(), code.rot_fixed_x(), code.rot_fixed_y(), code.rot_fixed_z(), code.rot_format(), num_frames).map_err(child_error)?;
+        let translation = keyframe_holder(child_data, &mut data_offset, code.translation_exists(), code.translation_isotropic(), code.translation_fixed_x(), code.translation_fixed_y(), code.translation_fixed_z(), code.translation_format(), num_frames).map_err(child_error)?;
 
         children.push(Chr0Child {
             name: resource.string,
-            _code: code,
+            code,
             scale,
             rot,
             translation,
         });
     }
 
-    Chr0 {
+    Ok(Chr0 {
         name,
         _size,
         _version: version,
@@ -63,9 +71,9 @@ pub(crate) fn chr0(data: FancySlice) -> Chr0 {
         num_frames,
         _num_children,
         loop_value: loop_value != 0,
-        _scaling_rule,
+        scaling_rule,
         children,
-    }
+    })
 }
 
 #[derive(Clone, Debug)]
@@ -78,10 +86,109 @@ pub struct Chr0 {
     pub num_frames: u16,
     _num_children: u16,
     pub loop_value: bool,
-    _scaling_rule: i32,
+    scaling_rule: i32,
     pub children: Vec<Chr0Child>,
 }
 
+/// Small enough that the error is never visible, while still allowing values that are only constant
+/// or linear to within float precision to use the smaller encodings.
+pub const CHR0_DEFAULT_TOLERANCE: f32 = 0.0001;
+
+impl Chr0 {
+    pub fn new(name: String, num_frames: u16, loop_value: bool, children: Vec<Chr0Child>) -> Chr0 {
+        Chr0 {
+            name,
+            _size: 0,
+            _version: 4,
+            _bres_offset: 0,
+            _orig_path_offset: 0,
+            num_frames,
+            _num_children: children.len() as u16,
+            loop_value,
+            scaling_rule: 0,
+            children,
+        }
+    }
+
+    /// Compiles with a tolerance of `CHR0_DEFAULT_TOLERANCE`, refer to `Chr0::compile_with_tolerance`
+    pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
+        self.compile_with_tolerance(bres_offset, CHR0_DEFAULT_TOLERANCE)
+    }
+
+    /// Every keyframe is reencoded into the smallest encoding that keeps its value at each frame in `0..num_frames`
+    /// within `tolerance` of the value it had before compiling.
+    ///
+    /// Keyframes can be modified or replaced before compiling, e.g. to retime or trim an animation,
+    /// as long as they can still be evaluated at every frame in `0..num_frames`.
+    pub fn compile_with_tolerance(&self, bres_offset: i32, tolerance: f32) -> Vec<u8> {
+        let mut output = vec![];
+        let mut strings = StringTable::new();
+
+        // create CHR0 header
+        output.extend("CHR0".chars().map(|x| x as u8));
+        output.extend(i32::to_be_bytes(0)); // size, filled in at the end
+        output.extend(i32::to_be_bytes(4)); // version
+        output.extend(i32::to_be_bytes(bres_offset));
+        output.extend(i32::to_be_bytes(CHR0_HEADER_SIZE as i32)); // resources_offset
+        strings.reference(output.len(), 0, &self.name);
+        output.extend(i32::to_be_bytes(0)); // string_offset
+        output.extend(i32::to_be_bytes(0)); // TODO: orig_path_offset
+        output.extend(u16::to_be_bytes(self.num_frames));
+        output.extend(u16::to_be_bytes(self.children.len() as u16));
+        output.extend(i32::to_be_bytes(self.loop_value as i32));
+        output.extend(i32::to_be_bytes(self.scaling_rule));
+
+        let names: Vec<&str> = self.children.iter().map(|x| x.name.as_str()).collect();
+        let group = resources::compile_resources(&mut output, &mut strings, &names);
+
+        // create children, leaving the keyframe offsets to be filled in once the keyframes are written
+        let encoded: Vec<EncodedChild> = self
+            .children
+            .iter()
+            .map(|x| x.encode(self.num_frames, tolerance))
+            .collect();
+        let mut child_offsets = vec![];
+        for (i, (child, encoded)) in self.children.iter().zip(encoded.iter()).enumerate() {
+            let child_offset = output.len();
+            resources::set_resource_data_offset(&mut output, group, i, child_offset);
+            strings.reference(output.len(), child_offset, &child.name);
+            output.extend(i32::to_be_bytes(0)); // string_offset
+            output.extend(u32::to_be_bytes(encoded.code));
+            for value in &encoded.values {
+                match value {
+                    EncodedValue::Fixed(value) => output.extend(f32::to_be_bytes(*value)),
+                    EncodedValue::Keyframe(_) => output.extend(i32::to_be_bytes(0)),
+                }
+            }
+            child_offsets.push(child_offset);
+        }
+
+        // create keyframes
+        for (child_offset, encoded) in child_offsets.iter().zip(encoded.iter()) {
+            for (i, value) in encoded.values.iter().enumerate() {
+                if let EncodedValue::Keyframe(keyframe) = value {
+                    let field = child_offset + CHR0_CHILD_SIZE + i * 4;
+                    let offset = (output.len() - child_offset) as i32;
+                    output[field..field + 4].copy_from_slice(&i32::to_be_bytes(offset));
+                    output.extend(keyframe);
+                    while output.len() % 4 != 0 {
+                        output.push(0x00);
+                    }
+                }
+            }
+        }
+
+        strings.compile(&mut output);
+
+        let size = output.len() as i32;
+        output[0x4..0x8].copy_from_slice(&i32::to_be_bytes(size));
+
+        output
+    }
+}
+
+const CHR0_HEADER_SIZE: usize = 0x28;
+
 const CHR0_CHILD_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
 pub struct Chr0Child {
@@ -89,10 +196,27 @@ pub struct Chr0Child {
     pub scale: KeyframeHolder,
     pub rot: KeyframeHolder,
     pub translation: KeyframeHolder,
-    _code: Chr0ChildCode,
+    code: Chr0ChildCode,
 }
 
 impl Chr0Child {
+    /// Creates a child that animates the bone with the same name.
+    /// The keyframes are reencoded when compiled so any keyframe type can be used.
+    pub fn new(
+        name: String,
+        scale: KeyframeHolder,
+        rot: KeyframeHolder,
+        translation: KeyframeHolder,
+    ) -> Chr0Child {
+        Chr0Child {
+            name,
+            scale,
+            rot,
+            translation,
+            code: Chr0ChildCode::new(1),
+        }
+    }
+
     pub fn get_transform(&self, loop_value: bool, frame: i32) -> Matrix4<f32> {
         let scale = self.scale.get_value(loop_value, frame, 1.0);
         let rot = self.rot.get_value(loop_value, frame, 0.0);
@@ -106,6 +230,396 @@ impl Chr0Child {
         let translation = Vector3::new(0.0, 0.0, 0.0);
         math::gen_transform(scale, rot, translation)
     }
+
+    fn encode(&self, num_frames: u16, tolerance: f32) -> EncodedChild {
+        // The scale and translation formats only have room for the interpolated encodings
+        let scale = encode_keyframe_holder(&self.scale, num_frames, tolerance, false);
+        let rot = encode_keyframe_holder(&self.rot, num_frames, tolerance, true);
+        let translation = encode_keyframe_holder(&self.translation, num_frames, tolerance, false);
+
+        // Keep the flags that dont affect how the keyframes are stored
+        let mut code = self.code.value & 0b0000_0000_0000_0000_0001_1111_1000_0000;
+        code |= 1;
+        if scale.is_none() && rot.is_none() && translation.is_none() {
+            code |= 1 << 1;
+        }
+        if rot.is_none() && translation.is_none() {
+            code |= 1 << 2;
+        }
+        if scale.is_none() {
+            code |= 1 << 3;
+        }
+
+        let mut values = vec![];
+        for (holder, isotropic_bit, fixed_bit, exists_bit, format_bit) in [
+            (scale, 4, 13, 22, 25),
+            (rot, 5, 16, 23, 27),
+            (translation, 6, 19, 24, 30),
+        ] {
+            if let Some(holder) = holder {
+                code |= (holder.isotropic as u32) << isotropic_bit;
+                for (i, value) in holder.values.iter().enumerate() {
+                    let fixed = matches!(value, EncodedValue::Fixed(_));
+                    // isotropic values use the z fixed flag
+                    let axis = if holder.isotropic { 2 } else { i };
+                    code |= (fixed as u32) << (fixed_bit + axis);
+                }
+                code |= 1 << exists_bit;
+                code |= holder.format << format_bit;
+                values.extend(holder.values);
+            }
+        }
+
+        EncodedChild { code, values }
+    }
+}
+
+struct EncodedChild {
+    code: u32,
+    values: Vec<EncodedValue>,
+}
+
+struct EncodedHolder {
+    isotropic: bool,
+    format: u32,
+    values: Vec<EncodedValue>,
+}
+
+enum EncodedValue {
+    Fixed(f32),
+    Keyframe(Vec<u8>),
+}
+
+/// Returns None when there are no keyframes to store
+fn encode_keyframe_holder(
+    holder: &KeyframeHolder,
+    num_frames: u16,
+    tolerance: f32,
+    allow_linear: bool,
+) -> Option<EncodedHolder> {
+    let frames = num_frames.max(1) as i32;
+    let samples = |keyframe: &Keyframe| -> Vec<f32> {
+        (0..frames)
+            .map(|frame| keyframe.get_value(false, frame))
+            .collect()
+    };
+
+    let axes = match holder {
+        KeyframeHolder::None => return None,
+        KeyframeHolder::Isotropic(keyframe) => vec![samples(keyframe)],
+        KeyframeHolder::Individual { x, y, z } => {
+            let (x, y, z) = (samples(x), samples(y), samples(z));
+            if x == y && x == z {
+                vec![x]
+            } else {
+                vec![x, y, z]
+            }
+        }
+    };
+    let isotropic = axes.len() == 1;
+
+    let is_fixed = |samples: &[f32]| samples.iter().all(|x| (x - samples[0]).abs() <= tolerance);
+
+    // Find the smallest format that can store every non fixed axis
+    let mut formats = vec![
+        Chr0Format::Interpolated4,
+        Chr0Format::Interpolated6,
+        Chr0Format::Interpolated12,
+    ];
+    if allow_linear && num_frames > 0 {
+        formats.extend([
+            Chr0Format::Linear1,
+            Chr0Format::Linear2,
+            Chr0Format::Linear4,
+        ]);
+    }
+    let animated: Vec<&Vec<f32>> = axes.iter().filter(|x| !is_fixed(x)).collect();
+    let mut best: Option<(u32, Vec<Vec<u8>>)> = None;
+    if !animated.is_empty() {
+        for format in formats {
+            let encoded: Option<Vec<Vec<u8>>> = animated
+                .iter()
+                .map(|samples| encode_keyframe(samples, &format, tolerance))
+                .collect();
+            if let Some(encoded) = encoded {
+                let size = |encoded: &Vec<Vec<u8>>| -> usize {
+                    encoded.iter().map(|x| x.len().div_ceil(4) * 4).sum()
+                };
+                if best
+                    .as_ref()
+                    .is_none_or(|(_, best)| size(&encoded) < size(best))
+                {
+                    best = Some((format.value(), encoded));
+                }
+            }
+        }
+    }
+    let (format, mut encoded) = best.unwrap_or((0, vec![]));
+    encoded.reverse();
+
+    let values = axes
+        .iter()
+        .map(|samples| {
+            if is_fixed(samples) {
+                EncodedValue::Fixed(samples[0])
+            } else {
+                EncodedValue::Keyframe(encoded.pop().unwrap())
+            }
+        })
+        .collect();
+
+    Some(EncodedHolder {
+        isotropic,
+        format,
+        values,
+    })
+}
+
+/// Encodes the value at each frame into the keyframe format.
+/// Returns None if the format cannot stay within tolerance.
+fn encode_keyframe(samples: &[f32], format: &Chr0Format, tolerance: f32) -> Option<Vec<u8>> {
+    let error = |keyframe: &Keyframe| -> (usize, f32) {
+        samples
+            .iter()
+            .enumerate()
+            .map(|(frame, sample)| {
+                (
+                    frame,
+                    (keyframe.get_value(false, frame as i32) - sample).abs(),
+                )
+            })
+            .fold((0, 0.0), |max, x| if x.1 > max.1 { x } else { max })
+    };
+
+    let keyframe = match format {
+        Chr0Format::Linear1 | Chr0Format::Linear2 | Chr0Format::Linear4 => {
+            let keyframe = linear_keyframe(samples, format);
+            if error(&keyframe).1 > tolerance {
+                return None;
+            }
+            keyframe
+        }
+        _ => {
+            // Start with a keyframe on the first and last frame, then keep adding a keyframe at the frame with the most error.
+            let mut frames = vec![0, samples.len() - 1];
+            frames.dedup();
+            loop {
+                let keyframe = interpolated_keyframe(samples, &frames, format)?;
+                let (worst_frame, worst_error) = error(&keyframe);
+                if worst_error <= tolerance {
+                    break keyframe;
+                }
+                match frames.binary_search(&worst_frame) {
+                    // Already a keyframe here, the format is too imprecise
+                    Ok(_) => return None,
+                    Err(index) => frames.insert(index, worst_frame),
+                }
+            }
+        }
+    };
+
+    Some(keyframe_bytes(&keyframe))
+}
+
+fn linear_keyframe(samples: &[f32], format: &Chr0Format) -> Keyframe {
+    let (base, step) = quantize_range(
+        samples,
+        if let Chr0Format::Linear1 = format {
+            0xFF
+        } else {
+            0xFFFF
+        },
+    );
+    let steps = samples.iter().map(|x| {
+        if step == 0.0 {
+            0.0
+        } else {
+            ((x - base) / step).round()
+        }
+    });
+    match format {
+        Chr0Format::Linear1 => Keyframe::Linear1(Linear1Header {
+            step,
+            base,
+            children_steps: steps.map(|x| x as u8).collect(),
+        }),
+        Chr0Format::Linear2 => Keyframe::Linear2(Linear2Header {
+            step,
+            base,
+            children_steps: steps.map(|x| x as u16).collect(),
+        }),
+        _ => Keyframe::Linear4(samples.to_vec()),
+    }
+}
+
+/// Creates a keyframe for each of the specified frames, returns None if the frames cant be stored in the format.
+fn interpolated_keyframe(
+    samples: &[f32],
+    frames: &[usize],
+    format: &Chr0Format,
+) -> Option<Keyframe> {
+    let values: Vec<f32> = frames.iter().map(|x| samples[*x]).collect();
+    let tangents: Vec<f32> = frames
+        .iter()
+        .map(|frame| {
+            let prev = frame.saturating_sub(1);
+            let next = (frame + 1).min(samples.len() - 1);
+            if next == prev {
+                0.0
+            } else {
+                (samples[next] - samples[prev]) / (next - prev) as f32
+            }
+        })
+        .collect();
+    let frame_scale = if samples.len() > 1 {
+        1.0 / (samples.len() - 1) as f32
+    } else {
+        0.0
+    };
+
+    match format {
+        Chr0Format::Interpolated4 => {
+            if frames.iter().any(|x| *x > 0xFF) {
+                return None;
+            }
+            let (base, step) = quantize_range(&values, 0xFFF);
+            let children = frames
+                .iter()
+                .zip(values.iter().zip(tangents.iter()))
+                .map(|(frame, (value, tangent))| {
+                    let step = if step == 0.0 {
+                        0
+                    } else {
+                        ((value - base) / step).round() as u32
+                    };
+                    let tangent =
+                        (tangent * 32.0).round().clamp(-0x800 as f32, 0x7FF as f32) as i32 as u32;
+                    Interpolated4Entry {
+                        data: ((*frame as u32) << 24) | (step << 12) | (tangent & 0xFFF),
+                    }
+                })
+                .collect();
+            Some(Keyframe::Interpolated4(Interpolated4Header {
+                entries: frames.len() as u16,
+                unk: 0,
+                frame_scale,
+                step,
+                base,
+                children,
+            }))
+        }
+        Chr0Format::Interpolated6 => {
+            if frames.iter().any(|x| *x > 0x7FF) {
+                return None;
+            }
+            let (base, step) = quantize_range(&values, 0xFFFF);
+            let children = frames
+                .iter()
+                .zip(values.iter().zip(tangents.iter()))
+                .map(|(frame, (value, tangent))| Interpolated6Entry {
+                    frame_index: (*frame as u16) << 5,
+                    step: if step == 0.0 {
+                        0
+                    } else {
+                        ((value - base) / step).round() as u16
+                    },
+                    tangent: (tangent * 256.0)
+                        .round()
+                        .clamp(i16::MIN as f32, i16::MAX as f32)
+                        as i16,
+                })
+                .collect();
+            Some(Keyframe::Interpolated6(Interpolated6Header {
+                num_frames: frames.len() as u16,
+                unk: 0,
+                frame_scale,
+                step,
+                base,
+                children,
+            }))
+        }
+        _ => {
+            let children = frames
+                .iter()
+                .zip(values.iter().zip(tangents.iter()))
+                .map(|(frame, (value, tangent))| Interpolated12Entry {
+                    frame_index: *frame as f32,
+                    value: *value,
+                    tangent: *tangent,
+                })
+                .collect();
+            Some(Keyframe::Interpolated12(Interpolated12Header {
+                num_frames: frames.len() as u16,
+                unk: 0,
+                frame_scale,
+                children,
+            }))
+        }
+    }
+}
+
+/// Returns the base and step needed to store the values as integers between 0 and `max`
+fn quantize_range(values: &[f32], max: u32) -> (f32, f32) {
+    let min_value = values.iter().cloned().fold(f32::INFINITY, f32::min);
+    let max_value = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    (min_value, (max_value - min_value) / max as f32)
+}
+
+fn keyframe_bytes(keyframe: &Keyframe) -> Vec<u8> {
+    let mut output = vec![];
+    match keyframe {
+        Keyframe::Fixed(value) => output.extend(f32::to_be_bytes(*value)),
+        Keyframe::Interpolated4(header) => {
+            output.extend(u16::to_be_bytes(header.children.len() as u16));
+            output.extend(u16::to_be_bytes(header.unk));
+            output.extend(f32::to_be_bytes(header.frame_scale));
+            output.extend(f32::to_be_bytes(header.step));
+            output.extend(f32::to_be_bytes(header.base));
+            for child in &header.children {
+                output.extend(u32::to_be_bytes(child.data));
+            }
+        }
+        Keyframe::Interpolated6(header) => {
+            output.extend(u16::to_be_bytes(header.children.len() as u16));
+            output.extend(u16::to_be_bytes(header.unk));
+            output.extend(f32::to_be_bytes(header.frame_scale));
+            output.extend(f32::to_be_bytes(header.step));
+            output.extend(f32::to_be_bytes(header.base));
+            for child in &header.children {
+                output.extend(u16::to_be_bytes(child.frame_index));
+                output.extend(u16::to_be_bytes(child.step));
+                output.extend(i16::to_be_bytes(child.tangent));
+            }
+        }
+        Keyframe::Interpolated12(header) => {
+            output.extend(u16::to_be_bytes(header.children.len() as u16));
+            output.extend(u16::to_be_bytes(header.unk));
+            output.extend(f32::to_be_bytes(header.frame_scale));
+            for child in &header.children {
+                output.extend(f32::to_be_bytes(child.frame_index));
+                output.extend(f32::to_be_bytes(child.value));
+                output.extend(f32::to_be_bytes(child.tangent));
+            }
+        }
+        Keyframe::Linear1(header) => {
+            output.extend(f32::to_be_bytes(header.step));
+            output.extend(f32::to_be_bytes(header.base));
+            output.extend(&header.children_steps);
+        }
+        Keyframe::Linear2(header) => {
+            output.extend(f32::to_be_bytes(header.step));
+            output.extend(f32::to_be_bytes(header.base));
+            for step in &header.children_steps {
+                output.extend(u16::to_be_bytes(*step));
+            }
+        }
+        Keyframe::Linear4(values) => {
+            for value in values {
+                output.extend(f32::to_be_bytes(*value));
+            }
+        }
+    }
+    output
 }
 
 #[derive(Clone, Debug)]
@@ -181,6 +695,20 @@ pub enum Chr0Format {
     Linear4,
 }
 
+impl Chr0Format {
+    fn value(&self) -> u32 {
+        match self {
+            Chr0Format::None => 0,
+            Chr0Format::Interpolated4 => 1,
+            Chr0Format::Interpolated6 => 2,
+            Chr0Format::Interpolated12 => 3,
+            Chr0Format::Linear1 => 4,
+            Chr0Format::Linear2 => 5,
+            Chr0Format::Linear4 => 6,
+        }
+    }
+}
+
 fn keyframe_holder(
     child_data: FancySlice,
     data_offset: &mut usize,
@@ -191,60 +719,32 @@ fn keyframe_holder(
     fixed_z: bool,
     format: Chr0Format,
     num_frames: u16,
-) -> KeyframeHolder {
-    if !exists {
-        KeyframeHolder::None
-    } else if isotropic {
-        let keyframe = if fixed_z {
+) -> Result<KeyframeHolder, ParseError> {
+    // Each value is either fixed or an offset to its keyframe relative to the child
+    let mut next_keyframe = |fixed: bool| {
+        check_len(child_data, *data_offset + 4, "CHR0 child")?;
+        let keyframe = if fixed {
             Keyframe::Fixed(child_data.f32_be(*data_offset))
         } else {
-            let offset = child_data.u32_be(*data_offset);
-            keyframe(
-                child_data.relative_fancy_slice(offset as usize..),
-                &format,
-                num_frames,
-            )
-        };
-        *data_offset += 4;
-        KeyframeHolder::Isotropic(keyframe)
-    } else {
-        let x = if fixed_x {
-            Keyframe::Fixed(child_data.f32_be(*data_offset))
-        } else {
-            let offset = child_data.u32_be(*data_offset);
-            keyframe(
-                child_data.relative_fancy_slice(offset as usize..),
-                &format,
-                num_frames,
-            )
+            let offset = child_data.u32_be(*data_offset) as i64;
+            let keyframe_data = try_offset(&child_data, offset, 0, "CHR0 keyframe")?;
+            try_keyframe(keyframe_data, &format, num_frames, "CHR0 keyframe")
+                .map_err(|err| err.offset_by(offset as usize))?
         };
         *data_offset += 4;
+        Ok(keyframe)
+    };
 
-        let y = if fixed_y {
-            Keyframe::Fixed(child_data.f32_be(*data_offset))
-        } else {
-            let offset = child_data.u32_be(*data_offset);
-            keyframe(
-                child_data.relative_fancy_slice(offset as usize..),
-                &format,
-                num_frames,
-            )
-        };
-        *data_offset += 4;
-
-        let z = if fixed_z {
-            Keyframe::Fixed(child_data.f32_be(*data_offset))
-        } else {
-            let offset = child_data.u32_be(*data_offset);
-            keyframe(
-                child_data.relative_fancy_slice(offset as usize..),
-                &format,
-                num_frames,
-            )
-        };
-        *data_offset += 4;
+    Ok(if !exists {
+        KeyframeHolder::None
+    } else if isotropic {
+        KeyframeHolder::Isotropic(next_keyframe(fixed_z)?)
+    } else {
+        let x = next_keyframe(fixed_x)?;
+        let y = next_keyframe(fixed_y)?;
+        let z = next_keyframe(fixed_z)?;
         KeyframeHolder::Individual { x, y, z }
-    }
+    })
 }
 
 #[derive(Clone, Debug)]
@@ -536,6 +1036,50 @@ pub struct Linear2Header {
 const LINEAR_2_ENTRY_SIZE: usize = 0x2;
 const LINEAR_4_ENTRY_SIZE: usize = 0x4;
 
+/// Parses an Interpolated12 keyframe, returning an error if the keyframe extends past the end of `data`.
+/// Used by the other animation formats, which always store their keyframes this way.
+pub(crate) fn try_interpolated12_keyframe(
+    data: FancySlice,
+    structure: &'static str,
+) -> Result<Keyframe, ParseError> {
+    try_keyframe(data, &Chr0Format::Interpolated12, 0, structure)
+}
+
+/// Parses a keyframe, returning an error if the keyframe extends past the end of `data`.
+/// The linear formats store a value for each of the `num_frames` frames, the interpolated formats store their own count.
+fn try_keyframe(
+    data: FancySlice,
+    format: &Chr0Format,
+    num_frames: u16,
+    structure: &'static str,
+) -> Result<Keyframe, ParseError> {
+    let (header_size, entry_size) = match format {
+        Chr0Format::Interpolated4 => (INTERPOLATED_4_HEADER_SIZE, INTERPOLATED_4_ENTRY_SIZE),
+        Chr0Format::Interpolated6 => (INTERPOLATED_6_HEADER_SIZE, INTERPOLATED_6_ENTRY_SIZE),
+        Chr0Format::Interpolated12 => (INTERPOLATED_12_HEADER_SIZE, INTERPOLATED_12_ENTRY_SIZE),
+        Chr0Format::Linear1 => (LINEAR_1_HEADER_SIZE, 1),
+        Chr0Format::Linear2 => (LINEAR_2_HEADER_SIZE, LINEAR_2_ENTRY_SIZE),
+        Chr0Format::Linear4 => (0, LINEAR_4_ENTRY_SIZE),
+        Chr0Format::None => {
+            return Err(ParseError::new(
+                structure,
+                0,
+                ParseErrorKind::Malformed(String::from("animated value has no keyframe format")),
+            ));
+        }
+    };
+    check_len(data, header_size, structure)?;
+    let entries = match format {
+        Chr0Format::Interpolated4 | Chr0Format::Interpolated6 | Chr0Format::Interpolated12 => {
+            data.u16_be(0x0) as usize
+        }
+        _ => num_frames as usize,
+    };
+    check_len(data, header_size + entry_size * entries, structure)?;
+    Ok(keyframe(data, format, num_frames))
+}
+
+/// Reads a keyframe, `data` must already be checked to contain the whole keyframe
 #[rustfmt::skip]
 fn keyframe(data: FancySlice, format: &Chr0Format, num_frames: u16) -> Keyframe {
     match format {
@@ -607,6 +1151,107 @@ fn keyframe(data: FancySlice, format: &Chr0Format, num_frames: u16) -> Keyframe
             }
             Keyframe::Linear4 (values)
         }
-        Chr0Format::None => panic!("this function should not be called with a format of None"),
+        Chr0Format::None => unreachable!("rejected by try_keyframe"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A simple xorshift so the noisy curves dont need a dependency
+    fn random(state: &mut u32) -> u32 {
+        *state ^= *state << 13;
+        *state ^= *state >> 17;
+        *state ^= *state << 5;
+        *state
+    }
+
+    /// Each curve is chosen so that the smallest encoding within `tolerance` is `format`
+    #[test]
+    fn compile_with_tolerance_per_format() {
+        let num_frames = 60;
+        let tolerance = 0.001;
+        let mut state = 0x1234_5678;
+        let mut noise = |steps: u32, step: f32| -> Vec<f32> {
+            let mut samples: Vec<f32> = (0..num_frames)
+                .map(|_| (random(&mut state) % steps) as f32 * step)
+                .collect();
+            // use the full range, so that the steps of the encoding line up with the samples
+            samples[0] = 0.0;
+            samples[1] = (steps - 1) as f32 * step;
+            samples
+        };
+        // Noise needs a keyframe on every frame, so the precision of the values decides the format.
+        // Only rotations can use the linear formats, so the interpolated curves are stored as translations.
+        let curves = [
+            (Chr0Format::Interpolated4, noise(0x1000, 0.001)),
+            (Chr0Format::Interpolated6, noise(0x10000, 0.001)),
+            (Chr0Format::Interpolated12, noise(u32::MAX, 0.001)),
+            (Chr0Format::Linear1, noise(0x100, 0.5)),
+            (Chr0Format::Linear2, noise(0x10000, 0.01)),
+            (Chr0Format::Linear4, noise(u32::MAX, 0.001)),
+        ];
+        let is_linear = |format: &Chr0Format| {
+            matches!(
+                format,
+                Chr0Format::Linear1 | Chr0Format::Linear2 | Chr0Format::Linear4
+            )
+        };
+
+        let children = curves
+            .iter()
+            .map(|(format, samples)| {
+                let keyframe = KeyframeHolder::Isotropic(Keyframe::Linear4(samples.clone()));
+                let (rot, translation) = if is_linear(format) {
+                    (keyframe, KeyframeHolder::None)
+                } else {
+                    (KeyframeHolder::None, keyframe)
+                };
+                Chr0Child::new(
+                    format!("{format:?}"),
+                    KeyframeHolder::None,
+                    rot,
+                    translation,
+                )
+            })
+            .collect();
+        let chr0 = Chr0::new(String::from("test"), num_frames, false, children);
+        let compiled = chr0.compile_with_tolerance(0, tolerance);
+        let parsed = try_chr0(FancySlice::new(&compiled)).unwrap();
+
+        for ((format, samples), child) in curves.iter().zip(&parsed.children) {
+            assert_eq!(child.name, format!("{format:?}"));
+            let holder = if is_linear(format) {
+                &child.rot
+            } else {
+                &child.translation
+            };
+            let KeyframeHolder::Isotropic(keyframe) = holder else {
+                panic!("expected an isotropic keyframe, got {holder:?}");
+            };
+            let encoded = match keyframe {
+                Keyframe::Interpolated4(_) => Chr0Format::Interpolated4,
+                Keyframe::Interpolated6(_) => Chr0Format::Interpolated6,
+                Keyframe::Interpolated12(_) => Chr0Format::Interpolated12,
+                Keyframe::Linear1(_) => Chr0Format::Linear1,
+                Keyframe::Linear2(_) => Chr0Format::Linear2,
+                Keyframe::Linear4(_) => Chr0Format::Linear4,
+                Keyframe::Fixed(_) => panic!("{format:?} curve was stored as fixed"),
+            };
+            assert_eq!(
+                encoded.value(),
+                format.value(),
+                "{format:?} curve was stored as {encoded:?}"
+            );
+
+            for (frame, sample) in samples.iter().enumerate() {
+                let value = keyframe.get_value(false, frame as i32);
+                assert!(
+                    (value - sample).abs() <= tolerance,
+                    "{format:?} frame {frame} is {value} instead of {sample}"
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,738 @@
+//! Import animations made in other tools, such as Blender, as CHR0s that can be inserted into a Fit{}Motion.pac
+//!
+//! The animations are resampled to brawls 60 frames per second and matched to the bones of the skeleton by name.
+//! Each bone is then animated relative to its parent in the brawl skeleton, so the external skeleton is allowed to
+//! contain extra bones or use a different hierarchy.
+
+use std::collections::HashMap;
+
+use anyhow::{Error, bail};
+use cgmath::{InnerSpace, Matrix4, Quaternion, SquareMatrix, Vector3, Vector4};
+
+use crate::chr0::{Chr0, Chr0Child, Keyframe, KeyframeHolder};
+use crate::math;
+use crate::mdl0::bones::Bone;
+
+const FRAMES_PER_SECOND: f32 = 60.0;
+
+/// Returns a CHR0 for every animation in the .glb
+pub fn chr0s_from_glb(glb: &[u8], skeleton: &Bone) -> Result<Vec<Chr0>, Error> {
+    let (gltf, bin) = gltf::read_glb(glb)?;
+    gltf::animations(&gltf, bin)?
+        .iter()
+        .map(|animation| chr0(animation, skeleton))
+        .collect()
+}
+
+/// Returns a CHR0 named `name` of the animation in the .dae
+pub fn chr0_from_collada(dae: &str, name: &str, skeleton: &Bone) -> Result<Chr0, Error> {
+    let animation = collada::animation(dae, name)?;
+    chr0(&animation, skeleton)
+}
+
+/// An animation sampled at every brawl frame, before it is matched to the brawl skeleton
+struct ImportedAnimation {
+    name: String,
+    num_frames: usize,
+    nodes: Vec<ImportedNode>,
+}
+
+struct ImportedNode {
+    name: String,
+    parent: Option<usize>,
+    /// The transform relative to the parent node at each frame
+    transforms: Vec<Matrix4<f32>>,
+}
+
+impl ImportedAnimation {
+    /// Returns the transform of each node relative to the root of the file, at each frame
+    fn world_transforms(&self) -> Vec<Vec<Matrix4<f32>>> {
+        let mut world: Vec<Option<Vec<Matrix4<f32>>>> = vec![None; self.nodes.len()];
+        for i in 0..self.nodes.len() {
+            self.world_transform(i, &mut world);
+        }
+        world.into_iter().map(|x| x.unwrap()).collect()
+    }
+
+    fn world_transform(&self, index: usize, world: &mut [Option<Vec<Matrix4<f32>>>]) {
+        if world[index].is_some() {
+            return;
+        }
+        let node = &self.nodes[index];
+        let transforms = match node.parent {
+            Some(parent) => {
+                self.world_transform(parent, world);
+                let parent = world[parent].as_ref().unwrap();
+                parent
+                    .iter()
+                    .zip(node.transforms.iter())
+                    .map(|(parent, local)| parent * local)
+                    .collect()
+            }
+            None => node.transforms.clone(),
+        };
+        world[index] = Some(transforms);
+    }
+}
+
+fn chr0(animation: &ImportedAnimation, skeleton: &Bone) -> Result<Chr0, Error> {
+    if animation.num_frames > u16::MAX as usize {
+        bail!(
+            "Animation {} is {} frames long but a CHR0 can only store {} frames",
+            animation.name,
+            animation.num_frames,
+            u16::MAX
+        );
+    }
+
+    let world = animation.world_transforms();
+    let mut children = vec![];
+    chr0_children(animation, &world, skeleton, None, &mut children);
+    if children.is_empty() {
+        bail!(
+            "None of the bones animated by {} are in the skeleton",
+            animation.name
+        );
+    }
+
+    Ok(Chr0::new(
+        animation.name.clone(),
+        animation.num_frames as u16,
+        false,
+        children,
+    ))
+}
+
+fn chr0_children(
+    animation: &ImportedAnimation,
+    world: &[Vec<Matrix4<f32>>],
+    bone: &Bone,
+    parent: Option<&Bone>,
+    children: &mut Vec<Chr0Child>,
+) {
+    let find_node = |name: &str| animation.nodes.iter().position(|x| x.name == name);
+    if let Some(node) = find_node(&bone.name) {
+        // When the parent bone is not in the file we have to assume the files parent node matches it.
+        let transforms: Vec<Matrix4<f32>> = match parent.and_then(|x| find_node(&x.name)) {
+            Some(parent_node) => world[parent_node]
+                .iter()
+                .zip(world[node].iter())
+                .map(|(parent, node)| parent.invert().unwrap_or(Matrix4::identity()) * node)
+                .collect(),
+            None => animation.nodes[node].transforms.clone(),
+        };
+
+        let mut scale = [vec![], vec![], vec![]];
+        let mut rot: [Vec<f32>; 3] = [vec![], vec![], vec![]];
+        let mut translation = [vec![], vec![], vec![]];
+        for transform in transforms {
+            let (frame_scale, frame_rot, frame_translation) = math::decompose_transform(transform);
+            for axis in 0..3 {
+                // Avoid spinning the long way around when interpolating between frames
+                let mut angle = frame_rot[axis];
+                if let Some(prev) = rot[axis].last() {
+                    angle += ((prev - angle) / 360.0).round() * 360.0;
+                }
+                rot[axis].push(angle);
+                scale[axis].push(frame_scale[axis]);
+                translation[axis].push(frame_translation[axis]);
+            }
+        }
+
+        let holder = |[x, y, z]: [Vec<f32>; 3]| KeyframeHolder::Individual {
+            x: Keyframe::Linear4(x),
+            y: Keyframe::Linear4(y),
+            z: Keyframe::Linear4(z),
+        };
+        children.push(Chr0Child::new(
+            bone.name.clone(),
+            holder(scale),
+            holder(rot),
+            holder(translation),
+        ));
+    }
+
+    for child in &bone.children {
+        chr0_children(animation, world, child, Some(bone), children);
+    }
+}
+
+/// Returns the number of brawl frames needed to play an animation of `duration` seconds
+fn num_frames(duration: f32) -> usize {
+    (duration * FRAMES_PER_SECOND).round().max(0.0) as usize + 1
+}
+
+/// Returns the index of the keys before and after `time` and how far `time` is between them
+fn key_position(times: &[f32], time: f32) -> (usize, usize, f32) {
+    let next = times.partition_point(|x| *x <= time);
+    if next == 0 {
+        (0, 0, 0.0)
+    } else if next == times.len() {
+        (next - 1, next - 1, 0.0)
+    } else {
+        let prev = next - 1;
+        let span = times[next] - times[prev];
+        let amount = if span > 0.0 {
+            (time - times[prev]) / span
+        } else {
+            0.0
+        };
+        (prev, next, amount)
+    }
+}
+
+mod gltf {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub(super) struct Gltf {
+        #[serde(default)]
+        nodes: Vec<Node>,
+        #[serde(default)]
+        animations: Vec<Animation>,
+        #[serde(default)]
+        accessors: Vec<Accessor>,
+        #[serde(default)]
+        buffer_views: Vec<BufferView>,
+    }
+
+    #[derive(Deserialize)]
+    struct Node {
+        name: Option<String>,
+        #[serde(default)]
+        children: Vec<usize>,
+        matrix: Option<[f32; 16]>,
+        translation: Option<[f32; 3]>,
+        rotation: Option<[f32; 4]>,
+        scale: Option<[f32; 3]>,
+    }
+
+    #[derive(Deserialize)]
+    struct Animation {
+        name: Option<String>,
+        channels: Vec<Channel>,
+        samplers: Vec<Sampler>,
+    }
+
+    #[derive(Deserialize)]
+    struct Channel {
+        sampler: usize,
+        target: Target,
+    }
+
+    #[derive(Deserialize)]
+    struct Target {
+        node: Option<usize>,
+        path: String,
+    }
+
+    #[derive(Deserialize)]
+    struct Sampler {
+        input: usize,
+        output: usize,
+        interpolation: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Accessor {
+        buffer_view: Option<usize>,
+        #[serde(default)]
+        byte_offset: usize,
+        component_type: u32,
+        #[serde(default)]
+        normalized: bool,
+        count: usize,
+        #[serde(rename = "type")]
+        ty: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct BufferView {
+        buffer: usize,
+        #[serde(default)]
+        byte_offset: usize,
+        byte_length: usize,
+        byte_stride: Option<usize>,
+    }
+
+    /// Returns the JSON and binary chunks of the .glb
+    pub(super) fn read_glb(glb: &[u8]) -> Result<(Gltf, &[u8]), Error> {
+        let u32_at = |offset: usize| -> Option<usize> {
+            glb.get(offset..offset + 4)
+                .map(|x| u32::from_le_bytes(x.try_into().unwrap()) as usize)
+        };
+        if glb.get(0..4) != Some(b"glTF") {
+            bail!("Not a glTF binary, missing the glTF magic");
+        }
+        if u32_at(4) != Some(2) {
+            bail!("Only glTF 2.0 is supported");
+        }
+
+        let mut json = None;
+        let mut bin: &[u8] = &[];
+        let mut offset = 12;
+        while let (Some(len), Some(ty)) = (u32_at(offset), glb.get(offset + 4..offset + 8)) {
+            let Some(data) = glb.get(offset + 8..offset + 8 + len) else {
+                bail!(
+                    "glTF chunk at 0x{:x} extends past the end of the file",
+                    offset
+                );
+            };
+            match ty {
+                b"JSON" => json = Some(serde_json::from_slice(data)?),
+                b"BIN\0" => bin = data,
+                _ => {}
+            }
+            offset += 8 + len;
+        }
+
+        match json {
+            Some(json) => Ok((json, bin)),
+            None => bail!("glTF binary has no JSON chunk"),
+        }
+    }
+
+    impl Gltf {
+        /// Returns the values of the accessor, with each element flattened into its components.
+        fn accessor(&self, bin: &[u8], index: usize) -> Result<Vec<f32>, Error> {
+            let Some(accessor) = self.accessors.get(index) else {
+                bail!("Accessor {} does not exist", index);
+            };
+            let components = match accessor.ty.as_str() {
+                "SCALAR" => 1,
+                "VEC2" => 2,
+                "VEC3" => 3,
+                "VEC4" => 4,
+                "MAT4" => 16,
+                ty => bail!("Unsupported accessor type {}", ty),
+            };
+            let component_size = match accessor.component_type {
+                5120 | 5121 => 1,
+                5122 | 5123 => 2,
+                5125 | 5126 => 4,
+                ty => bail!("Unsupported accessor component type {}", ty),
+            };
+
+            let Some(view) = accessor.buffer_view.and_then(|x| self.buffer_views.get(x)) else {
+                // An accessor without a buffer view is all zeros
+                return Ok(vec![0.0; accessor.count * components]);
+            };
+            if view.buffer != 0 {
+                bail!("Only the buffer stored in the glTF binary is supported");
+            }
+            let stride = view.byte_stride.unwrap_or(components * component_size);
+            let Some(data) = bin.get(view.byte_offset..view.byte_offset + view.byte_length) else {
+                bail!("Buffer view extends past the end of the buffer");
+            };
+
+            let mut values = Vec::with_capacity(accessor.count * components);
+            for element in 0..accessor.count {
+                for component in 0..components {
+                    let offset =
+                        accessor.byte_offset + element * stride + component * component_size;
+                    let Some(bytes) = data.get(offset..offset + component_size) else {
+                        bail!("Accessor {} extends past the end of its buffer view", index);
+                    };
+                    let normalized = accessor.normalized;
+                    let value = match accessor.component_type {
+                        5120 if normalized => (bytes[0] as i8 as f32 / 127.0).max(-1.0),
+                        5120 => bytes[0] as i8 as f32,
+                        5121 if normalized => bytes[0] as f32 / 255.0,
+                        5121 => bytes[0] as f32,
+                        5122 => {
+                            let value = i16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+                            if normalized {
+                                (value / 32767.0).max(-1.0)
+                            } else {
+                                value
+                            }
+                        }
+                        5123 => {
+                            let value = u16::from_le_bytes([bytes[0], bytes[1]]) as f32;
+                            if normalized { value / 65535.0 } else { value }
+                        }
+                        5125 => u32::from_le_bytes(bytes.try_into().unwrap()) as f32,
+                        _ => f32::from_le_bytes(bytes.try_into().unwrap()),
+                    };
+                    values.push(value);
+                }
+            }
+            Ok(values)
+        }
+    }
+
+    pub(super) fn animations(gltf: &Gltf, bin: &[u8]) -> Result<Vec<ImportedAnimation>, Error> {
+        let mut parents = vec![None; gltf.nodes.len()];
+        for (i, node) in gltf.nodes.iter().enumerate() {
+            for child in &node.children {
+                if let Some(parent) = parents.get_mut(*child) {
+                    if parent.is_some() {
+                        bail!("Node {} is the child of more than one node", child);
+                    }
+                    *parent = Some(i);
+                }
+            }
+        }
+        // With one parent per node, the hierarchy is a tree as long as following the parents never loops
+        for i in 0..parents.len() {
+            let mut node = i;
+            for _ in 0..parents.len() {
+                match parents[node] {
+                    Some(parent) => node = parent,
+                    None => break,
+                }
+            }
+            if parents[node].is_some() {
+                bail!("Node {} is its own ancestor", i);
+            }
+        }
+
+        let mut animations = vec![];
+        for (i, animation) in gltf.animations.iter().enumerate() {
+            // the values of each channel, so they can be sampled at each frame
+            let mut channels = vec![];
+            let mut duration: f32 = 0.0;
+            for channel in &animation.channels {
+                let Some(node) = channel.target.node else {
+                    continue;
+                };
+                let Some(sampler) = animation.samplers.get(channel.sampler) else {
+                    bail!(
+                        "Animation channel refers to sampler {} which does not exist",
+                        channel.sampler
+                    );
+                };
+                let components = match channel.target.path.as_str() {
+                    "translation" | "scale" => 3,
+                    "rotation" => 4,
+                    // morph target weights cannot be stored in a CHR0
+                    _ => continue,
+                };
+                let times = gltf.accessor(bin, sampler.input)?;
+                let mut values = gltf.accessor(bin, sampler.output)?;
+                let interpolation = sampler.interpolation.as_deref().unwrap_or("LINEAR");
+                if interpolation == "CUBICSPLINE" {
+                    if values.len() != times.len() * components * 3 {
+                        bail!(
+                            "Cubic spline animation sampler does not have a value and two tangents for every key"
+                        );
+                    }
+                    // Each key stores an in tangent, the value and an out tangent.
+                    // Only the value is kept, the curve is linearly interpolated instead.
+                    values = values
+                        .chunks(components * 3)
+                        .flat_map(|x| x[components..components * 2].to_vec())
+                        .collect();
+                }
+                if times.is_empty() || values.len() < times.len() * components {
+                    bail!("Animation sampler does not have a value for every key");
+                }
+                duration = times.iter().cloned().fold(duration, f32::max);
+                channels.push((
+                    node,
+                    channel.target.path.as_str(),
+                    times,
+                    values,
+                    interpolation == "STEP",
+                ));
+            }
+
+            let num_frames = num_frames(duration);
+            let nodes = gltf
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(node_index, node)| {
+                    let node_channels: Vec<_> =
+                        channels.iter().filter(|x| x.0 == node_index).collect();
+                    let transforms = (0..num_frames)
+                        .map(|frame| {
+                            let time = frame as f32 / FRAMES_PER_SECOND;
+                            node_transform(node, &node_channels, time)
+                        })
+                        .collect();
+                    ImportedNode {
+                        name: node.name.clone().unwrap_or_default(),
+                        parent: parents[node_index],
+                        transforms,
+                    }
+                })
+                .collect();
+
+            animations.push(ImportedAnimation {
+                name: animation
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format!("Animation{}", i)),
+                num_frames,
+                nodes,
+            });
+        }
+        Ok(animations)
+    }
+
+    type ChannelValues<'a> = (usize, &'a str, Vec<f32>, Vec<f32>, bool);
+
+    /// Returns the transform of the node at `time`, channels override the nodes own transform
+    fn node_transform(node: &Node, channels: &[&ChannelValues], time: f32) -> Matrix4<f32> {
+        if let Some(matrix) = node.matrix
+            && channels.is_empty()
+        {
+            let matrix: &Matrix4<f32> = (&matrix).into();
+            return *matrix;
+        }
+
+        let mut translation = Vector3::from(node.translation.unwrap_or([0.0; 3]));
+        let [x, y, z, w] = node.rotation.unwrap_or([0.0, 0.0, 0.0, 1.0]);
+        let mut rotation = Quaternion::new(w, x, y, z);
+        let mut scale = Vector3::from(node.scale.unwrap_or([1.0; 3]));
+
+        for (_, path, times, values, step) in channels.iter().copied() {
+            let (prev, next, amount) = key_position(times, time);
+            let amount = if *step { 0.0 } else { amount };
+            match *path {
+                "rotation" => {
+                    let value = |key: usize| {
+                        let v = &values[key * 4..key * 4 + 4];
+                        Quaternion::new(v[3], v[0], v[1], v[2])
+                    };
+                    let (prev, mut next) = (value(prev), value(next));
+                    if prev.dot(next) < 0.0 {
+                        next = -next;
+                    }
+                    rotation = (prev * (1.0 - amount) + next * amount).normalize();
+                }
+                path => {
+                    let value = |key: usize| {
+                        Vector3::new(values[key * 3], values[key * 3 + 1], values[key * 3 + 2])
+                    };
+                    let value = value(prev) * (1.0 - amount) + value(next) * amount;
+                    if path == "translation" {
+                        translation = value;
+                    } else {
+                        scale = value;
+                    }
+                }
+            }
+        }
+
+        Matrix4::from_translation(translation)
+            * Matrix4::from(rotation)
+            * Matrix4::from_nonuniform_scale(scale.x, scale.y, scale.z)
+    }
+}
+
+mod collada {
+    use super::*;
+    use cgmath::{Matrix3, VectorSpace};
+    use roxmltree::{Document, Node};
+
+    pub(super) fn animation(dae: &str, name: &str) -> Result<ImportedAnimation, Error> {
+        let document = Document::parse(dae)?;
+        let root = document.root_element();
+
+        // Collect the animated matrix of each node, keyed by `<node id>/<matrix sid>`
+        let mut samplers: HashMap<String, (Vec<f32>, Vec<f32>)> = HashMap::new();
+        let mut duration: f32 = 0.0;
+        for channel in root.descendants().filter(|x| x.has_tag_name("channel")) {
+            let (Some(source), Some(target)) =
+                (channel.attribute("source"), channel.attribute("target"))
+            else {
+                continue;
+            };
+            let Some(sampler) = element_by_id(root, source) else {
+                bail!(
+                    "Collada channel refers to sampler {} which does not exist",
+                    source
+                );
+            };
+            let input = |semantic: &str| -> Result<Vec<f32>, Error> {
+                let Some(input) = sampler
+                    .children()
+                    .find(|x| x.has_tag_name("input") && x.attribute("semantic") == Some(semantic))
+                else {
+                    bail!("Collada sampler {} has no {} input", source, semantic);
+                };
+                let source = input.attribute("source").unwrap_or_default();
+                let Some(source) = element_by_id(root, source) else {
+                    bail!(
+                        "Collada sampler refers to source {} which does not exist",
+                        source
+                    );
+                };
+                float_array(source)
+            };
+            let times = input("INPUT")?;
+            let values = input("OUTPUT")?;
+            if values.len() != times.len() * 16 {
+                // Only baked matrices are supported, which is what Blender exports
+                warn!(
+                    "Skipping collada channel {} as it does not animate a matrix",
+                    target
+                );
+                continue;
+            }
+            duration = times.iter().cloned().fold(duration, f32::max);
+            samplers.insert(target.to_string(), (times, values));
+        }
+
+        let Some(scene) = root.descendants().find(|x| x.has_tag_name("visual_scene")) else {
+            bail!("Collada file has no visual scene");
+        };
+        let num_frames = num_frames(duration);
+        let mut nodes = vec![];
+        for node in scene.children().filter(|x| x.has_tag_name("node")) {
+            add_node(node, None, &samplers, num_frames, &mut nodes)?;
+        }
+
+        Ok(ImportedAnimation {
+            name: name.to_string(),
+            num_frames,
+            nodes,
+        })
+    }
+
+    fn add_node(
+        node: Node,
+        parent: Option<usize>,
+        samplers: &HashMap<String, (Vec<f32>, Vec<f32>)>,
+        num_frames: usize,
+        nodes: &mut Vec<ImportedNode>,
+    ) -> Result<(), Error> {
+        let id = node.attribute("id").unwrap_or_default();
+        let name = node.attribute("name").unwrap_or(id);
+
+        // The transform is the product of the transform elements in order
+        let mut transforms = vec![Matrix4::identity(); num_frames];
+        for element in node.children().filter(|x| x.is_element()) {
+            let values =
+                || -> Result<Vec<f32>, Error> { floats(element.text().unwrap_or_default()) };
+            let sid = element.attribute("sid").unwrap_or_default();
+            let animated = samplers.get(&format!("{}/{}", id, sid));
+            let element_transform = match element.tag_name().name() {
+                "matrix" => row_major(&values()?)?,
+                "translate" => {
+                    let v = values()?;
+                    if v.len() != 3 {
+                        bail!("Collada translate needs 3 values");
+                    }
+                    Matrix4::from_translation(Vector3::new(v[0], v[1], v[2]))
+                }
+                "rotate" => {
+                    let v = values()?;
+                    if v.len() != 4 {
+                        bail!("Collada rotate needs 4 values");
+                    }
+                    let axis = Vector3::new(v[0], v[1], v[2]);
+                    if axis.magnitude2() == 0.0 {
+                        Matrix4::identity()
+                    } else {
+                        Matrix4::from_axis_angle(axis.normalize(), cgmath::Deg(v[3]))
+                    }
+                }
+                "scale" => {
+                    let v = values()?;
+                    if v.len() != 3 {
+                        bail!("Collada scale needs 3 values");
+                    }
+                    Matrix4::from_nonuniform_scale(v[0], v[1], v[2])
+                }
+                _ => continue,
+            };
+
+            for (frame, transform) in transforms.iter_mut().enumerate() {
+                let element_transform = match animated {
+                    Some((times, values)) => {
+                        let time = frame as f32 / FRAMES_PER_SECOND;
+                        let (prev, next, amount) = key_position(times, time);
+                        let prev = row_major(&values[prev * 16..prev * 16 + 16])?;
+                        let next = row_major(&values[next * 16..next * 16 + 16])?;
+                        lerp_transform(prev, next, amount)
+                    }
+                    None => element_transform,
+                };
+                *transform = *transform * element_transform;
+            }
+        }
+
+        let index = nodes.len();
+        nodes.push(ImportedNode {
+            name: name.to_string(),
+            parent,
+            transforms,
+        });
+        for child in node.children().filter(|x| x.has_tag_name("node")) {
+            add_node(child, Some(index), samplers, num_frames, nodes)?;
+        }
+        Ok(())
+    }
+
+    /// Interpolates the translation, rotation and scale separately so the rotation does not shrink the transform
+    fn lerp_transform(prev: Matrix4<f32>, next: Matrix4<f32>, amount: f32) -> Matrix4<f32> {
+        if amount == 0.0 {
+            return prev;
+        }
+        let decompose = |transform: Matrix4<f32>| {
+            let scale = Vector3::new(
+                transform.x.truncate().magnitude(),
+                transform.y.truncate().magnitude(),
+                transform.z.truncate().magnitude(),
+            );
+            let rotation = Matrix3::from_cols(
+                transform.x.truncate() / scale.x,
+                transform.y.truncate() / scale.y,
+                transform.z.truncate() / scale.z,
+            );
+            (transform.w.truncate(), Quaternion::from(rotation), scale)
+        };
+        let (prev_translation, prev_rotation, prev_scale) = decompose(prev);
+        let (next_translation, mut next_rotation, next_scale) = decompose(next);
+        if prev_rotation.dot(next_rotation) < 0.0 {
+            next_rotation = -next_rotation;
+        }
+
+        let translation = prev_translation.lerp(next_translation, amount);
+        let rotation = (prev_rotation * (1.0 - amount) + next_rotation * amount).normalize();
+        let scale = prev_scale.lerp(next_scale, amount);
+        Matrix4::from_translation(translation)
+            * Matrix4::from(rotation)
+            * Matrix4::from_nonuniform_scale(scale.x, scale.y, scale.z)
+    }
+
+    /// Finds the element referred to by a `#id` url
+    fn element_by_id<'a, 'input>(root: Node<'a, 'input>, url: &str) -> Option<Node<'a, 'input>> {
+        let id = url.strip_prefix('#').unwrap_or(url);
+        root.descendants().find(|x| x.attribute("id") == Some(id))
+    }
+
+    fn float_array(source: Node) -> Result<Vec<f32>, Error> {
+        match source.children().find(|x| x.has_tag_name("float_array")) {
+            Some(array) => floats(array.text().unwrap_or_default()),
+            None => bail!("Collada source has no float_array"),
+        }
+    }
+
+    fn floats(text: &str) -> Result<Vec<f32>, Error> {
+        text.split_whitespace()
+            .map(|x| x.parse::<f32>().map_err(Error::from))
+            .collect()
+    }
+
+    /// Collada stores matrices row by row while cgmath stores them column by column
+    fn row_major(values: &[f32]) -> Result<Matrix4<f32>, Error> {
+        if values.len() != 16 {
+            bail!("Collada matrix needs 16 values");
+        }
+        let column =
+            |i: usize| Vector4::new(values[i], values[4 + i], values[8 + i], values[12 + i]);
+        Ok(Matrix4::from_cols(
+            column(0),
+            column(1),
+            column(2),
+            column(3),
+        ))
+    }
+}
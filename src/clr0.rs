@@ -0,0 +1,209 @@
+use fancy_slice::FancySlice;
+
+use crate::bres::RawBresChild;
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
+use crate::resources;
+use crate::util;
+
+/// Parses a CLR0, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_clr0(data: FancySlice) -> Result<Clr0, ParseError> {
+    check_len(data, 0x14, "CLR0")?;
+    let _size             = data.i32_be(0x4);
+    let version           = data.i32_be(0x8);
+    let _bres_offset      = data.i32_be(0xc);
+    let resources_offset  = data.i32_be(0x10);
+    // version 4 adds a user data offset, shifting every following field along
+    let offset = match version {
+        3 => 0x14,
+        4 => 0x18,
+        _ => return Err(ParseError::new("CLR0", 0, ParseErrorKind::UnknownVersion(version))),
+    };
+    check_len(data, offset + 0x10, "CLR0")?;
+    let string_offset     = data.i32_be(offset);
+    let _orig_path_offset = data.i32_be(offset + 0x4);
+    let num_frames        = data.u16_be(offset + 0x8);
+    let _num_children     = data.u16_be(offset + 0xa);
+    let loop_value        = data.i32_be(offset + 0xc);
+
+    let name = try_str(&data, string_offset as usize, "CLR0 name")
+        .map_err(|err| err.offset_by(string_offset as usize))?
+        .to_string();
+
+    let resources_data = try_offset(&data, resources_offset as i64, 0, "CLR0")?;
+    let resources = resources::try_resources(resources_data)
+        .map_err(|err| err.offset_by(resources_offset as usize))?;
+
+    let mut materials = vec!();
+    for resource in resources {
+        let material_offset = resources_offset as i64 + resource.data_offset as i64;
+        let material_data = try_offset(&data, material_offset, CLR0_MATERIAL_SIZE, "CLR0 material")?;
+        let flags = material_data.u32_be(0x4);
+
+        let mut targets = vec!();
+        let mut target_offset = CLR0_MATERIAL_SIZE;
+        for index in 0..CLR0_TARGETS {
+            let target_flags = flags >> (index * 2);
+            let exists   = target_flags & 1 != 0;
+            let constant = target_flags & 2 != 0;
+            if exists {
+                check_len(material_data, target_offset + CLR0_TARGET_SIZE, "CLR0 material")
+                    .map_err(|err| err.offset_by(material_offset as usize))?;
+                let mask = rgba(material_data, target_offset);
+                let colors = if constant {
+                    Clr0Colors::Constant(rgba(material_data, target_offset + 4))
+                } else {
+                    // The offset is relative to itself
+                    let colors_offset = (target_offset + 4) as i64 + material_data.i32_be(target_offset + 4) as i64;
+                    let colors_data = try_offset(&material_data, colors_offset, num_frames as usize * 4, "CLR0 colors")
+                        .map_err(|err| err.offset_by(material_offset as usize))?;
+                    let colors = (0..num_frames as usize)
+                        .map(|i| rgba(colors_data, i * 4))
+                        .collect();
+                    Clr0Colors::Animated(colors)
+                };
+                targets.push(Clr0Target {
+                    target: Clr0TargetType::new(index),
+                    mask,
+                    colors,
+                });
+                target_offset += CLR0_TARGET_SIZE;
+            }
+        }
+
+        materials.push(Clr0Material {
+            name: resource.string,
+            targets,
+        });
+    }
+
+    let raw = RawBresChild::animation(data, resources_offset as usize, offset, 0x0, offset != 0x14, "CLR0")?;
+
+    Ok(Clr0 {
+        name,
+        num_frames,
+        loop_value: loop_value != 0,
+        materials,
+        raw,
+    })
+}
+
+fn rgba(data: FancySlice, offset: usize) -> [u8; 4] {
+    [
+        data.u8(offset),
+        data.u8(offset + 1),
+        data.u8(offset + 2),
+        data.u8(offset + 3),
+    ]
+}
+
+/// Material color animation
+#[derive(Clone, Debug)]
+pub struct Clr0 {
+    name: String,
+    num_frames: u16,
+    loop_value: bool,
+    materials: Vec<Clr0Material>,
+    raw: RawBresChild,
+}
+
+impl Clr0 {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn num_frames(&self) -> u16 {
+        self.num_frames
+    }
+
+    pub fn loop_value(&self) -> bool {
+        self.loop_value
+    }
+
+    pub fn materials(&self) -> &[Clr0Material] {
+        &self.materials
+    }
+
+    /// Returns the bytes the CLR0 was parsed from, only the names are rewritten to point into the new string table.
+    pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
+        self.raw.compile(bres_offset)
+    }
+}
+
+const CLR0_MATERIAL_SIZE: usize = 0x8;
+const CLR0_TARGETS: u32 = 11;
+#[derive(Clone, Debug)]
+pub struct Clr0Material {
+    /// Name of the MDL0 material being animated
+    pub name: String,
+    pub targets: Vec<Clr0Target>,
+}
+
+const CLR0_TARGET_SIZE: usize = 0x8;
+#[derive(Clone, Debug)]
+pub struct Clr0Target {
+    pub target: Clr0TargetType,
+    /// Bits set here are kept from the materials original color, the rest are taken from the animation.
+    pub mask: [u8; 4],
+    pub colors: Clr0Colors,
+}
+
+impl Clr0Target {
+    /// Returns the RGBA color of the animation at the specified frame
+    pub fn get_color(&self, loop_value: bool, frame: i32) -> [u8; 4] {
+        match &self.colors {
+            Clr0Colors::Constant(color) => *color,
+            Clr0Colors::Animated(colors) => {
+                colors[util::frame_index(loop_value, frame, colors.len())]
+            }
+        }
+    }
+
+    /// Combines the materials original RGBA color with the color of the animation at the specified frame
+    pub fn apply(&self, original: [u8; 4], loop_value: bool, frame: i32) -> [u8; 4] {
+        let color = self.get_color(loop_value, frame);
+        std::array::from_fn(|i| (original[i] & self.mask[i]) | (color[i] & !self.mask[i]))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Clr0Colors {
+    Constant([u8; 4]),
+    /// One color per frame
+    Animated(Vec<[u8; 4]>),
+}
+
+/// The material color being animated
+#[derive(Clone, Debug)]
+pub enum Clr0TargetType {
+    LightChannel0MaterialColor,
+    LightChannel0AmbientColor,
+    LightChannel1MaterialColor,
+    LightChannel1AmbientColor,
+    TevColorRegister0,
+    TevColorRegister1,
+    TevColorRegister2,
+    TevKonstantColorRegister0,
+    TevKonstantColorRegister1,
+    TevKonstantColorRegister2,
+    TevKonstantColorRegister3,
+}
+
+impl Clr0TargetType {
+    fn new(index: u32) -> Clr0TargetType {
+        match index {
+            0 => Clr0TargetType::LightChannel0MaterialColor,
+            1 => Clr0TargetType::LightChannel0AmbientColor,
+            2 => Clr0TargetType::LightChannel1MaterialColor,
+            3 => Clr0TargetType::LightChannel1AmbientColor,
+            4 => Clr0TargetType::TevColorRegister0,
+            5 => Clr0TargetType::TevColorRegister1,
+            6 => Clr0TargetType::TevColorRegister2,
+            7 => Clr0TargetType::TevKonstantColorRegister0,
+            8 => Clr0TargetType::TevKonstantColorRegister1,
+            9 => Clr0TargetType::TevKonstantColorRegister2,
+            10 => Clr0TargetType::TevKonstantColorRegister3,
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,650 @@
+use cgmath::Vector2;
+use fancy_slice::FancySlice;
+
+use crate::high_level_fighter::Extent;
+use crate::parse_error::{ParseError, ParseErrorKind, check_len};
+
+/// COLL files have no tag, so they are identified by their header
+pub(crate) fn is_coll(data: FancySlice) -> bool {
+    if data.len() < COLL_HEADER_SIZE {
+        return false;
+    }
+    let num_vertices = data.i32_be(0x00) as i64;
+    let vertex_offset = data.i32_be(0x04) as i64;
+    let num_planes = data.i32_be(0x08) as i64;
+    let plane_offset = data.i32_be(0x0c) as i64;
+    let num_objects = data.i32_be(0x10) as i64;
+    let object_offset = data.i32_be(0x14) as i64;
+
+    vertex_offset == COLL_HEADER_SIZE as i64
+        && num_vertices >= 0
+        && num_planes >= 0
+        && num_objects >= 0
+        && plane_offset >= vertex_offset + num_vertices * COLL_VERTEX_SIZE as i64
+        && object_offset >= plane_offset + num_planes * COLL_PLANE_SIZE as i64
+        && object_offset + num_objects * COLL_OBJECT_SIZE as i64 <= data.len() as i64
+}
+
+/// Parses a COLL, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_coll(data: FancySlice) -> Result<Coll, ParseError> {
+    check_len(data, COLL_HEADER_SIZE, "COLL")?;
+    if !is_coll(data) {
+        return Err(ParseError::new("COLL", 0, ParseErrorKind::Malformed(String::from("Invalid COLL header"))));
+    }
+    let num_vertices  = data.i32_be(0x00) as usize;
+    let vertex_offset = data.i32_be(0x04) as usize;
+    let num_planes    = data.i32_be(0x08) as usize;
+    let plane_offset  = data.i32_be(0x0c) as usize;
+    let num_objects   = data.i32_be(0x10) as usize;
+    let object_offset = data.i32_be(0x14) as usize;
+
+    let vertices = (0..num_vertices).map(|i| {
+        let vertex = vertex_offset + i * COLL_VERTEX_SIZE;
+        Vector2::new(data.f32_be(vertex), data.f32_be(vertex + 4))
+    }).collect();
+
+    let mut planes = vec!();
+    for i in 0..num_planes {
+        let plane = data.relative_fancy_slice(plane_offset + i * COLL_PLANE_SIZE..);
+        let link = |value: i16| if value < 0 { None } else { Some(value as usize) };
+        planes.push(CollisionPlane {
+            vertex0:    plane.u16_be(0x0) as usize,
+            vertex1:    plane.u16_be(0x2) as usize,
+            link_prev:  link(plane.i16_be(0x4)),
+            link_next:  link(plane.i16_be(0x6)),
+            unk0:       plane.i32_be(0x8),
+            ty:         CollisionPlaneType::from_bits_retain(plane.u16_be(0xc)),
+            flags:      CollisionPlaneFlags::from_bits_retain(plane.u8(0xe)),
+            material:   CollisionMaterial::new(plane.u8(0xf)),
+        });
+    }
+
+    let mut objects = vec!();
+    for i in 0..num_objects {
+        let object = data.relative_fancy_slice(object_offset + i * COLL_OBJECT_SIZE..);
+        let name = |offset: usize| {
+            let bytes = object.relative_slice(offset..offset + COLL_OBJECT_NAME_SIZE);
+            let len = bytes.iter().position(|x| *x == 0).unwrap_or(bytes.len());
+            String::from_utf8_lossy(&bytes[..len]).to_string()
+        };
+        objects.push(CollisionObject {
+            plane_index:  object.u16_be(0x00) as usize,
+            plane_count:  object.u16_be(0x02) as usize,
+            unk0:         object.i32_be(0x04),
+            unk1:         object.i32_be(0x08),
+            unk2:         object.i32_be(0x0c),
+            flags:        object.u16_be(0x10),
+            unk3:         object.u16_be(0x12),
+            bounds_min:   Vector2::new(object.f32_be(0x14), object.f32_be(0x18)),
+            bounds_max:   Vector2::new(object.f32_be(0x1c), object.f32_be(0x20)),
+            vertex_index: object.u16_be(0x24) as usize,
+            vertex_count: object.u16_be(0x26) as usize,
+            unk4:         object.u16_be(0x28),
+            bone_index:   object.i16_be(0x2a),
+            model_name:   name(0x2c),
+            bone_name:    name(0x2c + COLL_OBJECT_NAME_SIZE),
+        });
+    }
+
+    for (i, plane) in planes.iter().enumerate() {
+        if plane.vertex0 >= num_vertices || plane.vertex1 >= num_vertices {
+            return Err(ParseError::new(
+                "COLL plane",
+                plane_offset + i * COLL_PLANE_SIZE,
+                ParseErrorKind::Malformed(format!("Vertex index out of range, there are only {num_vertices} vertices")),
+            ));
+        }
+    }
+
+    let vertices_end = vertex_offset + num_vertices * COLL_VERTEX_SIZE;
+    let planes_end   = plane_offset  + num_planes   * COLL_PLANE_SIZE;
+    let objects_end  = object_offset + num_objects  * COLL_OBJECT_SIZE;
+
+    Ok(Coll {
+        unk0:           data.i32_be(0x18),
+        unk1:           data.i32_be(0x1c),
+        unk2:           data.i32_be(0x20),
+        unk3:           data.i32_be(0x24),
+        vertex_padding: data.relative_slice(vertices_end..plane_offset).to_vec(),
+        plane_padding:  data.relative_slice(planes_end..object_offset).to_vec(),
+        object_padding: data.relative_slice(objects_end..).to_vec(),
+        vertices,
+        planes,
+        objects,
+    })
+}
+
+const COLL_HEADER_SIZE: usize = 0x28;
+/// The collision of a stage, the surfaces that fighters and items can stand on and bump into.
+#[derive(Clone, Debug)]
+pub struct Coll {
+    pub vertices: Vec<Vector2<f32>>,
+    /// Each plane is a line between two vertices
+    pub planes: Vec<CollisionPlane>,
+    /// Groups of planes that move together e.g. a moving platform
+    pub objects: Vec<CollisionObject>,
+    pub unk0: i32,
+    pub unk1: i32,
+    pub unk2: i32,
+    pub unk3: i32,
+    /// Bytes between the vertices and the planes, written back unchanged when compiling
+    pub vertex_padding: Vec<u8>,
+    /// Bytes between the planes and the objects, written back unchanged when compiling
+    pub plane_padding: Vec<u8>,
+    /// Bytes after the objects, written back unchanged when compiling
+    pub object_padding: Vec<u8>,
+}
+
+impl Coll {
+    /// The floors that can be dropped through by pressing down
+    pub fn drop_through_planes(&self) -> impl Iterator<Item = &CollisionPlane> {
+        self.planes.iter().filter(|x| {
+            x.ty.contains(CollisionPlaneType::FLOOR)
+                && x.flags.contains(CollisionPlaneFlags::DROP_THROUGH)
+        })
+    }
+
+    /// The end points of floors that can be grabbed
+    pub fn ledges(&self) -> Vec<Ledge> {
+        let mut ledges = vec![];
+        for (i, plane) in self.planes.iter().enumerate() {
+            let (left, right) = self.plane_left_right(plane);
+            if plane.flags.contains(CollisionPlaneFlags::LEFT_LEDGE) {
+                ledges.push(Ledge {
+                    plane: i,
+                    side: LedgeSide::Left,
+                    x: left.x,
+                    y: left.y,
+                });
+            }
+            if plane.flags.contains(CollisionPlaneFlags::RIGHT_LEDGE) {
+                ledges.push(Ledge {
+                    plane: i,
+                    side: LedgeSide::Right,
+                    x: right.x,
+                    y: right.y,
+                });
+            }
+        }
+        ledges
+    }
+
+    /// The height of the highest floor at `x`.
+    /// The vertices of objects attached to a bone are used as is, without applying the bones transform.
+    pub fn ground_height(&self, x: f32) -> Option<f32> {
+        self.planes
+            .iter()
+            .filter(|plane| plane.ty.contains(CollisionPlaneType::FLOOR))
+            .filter_map(|plane| {
+                let (left, right) = self.plane_left_right(plane);
+                if x < left.x || x > right.x {
+                    None
+                } else if right.x == left.x {
+                    Some(left.y.max(right.y))
+                } else {
+                    Some(left.y + (right.y - left.y) * (x - left.x) / (right.x - left.x))
+                }
+            })
+            .reduce(f32::max)
+    }
+
+    /// The ledge that a `HighLevelFrame::ledge_grab_box` would grab when the fighter is at `x_pos`, `y_pos`.
+    /// A fighter facing right grabs left ledges and a fighter facing left grabs right ledges.
+    pub fn grabbable_ledge(
+        &self,
+        ledge_grab_box: &Extent,
+        x_pos: f32,
+        y_pos: f32,
+        facing_right: bool,
+    ) -> Option<Ledge> {
+        // the ledge grab box is relative to the fighter facing right
+        let (left, right) = if facing_right {
+            (x_pos + ledge_grab_box.left, x_pos + ledge_grab_box.right)
+        } else {
+            (x_pos - ledge_grab_box.right, x_pos - ledge_grab_box.left)
+        };
+        let side = if facing_right {
+            LedgeSide::Left
+        } else {
+            LedgeSide::Right
+        };
+        self.ledges().into_iter().find(|ledge| {
+            ledge.side == side
+                && ledge.x >= left
+                && ledge.x <= right
+                && ledge.y >= y_pos + ledge_grab_box.down
+                && ledge.y <= y_pos + ledge_grab_box.up
+        })
+    }
+
+    /// Returns the vertices of the plane ordered from left to right
+    pub fn plane_left_right(&self, plane: &CollisionPlane) -> (Vector2<f32>, Vector2<f32>) {
+        let a = self.vertices[plane.vertex0];
+        let b = self.vertices[plane.vertex1];
+        if a.x <= b.x { (a, b) } else { (b, a) }
+    }
+
+    pub fn compile(&self) -> Vec<u8> {
+        let vertex_offset = COLL_HEADER_SIZE;
+        let plane_offset =
+            vertex_offset + self.vertices.len() * COLL_VERTEX_SIZE + self.vertex_padding.len();
+        let object_offset =
+            plane_offset + self.planes.len() * COLL_PLANE_SIZE + self.plane_padding.len();
+
+        let mut output = Vec::with_capacity(
+            object_offset + self.objects.len() * COLL_OBJECT_SIZE + self.object_padding.len(),
+        );
+        output.extend(i32::to_be_bytes(self.vertices.len() as i32));
+        output.extend(i32::to_be_bytes(vertex_offset as i32));
+        output.extend(i32::to_be_bytes(self.planes.len() as i32));
+        output.extend(i32::to_be_bytes(plane_offset as i32));
+        output.extend(i32::to_be_bytes(self.objects.len() as i32));
+        output.extend(i32::to_be_bytes(object_offset as i32));
+        output.extend(i32::to_be_bytes(self.unk0));
+        output.extend(i32::to_be_bytes(self.unk1));
+        output.extend(i32::to_be_bytes(self.unk2));
+        output.extend(i32::to_be_bytes(self.unk3));
+
+        for vertex in &self.vertices {
+            output.extend(f32::to_be_bytes(vertex.x));
+            output.extend(f32::to_be_bytes(vertex.y));
+        }
+        output.extend(&self.vertex_padding);
+
+        let link = |value: Option<usize>| value.map(|x| x as i16).unwrap_or(-1);
+        for plane in &self.planes {
+            output.extend(u16::to_be_bytes(plane.vertex0 as u16));
+            output.extend(u16::to_be_bytes(plane.vertex1 as u16));
+            output.extend(i16::to_be_bytes(link(plane.link_prev)));
+            output.extend(i16::to_be_bytes(link(plane.link_next)));
+            output.extend(i32::to_be_bytes(plane.unk0));
+            output.extend(u16::to_be_bytes(plane.ty.bits()));
+            output.push(plane.flags.bits());
+            output.push(plane.material.value());
+        }
+        output.extend(&self.plane_padding);
+
+        for object in &self.objects {
+            output.extend(u16::to_be_bytes(object.plane_index as u16));
+            output.extend(u16::to_be_bytes(object.plane_count as u16));
+            output.extend(i32::to_be_bytes(object.unk0));
+            output.extend(i32::to_be_bytes(object.unk1));
+            output.extend(i32::to_be_bytes(object.unk2));
+            output.extend(u16::to_be_bytes(object.flags));
+            output.extend(u16::to_be_bytes(object.unk3));
+            output.extend(f32::to_be_bytes(object.bounds_min.x));
+            output.extend(f32::to_be_bytes(object.bounds_min.y));
+            output.extend(f32::to_be_bytes(object.bounds_max.x));
+            output.extend(f32::to_be_bytes(object.bounds_max.y));
+            output.extend(u16::to_be_bytes(object.vertex_index as u16));
+            output.extend(u16::to_be_bytes(object.vertex_count as u16));
+            output.extend(u16::to_be_bytes(object.unk4));
+            output.extend(i16::to_be_bytes(object.bone_index));
+            for name in [&object.model_name, &object.bone_name] {
+                let start = output.len();
+                output.extend(name.bytes().take(COLL_OBJECT_NAME_SIZE));
+                output.resize(start + COLL_OBJECT_NAME_SIZE, 0);
+            }
+        }
+        output.extend(&self.object_padding);
+
+        output
+    }
+}
+
+const COLL_VERTEX_SIZE: usize = 0x8;
+
+const COLL_PLANE_SIZE: usize = 0x10;
+#[derive(Clone, Debug)]
+pub struct CollisionPlane {
+    /// Index into `Coll::vertices`
+    pub vertex0: usize,
+    /// Index into `Coll::vertices`
+    pub vertex1: usize,
+    /// The plane connected to `vertex0`, index into `Coll::planes`
+    pub link_prev: Option<usize>,
+    /// The plane connected to `vertex1`, index into `Coll::planes`
+    pub link_next: Option<usize>,
+    pub unk0: i32,
+    pub ty: CollisionPlaneType,
+    pub flags: CollisionPlaneFlags,
+    pub material: CollisionMaterial,
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[rustfmt::skip]
+    pub struct CollisionPlaneType: u16 {
+        const FLOOR             = 0x1;
+        const CEILING           = 0x2;
+        const RIGHT_WALL        = 0x4;
+        const LEFT_WALL         = 0x8;
+        /// Fighters collide with the plane
+        const CHARACTERS        = 0x10;
+        /// Items collide with the plane
+        const ITEMS             = 0x20;
+        /// The Pokemon Trainer collides with the plane
+        const POKEMON_TRAINER   = 0x40;
+        const UNKNOWN_STAGE_BOX = 0x80;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    #[rustfmt::skip]
+    pub struct CollisionPlaneFlags: u8 {
+        /// Fighters can fall through the floor by pressing down
+        const DROP_THROUGH = 0x1;
+        const UNKNOWN1     = 0x2;
+        const UNKNOWN2     = 0x4;
+        const ROTATING     = 0x8;
+        const UNKNOWN3     = 0x10;
+        /// The left end of the floor can be grabbed
+        const LEFT_LEDGE   = 0x20;
+        /// The right end of the floor can be grabbed
+        const RIGHT_LEDGE  = 0x40;
+        /// Fighters cannot wall jump off the wall
+        const NO_WALLJUMP  = 0x80;
+    }
+}
+
+/// Determines the sound effects and particles of fighters interacting with the plane
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum CollisionMaterial {
+    Basic,
+    Rock,
+    Grass,
+    Soil,
+    Wood,
+    LightMetal,
+    HeavyMetal,
+    Carpet,
+    Alien,
+    MasterFortress,
+    Unknown(u8),
+}
+
+impl CollisionMaterial {
+    fn new(value: u8) -> CollisionMaterial {
+        match value {
+            0x00 => CollisionMaterial::Basic,
+            0x01 => CollisionMaterial::Rock,
+            0x02 => CollisionMaterial::Grass,
+            0x03 => CollisionMaterial::Soil,
+            0x04 => CollisionMaterial::Wood,
+            0x05 => CollisionMaterial::LightMetal,
+            0x06 => CollisionMaterial::HeavyMetal,
+            0x07 => CollisionMaterial::Carpet,
+            0x08 => CollisionMaterial::Alien,
+            0x09 => CollisionMaterial::MasterFortress,
+            _ => CollisionMaterial::Unknown(value),
+        }
+    }
+
+    fn value(&self) -> u8 {
+        match self {
+            CollisionMaterial::Basic => 0x00,
+            CollisionMaterial::Rock => 0x01,
+            CollisionMaterial::Grass => 0x02,
+            CollisionMaterial::Soil => 0x03,
+            CollisionMaterial::Wood => 0x04,
+            CollisionMaterial::LightMetal => 0x05,
+            CollisionMaterial::HeavyMetal => 0x06,
+            CollisionMaterial::Carpet => 0x07,
+            CollisionMaterial::Alien => 0x08,
+            CollisionMaterial::MasterFortress => 0x09,
+            CollisionMaterial::Unknown(value) => *value,
+        }
+    }
+}
+
+const COLL_OBJECT_SIZE: usize = 0x6c;
+const COLL_OBJECT_NAME_SIZE: usize = 0x20;
+#[derive(Clone, Debug)]
+pub struct CollisionObject {
+    /// Index of the first plane in `Coll::planes`
+    pub plane_index: usize,
+    pub plane_count: usize,
+    pub unk0: i32,
+    pub unk1: i32,
+    pub unk2: i32,
+    pub flags: u16,
+    pub unk3: u16,
+    pub bounds_min: Vector2<f32>,
+    pub bounds_max: Vector2<f32>,
+    /// Index of the first vertex in `Coll::vertices`
+    pub vertex_index: usize,
+    pub vertex_count: usize,
+    pub unk4: u16,
+    /// The bone in `model_name` that the planes move with, the vertices are relative to this bone.
+    /// -1 if the planes do not move.
+    pub bone_index: i16,
+    pub model_name: String,
+    pub bone_name: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LedgeSide {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug)]
+pub struct Ledge {
+    /// Index into `Coll::planes` of the floor the ledge belongs to
+    pub plane: usize,
+    pub side: LedgeSide,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::arc::tests::arc_fixture;
+    use crate::arc::{ArcChildData, try_arc};
+    use crate::wii_memory::WiiMemory;
+
+    /// A COLL with a main floor that has both ledges, a drop through platform above it and a wall below the right ledge.
+    /// The unknown fields and the padding between and after the sections are filled in so they can be checked after compiling.
+    pub(crate) fn coll_fixture() -> Vec<u8> {
+        let vertices = [
+            (-10.0, 0.0),
+            (10.0, 0.0),
+            (-5.0, 10.0),
+            (5.0, 10.0),
+            (10.0, -5.0),
+        ];
+        // vertex0, vertex1, link_prev, link_next, unk0, type, flags, material
+        let planes = [
+            (0u16, 1u16, -1i16, 2i16, -1i32, 0x11u16, 0x60u8, 0x01u8),
+            (2, 3, -1, -1, 0x12345678, 0x11, 0x01, 0x00),
+            (1, 4, 0, -1, -1, 0x14, 0x00, 0x05),
+        ];
+
+        let mut output = vec![];
+        output.extend(i32::to_be_bytes(vertices.len() as i32));
+        output.extend(i32::to_be_bytes(0x28)); // vertex_offset
+        output.extend(i32::to_be_bytes(planes.len() as i32));
+        output.extend(i32::to_be_bytes(0x50)); // plane_offset
+        output.extend(i32::to_be_bytes(1));
+        output.extend(i32::to_be_bytes(0x88)); // object_offset
+        for unk in [1, 2, 3, 4] {
+            output.extend(i32::to_be_bytes(unk));
+        }
+
+        for (x, y) in vertices {
+            output.extend(f32::to_be_bytes(x));
+            output.extend(f32::to_be_bytes(y));
+        }
+
+        for (vertex0, vertex1, link_prev, link_next, unk0, ty, flags, material) in planes {
+            output.extend(u16::to_be_bytes(vertex0));
+            output.extend(u16::to_be_bytes(vertex1));
+            output.extend(i16::to_be_bytes(link_prev));
+            output.extend(i16::to_be_bytes(link_next));
+            output.extend(i32::to_be_bytes(unk0));
+            output.extend(u16::to_be_bytes(ty));
+            output.push(flags);
+            output.push(material);
+        }
+        output.extend([0xcc; 8]); // plane padding
+
+        // object
+        output.extend(u16::to_be_bytes(0)); // plane_index
+        output.extend(u16::to_be_bytes(3)); // plane_count
+        output.extend(i32::to_be_bytes(5));
+        output.extend(i32::to_be_bytes(6));
+        output.extend(i32::to_be_bytes(7));
+        output.extend(u16::to_be_bytes(0x8)); // flags
+        output.extend(u16::to_be_bytes(9));
+        for bound in [-10.0f32, -5.0, 10.0, 10.0] {
+            output.extend(f32::to_be_bytes(bound));
+        }
+        output.extend(u16::to_be_bytes(0)); // vertex_index
+        output.extend(u16::to_be_bytes(5)); // vertex_count
+        output.extend(u16::to_be_bytes(10));
+        output.extend(i16::to_be_bytes(-1)); // bone_index
+        for name in ["model", "bone"] {
+            let start = output.len();
+            output.extend(name.as_bytes());
+            output.resize(start + COLL_OBJECT_NAME_SIZE, 0);
+        }
+        output.extend([0xdd; 4]); // object padding
+        output
+    }
+
+    #[test]
+    fn parse_coll() {
+        let data = coll_fixture();
+        let coll = try_coll(FancySlice::new(&data)).unwrap();
+
+        assert_eq!(coll.vertices.len(), 5);
+        assert_eq!(coll.planes.len(), 3);
+        assert_eq!(coll.planes[0].link_next, Some(2));
+        assert_eq!(coll.planes[1].link_prev, None);
+        assert_eq!(coll.planes[1].unk0, 0x12345678);
+        assert_eq!(coll.planes[2].material, CollisionMaterial::LightMetal);
+        assert_eq!(coll.objects.len(), 1);
+        assert_eq!(coll.objects[0].model_name, "model");
+        assert_eq!(coll.objects[0].bone_name, "bone");
+        assert_eq!([coll.unk0, coll.unk1, coll.unk2, coll.unk3], [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn compile_unmodified_coll() {
+        let data = coll_fixture();
+        let coll = try_coll(FancySlice::new(&data)).unwrap();
+        assert_eq!(coll.compile(), data);
+    }
+
+    #[test]
+    fn coll_invalid_vertex_index() {
+        let mut data = coll_fixture();
+        // vertex1 of the second plane
+        data[0x50 + COLL_PLANE_SIZE + 2..0x50 + COLL_PLANE_SIZE + 4].copy_from_slice(&[0, 5]);
+        assert!(is_coll(FancySlice::new(&data)));
+        assert!(try_coll(FancySlice::new(&data)).is_err());
+    }
+
+    #[test]
+    fn ground_height() {
+        let data = coll_fixture();
+        let coll = try_coll(FancySlice::new(&data)).unwrap();
+
+        assert_eq!(coll.ground_height(-7.0), Some(0.0));
+        // the platform is above the main floor
+        assert_eq!(coll.ground_height(0.0), Some(10.0));
+        assert_eq!(coll.ground_height(5.0), Some(10.0));
+        // walls are not floors
+        assert_eq!(coll.ground_height(10.0), Some(0.0));
+        assert_eq!(coll.ground_height(11.0), None);
+    }
+
+    #[test]
+    fn ledges() {
+        let data = coll_fixture();
+        let coll = try_coll(FancySlice::new(&data)).unwrap();
+
+        let ledges = coll.ledges();
+        assert_eq!(ledges.len(), 2);
+        assert_eq!(ledges[0].plane, 0);
+        assert_eq!(ledges[0].side, LedgeSide::Left);
+        assert_eq!((ledges[0].x, ledges[0].y), (-10.0, 0.0));
+        assert_eq!(ledges[1].plane, 0);
+        assert_eq!(ledges[1].side, LedgeSide::Right);
+        assert_eq!((ledges[1].x, ledges[1].y), (10.0, 0.0));
+    }
+
+    #[test]
+    fn grabbable_ledge() {
+        let data = coll_fixture();
+        let coll = try_coll(FancySlice::new(&data)).unwrap();
+        let ledge_grab_box = Extent {
+            left: 0.0,
+            right: 4.0,
+            up: 2.0,
+            down: -2.0,
+        };
+
+        // facing right, below and to the left of the left ledge
+        let ledge = coll
+            .grabbable_ledge(&ledge_grab_box, -12.0, -1.0, true)
+            .unwrap();
+        assert_eq!(ledge.side, LedgeSide::Left);
+        assert_eq!(ledge.x, -10.0);
+
+        // facing left, below and to the right of the right ledge
+        let ledge = coll
+            .grabbable_ledge(&ledge_grab_box, 12.0, -1.0, false)
+            .unwrap();
+        assert_eq!(ledge.side, LedgeSide::Right);
+        assert_eq!(ledge.x, 10.0);
+
+        // facing away from the ledge
+        assert!(
+            coll.grabbable_ledge(&ledge_grab_box, -12.0, -1.0, false)
+                .is_none()
+        );
+        // too far below the ledge
+        assert!(
+            coll.grabbable_ledge(&ledge_grab_box, -12.0, -5.0, true)
+                .is_none()
+        );
+        // the ledge grab box does not reach the ledge
+        assert!(
+            coll.grabbable_ledge(&ledge_grab_box, -15.0, -1.0, true)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn drop_through_planes() {
+        let data = coll_fixture();
+        let coll = try_coll(FancySlice::new(&data)).unwrap();
+
+        let planes: Vec<_> = coll.drop_through_planes().collect();
+        assert_eq!(planes.len(), 1);
+        assert_eq!((planes[0].vertex0, planes[0].vertex1), (2, 3));
+    }
+
+    #[test]
+    fn arc_keeps_invalid_coll() {
+        let coll = coll_fixture();
+        let mut invalid_coll = coll_fixture();
+        // vertex1 of the second plane is out of range, but the header still looks like a COLL
+        invalid_coll[0x50 + COLL_PLANE_SIZE + 2..0x50 + COLL_PLANE_SIZE + 4]
+            .copy_from_slice(&[0, 5]);
+        let data = arc_fixture("STGTEST", &[&coll, &invalid_coll]);
+
+        let arc = try_arc(FancySlice::new(&data), &WiiMemory::new(), false).unwrap();
+        assert!(matches!(arc.children[0].data, ArcChildData::Coll(_)));
+        let ArcChildData::Unknown(raw) = &arc.children[1].data else {
+            panic!("expected the invalid COLL to be kept as unknown data");
+        };
+        assert_eq!(raw, &invalid_coll);
+
+        assert_eq!(arc.compile().unwrap(), data);
+    }
+}
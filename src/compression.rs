@@ -1,5 +1,9 @@
 use fancy_slice::FancySlice;
 
+use crate::parse_error::{ParseError, ParseErrorKind, check_len};
+
+use std::collections::HashMap;
+
 #[derive(Debug)]
 enum CompressionType {
     None,
@@ -9,54 +13,146 @@ enum CompressionType {
 }
 
 impl CompressionType {
-    fn new(value: u8) -> Self {
+    fn new(value: u8) -> Result<Self, ParseError> {
         match value {
-            0b00000 => CompressionType::None,
-            0b10000 => CompressionType::LZ77,
-            0b10001 => CompressionType::ExtendedLZ77,
-            _ => panic!("Unknown compression type {value}"),
+            0b00000 => Ok(CompressionType::None),
+            0b10000 => Ok(CompressionType::LZ77),
+            0b10001 => Ok(CompressionType::ExtendedLZ77),
+            _ => Err(ParseError::new(
+                "compression header",
+                0,
+                ParseErrorKind::UnknownCompressionType(value),
+            )),
         }
     }
 }
 
+/// Decompresses data compressed with any of the compression types used by brawl.
 pub fn decompress(bytes: FancySlice) -> Vec<u8> {
-    let compression_type = CompressionType::new(bytes.u8(0));
+    try_decompress(bytes).unwrap_or_else(|err| panic!("{err}"))
+}
+
+/// Same as `decompress` but returns an error instead of panicking on malformed data.
+pub fn try_decompress(bytes: FancySlice) -> Result<Vec<u8>, ParseError> {
+    check_len(bytes, 4, "compression header")?;
+    let compression_type = CompressionType::new(bytes.u8(0))?;
     // TODO: bytes.le_u32(0) & 0xFFFFFF
-    let decompressed_len =
+    let mut decompressed_len =
         (bytes.u8(1) as u32) | ((bytes.u8(2) as u32) << 8) | ((bytes.u8(3) as u32) << 16);
-    let mut output = if decompressed_len == 0 {
-        panic!("extended length unimplemented")
-    } else {
-        vec![0; decompressed_len as usize]
-    };
+    let mut header_len = 4;
+    if decompressed_len == 0 {
+        // The length did not fit in 24 bits so it is stored in the following u32 instead
+        check_len(bytes, 8, "compression header")?;
+        decompressed_len = (bytes.u8(4) as u32)
+            | ((bytes.u8(5) as u32) << 8)
+            | ((bytes.u8(6) as u32) << 16)
+            | ((bytes.u8(7) as u32) << 24);
+        header_len = 8;
+    }
+    let mut output = vec![0; decompressed_len as usize];
+    let data = bytes.relative_slice(header_len..);
     match compression_type {
+        CompressionType::None => {
+            check_len(FancySlice::new(data), output.len(), "uncompressed data")
+                .map_err(|err| err.offset_by(header_len))?;
+            output.copy_from_slice(&data[..decompressed_len as usize]);
+        }
+        CompressionType::LZ77 => {
+            decompress_lz77(data, &mut output).map_err(|err| err.offset_by(header_len))?;
+        }
         CompressionType::ExtendedLZ77 => {
-            decompress_lz77_extended(bytes.relative_slice(4..), &mut output);
+            decompress_lz77_extended(data, &mut output).map_err(|err| err.offset_by(header_len))?;
         }
-        other => panic!("Support for compression type {other:?} is not yet implemented"),
     }
 
-    output
+    Ok(output)
+}
+
+/// Returns an error if `data` does not contain `needed` bytes starting at `index`
+fn check_source(data: &[u8], index: usize, needed: usize) -> Result<(), ParseError> {
+    check_len(
+        FancySlice::new(&data[index.min(data.len())..]),
+        needed,
+        "compressed data",
+    )
+    .map_err(|err| err.offset_by(index))
 }
 
-fn decompress_lz77_extended(data: &[u8], destination: &mut [u8]) {
+/// Returns an error if a back reference points to before the start of the output
+fn check_back_reference(dest_index: usize, distance: usize) -> Result<(), ParseError> {
+    if distance > dest_index {
+        Err(ParseError::new(
+            "compressed data",
+            0,
+            ParseErrorKind::Malformed(format!(
+                "back reference of 0x{distance:x} bytes at output offset 0x{dest_index:x} is before the start of the output"
+            )),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn decompress_lz77(data: &[u8], destination: &mut [u8]) -> Result<(), ParseError> {
     let mut source_index = 0;
     let mut dest_index = 0;
     while dest_index < destination.len() {
+        check_source(data, source_index, 1)?;
         let control = data[source_index];
         source_index += 1;
         for bit in (0..8).rev() {
             if dest_index >= destination.len() {
-                return;
+                return Ok(());
             }
             if (control & (1 << bit)) == 0 {
+                check_source(data, source_index, 1)?;
                 destination[dest_index] = data[source_index];
                 dest_index += 1;
                 source_index += 1;
             } else {
+                check_source(data, source_index, 2)?;
+                let num = ((data[source_index] as usize) >> 4) + 3;
+                let offset = ((((data[source_index] as usize) & 0xF) << 8)
+                    | data[source_index + 1] as usize)
+                    + 1;
+                source_index += 2;
+                check_back_reference(dest_index, offset)?;
+
+                for _ in 0..num {
+                    if dest_index >= destination.len() {
+                        return Ok(());
+                    }
+                    destination[dest_index] = destination[dest_index - offset];
+                    dest_index += 1;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn decompress_lz77_extended(data: &[u8], destination: &mut [u8]) -> Result<(), ParseError> {
+    let mut source_index = 0;
+    let mut dest_index = 0;
+    while dest_index < destination.len() {
+        check_source(data, source_index, 1)?;
+        let control = data[source_index];
+        source_index += 1;
+        for bit in (0..8).rev() {
+            if dest_index >= destination.len() {
+                return Ok(());
+            }
+            if (control & (1 << bit)) == 0 {
+                check_source(data, source_index, 1)?;
+                destination[dest_index] = data[source_index];
+                dest_index += 1;
+                source_index += 1;
+            } else {
+                check_source(data, source_index, 1)?;
                 let nibble = (data[source_index] as i32) >> 4;
                 let num;
                 if nibble == 1 {
+                    check_source(data, source_index, 4)?;
                     num = (((data[source_index] as i32 & 0x0F) << 12)
                         | ((data[source_index + 1] as i32) << 4)
                         | ((data[source_index + 2] as i32) >> 4))
@@ -65,27 +161,217 @@ fn decompress_lz77_extended(data: &[u8], destination: &mut [u8]) {
                         + 3;
                     source_index += 2;
                 } else if nibble == 0 {
+                    check_source(data, source_index, 3)?;
                     num = ((((data[source_index] as i32) & 0x0F) << 4)
                         | ((data[source_index + 1]) as i32 >> 4))
                         + 0xF
                         + 2;
                     source_index += 1;
                 } else {
+                    check_source(data, source_index, 2)?;
                     num = nibble + 1
                 }
                 let offset = ((((data[source_index] as i32) & 0xF) << 8)
                     | data[source_index + 1] as i32)
                     + 2;
                 source_index += 2;
+                check_back_reference(dest_index, offset as usize - 1)?;
 
                 for _ in 0..num {
                     if dest_index >= destination.len() {
-                        return;
+                        return Ok(());
                     }
-                    destination[dest_index] = destination[dest_index - offset as usize + 1];
+                    destination[dest_index] = destination[dest_index + 1 - offset as usize];
                     dest_index += 1;
                 }
             }
         }
     }
+    Ok(())
+}
+
+/// Furthest back a match can reference
+const WINDOW_SIZE: usize = 0x1000;
+/// Shortest match worth encoding, anything smaller is cheaper as literals
+const MIN_MATCH: usize = 3;
+/// Longest match that ExtendedLZ77 can encode
+const MAX_MATCH: usize = 0xFFFF + 0x111;
+/// How many previous occurrences of a 3 byte sequence we check before giving up on finding a longer match
+const MAX_CHAIN: usize = 0x80;
+
+/// Compresses data with ExtendedLZ77, the compression type brawl uses for its compressed archives.
+///
+/// The output is accepted by the game and by `decompress`, but is not necessarily byte identical to the original files.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() / 2 + 8);
+
+    // create header
+    output.push(0b10001);
+    // A length of 0 is used to indicate an extended length, so we need to use an extended length to store a length of 0
+    if !data.is_empty() && data.len() <= 0xFFFFFF {
+        output.extend(&u32::to_le_bytes(data.len() as u32)[..3]);
+    } else {
+        output.extend([0x00; 3]);
+        output.extend(u32::to_le_bytes(data.len() as u32));
+    }
+
+    // Maps the first 3 bytes of a sequence to the most recent location it was seen at.
+    // Each location then links to the previous location with the same 3 bytes in `prev`.
+    let mut heads: HashMap<[u8; 3], usize> = HashMap::new();
+    let mut prev = vec![usize::MAX; data.len()];
+
+    let mut index = 0;
+    while index < data.len() {
+        let control_index = output.len();
+        output.push(0x00);
+        for bit in (0..8).rev() {
+            if index >= data.len() {
+                break;
+            }
+
+            let (length, distance) = find_match(data, index, &heads, &prev);
+            let step = if length >= MIN_MATCH {
+                output[control_index] |= 1 << bit;
+                let disp = distance - 1;
+                if length <= 0x10 {
+                    output.push((((length - 1) << 4) | (disp >> 8)) as u8);
+                } else if length <= 0xFF + 0x11 {
+                    let value = length - 0x11;
+                    output.push((value >> 4) as u8);
+                    output.push((((value & 0xF) << 4) | (disp >> 8)) as u8);
+                } else {
+                    let value = length - 0x111;
+                    output.push((0x10 | (value >> 12)) as u8);
+                    output.push((value >> 4) as u8);
+                    output.push((((value & 0xF) << 4) | (disp >> 8)) as u8);
+                }
+                output.push(disp as u8);
+                length
+            } else {
+                output.push(data[index]);
+                1
+            };
+
+            for _ in 0..step {
+                if let Some(key) = data.get(index..index + 3) {
+                    let key = [key[0], key[1], key[2]];
+                    if let Some(head) = heads.insert(key, index) {
+                        prev[index] = head;
+                    }
+                }
+                index += 1;
+            }
+        }
+    }
+
+    while output.len() % 4 != 0 {
+        output.push(0x00);
+    }
+
+    output
+}
+
+/// Returns the (length, distance) of the longest match for the data at `index` within the window
+fn find_match(
+    data: &[u8],
+    index: usize,
+    heads: &HashMap<[u8; 3], usize>,
+    prev: &[usize],
+) -> (usize, usize) {
+    let mut best = (0, 0);
+    let key = match data.get(index..index + 3) {
+        Some(key) => [key[0], key[1], key[2]],
+        None => return best,
+    };
+    let max_length = (data.len() - index).min(MAX_MATCH);
+
+    let mut candidate = heads.get(&key).cloned().unwrap_or(usize::MAX);
+    let mut chain = 0;
+    while candidate != usize::MAX && index - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+        let mut length = 0;
+        while length < max_length && data[candidate + length] == data[index + length] {
+            length += 1;
+        }
+        if length > best.0 {
+            best = (length, index - candidate);
+            if length == max_length {
+                break;
+            }
+        }
+        candidate = prev[candidate];
+        chain += 1;
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(data: &[u8]) {
+        let compressed = compress(data);
+        assert_eq!(compressed.len() % 4, 0);
+        assert_eq!(try_decompress(FancySlice::new(&compressed)).unwrap(), data);
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        round_trip(&[]);
+        round_trip(b"a");
+        round_trip(b"abcabcabcabcabcabc");
+
+        // long runs use the 2 and 3 byte length encodings
+        round_trip(&[0; 0x20]);
+        round_trip(&[0; 0x200]);
+        round_trip(&[0; 0x20000]);
+
+        // matches further back than the window
+        let mut state = 1u32;
+        let random: Vec<u8> = (0..0x3000)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        let mut data = random.clone();
+        data.extend(&random);
+        round_trip(&data);
+    }
+
+    #[test]
+    fn decompress_none() {
+        let data = [0x00, 0x03, 0x00, 0x00, 1, 2, 3];
+        assert_eq!(try_decompress(FancySlice::new(&data)).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn decompress_extended_length() {
+        let data = [0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 1, 2];
+        assert_eq!(try_decompress(FancySlice::new(&data)).unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn decompress_lz77() {
+        // 3 literals followed by a 6 byte back reference 3 bytes back
+        let data = [0x10, 0x09, 0x00, 0x00, 0x10, b'a', b'b', b'c', 0x30, 0x02];
+        assert_eq!(
+            try_decompress(FancySlice::new(&data)).unwrap(),
+            b"abcabcabc"
+        );
+    }
+
+    #[test]
+    fn decompress_errors() {
+        // unknown compression type
+        assert!(try_decompress(FancySlice::new(&[0x20, 0x01, 0x00, 0x00])).is_err());
+        // not enough uncompressed data
+        assert!(try_decompress(FancySlice::new(&[0x00, 0x04, 0x00, 0x00, 1])).is_err());
+        // back reference before the start of the output
+        let data = [0x10, 0x04, 0x00, 0x00, 0x80, 0x10, 0x00];
+        assert!(try_decompress(FancySlice::new(&data)).is_err());
+        // compressed data ends early
+        let data = [0x11, 0x04, 0x00, 0x00, 0x00, b'a'];
+        assert!(try_decompress(FancySlice::new(&data)).is_err());
+    }
 }
@@ -0,0 +1,81 @@
+use fancy_slice::FancySlice;
+
+use crate::parse_error::{ParseError, check_len, try_str};
+
+/// Parses an EFLS, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_efls(data: FancySlice) -> Result<Efls, ParseError> {
+    check_len(data, EFLS_HEADER_SIZE, "EFLS")?;
+    let num_bres    = data.i32_be(0x4);
+    let num_entries = data.i32_be(0x8);
+    let unk         = data.i32_be(0xc);
+
+    check_len(data, EFLS_HEADER_SIZE + EFLS_ENTRY_SIZE * num_entries.max(0) as usize, "EFLS")?;
+    let mut entries = vec!();
+    for i in 0..num_entries.max(0) as usize {
+        let entry_offset = EFLS_HEADER_SIZE + EFLS_ENTRY_SIZE * i;
+        let entry_data = data.relative_fancy_slice(entry_offset ..);
+        let bres_index  = entry_data.i16_be(0x0);
+        let unk1        = entry_data.i16_be(0x2);
+        let name_offset = entry_data.i32_be(0x4);
+        let unk2        = entry_data.i32_be(0x8);
+        let unk3        = entry_data.i32_be(0xc);
+
+        // The name offset is relative to the start of the EFLS
+        let name = if name_offset > 0 {
+            Some(try_str(&data, name_offset as usize, "EFLS entry name")
+                .map_err(|err| err.offset_by(name_offset as usize))?
+                .to_string())
+        } else {
+            None
+        };
+
+        entries.push(EflsEntry { name, bres_index, unk1, unk2, unk3 });
+    }
+
+    Ok(Efls { num_bres, unk, entries, raw: data.relative_slice(..).to_vec() })
+}
+
+const EFLS_HEADER_SIZE: usize = 0x10;
+/// The effect list of an effect ARC.
+///
+/// The graphic effect events in moveset scripts refer to effects by their index into `entries`.
+/// Each entry then refers to an emitter in the REFF and optionally a model in one of the ARCs bres files.
+#[derive(Clone, Debug)]
+pub struct Efls {
+    num_bres: i32,
+    unk: i32,
+    entries: Vec<EflsEntry>,
+    raw: Vec<u8>,
+}
+
+impl Efls {
+    pub fn num_bres(&self) -> i32 {
+        self.num_bres
+    }
+
+    pub fn unk(&self) -> i32 {
+        self.unk
+    }
+
+    pub fn entries(&self) -> &[EflsEntry] {
+        &self.entries
+    }
+
+    /// Returns the original EFLS, which is unchanged as the entries are read only.
+    pub fn compile(&self) -> Vec<u8> {
+        self.raw.clone()
+    }
+}
+
+const EFLS_ENTRY_SIZE: usize = 0x10;
+#[derive(Clone, Debug)]
+pub struct EflsEntry {
+    /// Name of the REFF emitter
+    pub name: Option<String>,
+    /// Index of the bres, within the effect ARC, containing the models used by this effect
+    pub bres_index: i16,
+    pub unk1: i16,
+    pub unk2: i32,
+    pub unk3: i32,
+}
@@ -5,14 +5,21 @@ use std::path::Path;
 
 use rayon::prelude::*;
 
+use crate::aipd::Aipd;
 use crate::arc;
 use crate::arc::{Arc, ArcChildData};
-use crate::bres::BresChildData;
+use crate::atkd::Atkd;
+use crate::bres::{BresChild, BresChildData};
 use crate::chr0::Chr0;
+use crate::efls::Efls;
+use crate::mdl0::Mdl0;
 use crate::mdl0::bones::Bone;
+use crate::plt0::Plt0;
+use crate::sakurai::article::Article;
 use crate::sakurai::fighter_data::ArcFighterData;
 use crate::sakurai::fighter_data_common::ArcFighterDataCommon;
 use crate::sakurai::{ArcSakurai, SectionData, SectionScript};
+use crate::tex0::Tex0;
 use crate::wii_memory::WiiMemory;
 
 use fancy_slice::FancySlice;
@@ -85,7 +92,14 @@ impl Fighter {
         let moveset_file_name = format!("Fit{}.pac", fighter_data.cased_name);
         let moveset = if let Some(data) = fighter_data.data.get(&moveset_file_name) {
             let data = FancySlice::new(data);
-            arc::arc(data, wii_memory, false)
+            match arc::try_arc(data, wii_memory, false) {
+                Ok(moveset) => moveset,
+                Err(err) => {
+                    let err = err.with_file(Path::new(&moveset_file_name));
+                    error!("Failed to load {}, {}", fighter_data.cased_name, err);
+                    return None;
+                }
+            }
         } else {
             error!(
                 "Failed to load {}, missing moveset file: {}",
@@ -111,10 +125,12 @@ impl Fighter {
             // Currently I dont need that file at all (What does it even contain?)
             // But when I do, I'll need to rethink how I abstract characters with and without combined Motion + Etc
             let data = FancySlice::new(data);
-            arc::arc(data, wii_memory, false)
+            arc::try_arc(data, wii_memory, false)
+                .map_err(|err| err.with_file(Path::new(&motion_file_name)))
         } else if let Some(data) = fighter_data.data.get(&motion_etc_file_name) {
             let data = FancySlice::new(data);
-            arc::arc(data, wii_memory, false)
+            arc::try_arc(data, wii_memory, false)
+                .map_err(|err| err.with_file(Path::new(&motion_etc_file_name)))
         } else {
             error!(
                 "Failed to load {}, Missing motion file: {} or {}",
@@ -122,15 +138,27 @@ impl Fighter {
             );
             return None;
         };
+        let motion = match motion {
+            Ok(motion) => motion,
+            Err(err) => {
+                error!("Failed to load {}, {}", fighter_data.cased_name, err);
+                return None;
+            }
+        };
 
         let mut models = vec![];
         for i in 0..100 {
-            if let Some(model_data) = fighter_data
-                .data
-                .get(&format!("Fit{}{:02}.pac", fighter_data.cased_name, i))
-            {
+            let model_file_name = format!("Fit{}{:02}.pac", fighter_data.cased_name, i);
+            if let Some(model_data) = fighter_data.data.get(&model_file_name) {
                 let data = FancySlice::new(model_data);
-                models.push(arc::arc(data, wii_memory, false));
+                match arc::try_arc(data, wii_memory, false) {
+                    Ok(model) => models.push(model),
+                    Err(err) => error!(
+                        "Skipping model of {}, {}",
+                        fighter_data.cased_name,
+                        err.with_file(Path::new(&model_file_name))
+                    ),
+                }
                 if single_model {
                     break;
                 }
@@ -274,8 +302,48 @@ impl Fighter {
         scripts
     }
 
+    /// retrieves the attack data the CPU uses to pick attacks
+    pub fn get_atkd(&self) -> Option<&Atkd> {
+        for sub_arc in &self.moveset.children {
+            if let ArcChildData::Atkd(atkd) = &sub_arc.data {
+                return Some(atkd);
+            }
+        }
+        None
+    }
+
+    /// retrieves the parameters controlling how the CPU plays the fighter
+    pub fn get_aipd(&self) -> Option<&Aipd> {
+        for sub_arc in &self.moveset.children {
+            if let ArcChildData::Aipd(aipd) = &sub_arc.data {
+                return Some(aipd);
+            }
+        }
+        None
+    }
+
+    /// retrieves the effect lists from the effect arcs in the moveset
+    pub fn get_effect_lists(&self) -> Vec<&Efls> {
+        let mut effect_lists = vec![];
+        for sub_arc in &self.moveset.children {
+            if let ArcChildData::Arc(arc) = &sub_arc.data {
+                for child in &arc.children {
+                    if let ArcChildData::Efls(efls) = &child.data {
+                        effect_lists.push(efls);
+                    }
+                }
+            }
+        }
+        effect_lists
+    }
+
     /// retrieves the bones from a character model
     pub fn get_bones(&self) -> Option<&Bone> {
+        self.get_model().and_then(|model| model.bones.as_ref())
+    }
+
+    /// retrieves the character model
+    pub fn get_model(&self) -> Option<&Mdl0> {
         if let Some(model) = self.models.first() {
             for sub_arc in model.children.iter() {
                 match &sub_arc.data {
@@ -292,7 +360,7 @@ impl Fighter {
                                         // Instead, the first model is the characters model, so we just return it immediately.
 
                                         if let BresChildData::Mdl0(model) = &model_child.data {
-                                            return model.bones.as_ref();
+                                            return Some(model);
                                         }
                                     }
                                 }
@@ -310,6 +378,89 @@ impl Fighter {
         None
     }
 
+    /// retrieves the textures used by the character model
+    pub fn get_model_textures(&self) -> Vec<&Tex0> {
+        let mut textures = vec![];
+        for bres_child in self.model_bres_children() {
+            if let BresChildData::Bres(children) = &bres_child.data {
+                for child in children {
+                    if let BresChildData::Tex0(texture) = &child.data {
+                        textures.push(texture);
+                    }
+                }
+            }
+        }
+        textures
+    }
+
+    /// retrieves the palettes used by the indexed textures of the character model
+    pub fn get_model_palettes(&self) -> Vec<&Plt0> {
+        let mut palettes = vec![];
+        for bres_child in self.model_bres_children() {
+            if let BresChildData::Bres(children) = &bres_child.data {
+                for child in children {
+                    if let BresChildData::Plt0(palette) = &child.data {
+                        palettes.push(palette);
+                    }
+                }
+            }
+        }
+        palettes
+    }
+
+    /// The resource groups of every bres in the first model
+    fn model_bres_children(&self) -> impl Iterator<Item = &BresChild> {
+        self.models
+            .first()
+            .into_iter()
+            .flat_map(|model| &model.children)
+            .filter_map(|sub_arc| match &sub_arc.data {
+                ArcChildData::Bres(bres) => Some(&bres.children),
+                _ => None,
+            })
+            .flatten()
+    }
+
+    /// retrieves the model of an article from the arc group it references
+    pub fn get_article_model(&self, article: &Article) -> Option<&Mdl0> {
+        self.article_bres_children(article)
+            .find_map(|child| match &child.data {
+                BresChildData::Mdl0(model) => Some(model),
+                _ => None,
+            })
+    }
+
+    /// retrieves the animations of an article from the arc group it references
+    pub fn get_article_animations(&self, article: &Article) -> Vec<&Chr0> {
+        self.article_bres_children(article)
+            .filter_map(|child| match &child.data {
+                BresChildData::Chr0(chr0) => Some(chr0),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The contents of the resource groups of every bres in the first model that belongs to the articles arc group
+    fn article_bres_children(&self, article: &Article) -> impl Iterator<Item = &BresChild> {
+        self.models
+            .first()
+            .into_iter()
+            .flat_map(|model| &model.children)
+            .filter(move |sub_arc| {
+                article.arc_group >= 0 && sub_arc.group_index() as i32 == article.arc_group
+            })
+            .filter_map(|sub_arc| match &sub_arc.data {
+                ArcChildData::Bres(bres) => Some(&bres.children),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|bres_child| match &bres_child.data {
+                BresChildData::Bres(children) => Some(children),
+                _ => None,
+            })
+            .flatten()
+    }
+
     /// retrieves the animations for the character model
     pub fn get_animations(&self) -> Vec<&Chr0> {
         // When checking the arc names, the characters name cannot be included
@@ -0,0 +1,709 @@
+//! Export models and animations to binary glTF 2.0 (.glb) so they can be imported into tools such as Blender.
+
+use std::collections::HashMap;
+
+use anyhow::{Error, bail};
+use cgmath::{Deg, InnerSpace, Quaternion, Rotation3, Vector3};
+
+use crate::chr0::Chr0;
+use crate::fighter::Fighter;
+use crate::high_level_fighter::{HighLevelMesh, HighLevelModel, HighLevelTexture};
+use crate::mdl0::Mdl0;
+use crate::mdl0::bones::Bone;
+use crate::plt0::Plt0;
+use crate::tex0::Tex0;
+
+/// Brawl runs at 60 frames per second
+const FRAME_DURATION: f32 = 1.0 / 60.0;
+
+/// Returns a .glb of the fighters model, posable by its skeleton, with every animation of the fighter.
+pub fn fighter_glb(fighter: &Fighter) -> Result<Vec<u8>, Error> {
+    let Some(mdl0) = fighter.get_model() else {
+        bail!("Fighter {} has no model", fighter.cased_name);
+    };
+    glb(
+        mdl0,
+        &fighter.get_model_textures(),
+        &fighter.get_model_palettes(),
+        &fighter.get_animations(),
+    )
+}
+
+/// Returns a .glb of the model, skinned to its bones, with one glTF animation per CHR0.
+///
+/// `textures` and `palettes` are searched for the textures used by the meshes.
+/// CHR0 children that do not match the name of a bone are ignored.
+pub fn glb(
+    mdl0: &Mdl0,
+    textures: &[&Tex0],
+    palettes: &[&Plt0],
+    animations: &[&Chr0],
+) -> Result<Vec<u8>, Error> {
+    let Some(root_bone) = &mdl0.bones else {
+        bail!("MDL0 {} has no bones", mdl0.name);
+    };
+    let model = HighLevelModel::new(mdl0, textures, palettes);
+
+    let mut builder = Builder::default();
+
+    // The bones are the first nodes so the index of a joint in the skin is also the index of its node.
+    let mut bone_nodes = HashMap::new();
+    builder.bone_node(root_bone, &mut bone_nodes);
+    let num_bones = builder.gltf.nodes.len();
+
+    let inverse_bind_matrices: Vec<f32> = (0..num_bones)
+        .flat_map(|node| {
+            let matrix: &[f32; 16] = builder.bones[node].transform_inv.as_ref();
+            matrix.to_vec()
+        })
+        .collect();
+    let inverse_bind_matrices = builder.accessor_f32(&inverse_bind_matrices, "MAT4", None, false);
+    builder.gltf.skins.push(Skin {
+        inverse_bind_matrices,
+        joints: (0..num_bones).collect(),
+        skeleton: 0,
+    });
+
+    for texture in &model.textures {
+        builder.texture(texture)?;
+    }
+
+    let mut scene_nodes = vec![0];
+    for mesh in &model.meshes {
+        if let Some(mesh) = builder.mesh(mesh, &bone_nodes) {
+            scene_nodes.push(builder.gltf.nodes.len());
+            builder.gltf.nodes.push(Node {
+                name: builder.gltf.meshes[mesh].name.clone(),
+                mesh: Some(mesh),
+                skin: Some(0),
+                ..Node::default()
+            });
+        }
+    }
+    builder.gltf.scenes.push(Scene { nodes: scene_nodes });
+
+    for chr0 in animations {
+        builder.animation(chr0);
+    }
+
+    builder.finish()
+}
+
+#[derive(Default)]
+struct Builder<'a> {
+    gltf: Gltf,
+    bin: Vec<u8>,
+    /// The bone of each bone node
+    bones: Vec<&'a Bone>,
+    /// Index of the material used by meshes without a texture
+    untextured_material: Option<usize>,
+}
+
+impl<'a> Builder<'a> {
+    /// Adds a node for the bone and its descendants, returning the index of the bones node.
+    fn bone_node(&mut self, bone: &'a Bone, bone_nodes: &mut HashMap<i32, usize>) -> usize {
+        let index = self.gltf.nodes.len();
+        bone_nodes.insert(bone.index, index);
+        self.bones.push(bone);
+        self.gltf.nodes.push(Node {
+            name: Some(bone.name.clone()),
+            translation: Some(bone.translate.into()),
+            rotation: Some(rotation(bone.rot)),
+            scale: Some(bone.scale.into()),
+            ..Node::default()
+        });
+
+        let children = bone
+            .children
+            .iter()
+            .map(|child| self.bone_node(child, bone_nodes))
+            .collect();
+        self.gltf.nodes[index].children = children;
+        index
+    }
+
+    fn texture(&mut self, texture: &HighLevelTexture) -> Result<(), Error> {
+        let mut png = vec![];
+        {
+            let mut encoder =
+                png::Encoder::new(&mut png, texture.width as u32, texture.height as u32);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header()?;
+            writer.write_image_data(&texture.rgba)?;
+        }
+
+        let buffer_view = self.buffer_view(&png, None);
+        let image = self.gltf.images.len();
+        self.gltf.images.push(Image {
+            name: texture.name.clone(),
+            buffer_view,
+            mime_type: "image/png",
+        });
+
+        // Referred to by `HighLevelMesh::texture` so the material, texture and image indexes must all line up.
+        self.gltf.textures.push(Texture {
+            sampler: 0,
+            source: image,
+        });
+        if self.gltf.samplers.is_empty() {
+            self.gltf.samplers.push(Sampler {
+                mag_filter: 9729, // LINEAR
+                min_filter: 9729, // LINEAR
+            });
+        }
+        self.gltf.materials.push(Material {
+            name: Some(texture.name.clone()),
+            pbr_metallic_roughness: PbrMetallicRoughness {
+                base_color_texture: Some(TextureInfo { index: image }),
+                metallic_factor: 0.0,
+            },
+            alpha_mode: "MASK",
+            alpha_cutoff: Some(0.5),
+            double_sided: true,
+        });
+        Ok(())
+    }
+
+    /// Returns the index of the created mesh, or None if the mesh has no triangles.
+    fn mesh(&mut self, mesh: &HighLevelMesh, bone_nodes: &HashMap<i32, usize>) -> Option<usize> {
+        let vertices = &mesh.mesh.vertices;
+        if mesh.mesh.indices.is_empty() || vertices.is_empty() {
+            return None;
+        }
+
+        let positions: Vec<f32> = vertices
+            .iter()
+            .flat_map(|x| [x.position.x, x.position.y, x.position.z])
+            .collect();
+        let mut attributes = Attributes {
+            position: self.accessor_f32(&positions, "VEC3", Some(ARRAY_BUFFER), true),
+            ..Attributes::default()
+        };
+
+        if vertices.iter().all(|x| x.normal.is_some()) {
+            let normals: Vec<f32> = vertices
+                .iter()
+                .flat_map(|x| {
+                    let normal = x.normal.unwrap();
+                    let normal = if normal.magnitude2() > 0.0 {
+                        normal.normalize()
+                    } else {
+                        Vector3::unit_y()
+                    };
+                    [normal.x, normal.y, normal.z]
+                })
+                .collect();
+            attributes.normal =
+                Some(self.accessor_f32(&normals, "VEC3", Some(ARRAY_BUFFER), false));
+        }
+
+        if vertices.iter().all(|x| !x.uvs.is_empty()) {
+            let uvs: Vec<f32> = vertices
+                .iter()
+                .flat_map(|x| [x.uvs[0].x, x.uvs[0].y])
+                .collect();
+            attributes.texcoord_0 =
+                Some(self.accessor_f32(&uvs, "VEC2", Some(ARRAY_BUFFER), false));
+        }
+
+        if vertices.iter().all(|x| !x.colors.is_empty()) {
+            let colors: Vec<u8> = vertices.iter().flat_map(|x| x.colors[0]).collect();
+            let buffer_view = self.buffer_view(&colors, Some(ARRAY_BUFFER));
+            attributes.color_0 = Some(self.accessor(Accessor {
+                buffer_view,
+                component_type: UNSIGNED_BYTE,
+                normalized: true,
+                count: vertices.len(),
+                ty: "VEC4",
+                min: None,
+                max: None,
+            }));
+        }
+
+        // glTF only supports 4 weights per vertex, so only the 4 largest weights are kept.
+        // Vertices without weights are bound to the root bone.
+        let mut joints = Vec::with_capacity(vertices.len() * 4);
+        let mut weights = Vec::with_capacity(vertices.len() * 4);
+        for vertex in vertices {
+            let mut vertex_weights: Vec<_> = vertex
+                .weights
+                .iter()
+                .filter_map(|x| Some((*bone_nodes.get(&x.bone_index)? as u16, x.weight)))
+                .filter(|x| x.1 > 0.0)
+                .collect();
+            vertex_weights.sort_by(|a, b| b.1.total_cmp(&a.1));
+            vertex_weights.truncate(4);
+            if vertex_weights.is_empty() {
+                vertex_weights.push((0, 1.0));
+            }
+            let total: f32 = vertex_weights.iter().map(|x| x.1).sum();
+            vertex_weights.resize(4, (0, 0.0));
+            for (joint, weight) in vertex_weights {
+                joints.push(joint);
+                weights.push(weight / total);
+            }
+        }
+        let joints: Vec<u8> = joints.iter().flat_map(|x| x.to_le_bytes()).collect();
+        let buffer_view = self.buffer_view(&joints, Some(ARRAY_BUFFER));
+        attributes.joints_0 = Some(self.accessor(Accessor {
+            buffer_view,
+            component_type: UNSIGNED_SHORT,
+            normalized: false,
+            count: vertices.len(),
+            ty: "VEC4",
+            min: None,
+            max: None,
+        }));
+        attributes.weights_0 = Some(self.accessor_f32(&weights, "VEC4", Some(ARRAY_BUFFER), false));
+
+        let indices: Vec<u8> = mesh
+            .mesh
+            .indices
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect();
+        let buffer_view = self.buffer_view(&indices, Some(ELEMENT_ARRAY_BUFFER));
+        let indices = self.accessor(Accessor {
+            buffer_view,
+            component_type: UNSIGNED_INT,
+            normalized: false,
+            count: mesh.mesh.indices.len(),
+            ty: "SCALAR",
+            min: None,
+            max: None,
+        });
+
+        let material = match mesh.texture {
+            Some(texture) => texture,
+            None => self.untextured_material(),
+        };
+
+        let index = self.gltf.meshes.len();
+        self.gltf.meshes.push(GltfMesh {
+            name: mesh.mesh.name.clone(),
+            primitives: vec![Primitive {
+                attributes,
+                indices,
+                material,
+                mode: 4, // TRIANGLES
+            }],
+        });
+        Some(index)
+    }
+
+    fn untextured_material(&mut self) -> usize {
+        if let Some(material) = self.untextured_material {
+            return material;
+        }
+        let material = self.gltf.materials.len();
+        self.gltf.materials.push(Material {
+            name: None,
+            pbr_metallic_roughness: PbrMetallicRoughness {
+                base_color_texture: None,
+                metallic_factor: 0.0,
+            },
+            alpha_mode: "OPAQUE",
+            alpha_cutoff: None,
+            double_sided: true,
+        });
+        self.untextured_material = Some(material);
+        material
+    }
+
+    /// The keyframes are sampled on every frame, as the CHR0 interpolation cannot be represented in glTF.
+    fn animation(&mut self, chr0: &Chr0) {
+        if chr0.num_frames == 0 {
+            return;
+        }
+        let frames = 0..chr0.num_frames as i32;
+        let times: Vec<f32> = frames.clone().map(|x| x as f32 * FRAME_DURATION).collect();
+        let input = self.accessor_f32(&times, "SCALAR", None, true);
+
+        let mut animation = Animation {
+            name: chr0.name.clone(),
+            channels: vec![],
+            samplers: vec![],
+        };
+        for child in &chr0.children {
+            let Some(node) = self.bones.iter().position(|x| x.name == child.name) else {
+                continue;
+            };
+
+            let mut translations = vec![];
+            let mut rotations = vec![];
+            let mut scales = vec![];
+            let mut prev_rotation: Option<[f32; 4]> = None;
+            for frame in frames.clone() {
+                let translation = child.translation.get_value(chr0.loop_value, frame, 0.0);
+                translations.extend([translation.x, translation.y, translation.z]);
+
+                // Keep consecutive quaternions in the same hemisphere so interpolation takes the short path
+                let mut rot = rotation(child.rot.get_value(chr0.loop_value, frame, 0.0));
+                if let Some(prev) = prev_rotation
+                    && prev.iter().zip(rot).map(|(a, b)| a * b).sum::<f32>() < 0.0
+                {
+                    rot = rot.map(|x| -x);
+                }
+                prev_rotation = Some(rot);
+                rotations.extend(rot);
+
+                let scale = child.scale.get_value(chr0.loop_value, frame, 1.0);
+                scales.extend([scale.x, scale.y, scale.z]);
+            }
+
+            for (path, values, ty) in [
+                ("translation", translations, "VEC3"),
+                ("rotation", rotations, "VEC4"),
+                ("scale", scales, "VEC3"),
+            ] {
+                let output = self.accessor_f32(&values, ty, None, false);
+                animation.channels.push(Channel {
+                    sampler: animation.samplers.len(),
+                    target: Target { node, path },
+                });
+                animation.samplers.push(AnimationSampler {
+                    input,
+                    output,
+                    interpolation: "LINEAR",
+                });
+            }
+        }
+
+        if !animation.channels.is_empty() {
+            self.gltf.animations.push(animation);
+        }
+    }
+
+    fn buffer_view(&mut self, data: &[u8], target: Option<u32>) -> usize {
+        // Accessors require their data to be aligned to the size of their components
+        while self.bin.len() % 4 != 0 {
+            self.bin.push(0);
+        }
+        let index = self.gltf.buffer_views.len();
+        self.gltf.buffer_views.push(BufferView {
+            buffer: 0,
+            byte_offset: self.bin.len(),
+            byte_length: data.len(),
+            target,
+        });
+        self.bin.extend_from_slice(data);
+        index
+    }
+
+    fn accessor(&mut self, accessor: Accessor) -> usize {
+        let index = self.gltf.accessors.len();
+        self.gltf.accessors.push(accessor);
+        index
+    }
+
+    /// `bounds` needs to be set for positions and animation times, as glTF requires their min and max.
+    fn accessor_f32(
+        &mut self,
+        data: &[f32],
+        ty: &'static str,
+        target: Option<u32>,
+        bounds: bool,
+    ) -> usize {
+        let components = match ty {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" => 4,
+            "MAT4" => 16,
+            _ => unreachable!(),
+        };
+        let (min, max) = if bounds {
+            let mut min = vec![f32::INFINITY; components];
+            let mut max = vec![f32::NEG_INFINITY; components];
+            for element in data.chunks(components) {
+                for (i, value) in element.iter().enumerate() {
+                    min[i] = min[i].min(*value);
+                    max[i] = max[i].max(*value);
+                }
+            }
+            (Some(min), Some(max))
+        } else {
+            (None, None)
+        };
+
+        let bytes: Vec<u8> = data.iter().flat_map(|x| x.to_le_bytes()).collect();
+        let buffer_view = self.buffer_view(&bytes, target);
+        self.accessor(Accessor {
+            buffer_view,
+            component_type: FLOAT,
+            normalized: false,
+            count: data.len() / components,
+            ty,
+            min,
+            max,
+        })
+    }
+
+    fn finish(mut self) -> Result<Vec<u8>, Error> {
+        while self.bin.len() % 4 != 0 {
+            self.bin.push(0);
+        }
+        self.gltf.buffers.push(Buffer {
+            byte_length: self.bin.len(),
+        });
+
+        let mut json = serde_json::to_vec(&self.gltf)?;
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+
+        let total_len = 12 + 8 + json.len() + 8 + self.bin.len();
+        let mut output = Vec::with_capacity(total_len);
+        output.extend(b"glTF");
+        output.extend(u32::to_le_bytes(2));
+        output.extend(u32::to_le_bytes(total_len as u32));
+
+        output.extend(u32::to_le_bytes(json.len() as u32));
+        output.extend(b"JSON");
+        output.extend(json);
+
+        output.extend(u32::to_le_bytes(self.bin.len() as u32));
+        output.extend(b"BIN\0");
+        output.extend(self.bin);
+
+        Ok(output)
+    }
+}
+
+/// Converts euler angles in degrees, applied in the same order as `math::gen_transform`, into an xyzw quaternion
+fn rotation(rot: Vector3<f32>) -> [f32; 4] {
+    let quaternion = Quaternion::from_angle_z(Deg(rot.z))
+        * Quaternion::from_angle_y(Deg(rot.y))
+        * Quaternion::from_angle_x(Deg(rot.x));
+    [quaternion.v.x, quaternion.v.y, quaternion.v.z, quaternion.s]
+}
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_SHORT: u32 = 5123;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Gltf {
+    asset: Asset,
+    scene: usize,
+    scenes: Vec<Scene>,
+    nodes: Vec<Node>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    meshes: Vec<GltfMesh>,
+    skins: Vec<Skin>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    animations: Vec<Animation>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    materials: Vec<Material>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    textures: Vec<Texture>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<Image>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    samplers: Vec<Sampler>,
+    accessors: Vec<Accessor>,
+    buffer_views: Vec<BufferView>,
+    buffers: Vec<Buffer>,
+}
+
+impl Default for Gltf {
+    fn default() -> Self {
+        Gltf {
+            asset: Asset {
+                version: "2.0",
+                generator: "brawllib_rs",
+            },
+            scene: 0,
+            scenes: vec![],
+            nodes: vec![],
+            meshes: vec![],
+            skins: vec![],
+            animations: vec![],
+            materials: vec![],
+            textures: vec![],
+            images: vec![],
+            samplers: vec![],
+            accessors: vec![],
+            buffer_views: vec![],
+            buffers: vec![],
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Asset {
+    version: &'static str,
+    generator: &'static str,
+}
+
+#[derive(Serialize)]
+struct Scene {
+    nodes: Vec<usize>,
+}
+
+#[derive(Serialize, Default)]
+struct Node {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    translation: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rotation: Option<[f32; 4]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scale: Option<[f32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mesh: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skin: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct GltfMesh {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    primitives: Vec<Primitive>,
+}
+
+#[derive(Serialize)]
+struct Primitive {
+    attributes: Attributes,
+    indices: usize,
+    material: usize,
+    mode: u32,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+struct Attributes {
+    position: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normal: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    texcoord_0: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color_0: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    joints_0: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weights_0: Option<usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Skin {
+    inverse_bind_matrices: usize,
+    joints: Vec<usize>,
+    skeleton: usize,
+}
+
+#[derive(Serialize)]
+struct Animation {
+    name: String,
+    channels: Vec<Channel>,
+    samplers: Vec<AnimationSampler>,
+}
+
+#[derive(Serialize)]
+struct Channel {
+    sampler: usize,
+    target: Target,
+}
+
+#[derive(Serialize)]
+struct Target {
+    node: usize,
+    path: &'static str,
+}
+
+#[derive(Serialize)]
+struct AnimationSampler {
+    input: usize,
+    output: usize,
+    interpolation: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Material {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    pbr_metallic_roughness: PbrMetallicRoughness,
+    alpha_mode: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alpha_cutoff: Option<f32>,
+    double_sided: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PbrMetallicRoughness {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_color_texture: Option<TextureInfo>,
+    metallic_factor: f32,
+}
+
+#[derive(Serialize)]
+struct TextureInfo {
+    index: usize,
+}
+
+#[derive(Serialize)]
+struct Texture {
+    sampler: usize,
+    source: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Image {
+    name: String,
+    buffer_view: usize,
+    mime_type: &'static str,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Sampler {
+    mag_filter: u32,
+    min_filter: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: usize,
+    component_type: u32,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    ty: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<Vec<f32>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    byte_offset: usize,
+    byte_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Buffer {
+    byte_length: usize,
+}
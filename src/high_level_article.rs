@@ -0,0 +1,269 @@
+use cgmath::{Matrix4, SquareMatrix, Vector3};
+use rayon::prelude::*;
+
+use crate::chr0::Chr0;
+use crate::high_level_fighter::{
+    BoneTransforms, ECB, HighLevelFighter, HighLevelFrame, HighLevelScripts, HighLevelSubaction,
+    gen_high_level_hit_boxes, gen_high_level_interrupts, gen_hit_boxes, unique_subaction_name,
+};
+use crate::mdl0::bones::Bone;
+use crate::sakurai::ArcSakurai;
+use crate::sakurai::article::Article;
+use crate::sakurai::fighter_data::AnimationFlags;
+use crate::script::Script;
+use crate::script_ast::{Block, ScriptAst};
+use crate::script_runner::{ChangeSubaction, ScriptRunner, SimulationContext, VelModify};
+
+/// Subactions without an animation have no natural length, so they are run until their scripts finish or this many frames pass.
+const MAX_UNANIMATED_FRAMES: usize = 300;
+
+/// The HighLevelArticle stores the processed subactions of an `Article` e.g. a projectile or an item.
+#[derive(Serialize, Clone, Debug)]
+pub struct HighLevelArticle {
+    pub name: String,
+    pub subactions: Vec<HighLevelSubaction>,
+    pub scripts_fragment: Vec<ScriptAst>,
+}
+
+impl HighLevelArticle {
+    /// Runs every subaction of the article.
+    ///
+    /// `sakurai` is the archive the article was read from, the articles scripts can call into its fragment scripts.
+    /// `first_bone` and `animations` come from the articles model.
+    /// When there is no model, hitboxes are positioned relative to the articles origin.
+    pub fn new(
+        name: String,
+        article: &Article,
+        sakurai: &ArcSakurai,
+        first_bone: Option<&Bone>,
+        animations: &[&Chr0],
+    ) -> HighLevelArticle {
+        let scripts_fragment: Vec<_> = sakurai
+            .fragment_scripts
+            .iter()
+            .map(ScriptAst::new)
+            .collect();
+        let subaction_main: Vec<_> = article.subaction_main.iter().map(ScriptAst::new).collect();
+        let subaction_gfx: Vec<_> = article.subaction_gfx.iter().map(ScriptAst::new).collect();
+        let subaction_sfx: Vec<_> = article.subaction_sfx.iter().map(ScriptAst::new).collect();
+        let actions: Vec<_> = article.actions.iter().map(ScriptAst::new).collect();
+
+        let mut article_scripts = vec![];
+        for script in scripts_fragment
+            .iter()
+            .chain(subaction_main.iter())
+            .chain(subaction_gfx.iter())
+            .chain(subaction_sfx.iter())
+            .chain(actions.iter())
+        {
+            article_scripts.push(script);
+        }
+
+        // Articles have no other script, an empty one keeps the layout the same as fighters.
+        let empty_script = ScriptAst::new(&Script {
+            events: vec![],
+            offset: 0,
+        });
+        let trans_n = first_bone
+            .and_then(|bone| {
+                bone.flatten()
+                    .into_iter()
+                    .find(|x| x.bone.name == "TransN")
+                    .map(|x| x.bone.index)
+            })
+            .unwrap_or(-1);
+
+        let subactions = (0..subaction_main.len())
+            .into_par_iter()
+            .map(|i| {
+                let scripts = HighLevelScripts {
+                    script_main: subaction_main[i].clone(),
+                    script_gfx: subaction_gfx.get(i).unwrap_or(&empty_script).clone(),
+                    script_sfx: subaction_sfx.get(i).unwrap_or(&empty_script).clone(),
+                    script_other: empty_script.clone(),
+                };
+                let subaction_flags = article.subaction_flags.get(i);
+                let actual_name = subaction_flags.map(|x| x.name.clone()).unwrap_or_default();
+                let name = if subaction_flags.is_some() {
+                    unique_subaction_name(&article.subaction_flags, i)
+                } else {
+                    format!("Subaction{i}")
+                };
+                let animation_flags = subaction_flags
+                    .map(|x| x.animation_flags)
+                    .unwrap_or_else(AnimationFlags::empty);
+
+                let chr0 = animations.iter().find(|x| x.name == actual_name);
+                let no_animation = Chr0::new(
+                    actual_name.clone(),
+                    MAX_UNANIMATED_FRAMES as u16,
+                    false,
+                    vec![],
+                );
+                let num_frames = chr0.map(|x| x.num_frames as f32);
+                let chr0 = chr0.cloned().unwrap_or(&no_animation);
+
+                let subaction_scripts = [
+                    &scripts.script_main,
+                    &scripts.script_gfx,
+                    &scripts.script_sfx,
+                    &scripts.script_other,
+                ];
+                let mut script_runner = ScriptRunner::new(
+                    i,
+                    &[],
+                    &subaction_scripts,
+                    &article_scripts,
+                    &[],
+                    &[],
+                    &Block { events: vec![] },
+                    &article.model_visibility,
+                    actual_name.clone(),
+                    SimulationContext::default(),
+                );
+
+                let mut frames: Vec<HighLevelFrame> = vec![];
+                let mut iasa = None;
+                let mut prev_hit_boxes = None;
+                let mut prev_animation_xyz_offset = Vector3::new(0.0, 0.0, 0.0);
+                let mut x_vel = 0.0;
+                let mut y_vel = 0.0;
+                let mut x_pos = 0.0;
+                let mut y_pos = 0.0;
+
+                while script_runner.animation_index
+                    < num_frames.unwrap_or(MAX_UNANIMATED_FRAMES as f32)
+                {
+                    let (animation_xyz_offset, frame_bones) = if let Some(first_bone) = first_bone {
+                        HighLevelFighter::transform_bones(
+                            first_bone,
+                            trans_n,
+                            Matrix4::<f32>::identity(),
+                            Matrix4::<f32>::identity(),
+                            chr0,
+                            script_runner.animation_index as i32,
+                            animation_flags,
+                            1.0,
+                        )
+                    } else {
+                        let origin = BoneTransforms {
+                            index: 0,
+                            transform_normal: Matrix4::identity(),
+                            transform_hitbox: Matrix4::identity(),
+                            children: vec![],
+                        };
+                        (None, origin)
+                    };
+                    let animation_xyz_offset =
+                        animation_xyz_offset.unwrap_or_else(|| Vector3::new(0.0, 0.0, 0.0));
+                    let animation_xyz_velocity = animation_xyz_offset - prev_animation_xyz_offset;
+                    prev_animation_xyz_offset = animation_xyz_offset;
+
+                    let x_vel_modify = script_runner.x_vel_modify.clone();
+                    let y_vel_modify = script_runner.y_vel_modify.clone();
+
+                    let x_vel_temp = animation_xyz_velocity.z;
+                    let y_vel_temp = animation_xyz_velocity.y;
+
+                    match x_vel_modify {
+                        VelModify::Set(vel) => x_vel = vel,
+                        VelModify::Add(vel) => x_vel += vel,
+                        VelModify::None => {}
+                    }
+
+                    match y_vel_modify {
+                        VelModify::Set(vel) => y_vel = vel,
+                        VelModify::Add(vel) => y_vel += vel,
+                        VelModify::None => {}
+                    }
+
+                    x_pos += x_vel + x_vel_temp;
+                    y_pos += y_vel + y_vel_temp;
+
+                    let mut hit_boxes: Vec<_> =
+                        script_runner.hitboxes.iter().flatten().cloned().collect();
+                    if first_bone.is_none() {
+                        for hit_box in &mut hit_boxes {
+                            hit_box.bone_index = 0;
+                        }
+                    }
+                    let hit_boxes = gen_hit_boxes(&frame_bones, &hit_boxes, 1.0);
+                    let hl_hit_boxes = gen_high_level_hit_boxes(
+                        &hit_boxes,
+                        prev_hit_boxes.as_deref(),
+                        x_vel,
+                        y_vel,
+                    );
+
+                    frames.push(HighLevelFrame {
+                        throw: None,
+                        bone_matrices: vec![],
+                        // Articles do not have an ECB
+                        ecb: ECB {
+                            left: 0.0,
+                            right: 0.0,
+                            top: 0.0,
+                            bottom: 0.0,
+                            transn_x: 0.0,
+                            transn_y: 0.0,
+                        },
+                        x_pos,
+                        y_pos,
+                        x_vel_modify,
+                        y_vel_modify,
+                        x_vel_temp,
+                        y_vel_temp,
+                        ledge_grab_box: None,
+                        hurt_boxes: vec![],
+                        hit_boxes: hl_hit_boxes,
+                        interruptible: script_runner.can_interrupt(),
+                        interrupts: gen_high_level_interrupts(&script_runner),
+                        landing_lag: script_runner.landing_lag,
+                        edge_slide: script_runner.edge_slide.clone(),
+                        reverse_direction: script_runner.reverse_direction,
+                        airbourne: script_runner.airbourne,
+                        hitbox_sets_rehit: script_runner.hitbox_sets_rehit,
+                        slope_contour_stand: script_runner.slope_contour_stand,
+                        slope_contour_full: script_runner.slope_contour_full,
+                        rumble: script_runner.rumble,
+                        rumble_loop: script_runner.rumble_loop,
+                        grab_interrupt_damage: script_runner.grab_interrupt_damage,
+                    });
+
+                    if iasa.is_none() && script_runner.can_interrupt() {
+                        iasa = Some(script_runner.frame_count)
+                    }
+
+                    script_runner.step();
+                    prev_hit_boxes = Some(hit_boxes);
+
+                    if let ChangeSubaction::Continue = script_runner.change_subaction {
+                    } else {
+                        break;
+                    }
+
+                    let finished = script_runner.call_stacks.iter().all(|x| x.calls.is_empty())
+                        && script_runner.independent_threads.is_empty();
+                    if num_frames.is_none() && finished {
+                        break;
+                    }
+                }
+
+                HighLevelSubaction {
+                    name,
+                    iasa,
+                    landing_lag: None,
+                    frames,
+                    animation_flags,
+                    scripts,
+                }
+            })
+            .collect();
+
+        HighLevelArticle {
+            name,
+            subactions,
+            scripts_fragment,
+        }
+    }
+}
@@ -3,16 +3,27 @@ use rayon::prelude::*;
 
 use crate::chr0::Chr0;
 use crate::fighter::Fighter;
+use crate::high_level_article::HighLevelArticle;
 use crate::init_hack_script::init_hack_script;
-use crate::mdl0::bones::Bone;
+use crate::mdl0::Mdl0;
+use crate::mdl0::bones::{Bone, BoneFlags};
+use crate::mdl0::meshes::Mesh;
+use crate::plt0::Plt0;
 use crate::sakurai::fighter_data::misc_section::{BoneRefs, HurtBox};
-use crate::sakurai::fighter_data::{AnimationFlags, FighterAttributes};
+use crate::sakurai::fighter_data::{
+    AnimationFlags, ArcFighterData, FighterAttributes, SubactionFlags,
+};
 use crate::sakurai::{ExternalSubroutine, SectionScript};
+use crate::script_ast::variable_ast::InternalConstantInt;
 use crate::script_ast::{
-    AngleFlip, EdgeSlide, GrabBoxArguments, GrabTarget, HitBoxArguments, HitBoxEffect, HitBoxSound,
-    HitBoxSseType, HurtBoxState, LedgeGrabEnable, ScriptAst, SpecialHitBoxArguments,
+    AngleFlip, EdgeSlide, EventAst, GrabBoxArguments, GrabTarget, HitBoxArguments, HitBoxEffect,
+    HitBoxSound, HitBoxSseType, HurtBoxState, InterruptType, LedgeGrabEnable, ScriptAst,
+    SpecialHitBoxArguments,
+};
+use crate::script_runner::{
+    ChangeSubaction, ScriptCollisionBox, ScriptRunner, SimulationContext, VelModify,
 };
-use crate::script_runner::{ChangeSubaction, ScriptCollisionBox, ScriptRunner, VelModify};
+use crate::tex0::Tex0;
 
 /// The HighLevelFighter stores processed Fighter data in a format that is easy to read from.
 /// If brawllib_rs eventually implements the ability to modify character files via modifying Fighter and its children, then HighLevelFighter WILL NOT support that.
@@ -26,15 +37,37 @@ pub struct HighLevelFighter {
     pub scripts_fragment_fighter: Vec<ScriptAst>,
     pub scripts_fragment_common: Vec<ScriptAst>,
     pub scripts_section: Vec<SectionScriptAst>,
+    /// The fighters static articles followed by its entry article
+    pub articles: Vec<HighLevelArticle>,
+    /// Only generated by `HighLevelFighter::new_with_model`
+    pub model: Option<HighLevelModel>,
 }
 
 impl HighLevelFighter {
     /// Processes data from an &Fighter and stores it in a HighLevelFighter
+    ///
+    /// Every subaction is run against `context`.
+    /// Unless `context` overrides `InternalConstantInt::CurrentAction`, it is set to the action guessed to enter each subaction.
+    /// The fighters articles are always run against `SimulationContext::default()`.
     // TODO: Maybe expose a `multithreaded` argument so caller can disable multithread and run its own multithreading on the entire `HighLevelFighter::new`.
     // Because rayon uses a threadpool we arent at risk of it hammering the system by spawning too many threads.
     // However it may be ineffecient due to overhead of spawning threads for every action.
     // Will need to benchmark any such changes.
-    pub fn new(fighter: &Fighter) -> HighLevelFighter {
+    pub fn new(fighter: &Fighter, context: SimulationContext) -> HighLevelFighter {
+        HighLevelFighter::new_inner(fighter, context, false)
+    }
+
+    /// Like `HighLevelFighter::new` but also decodes the fighters model and stores the transform of every bone on every frame.
+    /// This is needed to render the fighters model but takes a lot more memory.
+    pub fn new_with_model(fighter: &Fighter, context: SimulationContext) -> HighLevelFighter {
+        HighLevelFighter::new_inner(fighter, context, true)
+    }
+
+    fn new_inner(
+        fighter: &Fighter,
+        context: SimulationContext,
+        with_model: bool,
+    ) -> HighLevelFighter {
         info!("Generating HighLevelFighter for {}", fighter.cased_name);
         let fighter_sakurai = fighter.get_fighter_sakurai().unwrap();
         let fighter_sakurai_common = fighter.get_fighter_sakurai_common().unwrap();
@@ -187,19 +220,7 @@ impl HighLevelFighter {
                 .map(|(i, scripts)| {
                     let subaction_flags = &fighter_data.subaction_flags[i];
                     let actual_name = subaction_flags.name.clone();
-
-                    // create a unique name for this subaction
-                    let mut count = 0;
-                    for j in 0..i {
-                        if fighter_data.subaction_flags[j].name == actual_name {
-                            count += 1;
-                        }
-                    }
-                    let name = if count == 0 {
-                        actual_name.clone()
-                    } else {
-                        format!("{}_{}", actual_name, count)
-                    };
+                    let name = unique_subaction_name(&fighter_data.subaction_flags, i);
 
                     let animation_flags = subaction_flags.animation_flags;
 
@@ -212,8 +233,19 @@ impl HighLevelFighter {
                     ];
                     let init_hack_script = init_hack_script(&fighter.cased_name, &actual_name);
 
+                    let mut context = context.clone();
+                    if !context
+                        .internal_constants
+                        .iter()
+                        .any(|(constant, _)| *constant == InternalConstantInt::CurrentAction)
+                        && let Some(action) = entering_action(&actions, i)
+                    {
+                        context
+                            .internal_constants
+                            .push((InternalConstantInt::CurrentAction, action as i32));
+                    }
+
                     let mut frames: Vec<HighLevelFrame> = vec![];
-                    let mut prev_animation_xyz_offset = Vector3::new(0.0, 0.0, 0.0);
                     let mut script_runner = ScriptRunner::new(
                         i,
                         &fighter.wiird_frame_speed_modifiers,
@@ -222,262 +254,31 @@ impl HighLevelFighter {
                         &common_scripts,
                         &scripts_section,
                         &init_hack_script,
-                        fighter_data,
+                        &fighter_data.model_visibility,
                         actual_name.clone(),
+                        context,
                     );
                     let mut iasa = None;
-                    let mut prev_hit_boxes: Option<Vec<PositionHitBox>> = None;
 
                     if let Some(chr0) = chr0 {
-                        let num_frames = match actual_name.as_ref() {
-                            "JumpSquat" => attributes.jump_squat_frames as f32,
-                            "LandingAirN" => attributes.nair_landing_lag,
-                            "LandingAirF" => attributes.fair_landing_lag,
-                            "LandingAirB" => attributes.bair_landing_lag,
-                            "LandingAirHi" => attributes.uair_landing_lag,
-                            "LandingAirLw" => attributes.dair_landing_lag,
-                            "LandingLight" => attributes.light_landing_lag, // TODO: This needs +1 do the others?!?!?
-                            "LandingHeavy" => attributes.normal_landing_lag,
-                            _ => chr0.num_frames as f32,
-                        };
-
-                        let mut x_vel = 0.0;
-                        let mut y_vel = 0.0;
-
-                        let mut x_pos = 0.0;
-                        let mut y_pos = 0.0;
+                        let num_frames = subaction_num_frames(&attributes, &actual_name, chr0);
+                        let mut frame_generator =
+                            FrameGenerator::new(fighter_data, first_bone, with_model);
 
                         while script_runner.animation_index < num_frames {
-                            let chr0_frame_index =
-                                script_runner.animation_index * chr0.num_frames as f32 / num_frames; // map frame count between [0, chr0.num_frames]
-                            let (animation_xyz_offset, frame_bones) =
-                                HighLevelFighter::transform_bones(
-                                    first_bone,
-                                    &fighter_data.misc.bone_refs,
-                                    Matrix4::<f32>::identity(),
-                                    Matrix4::<f32>::identity(),
-                                    chr0,
-                                    chr0_frame_index as i32,
-                                    animation_flags,
-                                    fighter_data.attributes.size,
-                                );
-                            let animation_xyz_offset =
-                                animation_xyz_offset.unwrap_or_else(|| Vector3::new(0.0, 0.0, 0.0));
-                            // TODO: should DisableMovement affect xyz_offset from transform_bones?????
-                            // script runner x-axis is equivalent to model z-axis
-
-                            let animation_xyz_velocity =
-                                animation_xyz_offset - prev_animation_xyz_offset;
-                            prev_animation_xyz_offset = animation_xyz_offset;
-
-                            let x_vel_modify = script_runner.x_vel_modify.clone();
-                            let y_vel_modify = script_runner.y_vel_modify.clone();
-
-                            let x_vel_temp = animation_xyz_velocity.z;
-                            let y_vel_temp = animation_xyz_velocity.y;
-
-                            match x_vel_modify {
-                                VelModify::Set(vel) => x_vel = vel,
-                                VelModify::Add(vel) => x_vel += vel,
-                                VelModify::None => {}
-                            }
-
-                            match y_vel_modify {
-                                VelModify::Set(vel) => y_vel = vel,
-                                VelModify::Add(vel) => y_vel += vel,
-                                VelModify::None => {}
-                            }
-
-                            x_pos += x_vel + x_vel_temp;
-                            y_pos += y_vel + y_vel_temp;
-
-                            let hurt_boxes = gen_hurt_boxes(
-                                &frame_bones,
-                                &fighter_data.misc.hurt_boxes,
+                            frames.push(frame_generator.gen_frame(
                                 &script_runner,
-                                fighter_data.attributes.size,
-                            );
-                            let hit_boxes: Vec<_> =
-                                script_runner.hitboxes.iter().flatten().cloned().collect();
-                            let hit_boxes = gen_hit_boxes(
-                                &frame_bones,
-                                &hit_boxes,
-                                fighter_data.attributes.size,
-                            );
-                            let mut hl_hit_boxes: Vec<_> = hit_boxes
-                                .iter()
-                                .map(|next| {
-                                    let mut prev_pos = None;
-                                    let mut prev_size = None;
-                                    let mut prev_values = None;
-                                    if next.interpolate
-                                        && let Some(prev_hit_boxes) = &prev_hit_boxes
-                                    {
-                                        for prev_hit_box in prev_hit_boxes {
-                                            if prev_hit_box.hitbox_id == next.hitbox_id {
-                                                // A bit hacky, but we need to undo the movement that occured this frame to get the correct hitbox interpolation
-                                                prev_pos = Some(
-                                                    prev_hit_box.hitbox_position
-                                                        - Vector3::new(0.0, y_vel, x_vel),
-                                                );
-                                                prev_size = Some(prev_hit_box.size);
-                                                prev_values = Some(prev_hit_box.values.clone());
-                                            }
-                                        }
-                                    }
-
-                                    // abuse the hitbox interpolation fields to create stretch hitboxes
-                                    if next.values.stretches_to_bone() {
-                                        prev_pos = Some(next.bone_position);
-                                        prev_size = Some(next.size);
-                                        prev_values = Some(next.values.clone());
-                                    }
-
-                                    HighLevelHitBox {
-                                        hitbox_id: next.hitbox_id,
-
-                                        prev_pos,
-                                        prev_size,
-                                        prev_values,
-
-                                        next_pos: next.hitbox_position,
-                                        next_size: next.size,
-                                        next_values: next.values.clone(),
-                                    }
-                                })
-                                .collect();
-                            hl_hit_boxes.sort_by_key(|x| x.hitbox_id);
-
-                            let mut option_ecb = None;
-                            for misc_ecb in &fighter_data.misc.ecbs {
-                                let min_ecb = ECB {
-                                    // This implementation is just a guess from my observations that:
-                                    // *    The higher the min_width the higher the right ecb point.
-                                    // *    The higher the min_width the lower the left ecb point.
-                                    // *    When further than all bones, both points move equally far apart.
-                                    // *    When further than all bones, actions that affect the ecb horizontally no longer affect the ecb e.g. marth jab
-                                    left: -misc_ecb.min_width / 2.0, // TODO: Should I divide by 2.0 here?
-                                    right: misc_ecb.min_width / 2.0, // TODO: Should I divide by 2.0 here?
-                                    top: -10000.0,
-                                    bottom: 10000.0,
-                                    transn_x: 0.0,
-                                    transn_y: 0.0,
-                                };
-                                let mut ecb = gen_ecb(
-                                    &frame_bones,
-                                    &misc_ecb.bones,
-                                    &fighter_data.misc.bone_refs,
-                                    min_ecb,
-                                );
-
-                                // This implementation is just a guess from my observations that:
-                                // *    The higher the min_height the higher the top ecb point.
-                                // *    The higher the min_height the lower the bottom ecb point, capping out at transN.
-                                // *    Actions such as crouching, lower the height of the top ecb point.
-                                {
-                                    let middle_y = (ecb.top + ecb.bottom) / 2.0;
-                                    let new_top = middle_y + misc_ecb.min_height / 2.0;
-                                    let new_bottom = middle_y - misc_ecb.min_height / 2.0;
-                                    if new_top > ecb.top {
-                                        ecb.top = new_top;
-                                    }
-                                    if new_bottom < ecb.bottom {
-                                        ecb.bottom = new_bottom;
-                                    }
-                                }
-
-                                // Ensure ecb bottom does not go below transn_y
-                                if ecb.bottom < ecb.transn_y {
-                                    ecb.bottom = ecb.transn_y
-                                }
-
-                                option_ecb = Some(ecb);
-                            }
-                            let ecb = option_ecb.unwrap();
-
-                            let weight_dependent_speed = match actual_name.as_ref() {
-                                "ThrowLw" => attributes.weight_dependent_throw_down,
-                                "ThrowHi" => attributes.weight_dependent_throw_up,
-                                "ThrowF" => attributes.weight_dependent_throw_forward,
-                                "ThrowB" => attributes.weight_dependent_throw_backward,
-                                _ => false,
-                            };
-
-                            let mut throw = None;
-                            if let Some(ref specify_throw) = script_runner.throw
-                                && script_runner.throw_activate
-                            {
-                                throw = Some(HighLevelThrow {
-                                    damage: specify_throw.damage,
-                                    trajectory: specify_throw.trajectory,
-                                    kbg: specify_throw.kbg,
-                                    wdsk: specify_throw.wdsk,
-                                    bkb: specify_throw.bkb,
-                                    effect: specify_throw.effect.clone(),
-                                    sfx: specify_throw.sfx.clone(),
-                                    grab_target: specify_throw.grab_target.clone(),
-                                    i_frames: specify_throw.i_frames,
-                                    weight_dependent_speed,
-                                });
-                            }
-
-                            let ledge_grab_box = if script_runner.ledge_grab_enable.enabled() {
-                                // The first misc.ledge_grabs entry seems to be used for everything, not sure what the other entries are for.
-                                if let Some(ledge_grab_box) =
-                                    fighter_data.misc.ledge_grab_boxes.first()
-                                {
-                                    let left = if let LedgeGrabEnable::EnableInFrontAndBehind =
-                                        script_runner.ledge_grab_enable
-                                    {
-                                        ecb.left - ledge_grab_box.x_padding
-                                    } else {
-                                        ledge_grab_box.x_left
-                                    };
-
-                                    Some(Extent {
-                                        left,
-                                        right: ecb.right + ledge_grab_box.x_padding,
-                                        up: ledge_grab_box.y + ledge_grab_box.height,
-                                        down: ledge_grab_box.y,
-                                    })
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            };
-
-                            frames.push(HighLevelFrame {
-                                throw,
-                                ecb,
-                                x_pos,
-                                y_pos,
-                                x_vel_modify,
-                                y_vel_modify,
-                                x_vel_temp,
-                                y_vel_temp,
-                                ledge_grab_box,
-                                hurt_boxes,
-                                hit_boxes: hl_hit_boxes,
-                                interruptible: script_runner.interruptible,
-                                landing_lag: script_runner.landing_lag,
-                                edge_slide: script_runner.edge_slide.clone(),
-                                reverse_direction: script_runner.reverse_direction,
-                                airbourne: script_runner.airbourne,
-                                hitbox_sets_rehit: script_runner.hitbox_sets_rehit,
-                                slope_contour_stand: script_runner.slope_contour_stand,
-                                slope_contour_full: script_runner.slope_contour_full,
-                                rumble: script_runner.rumble,
-                                rumble_loop: script_runner.rumble_loop,
-                                grab_interrupt_damage: script_runner.grab_interrupt_damage,
-                            });
+                                chr0,
+                                num_frames,
+                                animation_flags,
+                                &actual_name,
+                            ));
 
-                            if iasa.is_none() && script_runner.interruptible {
+                            if iasa.is_none() && script_runner.can_interrupt() {
                                 iasa = Some(script_runner.frame_count)
                             }
 
                             script_runner.step();
-                            prev_hit_boxes = Some(hit_boxes);
 
                             if let ChangeSubaction::Continue = script_runner.change_subaction {
                             } else {
@@ -504,8 +305,6 @@ impl HighLevelFighter {
                         _ => None,
                     };
 
-                    let bad_interrupts = !script_runner.bad_interrupts.is_empty();
-
                     HighLevelSubaction {
                         name,
                         iasa,
@@ -513,7 +312,6 @@ impl HighLevelFighter {
                         frames,
                         animation_flags,
                         scripts,
-                        bad_interrupts,
                     }
                 })
                 .collect()
@@ -521,26 +319,173 @@ impl HighLevelFighter {
             vec![]
         };
 
+        let static_articles = fighter_data
+            .static_articles
+            .iter()
+            .enumerate()
+            .map(|(i, article)| (format!("StaticArticle{i}"), article));
+        let entry_article = fighter_data
+            .entry_article
+            .iter()
+            .map(|article| (String::from("EntryArticle"), article));
+        let articles = static_articles
+            .chain(entry_article)
+            .map(|(name, article)| {
+                let model = fighter.get_article_model(article);
+                // Name the article after its model when it has one
+                let name = model.map(|x| x.name.clone()).unwrap_or(name);
+                HighLevelArticle::new(
+                    name,
+                    article,
+                    fighter_sakurai,
+                    model.and_then(|x| x.bones.as_ref()),
+                    &fighter.get_article_animations(article),
+                )
+            })
+            .collect();
+
+        let model = if with_model {
+            fighter.get_model().map(|model| {
+                HighLevelModel::new(
+                    model,
+                    &fighter.get_model_textures(),
+                    &fighter.get_model_palettes(),
+                )
+            })
+        } else {
+            None
+        };
+
         HighLevelFighter {
             internal_name: fighter.cased_name.clone(),
             name: crate::fighter_maps::fighter_name(&fighter.cased_name),
             scripts_fragment_fighter: fragment_scripts_fighter,
             scripts_fragment_common: fragment_scripts_common,
             scripts_section,
+            articles,
             attributes,
             actions,
             subactions,
+            model,
         }
     }
 
+    /// Runs the action at `action_index` starting from its entry script.
+    /// Subaction changes made by the scripts are followed.
+    /// Action transitions are followed when `context.follow_interrupts` is set.
+    /// When `context.opponent_position` is set, hitboxes covering it connect, so requirements such as HitboxConnects can be met.
+    ///
+    /// The timeline ends when the animation of the current subaction finishes, the action never changes to a subaction or after `MAX_TIMELINE_FRAMES` frames.
+    /// `fighter` must be the fighter this HighLevelFighter was created from.
+    /// Returns None if there is no such action or the fighter has no model.
+    pub fn action_timeline(
+        &self,
+        fighter: &Fighter,
+        action_index: usize,
+        context: SimulationContext,
+    ) -> Option<Vec<HighLevelActionFrame>> {
+        let fighter_data = fighter.get_fighter_data()?;
+        let first_bone = fighter.get_bones()?;
+        let fighter_animations = fighter.get_animations();
+
+        let mut fighter_scripts: Vec<&ScriptAst> = self.scripts_fragment_fighter.iter().collect();
+        let mut common_scripts: Vec<&ScriptAst> = self.scripts_fragment_common.iter().collect();
+        common_scripts.extend(self.scripts_section.iter().map(|x| &x.script));
+        for subaction in &self.subactions {
+            let scripts = &subaction.scripts;
+            fighter_scripts.push(&scripts.script_main);
+            fighter_scripts.push(&scripts.script_gfx);
+            fighter_scripts.push(&scripts.script_sfx);
+            fighter_scripts.push(&scripts.script_other);
+        }
+        for action in &self.actions {
+            if action.script_entry_common {
+                common_scripts.push(&action.script_entry);
+            } else {
+                fighter_scripts.push(&action.script_entry);
+            }
+            if action.script_exit_common {
+                common_scripts.push(&action.script_exit);
+            } else {
+                fighter_scripts.push(&action.script_exit);
+            }
+        }
+
+        let opponent_position = context.opponent_position;
+        let mut script_runner = ScriptRunner::new_action(
+            action_index,
+            &fighter.wiird_frame_speed_modifiers,
+            &self.actions,
+            &self.subactions,
+            &fighter_scripts,
+            &common_scripts,
+            &self.scripts_section,
+            &fighter_data.model_visibility,
+            context,
+        )?;
+
+        let mut frame_generator = FrameGenerator::new(fighter_data, first_bone, false);
+        let mut subaction = None;
+        let mut frames = vec![];
+        while frames.len() < MAX_TIMELINE_FRAMES {
+            if script_runner.subaction_changed {
+                frame_generator.reset_animation();
+                subaction = fighter_data
+                    .subaction_flags
+                    .get(script_runner.subaction_index)
+                    .and_then(|flags| {
+                        let chr0 = fighter_animations.iter().find(|x| x.name == flags.name)?;
+                        let num_frames = subaction_num_frames(&self.attributes, &flags.name, chr0);
+                        Some((flags, *chr0, num_frames))
+                    });
+            }
+            let Some((flags, chr0, num_frames)) = subaction else {
+                break;
+            };
+            if script_runner.animation_index >= num_frames {
+                break;
+            }
+
+            let frame = frame_generator.gen_frame(
+                &script_runner,
+                chr0,
+                num_frames,
+                flags.animation_flags,
+                &flags.name,
+            );
+            script_runner.hitbox_connected = opponent_position.is_some_and(|(x, y)| {
+                frame.hit_boxes.iter().any(|hit_box| {
+                    let x = x - frame.x_pos - hit_box.next_pos.z;
+                    let y = y - frame.y_pos - hit_box.next_pos.y;
+                    x * x + y * y <= hit_box.next_size * hit_box.next_size
+                })
+            });
+            frames.push(HighLevelActionFrame {
+                action_index: script_runner.action_index.unwrap_or(action_index),
+                subaction_index: script_runner.subaction_index,
+                frame,
+            });
+
+            script_runner.step();
+
+            // Any remaining change could not be followed e.g. the subaction does not exist
+            if let ChangeSubaction::Continue = script_runner.change_subaction {
+            } else {
+                break;
+            }
+        }
+
+        Some(frames)
+    }
+
     /// Generates a tree of BoneTransforms from the specified animation frame applied on the passed tree of bones
     /// The resulting matrices are independent of its parent bones matrix.
     /// Returns a tuple containing:
     ///     0.  The MOVES_CHARACTER offset if enabled. this is used by e.g. Ness's double jump
     ///     1.  The BoneTransforms tree.
-    fn transform_bones(
+    pub(crate) fn transform_bones(
         bone: &Bone,
-        bone_refs: &BoneRefs,
+        trans_n: i32,
         parent_transform: Matrix4<f32>,
         parent_transform_hitbox: Matrix4<f32>,
         chr0: &Chr0,
@@ -559,7 +504,7 @@ impl HighLevelFighter {
         for chr0_child in &chr0.children {
             if chr0_child.name == bone.name {
                 let transform = parent_transform * chr0_child.get_transform(chr0.loop_value, frame);
-                if moves_character && bone.index == get_bone_index(bone_refs.trans_n) {
+                if moves_character && bone.index == get_bone_index(trans_n) {
                     // in this case TransN is not part of the animation but instead used to move the character in game.
                     assert!(offset.is_none());
                     offset = Some(Vector3::new(transform.w.x, transform.w.y, transform.w.z));
@@ -585,7 +530,7 @@ impl HighLevelFighter {
         for child in bone.children.iter() {
             let (moves, processed_child) = HighLevelFighter::transform_bones(
                 child,
-                bone_refs,
+                trans_n,
                 transform_normal,
                 transform_hitbox,
                 chr0,
@@ -616,6 +561,107 @@ pub struct BoneTransforms {
     pub children: Vec<BoneTransforms>,
 }
 
+/// A fighter model decoded into meshes, posed by `HighLevelFrame::bone_matrices`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighLevelModel {
+    pub meshes: Vec<HighLevelMesh>,
+    pub textures: Vec<HighLevelTexture>,
+    /// The inverse of the bind pose transform of every bone, indexed by `Bone::index`
+    pub bone_transforms_inv: Vec<Matrix4<f32>>,
+}
+
+impl HighLevelModel {
+    /// `textures` and `palettes` are searched for the textures used by the meshes
+    pub fn new(mdl0: &Mdl0, textures: &[&Tex0], palettes: &[&Plt0]) -> HighLevelModel {
+        let bones = mdl0.bones.as_ref().map(|x| x.flatten()).unwrap_or_default();
+        let mut bone_transforms_inv = vec![];
+        for bone in &bones {
+            let index = bone.bone.index.max(0) as usize;
+            if bone_transforms_inv.len() <= index {
+                bone_transforms_inv.resize(index + 1, Matrix4::identity());
+            }
+            bone_transforms_inv[index] = bone.bone.transform_inv;
+        }
+
+        let mut meshes = vec![];
+        let mut hl_textures: Vec<HighLevelTexture> = vec![];
+        for object in mdl0.objects.iter().flatten() {
+            let draw_call = mdl0.object_draw_call(object);
+
+            // Objects attached to a hidden bone are not drawn by default e.g. alternate facial expressions
+            let visible = draw_call
+                .and_then(|draw_call| {
+                    bones
+                        .iter()
+                        .find(|x| x.bone.index == draw_call.visibility_bone_node as i32)
+                })
+                .map(|x| x.bone.flags.contains(BoneFlags::VISIBLE))
+                .unwrap_or(true);
+            if !visible {
+                continue;
+            }
+
+            let mesh = match mdl0.mesh(object) {
+                Ok(mesh) => mesh,
+                Err(err) => {
+                    error!("Failed to decode mesh of MDL0 {}: {}", mdl0.name, err);
+                    continue;
+                }
+            };
+
+            let mut texture = None;
+            let texture_name = draw_call
+                .and_then(|x| mdl0.material(x.material))
+                .and_then(|x| x.textures.first())
+                .and_then(|x| x.texture_name.as_deref());
+            if let Some(name) = texture_name {
+                if let Some(index) = hl_textures.iter().position(|x| x.name == name) {
+                    texture = Some(index);
+                } else if let Some(tex0) = textures.iter().find(|x| x.name == name) {
+                    let palette = palettes.iter().find(|x| x.name == name).copied();
+                    match tex0.decode(0, palette) {
+                        Ok(rgba) => {
+                            let (width, height) = tex0.mipmap_size(0);
+                            texture = Some(hl_textures.len());
+                            hl_textures.push(HighLevelTexture {
+                                name: name.to_string(),
+                                width,
+                                height,
+                                rgba,
+                            });
+                        }
+                        Err(err) => error!("Failed to decode texture {}: {}", name, err),
+                    }
+                }
+            }
+
+            meshes.push(HighLevelMesh { mesh, texture });
+        }
+
+        HighLevelModel {
+            meshes,
+            textures: hl_textures,
+            bone_transforms_inv,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighLevelMesh {
+    pub mesh: Mesh,
+    /// Index into `HighLevelModel::textures`
+    pub texture: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighLevelTexture {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    /// RGBA8 pixels, stored row by row starting from the top left
+    pub rgba: Vec<u8>,
+}
+
 #[derive(Serialize, Clone, Debug)]
 pub struct HighLevelAction {
     pub name: String,
@@ -627,6 +673,20 @@ pub struct HighLevelAction {
     pub script_exit_common: bool,
 }
 
+/// Timelines generated by `HighLevelFighter::action_timeline` are cut off at this many frames
+/// because some actions loop forever e.g. CatchWait
+pub const MAX_TIMELINE_FRAMES: usize = 1000;
+
+/// A frame of the timeline generated by `HighLevelFighter::action_timeline`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighLevelActionFrame {
+    /// The action that was running on this frame, this changes when an interrupt is followed
+    pub action_index: usize,
+    /// The subaction that was running on this frame, this changes when the action or subaction scripts change the subaction
+    pub subaction_index: usize,
+    pub frame: HighLevelFrame,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct HighLevelSubaction {
     pub name: String,
@@ -635,8 +695,6 @@ pub struct HighLevelSubaction {
     pub landing_lag: Option<f32>,
     pub animation_flags: AnimationFlags,
     pub scripts: HighLevelScripts,
-    /// A hack where bad interrupts are ignored was used to process this subaction
-    pub bad_interrupts: bool,
 }
 
 impl HighLevelSubaction {
@@ -719,7 +777,10 @@ pub struct HighLevelFrame {
     pub ledge_grab_box: Option<Extent>,
     pub x_pos: f32,
     pub y_pos: f32,
+    /// true if anything can interrupt the subaction on this frame
     pub interruptible: bool,
+    /// The interrupts created by the subaction that are active on this frame
+    pub interrupts: Vec<HighLevelInterrupt>,
     pub edge_slide: EdgeSlide,
     pub reverse_direction: bool,
     pub airbourne: bool,
@@ -740,6 +801,9 @@ pub struct HighLevelFrame {
     pub x_vel_temp: f32,
     /// Does not affect the next frames velocity
     pub y_vel_temp: f32,
+    /// The transform of every bone, indexed by `Bone::index`.
+    /// Only generated by `HighLevelFighter::new_with_model`
+    pub bone_matrices: Vec<Matrix4<f32>>,
 }
 
 impl HighLevelFrame {
@@ -1082,7 +1146,7 @@ impl HitBoxValues {
 }
 
 #[derive(Clone, Debug)]
-struct PositionHitBox {
+pub(crate) struct PositionHitBox {
     pub values: CollisionBoxValues,
     pub hitbox_id: u8,
     pub size: f32,
@@ -1146,6 +1210,19 @@ fn gen_ecb(bone: &BoneTransforms, ecb_bones: &[i32], bone_refs: &BoneRefs, mut e
     ecb
 }
 
+/// Flattens the tree of BoneTransforms into the transform of every bone, indexed by `Bone::index`
+fn gen_bone_matrices(bone: &BoneTransforms, matrices: &mut Vec<Matrix4<f32>>) {
+    let index = bone.index.max(0) as usize;
+    if matrices.len() <= index {
+        matrices.resize(index + 1, Matrix4::identity());
+    }
+    matrices[index] = bone.transform_normal;
+
+    for child in &bone.children {
+        gen_bone_matrices(child, matrices);
+    }
+}
+
 fn gen_hurt_boxes(
     bone: &BoneTransforms,
     hurt_boxes: &[HurtBox],
@@ -1196,7 +1273,7 @@ fn gen_hurt_boxes(
     hl_hurt_boxes
 }
 
-fn gen_hit_boxes(
+pub(crate) fn gen_hit_boxes(
     bone: &BoneTransforms,
     hit_boxes: &[ScriptCollisionBox],
     fighter_size: f32,
@@ -1243,6 +1320,363 @@ fn gen_hit_boxes(
     pos_hit_boxes
 }
 
+/// Generates a HighLevelFrame from the state of a ScriptRunner.
+/// The position and velocity of the fighter is carried across the frames it generates.
+struct FrameGenerator<'a> {
+    fighter_data: &'a ArcFighterData,
+    first_bone: &'a Bone,
+    with_model: bool,
+    x_vel: f32,
+    y_vel: f32,
+    x_pos: f32,
+    y_pos: f32,
+    prev_animation_xyz_offset: Vector3<f32>,
+    prev_hit_boxes: Option<Vec<PositionHitBox>>,
+}
+
+impl<'a> FrameGenerator<'a> {
+    fn new(fighter_data: &'a ArcFighterData, first_bone: &'a Bone, with_model: bool) -> Self {
+        FrameGenerator {
+            fighter_data,
+            first_bone,
+            with_model,
+            x_vel: 0.0,
+            y_vel: 0.0,
+            x_pos: 0.0,
+            y_pos: 0.0,
+            prev_animation_xyz_offset: Vector3::new(0.0, 0.0, 0.0),
+            prev_hit_boxes: None,
+        }
+    }
+
+    /// Needs to be called when the animation is changed, so that the offset of the new animation is not treated as movement.
+    fn reset_animation(&mut self) {
+        self.prev_animation_xyz_offset = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    /// `num_frames` is the length of the subaction, `chr0` is stretched to fit it.
+    fn gen_frame(
+        &mut self,
+        script_runner: &ScriptRunner,
+        chr0: &Chr0,
+        num_frames: f32,
+        animation_flags: AnimationFlags,
+        subaction_name: &str,
+    ) -> HighLevelFrame {
+        let chr0_frame_index = script_runner.animation_index * chr0.num_frames as f32 / num_frames; // map frame count between [0, chr0.num_frames]
+        let (animation_xyz_offset, frame_bones) = HighLevelFighter::transform_bones(
+            self.first_bone,
+            self.fighter_data.misc.bone_refs.trans_n,
+            Matrix4::<f32>::identity(),
+            Matrix4::<f32>::identity(),
+            chr0,
+            chr0_frame_index as i32,
+            animation_flags,
+            self.fighter_data.attributes.size,
+        );
+        let animation_xyz_offset =
+            animation_xyz_offset.unwrap_or_else(|| Vector3::new(0.0, 0.0, 0.0));
+        // TODO: should DisableMovement affect xyz_offset from transform_bones?????
+        // script runner x-axis is equivalent to model z-axis
+
+        let animation_xyz_velocity = animation_xyz_offset - self.prev_animation_xyz_offset;
+        self.prev_animation_xyz_offset = animation_xyz_offset;
+
+        let x_vel_modify = script_runner.x_vel_modify.clone();
+        let y_vel_modify = script_runner.y_vel_modify.clone();
+
+        let x_vel_temp = animation_xyz_velocity.z;
+        let y_vel_temp = animation_xyz_velocity.y;
+
+        match x_vel_modify {
+            VelModify::Set(vel) => self.x_vel = vel,
+            VelModify::Add(vel) => self.x_vel += vel,
+            VelModify::None => {}
+        }
+
+        match y_vel_modify {
+            VelModify::Set(vel) => self.y_vel = vel,
+            VelModify::Add(vel) => self.y_vel += vel,
+            VelModify::None => {}
+        }
+
+        self.x_pos += self.x_vel + x_vel_temp;
+        self.y_pos += self.y_vel + y_vel_temp;
+
+        let hurt_boxes = gen_hurt_boxes(
+            &frame_bones,
+            &self.fighter_data.misc.hurt_boxes,
+            script_runner,
+            self.fighter_data.attributes.size,
+        );
+        let hit_boxes: Vec<_> = script_runner.hitboxes.iter().flatten().cloned().collect();
+        let hit_boxes = gen_hit_boxes(&frame_bones, &hit_boxes, self.fighter_data.attributes.size);
+        let hl_hit_boxes = gen_high_level_hit_boxes(
+            &hit_boxes,
+            self.prev_hit_boxes.as_deref(),
+            self.x_vel,
+            self.y_vel,
+        );
+
+        let mut option_ecb = None;
+        for misc_ecb in &self.fighter_data.misc.ecbs {
+            let min_ecb = ECB {
+                // This implementation is just a guess from my observations that:
+                // *    The higher the min_width the higher the right ecb point.
+                // *    The higher the min_width the lower the left ecb point.
+                // *    When further than all bones, both points move equally far apart.
+                // *    When further than all bones, actions that affect the ecb horizontally no longer affect the ecb e.g. marth jab
+                left: -misc_ecb.min_width / 2.0, // TODO: Should I divide by 2.0 here?
+                right: misc_ecb.min_width / 2.0, // TODO: Should I divide by 2.0 here?
+                top: -10000.0,
+                bottom: 10000.0,
+                transn_x: 0.0,
+                transn_y: 0.0,
+            };
+            let mut ecb = gen_ecb(
+                &frame_bones,
+                &misc_ecb.bones,
+                &self.fighter_data.misc.bone_refs,
+                min_ecb,
+            );
+
+            // This implementation is just a guess from my observations that:
+            // *    The higher the min_height the higher the top ecb point.
+            // *    The higher the min_height the lower the bottom ecb point, capping out at transN.
+            // *    Actions such as crouching, lower the height of the top ecb point.
+            {
+                let middle_y = (ecb.top + ecb.bottom) / 2.0;
+                let new_top = middle_y + misc_ecb.min_height / 2.0;
+                let new_bottom = middle_y - misc_ecb.min_height / 2.0;
+                if new_top > ecb.top {
+                    ecb.top = new_top;
+                }
+                if new_bottom < ecb.bottom {
+                    ecb.bottom = new_bottom;
+                }
+            }
+
+            // Ensure ecb bottom does not go below transn_y
+            if ecb.bottom < ecb.transn_y {
+                ecb.bottom = ecb.transn_y
+            }
+
+            option_ecb = Some(ecb);
+        }
+        let ecb = option_ecb.unwrap();
+
+        let weight_dependent_speed = match subaction_name {
+            "ThrowLw" => self.fighter_data.attributes.weight_dependent_throw_down,
+            "ThrowHi" => self.fighter_data.attributes.weight_dependent_throw_up,
+            "ThrowF" => self.fighter_data.attributes.weight_dependent_throw_forward,
+            "ThrowB" => self.fighter_data.attributes.weight_dependent_throw_backward,
+            _ => false,
+        };
+
+        let mut throw = None;
+        if let Some(ref specify_throw) = script_runner.throw
+            && script_runner.throw_activate
+        {
+            throw = Some(HighLevelThrow {
+                damage: specify_throw.damage,
+                trajectory: specify_throw.trajectory,
+                kbg: specify_throw.kbg,
+                wdsk: specify_throw.wdsk,
+                bkb: specify_throw.bkb,
+                effect: specify_throw.effect.clone(),
+                sfx: specify_throw.sfx.clone(),
+                grab_target: specify_throw.grab_target.clone(),
+                i_frames: specify_throw.i_frames,
+                weight_dependent_speed,
+            });
+        }
+
+        let ledge_grab_box = if script_runner.ledge_grab_enable.enabled() {
+            // The first misc.ledge_grabs entry seems to be used for everything, not sure what the other entries are for.
+            if let Some(ledge_grab_box) = self.fighter_data.misc.ledge_grab_boxes.first() {
+                let left = if let LedgeGrabEnable::EnableInFrontAndBehind =
+                    script_runner.ledge_grab_enable
+                {
+                    ecb.left - ledge_grab_box.x_padding
+                } else {
+                    ledge_grab_box.x_left
+                };
+
+                Some(Extent {
+                    left,
+                    right: ecb.right + ledge_grab_box.x_padding,
+                    up: ledge_grab_box.y + ledge_grab_box.height,
+                    down: ledge_grab_box.y,
+                })
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        let mut bone_matrices = vec![];
+        if self.with_model {
+            gen_bone_matrices(&frame_bones, &mut bone_matrices);
+        }
+
+        let frame = HighLevelFrame {
+            throw,
+            bone_matrices,
+            ecb,
+            x_pos: self.x_pos,
+            y_pos: self.y_pos,
+            x_vel_modify,
+            y_vel_modify,
+            x_vel_temp,
+            y_vel_temp,
+            ledge_grab_box,
+            hurt_boxes,
+            hit_boxes: hl_hit_boxes,
+            interruptible: script_runner.can_interrupt(),
+            interrupts: gen_high_level_interrupts(script_runner),
+            landing_lag: script_runner.landing_lag,
+            edge_slide: script_runner.edge_slide.clone(),
+            reverse_direction: script_runner.reverse_direction,
+            airbourne: script_runner.airbourne,
+            hitbox_sets_rehit: script_runner.hitbox_sets_rehit,
+            slope_contour_stand: script_runner.slope_contour_stand,
+            slope_contour_full: script_runner.slope_contour_full,
+            rumble: script_runner.rumble,
+            rumble_loop: script_runner.rumble_loop,
+            grab_interrupt_damage: script_runner.grab_interrupt_damage,
+        };
+        self.prev_hit_boxes = Some(hit_boxes);
+        frame
+    }
+}
+
+/// The length of the subaction, this is normally the length of the animation but some subactions use an attribute instead
+fn subaction_num_frames(attributes: &FighterAttributes, subaction_name: &str, chr0: &Chr0) -> f32 {
+    match subaction_name {
+        "JumpSquat" => attributes.jump_squat_frames as f32,
+        "LandingAirN" => attributes.nair_landing_lag,
+        "LandingAirF" => attributes.fair_landing_lag,
+        "LandingAirB" => attributes.bair_landing_lag,
+        "LandingAirHi" => attributes.uair_landing_lag,
+        "LandingAirLw" => attributes.dair_landing_lag,
+        "LandingLight" => attributes.light_landing_lag, // TODO: This needs +1 do the others?!?!?
+        "LandingHeavy" => attributes.normal_landing_lag,
+        _ => chr0.num_frames as f32,
+    }
+}
+
+/// An interrupt that can change the current action to `action`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighLevelInterrupt {
+    pub interrupt_type: InterruptType,
+    pub interrupt_id: Option<i32>,
+    pub action: i32,
+    /// true if the requirements of the interrupt were met by the `SimulationContext` used to process the subaction
+    pub requirement_met: bool,
+}
+
+pub(crate) fn gen_high_level_interrupts(script_runner: &ScriptRunner) -> Vec<HighLevelInterrupt> {
+    script_runner
+        .active_interrupts()
+        .map(|x| HighLevelInterrupt {
+            interrupt_type: x.interrupt_type.clone(),
+            interrupt_id: x.interrupt_id,
+            action: x.action,
+            requirement_met: x.requirement_met,
+        })
+        .collect()
+}
+
+/// Pairs each hitbox with the same hitbox from the previous frame so it can be interpolated
+pub(crate) fn gen_high_level_hit_boxes(
+    hit_boxes: &[PositionHitBox],
+    prev_hit_boxes: Option<&[PositionHitBox]>,
+    x_vel: f32,
+    y_vel: f32,
+) -> Vec<HighLevelHitBox> {
+    let mut hl_hit_boxes: Vec<_> = hit_boxes
+        .iter()
+        .map(|next| {
+            let mut prev_pos = None;
+            let mut prev_size = None;
+            let mut prev_values = None;
+            if next.interpolate
+                && let Some(prev_hit_boxes) = prev_hit_boxes
+            {
+                for prev_hit_box in prev_hit_boxes {
+                    if prev_hit_box.hitbox_id == next.hitbox_id {
+                        // A bit hacky, but we need to undo the movement that occured this frame to get the correct hitbox interpolation
+                        prev_pos =
+                            Some(prev_hit_box.hitbox_position - Vector3::new(0.0, y_vel, x_vel));
+                        prev_size = Some(prev_hit_box.size);
+                        prev_values = Some(prev_hit_box.values.clone());
+                    }
+                }
+            }
+
+            // abuse the hitbox interpolation fields to create stretch hitboxes
+            if next.values.stretches_to_bone() {
+                prev_pos = Some(next.bone_position);
+                prev_size = Some(next.size);
+                prev_values = Some(next.values.clone());
+            }
+
+            HighLevelHitBox {
+                hitbox_id: next.hitbox_id,
+
+                prev_pos,
+                prev_size,
+                prev_values,
+
+                next_pos: next.hitbox_position,
+                next_size: next.size,
+                next_values: next.values.clone(),
+            }
+        })
+        .collect();
+    hl_hit_boxes.sort_by_key(|x| x.hitbox_id);
+    hl_hit_boxes
+}
+
+/// Returns the first action whose entry script changes to the subaction at `subaction_index`.
+/// Only the top level of the entry scripts is checked, subactions entered in any other way return None.
+///
+/// This is a heuristic, a subaction does not know which action it runs in.
+/// When several actions enter the same subaction, or the subaction is entered by a script or interrupt, the guess can be wrong.
+/// Callers that know the action can override `InternalConstantInt::CurrentAction` in the context passed to `HighLevelFighter::new`,
+/// or use `HighLevelFighter::action_timeline` to simulate a subaction as part of a known action.
+fn entering_action(actions: &[HighLevelAction], subaction_index: usize) -> Option<usize> {
+    actions.iter().position(|action| {
+        action
+            .script_entry
+            .block
+            .events
+            .iter()
+            .any(|event| match event {
+                EventAst::ChangeSubaction(subaction)
+                | EventAst::ChangeSubactionRestartFrame(subaction) => {
+                    *subaction as usize == subaction_index
+                }
+                _ => false,
+            })
+    })
+}
+
+/// Subactions can share a name, so later subactions with the same name get a numeric suffix
+pub(crate) fn unique_subaction_name(subaction_flags: &[SubactionFlags], index: usize) -> String {
+    let actual_name = &subaction_flags[index].name;
+    let count = subaction_flags[..index]
+        .iter()
+        .filter(|x| &x.name == actual_name)
+        .count();
+    if count == 0 {
+        actual_name.clone()
+    } else {
+        format!("{}_{}", actual_name, count)
+    }
+}
+
 // Kirby and Wario have indexes starting at 400.
 // These special indices can be remapped at runtime to allow moves to function after swapping models e.g. kirby's copy ability
 // By default these bone remappings are not used by any moves so we can safely ignore them by just offsetting back to 0.
@@ -1273,3 +1707,288 @@ impl SectionScriptAst {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::arc::tests::arc_fixture;
+    use crate::arc::{Arc, ArcChildData, try_arc};
+    use crate::bres::{Bres, BresChild, BresChildData};
+    use crate::fighter::ModType;
+    use crate::mdl0::bones::tests::bone;
+    use crate::mdl0::tests::mdl0;
+    use crate::sakurai::SectionData;
+    use crate::sakurai::fighter_data::misc_section::{ECB, ECBKind};
+    use crate::sakurai::tests::fighter_fixture;
+    use crate::script::Requirement;
+    use crate::script_ast::{Expression, FloatValue};
+    use crate::script_runner::tests::{create_interrupt, equals, script};
+    use crate::wii_memory::WiiMemory;
+    use fancy_slice::FancySlice;
+
+    const ANIMATION_FRAMES: u16 = 10;
+
+    /// An ARC named `name` whose only child is a BRES containing `children` in a folder
+    fn bres_arc(name: &str, children: Vec<BresChildData>) -> Arc {
+        let data = arc_fixture(name, &[b"DUMMY\0\0\0"]);
+        let mut arc = try_arc(FancySlice::new(&data), &WiiMemory::new(), false).unwrap();
+        let children = children
+            .into_iter()
+            .map(|data| BresChild {
+                name: String::new(),
+                data,
+            })
+            .collect();
+        arc.children[0].data = ArcChildData::Bres(Bres {
+            endian: 0xfeff,
+            version: 0,
+            children: vec![BresChild {
+                name: String::new(),
+                data: BresChildData::Bres(children),
+            }],
+        });
+        arc
+    }
+
+    /// A fighter with a single bone model and an animation shared by all of its subactions
+    fn fighter() -> Fighter {
+        let moveset = arc_fixture("FitTest", &[&fighter_fixture()]);
+        let mut moveset = try_arc(FancySlice::new(&moveset), &WiiMemory::new(), false).unwrap();
+        // Every frame needs an ECB
+        let ArcChildData::Sakurai(sakurai) = &mut moveset.children[0].data else {
+            unreachable!()
+        };
+        let SectionData::FighterData(fighter_data) = &mut sakurai.sections[0].data else {
+            unreachable!()
+        };
+        fighter_data.misc.ecbs.push(ECB {
+            kind: ECBKind::Fixed,
+            bones: vec![],
+            min_height: 10.0,
+            min_width: 5.0,
+            unk: 0.0,
+        });
+
+        let model = mdl0("FitTest00", bone("TopN", 0, vec![]));
+        let animation = Chr0::new(String::new(), ANIMATION_FRAMES, false, vec![]);
+        Fighter {
+            cased_name: "Test".to_string(),
+            moveset_common: Arc {
+                name: "Fighter".to_string(),
+                children: vec![],
+            },
+            moveset,
+            motion: bres_arc("FitTestMotion", vec![BresChildData::Chr0(animation)]),
+            models: vec![bres_arc("FitTest00", vec![BresChildData::Mdl0(model)])],
+            kirby_hats: vec![],
+            modded_by_psa: false,
+            mod_type: ModType::NotMod,
+            wiird_frame_speed_modifiers: vec![],
+        }
+    }
+
+    pub(crate) fn action(script_entry: Vec<EventAst>) -> HighLevelAction {
+        HighLevelAction {
+            name: String::new(),
+            script_entry: script(script_entry),
+            script_exit: script(vec![]),
+            script_entry_common: false,
+            script_exit_common: false,
+        }
+    }
+
+    pub(crate) fn subaction(script_main: Vec<EventAst>) -> HighLevelSubaction {
+        HighLevelSubaction {
+            name: String::new(),
+            iasa: None,
+            frames: vec![],
+            landing_lag: None,
+            animation_flags: AnimationFlags::empty(),
+            scripts: HighLevelScripts {
+                script_main: script(script_main),
+                script_gfx: script(vec![]),
+                script_sfx: script(vec![]),
+                script_other: script(vec![]),
+            },
+        }
+    }
+
+    /// Action 0 runs subaction 0, which can interrupt into action 1 on its third frame.
+    /// Action 1 runs subaction 1.
+    fn high_level_fighter(fighter: &Fighter) -> HighLevelFighter {
+        HighLevelFighter {
+            name: "Test".to_string(),
+            internal_name: "Test".to_string(),
+            attributes: fighter.get_fighter_data().unwrap().attributes.clone(),
+            actions: vec![
+                action(vec![EventAst::ChangeSubactionRestartFrame(0)]),
+                action(vec![EventAst::ChangeSubactionRestartFrame(1)]),
+                action(vec![]),
+            ],
+            subactions: vec![
+                subaction(vec![
+                    EventAst::SyncWait(2.0),
+                    create_interrupt(1, 1, equals(0, 0)),
+                ]),
+                subaction(vec![]),
+            ],
+            scripts_fragment_fighter: vec![],
+            scripts_fragment_common: vec![],
+            scripts_section: vec![],
+            articles: vec![],
+            model: None,
+        }
+    }
+
+    fn indexes(frames: &[HighLevelActionFrame]) -> Vec<(usize, usize)> {
+        frames
+            .iter()
+            .map(|x| (x.action_index, x.subaction_index))
+            .collect()
+    }
+
+    #[test]
+    fn action_timeline() {
+        let fighter = fighter();
+        let hl_fighter = high_level_fighter(&fighter);
+        let frames = hl_fighter
+            .action_timeline(&fighter, 0, SimulationContext::default())
+            .unwrap();
+
+        // The interrupt is only recorded, so the subaction runs until its animation ends
+        assert_eq!(indexes(&frames), vec![(0, 0); ANIMATION_FRAMES as usize]);
+        assert!(frames[1].frame.interrupts.is_empty());
+        assert_eq!(frames[2].frame.interrupts.len(), 1);
+        assert_eq!(frames[2].frame.interrupts[0].action, 1);
+        assert!(frames[2].frame.interrupts[0].requirement_met);
+    }
+
+    #[test]
+    fn action_timeline_follow_interrupts() {
+        let fighter = fighter();
+        let hl_fighter = high_level_fighter(&fighter);
+        let context = SimulationContext {
+            follow_interrupts: true,
+            ..SimulationContext::default()
+        };
+        let frames = hl_fighter.action_timeline(&fighter, 0, context).unwrap();
+
+        let mut expected = vec![(0, 0); 2];
+        expected.extend(vec![(1, 1); ANIMATION_FRAMES as usize]);
+        assert_eq!(indexes(&frames), expected);
+    }
+
+    #[test]
+    fn action_timeline_follow_interrupts_keep_frame() {
+        let fighter = fighter();
+        let mut hl_fighter = high_level_fighter(&fighter);
+        hl_fighter.actions[1] = action(vec![EventAst::ChangeSubaction(1)]);
+        let context = SimulationContext {
+            follow_interrupts: true,
+            ..SimulationContext::default()
+        };
+        let frames = hl_fighter.action_timeline(&fighter, 0, context).unwrap();
+
+        // The new subaction continues from the frame the interrupt happened on
+        let mut expected = vec![(0, 0); 2];
+        expected.extend(vec![(1, 1); ANIMATION_FRAMES as usize - 2]);
+        assert_eq!(indexes(&frames), expected);
+    }
+
+    #[test]
+    fn action_timeline_without_subaction() {
+        let fighter = fighter();
+        let hl_fighter = high_level_fighter(&fighter);
+        let frames = hl_fighter
+            .action_timeline(&fighter, 2, SimulationContext::default())
+            .unwrap();
+        assert!(frames.is_empty());
+
+        assert!(
+            hl_fighter
+                .action_timeline(&fighter, 3, SimulationContext::default())
+                .is_none()
+        );
+    }
+
+    /// A hitbox on the TopN bone at the fighters origin
+    fn hit_box(size: f32) -> EventAst {
+        EventAst::CreateHitBox(HitBoxArguments {
+            bone_index: 0,
+            hitbox_id: 0,
+            set_id: 0,
+            damage: FloatValue::Constant(10.0),
+            trajectory: 45,
+            wdsk: 0,
+            kbg: 100,
+            shield_damage: 0,
+            bkb: 10,
+            size,
+            x_offset: 0.0,
+            y_offset: 0.0,
+            z_offset: 0.0,
+            tripping_rate: 0.0,
+            hitlag_mult: 1.0,
+            sdi_mult: 1.0,
+            effect: HitBoxEffect::Normal,
+            unk1: false,
+            sound_level: 0,
+            unk2: false,
+            sound: HitBoxSound::Punch,
+            unk3: 0,
+            ground: true,
+            aerial: true,
+            unk4: 0,
+            sse_type: HitBoxSseType::None,
+            clang: true,
+            unk5: false,
+            direct: true,
+            unk6: 0,
+        })
+    }
+
+    #[test]
+    fn action_timeline_hitbox_connects() {
+        let fighter = fighter();
+        let mut hl_fighter = high_level_fighter(&fighter);
+        hl_fighter.subactions[0] = subaction(vec![
+            hit_box(5.0),
+            create_interrupt(1, 1, Expression::Nullary(Requirement::HitboxConnects)),
+        ]);
+
+        for (opponent_position, expected) in [
+            (None, vec![(0, 0); ANIMATION_FRAMES as usize]),
+            (Some((100.0, 0.0)), vec![(0, 0); ANIMATION_FRAMES as usize]),
+            // the hitbox connects on the first frame, so the interrupt is followed on the next
+            (Some((0.0, 0.0)), {
+                let mut expected = vec![(0, 0)];
+                expected.extend(vec![(1, 1); ANIMATION_FRAMES as usize]);
+                expected
+            }),
+        ] {
+            let context = SimulationContext {
+                follow_interrupts: true,
+                opponent_position,
+                ..SimulationContext::default()
+            };
+            let frames = hl_fighter.action_timeline(&fighter, 0, context).unwrap();
+            assert_eq!(
+                indexes(&frames),
+                expected,
+                "opponent at {opponent_position:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn entering_action_heuristic() {
+        let actions = vec![
+            action(vec![EventAst::SyncWait(1.0)]),
+            action(vec![EventAst::ChangeSubaction(1)]),
+            action(vec![EventAst::ChangeSubactionRestartFrame(1)]),
+        ];
+        // The first of the actions that enter the subaction is picked
+        assert_eq!(entering_action(&actions, 1), Some(1));
+        assert_eq!(entering_action(&actions, 0), None);
+    }
+}
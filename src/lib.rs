@@ -8,6 +8,7 @@
 //! ```rust,no_run
 //! use brawllib_rs::brawl_mod::BrawlMod;
 //! use brawllib_rs::high_level_fighter::HighLevelFighter;
+//! use brawllib_rs::script_runner::SimulationContext;
 //! use std::path::PathBuf;
 //!
 //! let brawl_path = PathBuf::from("path/to/a/brawl/dump/folder");
@@ -18,7 +19,7 @@
 //!     println!("Fighter name: {}", fighter.cased_name);
 //!     println!("The name of the first model file name: {}", fighter.models[0].name);
 //!
-//!     let hl_fighter = HighLevelFighter::new(&fighter);
+//!     let hl_fighter = HighLevelFighter::new(&fighter, SimulationContext::default());
 //!     println!("Hurtboxes on the 4th frame of 'Run' action {:#?}", hl_fighter.subactions.iter().find(|x| x.name == "Run").unwrap().frames[4].hurt_boxes);
 //! }
 //! ```
@@ -36,27 +37,44 @@ extern crate bitflags;
 extern crate log;
 
 mod action_names;
+pub mod aipd;
 pub mod arc;
+pub mod atkd;
 pub mod brawl_mod;
 pub mod bres;
 pub mod chr0;
-mod compression;
+pub mod chr0_import;
+pub mod clr0;
+pub mod coll;
+pub mod compression;
+pub mod efls;
 pub mod fighter;
 mod fighter_maps;
+pub mod gltf;
+pub mod high_level_article;
 pub mod high_level_fighter;
 mod init_hack_script;
 pub mod math;
 pub mod mbox;
 pub mod mdl0;
+pub mod parse_error;
+pub mod pat0;
 pub mod plt0;
+pub mod reff;
+pub mod reft;
 pub mod renderer;
 pub mod resources;
 pub mod sakurai;
 pub mod script;
 pub mod script_ast;
 pub mod script_runner;
+pub mod shp0;
+pub mod srt0;
+pub mod stage;
+pub mod tex0;
 pub mod user_data;
 mod util;
+pub mod vis0;
 pub mod wii_memory;
 pub mod wii_texture_formats;
 pub mod wiird_runner;
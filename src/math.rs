@@ -1,4 +1,4 @@
-use cgmath::{Matrix4, Vector3};
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
 use std::f32::consts::PI;
 
 pub fn gen_transform(
@@ -29,3 +29,29 @@ pub fn gen_transform(
         1.0,
     )
 }
+
+/// The inverse of `gen_transform`, returns the scale, rotation in degrees and translation of the transform.
+/// Any shear in the transform is lost.
+pub fn decompose_transform(transform: Matrix4<f32>) -> (Vector3<f32>, Vector3<f32>, Vector3<f32>) {
+    let translate = transform.w.truncate();
+    let mut scale = Vector3::new(
+        transform.x.truncate().magnitude(),
+        transform.y.truncate().magnitude(),
+        transform.z.truncate().magnitude(),
+    );
+    if transform.determinant() < 0.0 {
+        scale.x = -scale.x;
+    }
+
+    let x = transform.x.truncate() / scale.x;
+    let y = transform.y.truncate() / scale.y;
+    let z = transform.z.truncate() / scale.z;
+    let rot = if x.z.abs() < 0.99999 {
+        Vector3::new(y.z.atan2(z.z), (-x.z).asin(), x.y.atan2(x.x))
+    } else {
+        // gimbal lock, the x and z rotations are about the same axis so put it all in z
+        Vector3::new(0.0, (-x.z).clamp(-1.0, 1.0).asin(), (-y.x).atan2(y.y))
+    };
+
+    (scale, rot * (180.0 / PI), translate)
+}
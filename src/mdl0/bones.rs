@@ -4,20 +4,38 @@ use fancy_slice::FancySlice;
 use crate::math;
 use crate::mbox;
 use crate::mbox::MBox;
-use crate::resources::Resource;
-
-pub(crate) fn bones(data: FancySlice, resources: Vec<Resource>) -> Bone {
-    bone_siblings(
-        data.relative_fancy_slice(resources[0].data_offset as usize..),
-        0,
-    )
-    .pop()
-    .unwrap()
+use crate::parse_error::{ParseError, ParseErrorKind, try_offset, try_str};
+use crate::resources::{Resource, StringTable};
+
+pub(crate) fn bones(data: FancySlice, resources: Vec<Resource>) -> Result<Bone, ParseError> {
+    let root = resources.first().ok_or_else(|| {
+        ParseError::new(
+            "MDL0 bone",
+            0,
+            ParseErrorKind::Malformed(String::from("the bone group is empty")),
+        )
+    })?;
+    let root_offset = root.data_offset as i64;
+    let data_root = try_offset(&data, root_offset, 0, "MDL0 bone")?;
+    let mut visited = vec![];
+    let mut siblings = bone_siblings(data_root, 0, &mut visited)
+        .map_err(|err| err.offset_by(root_offset as usize))?;
+    // bone_siblings always returns at least the bone it was given
+    Ok(siblings.pop().unwrap())
 }
 
+/// `visited` holds the offset of every bone read so far, so that a malformed hierarchy that loops back on itself is rejected.
 #[rustfmt::skip]
-fn bone_siblings(data_root: FancySlice, offset: i32) -> Vec<Bone> {
-    let data = data_root.relative_fancy_slice(offset as usize..);
+fn bone_siblings(data_root: FancySlice, offset: i64, visited: &mut Vec<i64>) -> Result<Vec<Bone>, ParseError> {
+    if visited.contains(&offset) {
+        return Err(ParseError::new(
+            "MDL0 bone",
+            offset as usize,
+            ParseErrorKind::Malformed(String::from("the bone hierarchy loops back on itself")),
+        ));
+    }
+    visited.push(offset);
+    let data = try_offset(&data_root, offset, BONE_SIZE, "MDL0 bone")?;
 
     let _header_len   = data.i32_be(0x00);
     let mdl0_offset   = data.i32_be(0x04);
@@ -127,9 +145,15 @@ fn bone_siblings(data_root: FancySlice, offset: i32) -> Vec<Bone> {
          1.0,
     );
 
-    let name = data.str(string_offset as usize).unwrap().to_string();
+    let malformed = |message: String| {
+        ParseError::new("MDL0 bone", offset as usize, ParseErrorKind::Malformed(message))
+    };
+    let name = try_str(&data, string_offset as usize, "MDL0 bone")
+        .map_err(|err| err.offset_by(offset as usize))?
+        .to_string();
 
-    let flags = BoneFlags::from_bits(flags_int).unwrap();
+    let flags = BoneFlags::from_bits(flags_int)
+        .ok_or_else(|| malformed(format!("unknown bone flags 0x{flags_int:x}")))?;
     let billboard = match billboard_int {
         0 => BoneBillboard::Off,
         1 => BoneBillboard::Standard,
@@ -138,19 +162,19 @@ fn bone_siblings(data_root: FancySlice, offset: i32) -> Vec<Bone> {
         4 => BoneBillboard::RotationPerspective,
         5 => BoneBillboard::Y,
         6 => BoneBillboard::YPerspective,
-        _ => panic!("invalid bone billboard"),
+        _ => return Err(malformed(format!("invalid bone billboard {billboard_int}"))),
     };
 
     let children = if first_child_offset == 0 {
         vec!()
     } else {
-        bone_siblings(data_root, offset + first_child_offset)
+        bone_siblings(data_root, offset + first_child_offset as i64, visited)?
     };
 
     let mut siblings = if next_offset == 0 {
         vec!()
     } else {
-        bone_siblings(data_root, offset + next_offset)
+        bone_siblings(data_root, offset + next_offset as i64, visited)?
     };
 
     siblings.push(Bone {
@@ -172,9 +196,10 @@ fn bone_siblings(data_root: FancySlice, offset: i32) -> Vec<Bone> {
         transform_inv,
         children,
     });
-    siblings
+    Ok(siblings)
 }
 
+pub(crate) const BONE_SIZE: usize = 0xd0;
 #[derive(Debug, Clone)]
 pub struct Bone {
     pub name: String,
@@ -203,6 +228,13 @@ impl Bone {
         math::gen_transform(self.scale, self.rot, self.translate)
     }
 
+    /// Returns this bone and all of its descendants in the order they are stored in the MDL0.
+    pub(crate) fn flatten(&self) -> Vec<FlatBone<'_>> {
+        let mut flat = vec![];
+        flatten_siblings(std::iter::once(self), None, &mut flat);
+        flat
+    }
+
     pub(crate) fn gen_transform_rot_only(&self) -> Matrix4<f32> {
         math::gen_transform(
             Vector3::new(1.0, 1.0, 1.0),
@@ -212,6 +244,102 @@ impl Bone {
     }
 }
 
+/// A bone along with the indexes of its neighbours in the list returned by `Bone::flatten`
+pub(crate) struct FlatBone<'a> {
+    pub bone: &'a Bone,
+    parent: Option<usize>,
+    first_child: Option<usize>,
+    next: Option<usize>,
+    prev: Option<usize>,
+}
+
+/// Returns the index of the first sibling
+fn flatten_siblings<'a>(
+    siblings: impl Iterator<Item = &'a Bone>,
+    parent: Option<usize>,
+    flat: &mut Vec<FlatBone<'a>>,
+) -> Option<usize> {
+    let mut first = None;
+    let mut prev = None;
+    for bone in siblings {
+        let index = flat.len();
+        flat.push(FlatBone {
+            bone,
+            parent,
+            first_child: None,
+            next: None,
+            prev,
+        });
+        match prev {
+            Some(prev) => flat[prev].next = Some(index),
+            None => first = Some(index),
+        }
+
+        // bone_siblings stores the children in reverse order
+        flat[index].first_child = flatten_siblings(bone.children.iter().rev(), Some(index), flat);
+        prev = Some(index);
+    }
+    first
+}
+
+/// Writes the bones returned by `Bone::flatten` to the end of `output`, the MDL0 being compiled.
+/// Returns the offset of each bone.
+pub(crate) fn compile_bones(
+    bones: &[FlatBone],
+    output: &mut Vec<u8>,
+    strings: &mut StringTable,
+) -> Vec<usize> {
+    while output.len() % 4 != 0 {
+        output.push(0x00);
+    }
+    let start = output.len();
+    let offsets: Vec<usize> = (0..bones.len()).map(|i| start + i * BONE_SIZE).collect();
+    let relative = |index: Option<usize>, offset: usize| {
+        index
+            .map(|x| offsets[x] as i32 - offset as i32)
+            .unwrap_or(0)
+    };
+
+    for (flat, &offset) in bones.iter().zip(offsets.iter()) {
+        let bone = flat.bone;
+        let transform = bone.transform;
+        let transform_inv = bone.transform_inv;
+
+        // TODO: write user data, it is currently dropped by the parser
+        output.extend(i32::to_be_bytes(BONE_SIZE as i32)); // header_len
+        output.extend(i32::to_be_bytes(-(offset as i32))); // mdl0_offset
+        strings.reference(output.len(), offset, &bone.name);
+        output.extend(i32::to_be_bytes(0)); // string_offset
+        output.extend(i32::to_be_bytes(bone.index));
+        output.extend(i32::to_be_bytes(bone.node_id));
+        output.extend(u32::to_be_bytes(bone.flags.bits()));
+        output.extend(u32::to_be_bytes(bone.billboard.value()));
+        output.extend(u32::to_be_bytes(bone._bb_index));
+        for value in [bone.scale, bone.rot, bone.translate] {
+            output.extend(f32::to_be_bytes(value.x));
+            output.extend(f32::to_be_bytes(value.y));
+            output.extend(f32::to_be_bytes(value.z));
+        }
+        output.extend(bone.extents.compile());
+        output.extend(i32::to_be_bytes(relative(flat.parent, offset)));
+        output.extend(i32::to_be_bytes(relative(flat.first_child, offset)));
+        output.extend(i32::to_be_bytes(relative(flat.next, offset)));
+        output.extend(i32::to_be_bytes(relative(flat.prev, offset)));
+        output.extend(i32::to_be_bytes(0)); // user_data_offset
+
+        // Matrix43, stored row by row
+        for matrix in [transform, transform_inv] {
+            for row in 0..3 {
+                for column in 0..4 {
+                    output.extend(f32::to_be_bytes(matrix[column][row]));
+                }
+            }
+        }
+    }
+
+    offsets
+}
+
 bitflags! {
     #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
     #[rustfmt::skip]
@@ -246,3 +374,46 @@ pub enum BoneBillboard {
     Y,
     YPerspective,
 }
+
+impl BoneBillboard {
+    fn value(&self) -> u32 {
+        match self {
+            BoneBillboard::Off => 0,
+            BoneBillboard::Standard => 1,
+            BoneBillboard::StandardPerspective => 2,
+            BoneBillboard::Rotation => 3,
+            BoneBillboard::RotationPerspective => 4,
+            BoneBillboard::Y => 5,
+            BoneBillboard::YPerspective => 6,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use cgmath::SquareMatrix;
+
+    /// A bone at the origin with no transformation
+    pub(crate) fn bone(name: &str, index: i32, children: Vec<Bone>) -> Bone {
+        Bone {
+            name: name.to_string(),
+            _header_len: BONE_SIZE as i32,
+            _mdl0_offset: 0,
+            _string_offset: 0,
+            index,
+            node_id: index,
+            flags: BoneFlags::empty(),
+            billboard: BoneBillboard::Off,
+            _bb_index: 0,
+            scale: Vector3::new(1.0, 1.0, 1.0),
+            rot: Vector3::new(0.0, 0.0, 0.0),
+            translate: Vector3::new(0.0, 0.0, 0.0),
+            extents: mbox::mbox(FancySlice::new(&[0; 0x18])),
+            _user_data_offset: 0,
+            transform: Matrix4::identity(),
+            transform_inv: Matrix4::identity(),
+            children,
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use fancy_slice::FancySlice;
 
-use crate::resources;
+use crate::parse_error::{ParseError, check_len, try_offset};
 use crate::resources::Resource;
 
 #[derive(Clone, Debug)]
@@ -8,77 +8,189 @@ pub struct Definitions {
     pub values: Vec<Definition>,
 }
 
-impl Definitions {
-    pub fn compile(&self) -> Vec<u8> {
-        let mut output = vec![];
-
-        // create resources header
-        let resources_size =
-            (self.values.len() + 1) * resources::RESOURCE_SIZE + resources::RESOURCE_HEADER_SIZE; // includes the dummy child
-        output.extend(i32::to_be_bytes(resources_size as i32));
-        output.extend(i32::to_be_bytes(self.values.len() as i32)); // num_children
-
-        // insert the dummy child
-        output.extend([
-            0xff, 0xff, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-            0x00, 0x00,
-        ]);
-
-        //let data_offset = 0; // TODO
-
-        for _definition in &self.values {
-            //output.extend(&u16::to_be_bytes(child.id));
-            //output.extend(&u16::to_be_bytes(child.flag));
-            //output.extend(&u16::to_be_bytes(child.left_index));
-            //output.extend(&u16::to_be_bytes(child.right_index));
-            //output.extend(&i32::to_be_bytes(4)); // TODO: string_offset
-            //output.extend(&i32::to_be_bytes(data_offset));
-        }
-
-        output
-    }
-}
-
 #[rustfmt::skip]
-pub(crate) fn definitions(data: FancySlice, resources: Vec<Resource>) -> Definitions {
+pub(crate) fn definitions(data: FancySlice, resources: Vec<Resource>) -> Result<Definitions, ParseError> {
     let mut definitions = vec!();
     for resource in resources {
-        let data = data.relative_fancy_slice(resource.data_offset as usize ..);
+        let resource_offset = resource.data_offset as i64;
+        let data = try_offset(&data, resource_offset, 0, "MDL0 definition")?;
         let name = resource.string;
+        let check = |needed| {
+            check_len(data, needed, "MDL0 definition").map_err(|err| err.offset_by(resource_offset as usize))
+        };
 
         let mut offset = 0;
-        let mut draw_calls = vec!();
-        // TODO: Looks like data[offset] specifies what type the child is.
-        //       If so draw_calls should be renamed children and store an enum of all possible children types
-        //       Alternatively it might be branching on the names "DrawOpa" and "DrawXlu" - this is what brawlbox does, but brawlbox's implementation looks hacky.
-        while data.u8(offset) == 4 {
-            let material             = data.u16_be(offset + 0x01);
-            let object               = data.u16_be(offset + 0x03);
-            let visibility_bone_node = data.u16_be(offset + 0x05);
-            let draw_order           = data.u8    (offset + 0x07);
-            draw_calls.push(DrawCall { material, object, visibility_bone_node, draw_order });
-            offset += DEFINITION_SIZE;
+        let mut commands = vec!();
+        loop {
+            check(offset + 1)?;
+            let command = data.u8(offset);
+            match command {
+                0x01 => break,
+                0x02 => {
+                    check(offset + 5)?;
+                    commands.push(DefinitionCommand::NodeTree {
+                        bone_index:       data.u16_be(offset + 1),
+                        parent_matrix_id: data.u16_be(offset + 3),
+                    });
+                    offset += 5;
+                }
+                0x03 => {
+                    check(offset + 4)?;
+                    let num_weights = data.u8(offset + 3) as usize;
+                    check(offset + 4 + 6 * num_weights)?;
+                    let weights = (0..num_weights).map(|i| {
+                        let weight = offset + 4 + i * 6;
+                        NodeMixWeight {
+                            matrix_id: data.u16_be(weight),
+                            weight:    data.f32_be(weight + 2),
+                        }
+                    }).collect();
+                    commands.push(DefinitionCommand::NodeMix(NodeMix {
+                        matrix_id: data.u16_be(offset + 1),
+                        weights,
+                    }));
+                    offset += 4 + 6 * num_weights;
+                }
+                0x04 => {
+                    check(offset + DRAW_CALL_SIZE)?;
+                    commands.push(DefinitionCommand::Draw(DrawCall {
+                        material:             data.u16_be(offset + 0x01),
+                        object:               data.u16_be(offset + 0x03),
+                        visibility_bone_node: data.u16_be(offset + 0x05),
+                        draw_order:           data.u8    (offset + 0x07),
+                    }));
+                    offset += DRAW_CALL_SIZE;
+                }
+                0x05 => {
+                    check(offset + 5)?;
+                    commands.push(DefinitionCommand::EnvelopeMatrix {
+                        matrix_id:  data.u16_be(offset + 1),
+                        bone_index: data.u16_be(offset + 3),
+                    });
+                    offset += 5;
+                }
+                0x06 => {
+                    check(offset + 5)?;
+                    commands.push(DefinitionCommand::NodeCopy {
+                        dest_matrix_id:   data.u16_be(offset + 1),
+                        source_matrix_id: data.u16_be(offset + 3),
+                    });
+                    offset += 5;
+                }
+                _ => {
+                    error!("Unknown MDL0 definition command 0x{:x} in {}", command, name);
+                    break;
+                }
+            }
         }
-        definitions.push(Definition { name, draw_calls });
+
+        definitions.push(Definition { name, commands });
     }
-    Definitions { values: definitions }
+    Ok(Definitions { values: definitions })
 }
 
 impl Definition {
     pub fn compile(&self) -> Vec<u8> {
-        let output = vec![];
+        let mut output = vec![];
+
+        for command in &self.commands {
+            match command {
+                DefinitionCommand::NodeTree {
+                    bone_index,
+                    parent_matrix_id,
+                } => {
+                    output.push(0x02);
+                    output.extend(u16::to_be_bytes(*bone_index));
+                    output.extend(u16::to_be_bytes(*parent_matrix_id));
+                }
+                DefinitionCommand::NodeMix(node_mix) => {
+                    output.push(0x03);
+                    output.extend(u16::to_be_bytes(node_mix.matrix_id));
+                    output.push(node_mix.weights.len() as u8);
+                    for weight in &node_mix.weights {
+                        output.extend(u16::to_be_bytes(weight.matrix_id));
+                        output.extend(f32::to_be_bytes(weight.weight));
+                    }
+                }
+                DefinitionCommand::Draw(draw_call) => {
+                    output.push(0x04);
+                    output.extend(u16::to_be_bytes(draw_call.material));
+                    output.extend(u16::to_be_bytes(draw_call.object));
+                    output.extend(u16::to_be_bytes(draw_call.visibility_bone_node));
+                    output.push(draw_call.draw_order);
+                }
+                DefinitionCommand::EnvelopeMatrix {
+                    matrix_id,
+                    bone_index,
+                } => {
+                    output.push(0x05);
+                    output.extend(u16::to_be_bytes(*matrix_id));
+                    output.extend(u16::to_be_bytes(*bone_index));
+                }
+                DefinitionCommand::NodeCopy {
+                    dest_matrix_id,
+                    source_matrix_id,
+                } => {
+                    output.push(0x06);
+                    output.extend(u16::to_be_bytes(*dest_matrix_id));
+                    output.extend(u16::to_be_bytes(*source_matrix_id));
+                }
+            }
+        }
+        output.push(0x01); // end
 
         output
     }
+
+    /// The draw calls in this definition, only DrawOpa and DrawXlu contain draw calls.
+    pub fn draw_calls(&self) -> impl Iterator<Item = &DrawCall> {
+        self.commands.iter().filter_map(|x| match x {
+            DefinitionCommand::Draw(draw_call) => Some(draw_call),
+            _ => None,
+        })
+    }
+
+    /// The matrices that blend multiple bones together, used to bind vertices to more than one bone.
+    pub fn node_mixes(&self) -> impl Iterator<Item = &NodeMix> {
+        self.commands.iter().filter_map(|x| match x {
+            DefinitionCommand::NodeMix(node_mix) => Some(node_mix),
+            _ => None,
+        })
+    }
 }
 
-const DEFINITION_SIZE: usize = 0x8;
+/// The definitions are named after their purpose:
+/// *   NodeTree contains the bone hierarchy
+/// *   NodeMix contains the matrices that vertices are weighted to
+/// *   DrawOpa and DrawXlu contain the draw calls of opaque and translucent materials respectively
 #[derive(Clone, Debug)]
 pub struct Definition {
     pub name: String,
-    pub draw_calls: Vec<DrawCall>,
+    /// The commands in the order they are executed, the end command is not included.
+    pub commands: Vec<DefinitionCommand>,
+}
+
+#[derive(Clone, Debug)]
+pub enum DefinitionCommand {
+    /// Places the bone in the hierarchy under the bone with the given matrix id
+    NodeTree {
+        bone_index: u16,
+        parent_matrix_id: u16,
+    },
+    NodeMix(NodeMix),
+    Draw(DrawCall),
+    /// Uses the inverse bind matrix of the bone for the matrix id
+    EnvelopeMatrix {
+        matrix_id: u16,
+        bone_index: u16,
+    },
+    NodeCopy {
+        dest_matrix_id: u16,
+        source_matrix_id: u16,
+    },
 }
 
+const DRAW_CALL_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
 pub struct DrawCall {
     pub material: u16,
@@ -86,3 +198,17 @@ pub struct DrawCall {
     pub visibility_bone_node: u16,
     pub draw_order: u8,
 }
+
+#[derive(Clone, Debug)]
+pub struct NodeMix {
+    /// The matrix id that vertices use to refer to this node mix
+    pub matrix_id: u16,
+    pub weights: Vec<NodeMixWeight>,
+}
+
+#[derive(Clone, Debug)]
+pub struct NodeMixWeight {
+    /// The matrix id of the bone being blended, this matches `Bone::node_id`
+    pub matrix_id: u16,
+    pub weight: f32,
+}
@@ -0,0 +1,459 @@
+use fancy_slice::FancySlice;
+
+use super::{UnparsedResource, unparsed_resources};
+use crate::parse_error::ParseError;
+use crate::resources::Resource;
+
+/// `data` is the entire MDL0 and `group_offset` is the offset of the resource group from the start of the MDL0
+#[rustfmt::skip]
+pub(crate) fn materials(
+    data: FancySlice,
+    group_offset: usize,
+    resources: Vec<Resource>,
+    version: i32,
+) -> Result<Vec<Material>, ParseError> {
+    let mut materials = vec![];
+    for mut raw in unparsed_resources(data, group_offset, resources, MATERIAL_HEADER_SIZE, "MDL0 material")? {
+        raw.read_string(data, 0x08, 0)?;
+        let name = raw.name.clone();
+        let material_data = raw.data.clone();
+        let material = FancySlice::new(&material_data);
+
+        let index               = material.i32_be(0x0c);
+        let usage_flags         = material.u32_be(0x10);
+        let num_tex_gens        = material.u8(0x14);
+        let num_light_channels  = material.u8(0x15);
+        let num_tev_stages      = material.u8(0x16);
+        let num_indirect_stages = material.u8(0x17);
+        let cull_mode           = CullMode::new(material.i32_be(0x18));
+        let early_z             = material.u8(0x1c) != 0;
+        let light_set           = material.u8(0x1d) as i8;
+        let fog_set             = material.u8(0x1e) as i8;
+        let num_textures        = material.i32_be(0x2c).max(0) as usize;
+        let texture_refs_offset = material.i32_be(0x30).max(0) as usize;
+        // Fur versions have an extra offset to the fur data before the display list offset
+        let display_list_offset = material.i32_be(if version >= 0xA { 0x3c } else { 0x38 }).max(0) as usize;
+
+        // each texture reference stores the name of its texture and palette
+        let mut textures = vec![];
+        for i in 0..num_textures {
+            let texture_ref = texture_refs_offset + i * MATERIAL_TEXTURE_REF_SIZE;
+            if texture_refs_offset == 0 || texture_ref + MATERIAL_TEXTURE_REF_SIZE > raw.data.len() {
+                break;
+            }
+            raw.read_string(data, texture_ref, texture_ref)?;
+            raw.read_string(data, texture_ref + 0x4, texture_ref)?;
+            let name_at = |field: usize| {
+                raw.strings.iter().find(|x| x.field == field).map(|x| x.string.clone())
+            };
+
+            let texture_data = material.relative_fancy_slice(texture_ref..);
+            textures.push(MaterialTexture {
+                texture_name:     name_at(texture_ref),
+                palette_name:     name_at(texture_ref + 0x4),
+                tex_map_id:       texture_data.i32_be(0x10),
+                palette_id:       texture_data.i32_be(0x14),
+                wrap_s:           TextureWrap::new(texture_data.i32_be(0x18)),
+                wrap_t:           TextureWrap::new(texture_data.i32_be(0x1c)),
+                min_filter:       TextureFilter::new(texture_data.i32_be(0x20)),
+                mag_filter:       TextureFilter::new(texture_data.i32_be(0x24)),
+                lod_bias:         texture_data.f32_be(0x28),
+                max_anisotropy:   texture_data.i32_be(0x2c),
+                clamp_bias:       texture_data.u8(0x30) != 0,
+                texel_interpolate: texture_data.u8(0x31) != 0,
+            });
+        }
+
+        let mut alpha_compare = AlphaCompare {
+            ref0: 0,
+            ref1: 0,
+            comp0: CompareFunction::Always,
+            comp1: CompareFunction::Always,
+            op: AlphaOp::And,
+        };
+        let mut z_mode = ZMode {
+            enable: true,
+            function: CompareFunction::LessEqual,
+            update: true,
+        };
+        let mut blend_mode = BlendMode {
+            enable: false,
+            logic_op_enable: false,
+            dither: false,
+            color_update: true,
+            alpha_update: false,
+            subtract: false,
+            src_factor: BlendFactor::SrcAlpha,
+            dst_factor: BlendFactor::InvSrcAlpha,
+            logic_op: 0,
+        };
+        let mut constant_alpha = None;
+        let mut color_registers = [[0; 4]; 3];
+        let mut konst_colors = [[0; 4]; 4];
+
+        let display_list = raw.data.get(display_list_offset..).filter(|_| display_list_offset != 0).unwrap_or_default();
+        for (register, value) in bp_writes(display_list) {
+            match register {
+                0xf3 => alpha_compare = AlphaCompare::new(value),
+                0x40 => z_mode = ZMode::new(value),
+                0x41 => blend_mode = BlendMode::new(value),
+                0x42 => constant_alpha = if value & 0x100 != 0 { Some(value as u8) } else { None },
+                0xe0..=0xe7 => {
+                    // Each register is written in two halves, the red and alpha half then the blue and green half.
+                    let tev_register = (register - 0xe0) as usize / 2;
+                    let (first, second) = if register % 2 == 0 { (0, 3) } else { (2, 1) };
+                    if value & (1 << 23) != 0 {
+                        konst_colors[tev_register][first] = value as u8;
+                        konst_colors[tev_register][second] = (value >> 12) as u8;
+                    } else if tev_register > 0 {
+                        // Register 0 is the output of the previous stage so it cannot be set by the material
+                        color_registers[tev_register - 1][first] = sign_extend_11(value);
+                        color_registers[tev_register - 1][second] = sign_extend_11(value >> 12);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        materials.push(Material {
+            name,
+            index,
+            xlu: usage_flags & 0x8000_0000 != 0,
+            num_tex_gens,
+            num_light_channels,
+            num_tev_stages,
+            num_indirect_stages,
+            cull_mode,
+            early_z,
+            light_set,
+            fog_set,
+            shader: None,
+            textures,
+            alpha_compare,
+            z_mode,
+            blend_mode,
+            constant_alpha,
+            color_registers,
+            konst_colors,
+            raw,
+        });
+    }
+    Ok(materials)
+}
+
+fn sign_extend_11(value: u32) -> i16 {
+    (((value & 0x7ff) as i16) << 5) >> 5
+}
+
+/// Replays the BP register writes in a GX display list, returning the value of the register after each write.
+/// A BP mask write only applies to the next write, so the returned values already have the mask applied.
+pub(crate) fn bp_writes(display_list: &[u8]) -> Vec<(u8, u32)> {
+    let mut writes = vec![];
+    let mut registers = [0u32; 0x100];
+    let mut mask = 0xff_ffff;
+    let mut offset = 0;
+    while let Some(command) = display_list.get(offset) {
+        match command {
+            // nop, used for padding
+            0x00 => offset += 1,
+            // load BP register
+            0x61 => {
+                let Some(write) = display_list.get(offset + 1..offset + 5) else {
+                    break;
+                };
+                let register = write[0];
+                let value = u32::from_be_bytes([0, write[1], write[2], write[3]]);
+                if register == 0xfe {
+                    mask = value;
+                } else {
+                    let register_value = &mut registers[register as usize];
+                    *register_value = (*register_value & !mask) | (value & mask);
+                    writes.push((register, *register_value));
+                    mask = 0xff_ffff;
+                }
+                offset += 5;
+            }
+            // load CP register
+            0x08 => offset += 6,
+            // load XF registers
+            0x10 => {
+                let Some(count) = display_list.get(offset + 1..offset + 3) else {
+                    break;
+                };
+                let count = u16::from_be_bytes([count[0], count[1]]) as usize + 1;
+                offset += 5 + count * 4;
+            }
+            _ => break,
+        }
+    }
+    writes
+}
+
+const MATERIAL_HEADER_SIZE: usize = 0x40;
+const MATERIAL_TEXTURE_REF_SIZE: usize = 0x34;
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub name: String,
+    pub index: i32,
+    /// Translucent materials are drawn after the opaque materials, by the DrawXlu definition
+    pub xlu: bool,
+    pub num_tex_gens: u8,
+    pub num_light_channels: u8,
+    pub num_tev_stages: u8,
+    pub num_indirect_stages: u8,
+    pub cull_mode: CullMode,
+    /// The depth test is done before texturing, only possible when the alpha test cannot discard pixels
+    pub early_z: bool,
+    pub light_set: i8,
+    pub fog_set: i8,
+    /// Index into `Mdl0::shaders`
+    pub shader: Option<usize>,
+    pub textures: Vec<MaterialTexture>,
+    pub alpha_compare: AlphaCompare,
+    pub z_mode: ZMode,
+    pub blend_mode: BlendMode,
+    pub constant_alpha: Option<u8>,
+    /// The TEV color registers C0, C1 and C2 as rgba, each component is between -1024 and 1023
+    pub color_registers: [[i16; 4]; 3],
+    /// The TEV konst colors K0, K1, K2 and K3 as rgba
+    pub konst_colors: [[u8; 4]; 4],
+    /// TODO: Modifications are not compiled yet, the original bytes are written as is.
+    pub(crate) raw: UnparsedResource,
+}
+
+impl Material {
+    /// Offset from the start of the MDL0 the material was read from
+    pub(crate) fn offset(&self) -> usize {
+        self.raw.offset
+    }
+
+    /// Offset from the start of the material to the shader it uses
+    pub(crate) fn shader_offset(&self) -> i32 {
+        self.raw.i32(MATERIAL_SHADER_OFFSET)
+    }
+}
+
+pub(crate) const MATERIAL_SHADER_OFFSET: usize = 0x28;
+
+#[derive(Clone, Debug)]
+pub struct MaterialTexture {
+    pub texture_name: Option<String>,
+    /// Only used by textures with an indexed pixel format
+    pub palette_name: Option<String>,
+    pub tex_map_id: i32,
+    pub palette_id: i32,
+    pub wrap_s: TextureWrap,
+    pub wrap_t: TextureWrap,
+    pub min_filter: TextureFilter,
+    pub mag_filter: TextureFilter,
+    pub lod_bias: f32,
+    pub max_anisotropy: i32,
+    pub clamp_bias: bool,
+    pub texel_interpolate: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CullMode {
+    None,
+    /// Cull the faces facing the camera
+    Front,
+    /// Cull the faces facing away from the camera
+    Back,
+    All,
+    Unknown(i32),
+}
+
+impl CullMode {
+    fn new(value: i32) -> CullMode {
+        match value {
+            0 => CullMode::None,
+            1 => CullMode::Front,
+            2 => CullMode::Back,
+            3 => CullMode::All,
+            _ => CullMode::Unknown(value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureWrap {
+    Clamp,
+    Repeat,
+    Mirror,
+    Unknown(i32),
+}
+
+impl TextureWrap {
+    fn new(value: i32) -> TextureWrap {
+        match value {
+            0 => TextureWrap::Clamp,
+            1 => TextureWrap::Repeat,
+            2 => TextureWrap::Mirror,
+            _ => TextureWrap::Unknown(value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextureFilter {
+    Nearest,
+    Linear,
+    NearestMipmapNearest,
+    LinearMipmapNearest,
+    NearestMipmapLinear,
+    LinearMipmapLinear,
+    Unknown(i32),
+}
+
+impl TextureFilter {
+    fn new(value: i32) -> TextureFilter {
+        match value {
+            0 => TextureFilter::Nearest,
+            1 => TextureFilter::Linear,
+            2 => TextureFilter::NearestMipmapNearest,
+            3 => TextureFilter::LinearMipmapNearest,
+            4 => TextureFilter::NearestMipmapLinear,
+            5 => TextureFilter::LinearMipmapLinear,
+            _ => TextureFilter::Unknown(value),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompareFunction {
+    Never,
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl CompareFunction {
+    fn new(value: u32) -> CompareFunction {
+        match value & 0b111 {
+            0 => CompareFunction::Never,
+            1 => CompareFunction::Less,
+            2 => CompareFunction::Equal,
+            3 => CompareFunction::LessEqual,
+            4 => CompareFunction::Greater,
+            5 => CompareFunction::NotEqual,
+            6 => CompareFunction::GreaterEqual,
+            _ => CompareFunction::Always,
+        }
+    }
+}
+
+/// Pixels are discarded unless `(alpha comp0 ref0) op (alpha comp1 ref1)` is true
+#[derive(Clone, Debug)]
+pub struct AlphaCompare {
+    pub ref0: u8,
+    pub ref1: u8,
+    pub comp0: CompareFunction,
+    pub comp1: CompareFunction,
+    pub op: AlphaOp,
+}
+
+impl AlphaCompare {
+    fn new(value: u32) -> AlphaCompare {
+        AlphaCompare {
+            ref0: value as u8,
+            ref1: (value >> 8) as u8,
+            comp0: CompareFunction::new(value >> 16),
+            comp1: CompareFunction::new(value >> 19),
+            op: match (value >> 22) & 0b11 {
+                0 => AlphaOp::And,
+                1 => AlphaOp::Or,
+                2 => AlphaOp::Xor,
+                _ => AlphaOp::Xnor,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AlphaOp {
+    And,
+    Or,
+    Xor,
+    Xnor,
+}
+
+#[derive(Clone, Debug)]
+pub struct ZMode {
+    /// Enables the depth test
+    pub enable: bool,
+    pub function: CompareFunction,
+    /// Enables writing to the depth buffer
+    pub update: bool,
+}
+
+impl ZMode {
+    fn new(value: u32) -> ZMode {
+        ZMode {
+            enable: value & 1 != 0,
+            function: CompareFunction::new(value >> 1),
+            update: value & 0x10 != 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BlendMode {
+    pub enable: bool,
+    pub logic_op_enable: bool,
+    pub dither: bool,
+    pub color_update: bool,
+    pub alpha_update: bool,
+    /// The destination is subtracted from the source, ignoring the factors
+    pub subtract: bool,
+    pub src_factor: BlendFactor,
+    pub dst_factor: BlendFactor,
+    pub logic_op: u8,
+}
+
+impl BlendMode {
+    fn new(value: u32) -> BlendMode {
+        BlendMode {
+            enable: value & 0x1 != 0,
+            logic_op_enable: value & 0x2 != 0,
+            dither: value & 0x4 != 0,
+            color_update: value & 0x8 != 0,
+            alpha_update: value & 0x10 != 0,
+            dst_factor: BlendFactor::new(value >> 5),
+            src_factor: BlendFactor::new(value >> 8),
+            subtract: value & 0x800 != 0,
+            logic_op: ((value >> 12) & 0xf) as u8,
+        }
+    }
+}
+
+/// When used as the destination factor the color variants refer to the destination color instead
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColor,
+    InvSrcColor,
+    SrcAlpha,
+    InvSrcAlpha,
+    DstAlpha,
+    InvDstAlpha,
+}
+
+impl BlendFactor {
+    fn new(value: u32) -> BlendFactor {
+        match value & 0b111 {
+            0 => BlendFactor::Zero,
+            1 => BlendFactor::One,
+            2 => BlendFactor::SrcColor,
+            3 => BlendFactor::InvSrcColor,
+            4 => BlendFactor::SrcAlpha,
+            5 => BlendFactor::InvSrcAlpha,
+            6 => BlendFactor::DstAlpha,
+            _ => BlendFactor::InvDstAlpha,
+        }
+    }
+}
@@ -0,0 +1,521 @@
+use std::collections::HashMap;
+
+use anyhow::{Error, bail};
+use cgmath::{InnerSpace, Vector2, Vector3};
+
+use super::bones::Bone;
+use super::definitions::NodeMix;
+use super::objects::{Object, XFDataFormat};
+use super::vertices::VertexComponentType;
+use super::{Mdl0, UnparsedResource};
+use crate::wii_texture_formats::WiiPaletteFormat;
+
+/// An object decoded into indexed triangles
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Mesh {
+    pub name: Option<String>,
+    pub vertices: Vec<MeshVertex>,
+    /// Every 3 indices into `vertices` form a triangle, wound counter-clockwise.
+    pub indices: Vec<u32>,
+}
+
+/// To pose a vertex, blend the transform of each bone in `weights` multiplied by the `transform_inv` of that bone.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MeshVertex {
+    /// Position in model space when every bone is in its bind pose
+    pub position: Vector3<f32>,
+    /// Normal in model space when every bone is in its bind pose
+    pub normal: Option<Vector3<f32>>,
+    /// One color per color channel used by the object
+    pub colors: Vec<[u8; 4]>,
+    /// One texture coordinate per texture channel used by the object
+    pub uvs: Vec<Vector2<f32>>,
+    /// Either the `Bone::node_id` of a single bone or the `NodeMix::matrix_id` of multiple bones
+    pub matrix_id: Option<u16>,
+    /// The bones that move this vertex
+    pub weights: Vec<BoneWeight>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BoneWeight {
+    /// Matches `Bone::index`
+    pub bone_index: i32,
+    pub weight: f32,
+}
+
+pub(crate) fn mesh(mdl0: &Mdl0, object: &Object) -> Result<Mesh, Error> {
+    let name = object.name.as_deref().unwrap_or("unnamed");
+    let Some(display_list) = object.primitives() else {
+        bail!("The display list of object {} is out of bounds", name);
+    };
+
+    if let XFDataFormat::None = object.vertex_format() {
+        bail!("Object {} has no positions", name);
+    }
+    let positions = position_array(mdl0, object.vertex_id)?;
+    let normals = match object.normal_format() {
+        XFDataFormat::None => None,
+        _ => Some(normal_array(mdl0, object.normal_id)?),
+    };
+    let mut colors = vec![];
+    for (i, id) in object.color_ids.iter().enumerate() {
+        if let Some(XFDataFormat::None) | None = object.color_format(i) {
+            continue;
+        }
+        colors.push((i, color_array(mdl0, *id)?));
+    }
+    let mut uvs = vec![];
+    for (i, id) in object.uv_ids.iter().enumerate() {
+        if let Some(XFDataFormat::None) | None = object.tex_format(i) {
+            continue;
+        }
+        uvs.push((i, uv_array(mdl0, *id)?));
+    }
+
+    let matrices = Matrices::new(mdl0);
+    let single_bind = object.single_bind_node_id.map(|x| x as u16);
+
+    // Matrix ids loaded into the position matrix memory, vertices refer to them by their slot
+    let mut matrix_slots = [None; 10];
+    let mut vertices = vec![];
+    let mut indices = vec![];
+    // Display lists refer to the same vertex many times, so only create one vertex for each unique set of indices
+    let mut unique_vertices: HashMap<(Option<u16>, &[u8]), u32> = HashMap::new();
+
+    let mut reader = DisplayListReader {
+        data: display_list,
+        offset: 0,
+        name,
+    };
+    while reader.offset < display_list.len() {
+        let command = reader.u8()?;
+        match command {
+            0x00 => {}               // nop
+            0x08 => reader.skip(5)?, // load CP register
+            0x10 => {
+                // load XF registers
+                let num_values = reader.u16()? as usize + 1;
+                reader.skip(2 + num_values * 4)?;
+            }
+            0x20 => {
+                // load position matrix
+                let matrix_id = reader.u16()?;
+                let address = (reader.u16()? & 0xfff) as usize;
+                if let Some(slot) = matrix_slots.get_mut(address / 12) {
+                    *slot = Some(matrix_id);
+                }
+            }
+            0x28 | 0x30 | 0x38 => reader.skip(4)?, // load normal, texture and light matrices
+            0x48 => {}                             // invalidate vertex cache
+            0x80..=0xbf => {
+                let num_vertices = reader.u16()? as usize;
+                let mut primitive = Vec::with_capacity(num_vertices);
+                for _ in 0..num_vertices {
+                    let start = reader.offset;
+                    let matrix_id = if object.has_vertex_matrix() {
+                        let slot = reader.u8()? as usize / 3;
+                        matrix_slots.get(slot).copied().flatten()
+                    } else {
+                        single_bind
+                    };
+                    for i in 0..8 {
+                        if object.has_tex_matrix(i) {
+                            reader.skip(1)?;
+                        }
+                    }
+                    let position_index = reader.index(object.vertex_format())?;
+                    let normal_index = reader.index(object.normal_format())?;
+                    let mut color_indices = vec![];
+                    for i in 0..2 {
+                        color_indices.push(reader.index(object.color_format(i).unwrap())?);
+                    }
+                    let mut uv_indices = vec![];
+                    for i in 0..8 {
+                        uv_indices.push(reader.index(object.tex_format(i).unwrap())?);
+                    }
+
+                    let key = (matrix_id, &display_list[start..reader.offset]);
+                    if let Some(index) = unique_vertices.get(&key) {
+                        primitive.push(*index);
+                        continue;
+                    }
+
+                    let Some(position) = position_index.and_then(|i| positions.get(i)) else {
+                        bail!("Object {} refers to a position that does not exist", name);
+                    };
+                    let mut position = Vector3::from(position);
+                    let mut normal = match (&normals, normal_index) {
+                        (Some(normals), Some(i)) => match normals.get(i) {
+                            Some(normal) => Some(Vector3::from(normal)),
+                            None => bail!("Object {} refers to a normal that does not exist", name),
+                        },
+                        _ => None,
+                    };
+                    let mut vertex_colors = vec![];
+                    for (i, array) in &colors {
+                        let Some(color) = color_indices[*i].and_then(|x| array.get(x)) else {
+                            bail!("Object {} refers to a color that does not exist", name);
+                        };
+                        vertex_colors.push(color);
+                    }
+                    let mut vertex_uvs = vec![];
+                    for (i, array) in &uvs {
+                        let Some(uv) = uv_indices[*i].and_then(|x| array.get(x)) else {
+                            bail!("Object {} refers to a UV that does not exist", name);
+                        };
+                        vertex_uvs.push(Vector2::new(uv[0], uv[1]));
+                    }
+
+                    // Vertices bound to a single bone are stored relative to that bone
+                    if let Some(bone) = matrix_id.and_then(|x| matrices.bones.get(&x)) {
+                        position = (bone.transform * position.extend(1.0)).truncate();
+                        normal = normal.map(|x| {
+                            let normal = (bone.transform * x.extend(0.0)).truncate();
+                            if normal.magnitude2() > 0.0 {
+                                normal.normalize()
+                            } else {
+                                normal
+                            }
+                        });
+                    }
+
+                    let index = vertices.len() as u32;
+                    vertices.push(MeshVertex {
+                        position,
+                        normal,
+                        colors: vertex_colors,
+                        uvs: vertex_uvs,
+                        matrix_id,
+                        weights: matrix_id.map(|x| matrices.weights(x)).unwrap_or_default(),
+                    });
+                    unique_vertices.insert(key, index);
+                    primitive.push(index);
+                }
+                triangulate(command & 0xf8, &primitive, &mut indices);
+            }
+            _ => bail!(
+                "Unknown display list command 0x{:x} at 0x{:x} in object {}",
+                command,
+                reader.offset - 1,
+                name
+            ),
+        }
+    }
+
+    Ok(Mesh {
+        name: object.name.clone(),
+        vertices,
+        indices,
+    })
+}
+
+/// Converts a primitive into triangles, lines and points are skipped.
+fn triangulate(primitive: u8, vertices: &[u32], indices: &mut Vec<u32>) {
+    // The display lists wind triangles clockwise, so they are reversed here.
+    let mut triangle = |a: u32, b: u32, c: u32| {
+        // Skip the degenerate triangles used to join strips together
+        if a != b && b != c && a != c {
+            indices.extend([c, b, a]);
+        }
+    };
+    match primitive {
+        0x80 | 0x88 => {
+            for quad in vertices.chunks_exact(4) {
+                triangle(quad[0], quad[1], quad[2]);
+                triangle(quad[0], quad[2], quad[3]);
+            }
+        }
+        0x90 => {
+            for tri in vertices.chunks_exact(3) {
+                triangle(tri[0], tri[1], tri[2]);
+            }
+        }
+        0x98 => {
+            for i in 2..vertices.len() {
+                if i % 2 == 0 {
+                    triangle(vertices[i - 2], vertices[i - 1], vertices[i]);
+                } else {
+                    triangle(vertices[i - 1], vertices[i - 2], vertices[i]);
+                }
+            }
+        }
+        0xa0 => {
+            for i in 2..vertices.len() {
+                triangle(vertices[0], vertices[i - 1], vertices[i]);
+            }
+        }
+        _ => {}
+    }
+}
+
+struct DisplayListReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    name: &'a str,
+}
+
+impl DisplayListReader<'_> {
+    fn bytes(&mut self, len: usize) -> Result<&[u8], Error> {
+        let Some(bytes) = self.data.get(self.offset..self.offset + len) else {
+            bail!("The display list of object {} ends early", self.name);
+        };
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), Error> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads the index of a vertex component
+    fn index(&mut self, format: XFDataFormat) -> Result<Option<usize>, Error> {
+        match format {
+            XFDataFormat::None => Ok(None),
+            XFDataFormat::Direct => bail!(
+                "Object {} stores vertex components directly in the display list which is not supported",
+                self.name
+            ),
+            XFDataFormat::Index8 => Ok(Some(self.u8()? as usize)),
+            XFDataFormat::Index16 => Ok(Some(self.u16()? as usize)),
+        }
+    }
+}
+
+/// The bones that each matrix id refers to
+struct Matrices<'a> {
+    bones: HashMap<u16, &'a Bone>,
+    node_mixes: HashMap<u16, NodeMix>,
+}
+
+impl<'a> Matrices<'a> {
+    fn new(mdl0: &'a Mdl0) -> Self {
+        let bones = mdl0
+            .bones
+            .as_ref()
+            .map(|x| x.flatten())
+            .unwrap_or_default()
+            .iter()
+            .map(|x| (x.bone.node_id as u16, x.bone))
+            .collect();
+        let node_mixes = mdl0
+            .definitions
+            .iter()
+            .flat_map(|x| &x.values)
+            .flat_map(|x| x.node_mixes().cloned())
+            .map(|x| (x.matrix_id, x))
+            .collect();
+        Matrices { bones, node_mixes }
+    }
+
+    fn weights(&self, matrix_id: u16) -> Vec<BoneWeight> {
+        if let Some(bone) = self.bones.get(&matrix_id) {
+            vec![BoneWeight {
+                bone_index: bone.index,
+                weight: 1.0,
+            }]
+        } else if let Some(node_mix) = self.node_mixes.get(&matrix_id) {
+            node_mix
+                .weights
+                .iter()
+                .filter_map(|weight| {
+                    self.bones.get(&weight.matrix_id).map(|bone| BoneWeight {
+                        bone_index: bone.index,
+                        weight: weight.weight,
+                    })
+                })
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+}
+
+/// Positions, normals and UVs are all stored as arrays of fixed or floating point values
+struct ComponentArray<'a> {
+    data: &'a [u8],
+    component_type: VertexComponentType,
+    num_components: usize,
+    scale: f32,
+    stride: usize,
+    len: usize,
+}
+
+impl<'a> ComponentArray<'a> {
+    fn new(
+        data: &'a [u8],
+        component_type: VertexComponentType,
+        num_components: usize,
+        divisor: u8,
+        stride: u8,
+        len: u16,
+    ) -> Self {
+        let scale = match component_type {
+            VertexComponentType::F32 => 1.0,
+            _ => 1.0 / (1u32 << divisor.min(31)) as f32,
+        };
+        ComponentArray {
+            data,
+            component_type,
+            num_components,
+            scale,
+            stride: stride as usize,
+            len: len as usize,
+        }
+    }
+
+    /// Returns up to 3 components, any missing components are 0
+    fn get(&self, index: usize) -> Option<[f32; 3]> {
+        let size = self.component_type.size()?;
+        if index >= self.len {
+            return None;
+        }
+        let mut values = [0.0; 3];
+        for (i, value) in values.iter_mut().enumerate().take(self.num_components) {
+            let offset = index * self.stride + i * size;
+            let bytes = self.data.get(offset..offset + size)?;
+            *value = match self.component_type {
+                VertexComponentType::U8 => bytes[0] as f32,
+                VertexComponentType::I8 => bytes[0] as i8 as f32,
+                VertexComponentType::U16 => u16::from_be_bytes([bytes[0], bytes[1]]) as f32,
+                VertexComponentType::I16 => i16::from_be_bytes([bytes[0], bytes[1]]) as f32,
+                VertexComponentType::F32 => f32::from_be_bytes(bytes.try_into().unwrap()),
+                VertexComponentType::Unknown(_) => return None,
+            } * self.scale;
+        }
+        Some(values)
+    }
+}
+
+fn position_array(mdl0: &Mdl0, id: i16) -> Result<ComponentArray<'_>, Error> {
+    let Some(vertices) = mdl0
+        .vertices
+        .iter()
+        .flatten()
+        .find(|x| x.index == id as i32)
+    else {
+        bail!("MDL0 {} has no vertices with index {}", mdl0.name, id);
+    };
+    Ok(ComponentArray::new(
+        &vertices.data,
+        vertices.component_type.clone(),
+        if vertices.is_xyz { 3 } else { 2 },
+        vertices.divisor,
+        vertices.entry_stride,
+        vertices.num_vertices,
+    ))
+}
+
+const VERTEX_ARRAY_HEADER_SIZE: usize = 0x20;
+/// Normals, colors and UVs share the same header layout as the vertices, so they are read from the same place.
+fn unparsed_array<'a>(
+    mdl0: &Mdl0,
+    arrays: &'a Option<Vec<UnparsedResource>>,
+    id: i16,
+    kind: &str,
+) -> Result<&'a UnparsedResource, Error> {
+    let Some(array) = arrays
+        .iter()
+        .flatten()
+        .find(|x| x.data.len() >= VERTEX_ARRAY_HEADER_SIZE && x.i32(0x10) == id as i32)
+    else {
+        bail!("MDL0 {} has no {} with index {}", mdl0.name, kind, id);
+    };
+    Ok(array)
+}
+
+/// The array data starting at the data offset in the header
+fn array_data(array: &UnparsedResource) -> &[u8] {
+    array
+        .data
+        .get(array.i32(0x08).max(0) as usize..)
+        .unwrap_or_default()
+}
+
+fn normal_array(mdl0: &Mdl0, id: i16) -> Result<ComponentArray<'_>, Error> {
+    let array = unparsed_array(mdl0, &mdl0._normals, id, "normals")?;
+    // The binormals and tangents follow each normal in the same entry, so only the normal is read.
+    Ok(ComponentArray::new(
+        array_data(array),
+        VertexComponentType::new(array.i32(0x18)),
+        3,
+        array.data[0x1c],
+        array.data[0x1d],
+        u16::from_be_bytes([array.data[0x1e], array.data[0x1f]]),
+    ))
+}
+
+fn uv_array(mdl0: &Mdl0, id: i16) -> Result<ComponentArray<'_>, Error> {
+    let array = unparsed_array(mdl0, &mdl0._uv, id, "UVs")?;
+    let is_st = array.i32(0x14) != 0;
+    Ok(ComponentArray::new(
+        array_data(array),
+        VertexComponentType::new(array.i32(0x18)),
+        if is_st { 2 } else { 1 },
+        array.data[0x1c],
+        array.data[0x1d],
+        u16::from_be_bytes([array.data[0x1e], array.data[0x1f]]),
+    ))
+}
+
+struct ColorArray<'a> {
+    data: &'a [u8],
+    format: i32,
+    stride: usize,
+    len: usize,
+}
+
+impl ColorArray<'_> {
+    fn get(&self, index: usize) -> Option<[u8; 4]> {
+        if index >= self.len {
+            return None;
+        }
+        let offset = index * self.stride;
+        let bytes = |len: usize| self.data.get(offset..offset + len);
+        let expand =
+            |value: u32, bits: u32| ((value & ((1 << bits) - 1)) * 255 / ((1 << bits) - 1)) as u8;
+        match self.format {
+            0 => bytes(2)
+                .map(|x| WiiPaletteFormat::RGB565.decode_color(u16::from_be_bytes([x[0], x[1]]))),
+            1 | 2 => bytes(3).map(|x| [x[0], x[1], x[2], 0xff]),
+            3 => bytes(2).map(|x| {
+                let value = u16::from_be_bytes([x[0], x[1]]) as u32;
+                [
+                    expand(value >> 12, 4),
+                    expand(value >> 8, 4),
+                    expand(value >> 4, 4),
+                    expand(value, 4),
+                ]
+            }),
+            4 => bytes(3).map(|x| {
+                let value = u32::from_be_bytes([0, x[0], x[1], x[2]]);
+                [
+                    expand(value >> 18, 6),
+                    expand(value >> 12, 6),
+                    expand(value >> 6, 6),
+                    expand(value, 6),
+                ]
+            }),
+            5 => bytes(4).map(|x| [x[0], x[1], x[2], x[3]]),
+            _ => None,
+        }
+    }
+}
+
+fn color_array(mdl0: &Mdl0, id: i16) -> Result<ColorArray<'_>, Error> {
+    let array = unparsed_array(mdl0, &mdl0._colors, id, "colors")?;
+    Ok(ColorArray {
+        data: array_data(array),
+        format: array.i32(0x18),
+        stride: array.data[0x1c] as usize,
+        len: u16::from_be_bytes([array.data[0x1e], array.data[0x1f]]) as usize,
+    })
+}
@@ -1,25 +1,35 @@
 pub mod bones;
 pub mod definitions;
+pub mod materials;
+pub mod meshes;
 pub mod objects;
 pub mod palettes;
+pub mod shaders;
 pub mod textures;
 pub mod vertices;
 
+use anyhow::Error;
 use fancy_slice::FancySlice;
 
 use crate::mbox;
 use crate::mbox::MBox;
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
 use crate::resources;
-use crate::resources::Resource;
+use crate::resources::{RESOURCE_HEADER_SIZE, Resource, StringTable};
 use bones::Bone;
-use definitions::Definitions;
+use definitions::{Definitions, DrawCall};
+use materials::{MATERIAL_SHADER_OFFSET, Material};
+use meshes::Mesh;
 use objects::Object;
 use palettes::Palette;
+use shaders::Shader;
 use textures::Texture;
 use vertices::Vertices;
 
+/// Parses the MDL0 contained in `data`, returning an error instead of panicking when the data is malformed.
 #[rustfmt::skip]
-pub(crate) fn mdl0(data: FancySlice) -> Mdl0 {
+pub fn try_mdl0(data: FancySlice) -> Result<Mdl0, ParseError> {
+    check_len(data, 0x10, "MDL0")?;
     let _size        = data.i32_be(0x4);
     let version      = data.i32_be(0x8);
     let _bres_offset = data.i32_be(0xc);
@@ -29,8 +39,9 @@ pub(crate) fn mdl0(data: FancySlice) -> Mdl0 {
         0xB => 0x48,
         _   => 0x3C
     };
+    check_len(data, string_offset_offset + 4, "MDL0")?;
     let string_offset = data.i32_be(string_offset_offset);
-    let name = data.str(string_offset as usize).unwrap().to_string();
+    let name = try_str(&data, string_offset as usize, "MDL0")?.to_string();
 
     //let data_offset = match version {
     //    0xA => 0x40,
@@ -49,21 +60,40 @@ pub(crate) fn mdl0(data: FancySlice) -> Mdl0 {
     let props = if props_offset == 0 {
         None
     } else {
+        check_len(data, props_offset + MDL0_PROPS_SIZE, "MDL0 props")?;
+        let orig_path_offset = data.i32_be(props_offset + 0x18);
+        let orig_path = if orig_path_offset == 0 {
+            None
+        } else {
+            let orig_path = try_offset(&data, props_offset as i64 + orig_path_offset as i64, 0, "MDL0 props")?;
+            Some(try_str(&orig_path, 0, "MDL0 props")?.to_string())
+        };
+
+        let bone_table_offset = data.i32_be(props_offset + 0x24);
+        let bone_table = if bone_table_offset == 0 {
+            vec!()
+        } else {
+            let bone_table_offset = props_offset as i64 + bone_table_offset as i64;
+            let bone_table = try_offset(&data, bone_table_offset, 4, "MDL0 bone table")?;
+            let len = bone_table.u32_be(0) as usize;
+            check_len(bone_table, 4 + len * 4, "MDL0 bone table").map_err(|err| err.offset_by(bone_table_offset as usize))?;
+            (0..len).map(|i| bone_table.i32_be(4 + i * 4)).collect()
+        };
+
         Some(Mdl0Props {
             header_len:         data.u32_be(props_offset),
-            mdl0offset:         data.i32_be(props_offset + 0x04),
             scaling_rule:       data.i32_be(props_offset + 0x08),
             tex_matrix_mode:    data.i32_be(props_offset + 0x0c),
             num_vertices:       data.i32_be(props_offset + 0x10),
             num_triangles:      data.i32_be(props_offset + 0x14),
-            orig_path_offset:   data.i32_be(props_offset + 0x18),
+            orig_path,
             num_nodes:          data.i32_be(props_offset + 0x1c),
             need_nrm_mtx_array: data.u8    (props_offset + 0x20),
             need_tex_mtx_array: data.u8    (props_offset + 0x21),
             enable_extents:     data.u8    (props_offset + 0x22),
             env_mtx_mode:       data.u8    (props_offset + 0x23),
-            data_offset:        data.i32_be(props_offset + 0x24),
             extents: mbox::mbox(data.relative_fancy_slice(props_offset + 0x28..)),
+            bone_table,
         })
     };
 
@@ -75,45 +105,57 @@ pub(crate) fn mdl0(data: FancySlice) -> Mdl0 {
     let mut _uv = None;
     let mut fur_vectors = None;
     let mut fur_layer_coords = None;
-    let mut _materials = None;
-    let mut _shaders = None;
+    let mut materials = None;
+    let mut shaders = None;
     let mut objects = None;
     let mut texture_refs = None; // TODO: Bleh I think the naming of this and children is wrong
     let mut palette_refs = None;
 
     let fur_version = version >= 0xA;
     let num_children = if fur_version { 0xD } else { 0xB };
+    check_len(data, 0x10 + num_children * 0x4, "MDL0")?;
     for i in 0..num_children {
         let offset = 0x10 + i * 0x4;
 
         let resources_offset = data.i32_be(offset);
         if resources_offset != 0 {
-            let resources = resources::resources(data.relative_fancy_slice(resources_offset as usize .. ));
+            let group_data = try_offset(&data, resources_offset as i64, RESOURCE_HEADER_SIZE, "MDL0 resource group")?;
+            let group_offset = resources_offset as usize;
+            let resources = resources::try_resources(group_data).map_err(|err| err.offset_by(group_offset))?;
+            let group = |err: ParseError| err.offset_by(group_offset);
             match i {
-                0x6 if fur_version => { fur_vectors = Some(resources) }
-                0x7 if fur_version => { fur_layer_coords = Some(resources) }
-                0x8 if fur_version => { _materials = Some(resources) }
-                0x9 if fur_version => { _shaders = Some(resources) }
-                0xA if fur_version => { objects = Some(objects::objects(data.relative_fancy_slice(resources_offset as usize ..), resources)) }
-                0xB if fur_version => { texture_refs = Some(textures::textures(data.relative_fancy_slice(resources_offset as usize ..), resources)) }
-                0xC if fur_version => { palette_refs = Some(palettes::palettes(data.relative_fancy_slice(resources_offset as usize ..), resources)) }
-                0x0 => { definitions = Some(definitions::definitions(data.relative_fancy_slice(resources_offset as usize..), resources)) }
-                0x1 => { bones = Some(bones::bones(data.relative_fancy_slice(resources_offset as usize ..), resources)) }
-                0x2 => { vertices = Some(vertices::vertices(data.relative_fancy_slice(resources_offset as usize ..), resources)) }
-                0x3 => { _normals = Some(resources) }
-                0x4 => { _colors = Some(resources) }
-                0x5 => { _uv = Some(resources) }
-                0x6 => { _materials = Some(resources) }
-                0x7 => { _shaders = Some(resources) }
-                0x8 => { objects = Some(objects::objects(data.relative_fancy_slice(resources_offset as usize ..), resources)) }
-                0x9 => { texture_refs = Some(textures::textures(data.relative_fancy_slice(resources_offset as usize ..), resources)) }
-                0xA => { palette_refs = Some(palettes::palettes(data.relative_fancy_slice(resources_offset as usize ..), resources)) }
+                0x6 if fur_version => { fur_vectors = Some(vertex_arrays(data, group_offset, resources)?) }
+                0x7 if fur_version => { fur_layer_coords = Some(vertex_arrays(data, group_offset, resources)?) }
+                0x8 if fur_version => { materials = Some(materials::materials(data, group_offset, resources, version)?) }
+                0x9 if fur_version => { shaders = Some(shaders::shaders(data, group_offset, resources)?) }
+                0xA if fur_version => { objects = Some(objects::objects(group_data, group_offset, resources).map_err(group)?) }
+                0xB if fur_version => { texture_refs = Some(textures::textures(group_data, group_offset, resources).map_err(group)?) }
+                0xC if fur_version => { palette_refs = Some(palettes::palettes(group_data, group_offset, resources).map_err(group)?) }
+                0x0 => { definitions = Some(definitions::definitions(group_data, resources).map_err(group)?) }
+                0x1 => { bones = Some(bones::bones(group_data, resources).map_err(group)?) }
+                0x2 => { vertices = Some(vertices::vertices(group_data, resources).map_err(group)?) }
+                0x3 => { _normals = Some(vertex_arrays(data, group_offset, resources)?) }
+                0x4 => { _colors = Some(vertex_arrays(data, group_offset, resources)?) }
+                0x5 => { _uv = Some(vertex_arrays(data, group_offset, resources)?) }
+                0x6 => { materials = Some(materials::materials(data, group_offset, resources, version)?) }
+                0x7 => { shaders = Some(shaders::shaders(data, group_offset, resources)?) }
+                0x8 => { objects = Some(objects::objects(group_data, group_offset, resources).map_err(group)?) }
+                0x9 => { texture_refs = Some(textures::textures(group_data, group_offset, resources).map_err(group)?) }
+                0xA => { palette_refs = Some(palettes::palettes(group_data, group_offset, resources).map_err(group)?) }
                 _   => { unreachable!() }
             }
         }
     }
 
-    Mdl0 {
+    // materials point to their shader by offset
+    if let (Some(materials), Some(shaders)) = (&mut materials, &shaders) {
+        for material in materials.iter_mut() {
+            let shader_offset = material.offset() as i64 + material.shader_offset() as i64;
+            material.shader = shaders.iter().position(|x| x.offset() as i64 == shader_offset);
+        }
+    }
+
+    Ok(Mdl0 {
         name,
         version,
         props,
@@ -125,12 +167,12 @@ pub(crate) fn mdl0(data: FancySlice) -> Mdl0 {
         _uv,
         fur_vectors,
         fur_layer_coords,
-        _materials,
-        _shaders,
+        materials,
+        shaders,
         objects,
         texture_refs,
         palette_refs,
-    }
+    })
 }
 
 #[derive(Clone, Debug)]
@@ -141,110 +183,483 @@ pub struct Mdl0 {
     pub definitions: Option<Definitions>,
     pub bones: Option<Bone>,
     pub vertices: Option<Vec<Vertices>>,
-    _normals: Option<Vec<Resource>>,
-    _colors: Option<Vec<Resource>>,
-    _uv: Option<Vec<Resource>>,
-    fur_vectors: Option<Vec<Resource>>,
-    fur_layer_coords: Option<Vec<Resource>>,
-    _materials: Option<Vec<Resource>>,
-    _shaders: Option<Vec<Resource>>,
+    _normals: Option<Vec<UnparsedResource>>,
+    _colors: Option<Vec<UnparsedResource>>,
+    _uv: Option<Vec<UnparsedResource>>,
+    fur_vectors: Option<Vec<UnparsedResource>>,
+    fur_layer_coords: Option<Vec<UnparsedResource>>,
+    pub materials: Option<Vec<Material>>,
+    pub shaders: Option<Vec<Shader>>,
     pub objects: Option<Vec<Object>>,
     pub texture_refs: Option<Vec<Texture>>,
     pub palette_refs: Option<Vec<Palette>>,
 }
 
 impl Mdl0 {
+    /// Decodes every object into a triangle mesh, in the same order as `objects`
+    pub fn meshes(&self) -> Result<Vec<Mesh>, Error> {
+        self.objects
+            .iter()
+            .flatten()
+            .map(|object| meshes::mesh(self, object))
+            .collect()
+    }
+
+    /// Decodes the display list of one of this MDL0s objects into a triangle mesh
+    pub fn mesh(&self, object: &Object) -> Result<Mesh, Error> {
+        meshes::mesh(self, object)
+    }
+
+    /// The draw call that draws the object, this specifies the material and visibility of the object
+    pub fn object_draw_call(&self, object: &Object) -> Option<&DrawCall> {
+        self.definitions
+            .iter()
+            .flat_map(|x| &x.values)
+            .flat_map(|x| x.draw_calls())
+            .find(|x| x.object as u32 == object.index)
+    }
+
+    /// The material with the given index, as referred to by `DrawCall::material`
+    pub fn material(&self, index: u16) -> Option<&Material> {
+        self.materials
+            .iter()
+            .flatten()
+            .find(|x| x.index == index as i32)
+    }
+
+    /// The shader used by the material
+    pub fn material_shader(&self, material: &Material) -> Option<&Shader> {
+        self.shaders.as_ref()?.get(material.shader?)
+    }
+
     pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
         let mut output = vec![];
+        let mut strings = StringTable::new();
+
+        // TODO: Determine version from the fields used
+        let fur_version = self.version >= 0xA;
+        let num_groups = if fur_version { 0xD } else { 0xB };
+        let extra = if self.version >= 0xB { 4 } else { 0 }; // TODO: An extra something ... goes here
+        let string_offset_offset = 0x10 + num_groups * 4 + extra;
+        let props_offset = string_offset_offset + 4;
 
         // create mdl0 header
         output.extend("MDL0".chars().map(|x| x as u8));
-        output.extend(i32::to_be_bytes(0x512e)); // size
+        output.extend(i32::to_be_bytes(0)); // size, filled in at the end
         output.extend(i32::to_be_bytes(self.version));
         output.extend(i32::to_be_bytes(bres_offset));
+        output.resize(props_offset, 0x00); // the resource group offsets are filled in as the groups are written
+        strings.reference(string_offset_offset, 0, &self.name);
 
-        // TODO: Determine version from the fields used
-        let props_offset = match self.version {
-            0x08 => 0x40,
-            0x09 => 0x40,
-            0x0A => 0x48,
-            0x0B => 0x4C,
-            _ => panic!("Unknown MDL0 version"),
+        if let Some(props) = &self.props {
+            props.compile(&mut output, &mut strings);
+        }
+
+        // create resource groups
+        let bones = self.bones.as_ref().map(|x| x.flatten());
+        let mut groups = vec![
+            self.definitions
+                .as_ref()
+                .map(|x| x.values.iter().map(|x| x.name.as_str()).collect()),
+            bones
+                .as_ref()
+                .map(|x| x.iter().map(|x| x.bone.name.as_str()).collect()),
+            self.vertices
+                .as_ref()
+                .map(|x| x.iter().map(|x| x.name.as_str()).collect()),
+            unparsed_names(&self._normals),
+            unparsed_names(&self._colors),
+            unparsed_names(&self._uv),
+        ];
+        if fur_version {
+            groups.push(unparsed_names(&self.fur_vectors));
+            groups.push(unparsed_names(&self.fur_layer_coords));
+        }
+        groups.push(
+            self.materials
+                .as_ref()
+                .map(|x| x.iter().map(|x| x.name.as_str()).collect()),
+        );
+        groups.push(
+            self.shaders
+                .as_ref()
+                .map(|x| x.iter().map(|x| x.name.as_str()).collect()),
+        );
+        groups.push(self.objects.as_ref().map(|x| {
+            x.iter()
+                .map(|x| x.name.as_deref().unwrap_or_default())
+                .collect()
+        }));
+        groups.push(
+            self.texture_refs
+                .as_ref()
+                .map(|x| x.iter().map(|x| x.name.as_str()).collect()),
+        );
+        groups.push(
+            self.palette_refs
+                .as_ref()
+                .map(|x| x.iter().map(|x| x.name.as_str()).collect()),
+        );
+
+        let mut group_offsets = vec![];
+        for (i, names) in groups.iter().enumerate() {
+            group_offsets.push(names.as_ref().map(|names| {
+                let group = resources::compile_resources(&mut output, &mut strings, names);
+                let field = 0x10 + i * 4;
+                output[field..field + 4].copy_from_slice(&i32::to_be_bytes(group as i32));
+                group
+            }));
+        }
+        let set_data_offset = |output: &mut Vec<u8>, group: usize, offsets: Vec<usize>| {
+            if let Some(group_offset) = group_offsets[group] {
+                for (i, offset) in offsets.into_iter().enumerate() {
+                    resources::set_resource_data_offset(output, group_offset, i, offset);
+                }
+            }
         };
+        let fur_groups = if fur_version { 2 } else { 0 };
+
+        // create resource data
+        if let Some(definitions) = &self.definitions {
+            let mut offsets = vec![];
+            for definition in &definitions.values {
+                offsets.push(output.len());
+                output.extend(definition.compile());
+            }
+            set_data_offset(&mut output, 0x0, offsets);
+        }
+
+        if let Some(bones) = &bones {
+            let offsets = bones::compile_bones(bones, &mut output, &mut strings);
+            set_data_offset(&mut output, 0x1, offsets);
+        }
 
-        let num_props = match (&self.fur_vectors, &self.fur_layer_coords) {
-            (Some(_), Some(_)) => 0xD,
-            (None, None) => 0xB,
-            _ => panic!("Can't have just one of the fur fields set to Some(_)"),
+        let mut relocations = vec![];
+        if let Some(materials) = &self.materials {
+            let materials = materials.iter().map(|x| &x.raw);
+            let offsets = compile_unparsed(materials, &mut output, &mut strings, &mut relocations);
+            set_data_offset(&mut output, 0x6 + fur_groups, offsets);
+        }
+
+        if let Some(shaders) = &self.shaders {
+            let shaders = shaders.iter().map(|x| &x.raw);
+            let offsets = compile_unparsed(shaders, &mut output, &mut strings, &mut relocations);
+            set_data_offset(&mut output, 0x7 + fur_groups, offsets);
+        }
+
+        let relocate = |offset: usize| {
+            relocations
+                .iter()
+                .find(|x| offset >= x.old && offset < x.old + x.len)
+                .map(|x| x.new + offset - x.old)
+                .unwrap_or(offset)
         };
 
-        let header_size = props_offset + num_props;
+        // materials point to their shader
+        for material in self.materials.iter().flatten() {
+            let shader_offset = material.shader_offset();
+            if shader_offset != 0 {
+                let new_material = relocate(material.offset());
+                let new_shader = relocate((material.offset() as i32 + shader_offset) as usize);
+                let field = new_material + MATERIAL_SHADER_OFFSET;
+                output[field..field + 4]
+                    .copy_from_slice(&i32::to_be_bytes(new_shader as i32 - new_material as i32));
+            }
+        }
 
-        let definitions = self.definitions.as_ref().unwrap().compile();
+        if let Some(objects) = &self.objects {
+            let offsets = objects
+                .iter()
+                .map(|x| x.compile(&mut output, &mut strings))
+                .collect();
+            set_data_offset(&mut output, 0x8 + fur_groups, offsets);
+        }
 
-        output.extend(i32::to_be_bytes(header_size)); // definitions_offset
-        output.extend(i32::to_be_bytes(header_size + definitions.len() as i32)); // bones_offset
-        output.extend(i32::to_be_bytes(0)); // TODO: vertices_offset
-        output.extend(i32::to_be_bytes(0)); // TODO: normals_offset
-        output.extend(i32::to_be_bytes(0)); // TODO: colors_offset
-        output.extend(i32::to_be_bytes(0)); // TODO: uv_offset
-        output.extend(i32::to_be_bytes(0)); // TODO: materials_offset
-        output.extend(i32::to_be_bytes(0)); // TODO: shaders_offset
-        output.extend(i32::to_be_bytes(0)); // TODO: objects_offset
-        output.extend(i32::to_be_bytes(0)); // TODO: texture_refs_offset
-        output.extend(i32::to_be_bytes(0)); // TODO: palette_refs_offset
+        if let Some(texture_refs) = &self.texture_refs {
+            let offsets = texture_refs
+                .iter()
+                .map(|x| x.compile(&mut output, relocate))
+                .collect();
+            set_data_offset(&mut output, 0x9 + fur_groups, offsets);
+        }
 
-        if self.version >= 0xA {
-            output.extend(i32::to_be_bytes(0)); // TODO: fur_vectors_offset
-            output.extend(i32::to_be_bytes(0)); // TODO: fur_layer_coords_offset
+        if let Some(palette_refs) = &self.palette_refs {
+            let offsets = palette_refs
+                .iter()
+                .map(|x| x.compile(&mut output, relocate))
+                .collect();
+            set_data_offset(&mut output, 0xA + fur_groups, offsets);
         }
-        if self.version >= 0xB {
-            output.extend(i32::to_be_bytes(0)); // TODO: An extra something ... goes here
+
+        if let Some(vertices) = &self.vertices {
+            let offsets = vertices
+                .iter()
+                .map(|x| x.compile(&mut output, &mut strings))
+                .collect();
+            set_data_offset(&mut output, 0x2, offsets);
         }
 
-        output.extend(i32::to_be_bytes(0)); // TODO: string_offset
+        let mut vertex_relocations = vec![];
+        let vertex_arrays = [
+            (0x3, &self._normals),
+            (0x4, &self._colors),
+            (0x5, &self._uv),
+            (0x6, &self.fur_vectors),
+            (0x7, &self.fur_layer_coords),
+        ];
+        for (group, resources) in vertex_arrays {
+            if let Some(resources) = resources {
+                let offsets = compile_unparsed(
+                    resources.iter(),
+                    &mut output,
+                    &mut strings,
+                    &mut vertex_relocations,
+                );
+                set_data_offset(&mut output, group, offsets);
+            }
+        }
 
-        // TODO: Many of these should be generated rather than stored
-        if let Some(props) = &self.props {
-            output.extend(u32::to_be_bytes(props.header_len));
-            output.extend(i32::to_be_bytes(props.mdl0offset));
-            output.extend(i32::to_be_bytes(props.scaling_rule));
-            output.extend(i32::to_be_bytes(props.tex_matrix_mode));
-            output.extend(i32::to_be_bytes(props.num_vertices));
-            output.extend(i32::to_be_bytes(props.num_triangles));
-            output.extend(i32::to_be_bytes(props.orig_path_offset));
-            output.extend(i32::to_be_bytes(props.num_nodes));
-            output.push(props.need_nrm_mtx_array);
-            output.push(props.need_tex_mtx_array);
-            output.push(props.enable_extents);
-            output.push(props.env_mtx_mode);
-            output.extend(i32::to_be_bytes(props.data_offset));
-            output.extend(&props.extents.compile());
-        }
-
-        // TODO: What is the data here???
-        output.extend(&vec![0x00; 0x8]);
-
-        output.extend(definitions);
+        while output.len() % 4 != 0 {
+            output.push(0x00);
+        }
+        strings.compile(&mut output);
+
+        let size = output.len() as i32;
+        output[0x4..0x8].copy_from_slice(&i32::to_be_bytes(size));
 
         output
     }
 }
 
+/// A resource within the MDL0 that isnt parsed yet.
+/// The bytes are kept so that it can be written back out.
+#[derive(Clone, Debug)]
+pub(crate) struct UnparsedResource {
+    name: String,
+    /// Offset from the start of the MDL0, other resources may point to it.
+    offset: usize,
+    data: Vec<u8>,
+    /// The string offsets within `data`, these need to be rewritten when the strings move.
+    strings: Vec<UnparsedString>,
+}
+
+#[derive(Clone, Debug)]
+struct UnparsedString {
+    /// Offset within the resource of the string offset
+    field: usize,
+    /// Offset within the resource that the string offset is relative to
+    base: usize,
+    string: String,
+}
+
+impl UnparsedResource {
+    fn i32(&self, offset: usize) -> i32 {
+        i32::from_be_bytes([
+            self.data[offset],
+            self.data[offset + 1],
+            self.data[offset + 2],
+            self.data[offset + 3],
+        ])
+    }
+
+    /// `data` is the entire MDL0
+    fn read_string(
+        &mut self,
+        data: FancySlice,
+        field: usize,
+        base: usize,
+    ) -> Result<(), ParseError> {
+        let string_offset = self.i32(field);
+        if string_offset != 0 {
+            let string_offset = (self.offset + base) as i64 + string_offset as i64;
+            let string = try_offset(&data, string_offset, 0, "MDL0 string")
+                .and_then(|string| Ok(try_str(&string, 0, "MDL0 string")?.to_string()))?;
+            self.strings.push(UnparsedString {
+                field,
+                base,
+                string,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn unparsed_names(resources: &Option<Vec<UnparsedResource>>) -> Option<Vec<&str>> {
+    resources
+        .as_ref()
+        .map(|x| x.iter().map(|x| x.name.as_str()).collect())
+}
+
+/// Every resource type that isnt parsed yet starts with its size
+/// `group_offset` is the offset of the resource group from the start of the MDL0.
+/// Resources smaller than `min_size` are rejected, so that the caller can read their header.
+fn unparsed_resources(
+    data: FancySlice,
+    group_offset: usize,
+    resources: Vec<Resource>,
+    min_size: usize,
+    structure: &'static str,
+) -> Result<Vec<UnparsedResource>, ParseError> {
+    resources
+        .into_iter()
+        .map(|resource| {
+            let offset = group_offset as i64 + resource.data_offset as i64;
+            let resource_data = try_offset(&data, offset, 4, structure)?;
+            let size = resource_data.u32_be(0) as usize;
+            check_len(resource_data, size, structure)
+                .map_err(|err| err.offset_by(offset as usize))?;
+            // the mdl0_offset is always rewritten on compile so it must be present
+            if size < min_size.max(8) {
+                return Err(ParseError::new(
+                    structure,
+                    offset as usize,
+                    ParseErrorKind::Malformed(format!(
+                        "size 0x{size:x} is smaller than the header"
+                    )),
+                ));
+            }
+            Ok(UnparsedResource {
+                name: resource.string,
+                offset: offset as usize,
+                data: resource_data.relative_slice(..size).to_vec(),
+                strings: vec![],
+            })
+        })
+        .collect()
+}
+
+/// Normals, colors, UVs and fur data all store their name at the same place
+fn vertex_arrays(
+    data: FancySlice,
+    group_offset: usize,
+    resources: Vec<Resource>,
+) -> Result<Vec<UnparsedResource>, ParseError> {
+    let mut resources =
+        unparsed_resources(data, group_offset, resources, 0x10, "MDL0 vertex array")?;
+    for resource in &mut resources {
+        resource.read_string(data, 0x0c, 0)?;
+    }
+    Ok(resources)
+}
+
+/// Where a resource was moved to by `Mdl0::compile`
+struct Relocation {
+    old: usize,
+    len: usize,
+    new: usize,
+}
+
+/// Writes the resources to the end of `output`, the MDL0 being compiled, returning the offset of each resource.
+fn compile_unparsed<'a>(
+    resources: impl Iterator<Item = &'a UnparsedResource>,
+    output: &mut Vec<u8>,
+    strings: &mut StringTable,
+    relocations: &mut Vec<Relocation>,
+) -> Vec<usize> {
+    let mut offsets = vec![];
+    for resource in resources {
+        // multiple resources can share the same data e.g. materials sharing a shader
+        if let Some(relocation) = relocations.iter().find(|x| x.old == resource.offset) {
+            offsets.push(relocation.new);
+            continue;
+        }
+
+        // display lists and vertex data need to stay 0x20 aligned
+        while output.len() % 0x20 != resource.offset % 0x20 {
+            output.push(0x00);
+        }
+        let offset = output.len();
+        output.extend(&resource.data);
+        output[offset + 4..offset + 8].copy_from_slice(&i32::to_be_bytes(-(offset as i32))); // mdl0_offset
+
+        for string in &resource.strings {
+            let field = offset + string.field;
+            output[field..field + 4].copy_from_slice(&i32::to_be_bytes(0));
+            strings.reference(field, offset + string.base, &string.string);
+        }
+
+        relocations.push(Relocation {
+            old: resource.offset,
+            len: resource.data.len(),
+            new: offset,
+        });
+        offsets.push(offset);
+    }
+    offsets
+}
+
+const MDL0_PROPS_SIZE: usize = 0x40;
 #[derive(Clone, Debug)]
 pub struct Mdl0Props {
     header_len: u32,
-    mdl0offset: i32,
     scaling_rule: i32,
     tex_matrix_mode: i32,
     num_vertices: i32,
     num_triangles: i32,
-    orig_path_offset: i32,
+    orig_path: Option<String>,
     num_nodes: i32,
     need_nrm_mtx_array: u8,
     need_tex_mtx_array: u8,
     enable_extents: u8,
     env_mtx_mode: u8,
-    data_offset: i32,
     extents: MBox,
+    /// Maps matrix ids to bone indexes
+    bone_table: Vec<i32>,
+}
+
+impl Mdl0Props {
+    fn compile(&self, output: &mut Vec<u8>, strings: &mut StringTable) {
+        let offset = output.len();
+
+        // TODO: Many of these should be generated rather than stored
+        output.extend(u32::to_be_bytes(self.header_len));
+        output.extend(i32::to_be_bytes(-(offset as i32))); // mdl0_offset
+        output.extend(i32::to_be_bytes(self.scaling_rule));
+        output.extend(i32::to_be_bytes(self.tex_matrix_mode));
+        output.extend(i32::to_be_bytes(self.num_vertices));
+        output.extend(i32::to_be_bytes(self.num_triangles));
+        if let Some(orig_path) = &self.orig_path {
+            strings.reference(output.len(), offset, orig_path);
+        }
+        output.extend(i32::to_be_bytes(0)); // orig_path_offset
+        output.extend(i32::to_be_bytes(self.num_nodes));
+        output.push(self.need_nrm_mtx_array);
+        output.push(self.need_tex_mtx_array);
+        output.push(self.enable_extents);
+        output.push(self.env_mtx_mode);
+        output.extend(i32::to_be_bytes(MDL0_PROPS_SIZE as i32)); // bone_table_offset
+        output.extend(&self.extents.compile());
+
+        output.extend(i32::to_be_bytes(self.bone_table.len() as i32));
+        for bone in &self.bone_table {
+            output.extend(i32::to_be_bytes(*bone));
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A MDL0 with only bones
+    pub(crate) fn mdl0(name: &str, bones: Bone) -> Mdl0 {
+        Mdl0 {
+            name: name.to_string(),
+            version: 11,
+            props: None,
+            definitions: None,
+            bones: Some(bones),
+            vertices: None,
+            _normals: None,
+            _colors: None,
+            _uv: None,
+            fur_vectors: None,
+            fur_layer_coords: None,
+            materials: None,
+            shaders: None,
+            objects: None,
+            texture_refs: None,
+            palette_refs: None,
+        }
+    }
 }
@@ -1,13 +1,20 @@
 use fancy_slice::FancySlice;
 
-use crate::resources::Resource;
+use crate::parse_error::{ParseError, ParseErrorKind, try_offset, try_str};
+use crate::resources::{Resource, StringTable};
 
+/// `group_offset` is the offset of the resource group from the start of the MDL0
 #[rustfmt::skip]
-pub(crate) fn objects(data: FancySlice, resources: Vec<Resource>) -> Vec<Object> {
+pub(crate) fn objects(data: FancySlice, group_offset: usize, resources: Vec<Resource>) -> Result<Vec<Object>, ParseError> {
     let mut objects = vec!();
     for resource in resources {
-        let data = data.relative_fancy_slice(resource.data_offset as usize ..);
-        let _total_length            = data.i32_be(0x00);
+        let resource_offset = resource.data_offset as i64;
+        let data = try_offset(&data, resource_offset, OBJECT_SIZE, "MDL0 object")?;
+        let offset = group_offset + resource_offset as usize;
+        let malformed = |message: String| {
+            ParseError::new("MDL0 object", resource_offset as usize, ParseErrorKind::Malformed(message))
+        };
+        let total_length             = data.i32_be(0x00);
         let _mdl0_offset             = data.i32_be(0x04);
         let single_bind_node_id      = data.i32_be(0x08);
         let vertex_format1           = data.u32_be(0x0c);
@@ -15,10 +22,10 @@ pub(crate) fn objects(data: FancySlice, resources: Vec<Resource>) -> Vec<Object>
         let vertex_specs             = data.u32_be(0x14);
         let _definitions_buffer_size = data.i32_be(0x18);
         let _definitions_size        = data.i32_be(0x1c); // amount of the buffer_size that is actually used
-        let _definitions_offset      = data.i32_be(0x20); // relative to this struct
+        let _definitions_offset      = data.i32_be(0x20); // relative to definitions_buffer_size
         let _primitives_buffer_size  = data.i32_be(0x24);
-        let _primitives_size         = data.i32_be(0x28); // amount of the buffer_size that is actually used
-        let _primitives_offset       = data.i32_be(0x2c); // relative to this struct
+        let primitives_size          = data.i32_be(0x28); // amount of the buffer_size that is actually used
+        let primitives_offset        = data.i32_be(0x2c); // relative to primitives_buffer_size
         let _array_flags             = data.u32_be(0x30);
         let modifier                 = data.u32_be(0x34);
         let string_offset            = data.u32_be(0x38);
@@ -45,15 +52,26 @@ pub(crate) fn objects(data: FancySlice, resources: Vec<Resource>) -> Vec<Object>
             Some(single_bind_node_id as u32)
         };
 
-        let modifier = Modifier::new(modifier);
+        let modifier = Modifier::new(modifier)
+            .ok_or_else(|| malformed(format!("unknown modifier {modifier}")))?;
 
         let name = if string_offset == 0 {
             None
         } else {
-            Some(data.str(string_offset as usize).unwrap().to_string())
+            Some(
+                try_str(&data, string_offset as usize, "MDL0 object")
+                    .map_err(|err| err.offset_by(resource_offset as usize))?
+                    .to_string(),
+            )
         };
 
+        if total_length < 0 || total_length as usize > data.len() {
+            return Err(malformed(format!("object length 0x{total_length:x} is outside of the data")));
+        }
+
         objects.push(Object {
+            offset,
+            data: data.relative_slice(..total_length as usize).to_vec(),
             single_bind_node_id,
             vertex_format1,
             vertex_format2,
@@ -62,8 +80,8 @@ pub(crate) fn objects(data: FancySlice, resources: Vec<Resource>) -> Vec<Object>
             _definitions_size,
             _definitions_offset,
             _primitives_buffer_size,
-            _primitives_size,
-            _primitives_offset,
+            primitives_size,
+            primitives_offset,
             _array_flags,
             modifier,
             name,
@@ -77,13 +95,19 @@ pub(crate) fn objects(data: FancySlice, resources: Vec<Resource>) -> Vec<Object>
         });
     }
 
-    objects
+    Ok(objects)
 }
 
-const _OBJECT_SIZE: usize = 0x64;
+const PRIMITIVES_FIELDS_OFFSET: usize = 0x24;
+const OBJECT_SIZE: usize = 0x64;
 #[derive(Debug, Clone)]
 #[rustfmt::skip]
 pub struct Object {
+    /// Offset from the start of the MDL0 the object was read from
+    offset: usize,
+    /// TODO: The display lists are not parsed yet, so the object is written back out by modifying these bytes.
+    data: Vec<u8>,
+
     pub single_bind_node_id: Option<u32>,
 
     // TODO: I should really split these flags into individual fields,
@@ -126,8 +150,8 @@ pub struct Object {
 
     // TODO: Use these fields to put the data into a vec
     _primitives_buffer_size: i32,
-    _primitives_size: i32,
-    _primitives_offset: i32,
+    primitives_size: i32,
+    primitives_offset: i32,
 
     // TODO: havent implemented getters for this field as I want to rewrite to split into seperate fields instead
 
@@ -168,6 +192,55 @@ pub struct Object {
 }
 
 impl Object {
+    /// Writes the object to the end of `output`, the MDL0 being compiled, returning the offset it was written to.
+    pub(crate) fn compile(&self, output: &mut Vec<u8>, strings: &mut StringTable) -> usize {
+        // The display lists need to stay 0x20 aligned
+        while output.len() % 0x20 != self.offset % 0x20 {
+            output.push(0x00);
+        }
+        let offset = output.len();
+        output.extend(&self.data);
+
+        let mut write = |field: usize, bytes: &[u8]| {
+            output[offset + field..offset + field + bytes.len()].copy_from_slice(bytes);
+        };
+        write(0x04, &i32::to_be_bytes(-(offset as i32))); // mdl0_offset
+        write(
+            0x08,
+            &i32::to_be_bytes(self.single_bind_node_id.map(|x| x as i32).unwrap_or(-1)),
+        );
+        write(0x0c, &u32::to_be_bytes(self.vertex_format1));
+        write(0x10, &u32::to_be_bytes(self.vertex_format2));
+        write(0x14, &u32::to_be_bytes(self.vertex_specs));
+        write(0x30, &u32::to_be_bytes(self._array_flags));
+        write(0x34, &u32::to_be_bytes(self.modifier.value()));
+        write(0x38, &u32::to_be_bytes(0)); // string_offset
+        write(0x3c, &u32::to_be_bytes(self.index));
+        write(0x40, &u32::to_be_bytes(self.num_vertices));
+        write(0x44, &u32::to_be_bytes(self.num_faces));
+        write(0x48, &i16::to_be_bytes(self.vertex_id));
+        write(0x4a, &i16::to_be_bytes(self.normal_id));
+        for (i, id) in self.color_ids.iter().enumerate() {
+            write(0x4c + i * 2, &i16::to_be_bytes(*id));
+        }
+        for (i, id) in self.uv_ids.iter().enumerate() {
+            write(0x50 + i * 2, &i16::to_be_bytes(*id));
+        }
+
+        if let Some(name) = &self.name {
+            strings.reference(offset + 0x38, offset, name);
+        }
+
+        offset
+    }
+
+    /// The display list that draws the object
+    pub(crate) fn primitives(&self) -> Option<&[u8]> {
+        let start = (PRIMITIVES_FIELDS_OFFSET as i64 + self.primitives_offset as i64) as usize;
+        self.data
+            .get(start..start + self.primitives_size.max(0) as usize)
+    }
+
     pub fn has_vertex_matrix(&self) -> bool {
         self.vertex_format1 & 1 != 0
     }
@@ -217,7 +290,7 @@ impl Object {
     }
 
     pub fn num_textures(&self) -> usize {
-        ((self.vertex_specs >> 4) & 0xb1111) as usize
+        ((self.vertex_specs >> 4) & 0b1111) as usize
     }
 }
 
@@ -269,12 +342,20 @@ pub enum Modifier {
 }
 
 impl Modifier {
-    fn new(value: u32) -> Self {
+    fn new(value: u32) -> Option<Self> {
         match value {
-            0 => Modifier::None,
-            1 => Modifier::ChangeCurrentMatrix,
-            2 => Modifier::Invisible,
-            _ => panic!("Unknown Modifier."),
+            0 => Some(Modifier::None),
+            1 => Some(Modifier::ChangeCurrentMatrix),
+            2 => Some(Modifier::Invisible),
+            _ => None,
+        }
+    }
+
+    fn value(&self) -> u32 {
+        match self {
+            Modifier::None => 0,
+            Modifier::ChangeCurrentMatrix => 1,
+            Modifier::Invisible => 2,
         }
     }
 }
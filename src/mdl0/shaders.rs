@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+
+use fancy_slice::FancySlice;
+
+use super::materials::bp_writes;
+use super::{UnparsedResource, unparsed_resources};
+use crate::parse_error::ParseError;
+use crate::resources::Resource;
+
+/// `data` is the entire MDL0 and `group_offset` is the offset of the resource group from the start of the MDL0
+pub(crate) fn shaders(
+    data: FancySlice,
+    group_offset: usize,
+    resources: Vec<Resource>,
+) -> Result<Vec<Shader>, ParseError> {
+    let shaders = unparsed_resources(
+        data,
+        group_offset,
+        resources,
+        SHADER_HEADER_SIZE,
+        "MDL0 shader",
+    )?
+    .into_iter()
+    .map(|raw| {
+        let shader = FancySlice::new(&raw.data);
+        let index = shader.i32_be(0x08);
+        let num_stages = shader.u8(0x0c).min(16) as usize;
+
+        let display_list = raw.data.get(SHADER_HEADER_SIZE..).unwrap_or_default();
+        let registers: HashMap<u8, u32> = bp_writes(display_list).into_iter().collect();
+        let register = |address: usize| registers.get(&(address as u8)).cloned().unwrap_or(0);
+
+        // The swap tables share their registers with the konst selectors
+        let swap_tables = [0, 1, 2, 3].map(|i| {
+            let rg = register(0xf6 + i * 2);
+            let ba = register(0xf7 + i * 2);
+            [rg & 0b11, (rg >> 2) & 0b11, ba & 0b11, (ba >> 2) & 0b11].map(|x| x as u8)
+        });
+
+        let stages = (0..num_stages)
+            .map(|i| {
+                // Each of these registers is shared by two stages, the odd stage is stored in the upper bits
+                let odd = i % 2 == 1;
+                let order = register(0x28 + i / 2) >> if odd { 12 } else { 0 };
+                let ksel = register(0xf6 + i / 2) >> if odd { 14 } else { 4 };
+                let tex_enable = order & 0x40 != 0;
+
+                TevStage {
+                    tex_map: if tex_enable {
+                        Some((order & 0b111) as u8)
+                    } else {
+                        None
+                    },
+                    tex_coord: ((order >> 3) & 0b111) as u8,
+                    color_channel: ((order >> 7) & 0b111) as u8,
+                    konst_color_select: (ksel & 0x1f) as u8,
+                    konst_alpha_select: ((ksel >> 5) & 0x1f) as u8,
+                    color: TevColorEnv::new(register(0xc0 + i * 2)),
+                    alpha: TevAlphaEnv::new(register(0xc1 + i * 2)),
+                    indirect: register(0x10 + i),
+                }
+            })
+            .collect();
+
+        Shader {
+            name: raw.name.clone(),
+            index,
+            stages,
+            swap_tables,
+            raw,
+        }
+    })
+    .collect();
+    Ok(shaders)
+}
+
+const SHADER_HEADER_SIZE: usize = 0x20;
+/// The TEV configuration that combines textures, vertex colors and constant colors into the final color of a pixel.
+/// Multiple materials can share the same shader.
+#[derive(Clone, Debug)]
+pub struct Shader {
+    /// The name of the first material using the shader
+    pub name: String,
+    pub index: i32,
+    pub stages: Vec<TevStage>,
+    /// Each table maps the red, green, blue and alpha channels to the channel they are read from (0 = red ... 3 = alpha)
+    pub swap_tables: [[u8; 4]; 4],
+    /// TODO: Modifications are not compiled yet, the original bytes are written as is.
+    pub(crate) raw: UnparsedResource,
+}
+
+impl Shader {
+    /// Offset from the start of the MDL0 the shader was read from
+    pub(crate) fn offset(&self) -> usize {
+        self.raw.offset
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TevStage {
+    /// The texture sampled by this stage, the index of a `MaterialTexture`
+    pub tex_map: Option<u8>,
+    pub tex_coord: u8,
+    /// The rasterized color used by this stage: 0 = color0, 1 = color1, 5 = alpha bump, 6 = normalized alpha bump, 7 = zero
+    pub color_channel: u8,
+    /// Selects the constant used by `TevColorArg::Konst`: 0x00-0x07 are constants from 1 to 1/8, 0x0c-0x0f are the K0-K3 colors
+    /// and 0x10-0x1f are single channels of the K0-K3 colors
+    pub konst_color_select: u8,
+    /// Selects the constant used by `TevAlphaArg::Konst`: 0x00-0x07 are constants from 1 to 1/8
+    /// and 0x10-0x1f are single channels of the K0-K3 colors
+    pub konst_alpha_select: u8,
+    pub color: TevColorEnv,
+    pub alpha: TevAlphaEnv,
+    /// The raw indirect texture configuration
+    pub indirect: u32,
+}
+
+/// Computes `dest = clamp(scale * (d (+ or -) ((1 - c) * a + c * b) + bias))`
+#[derive(Clone, Debug)]
+pub struct TevColorEnv {
+    pub a: TevColorArg,
+    pub b: TevColorArg,
+    pub c: TevColorArg,
+    pub d: TevColorArg,
+    pub bias: TevBias,
+    pub subtract: bool,
+    pub clamp: bool,
+    pub scale: TevScale,
+    pub dest: TevRegister,
+}
+
+impl TevColorEnv {
+    fn new(value: u32) -> TevColorEnv {
+        TevColorEnv {
+            d: TevColorArg::new(value),
+            c: TevColorArg::new(value >> 4),
+            b: TevColorArg::new(value >> 8),
+            a: TevColorArg::new(value >> 12),
+            bias: TevBias::new(value >> 16),
+            subtract: value & (1 << 18) != 0,
+            clamp: value & (1 << 19) != 0,
+            scale: TevScale::new(value >> 20),
+            dest: TevRegister::new(value >> 22),
+        }
+    }
+}
+
+/// Computes `dest = clamp(scale * (d (+ or -) ((1 - c) * a + c * b) + bias))`
+#[derive(Clone, Debug)]
+pub struct TevAlphaEnv {
+    pub a: TevAlphaArg,
+    pub b: TevAlphaArg,
+    pub c: TevAlphaArg,
+    pub d: TevAlphaArg,
+    pub bias: TevBias,
+    pub subtract: bool,
+    pub clamp: bool,
+    pub scale: TevScale,
+    pub dest: TevRegister,
+    /// Index of the swap table applied to the rasterized color
+    pub ras_swap: u8,
+    /// Index of the swap table applied to the texture color
+    pub tex_swap: u8,
+}
+
+impl TevAlphaEnv {
+    fn new(value: u32) -> TevAlphaEnv {
+        TevAlphaEnv {
+            ras_swap: (value & 0b11) as u8,
+            tex_swap: ((value >> 2) & 0b11) as u8,
+            d: TevAlphaArg::new(value >> 4),
+            c: TevAlphaArg::new(value >> 7),
+            b: TevAlphaArg::new(value >> 10),
+            a: TevAlphaArg::new(value >> 13),
+            bias: TevBias::new(value >> 16),
+            subtract: value & (1 << 18) != 0,
+            clamp: value & (1 << 19) != 0,
+            scale: TevScale::new(value >> 20),
+            dest: TevRegister::new(value >> 22),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TevColorArg {
+    PrevColor,
+    PrevAlpha,
+    Color0,
+    Alpha0,
+    Color1,
+    Alpha1,
+    Color2,
+    Alpha2,
+    TextureColor,
+    TextureAlpha,
+    RasterizedColor,
+    RasterizedAlpha,
+    One,
+    Half,
+    Konst,
+    Zero,
+}
+
+impl TevColorArg {
+    fn new(value: u32) -> TevColorArg {
+        match value & 0xf {
+            0x0 => TevColorArg::PrevColor,
+            0x1 => TevColorArg::PrevAlpha,
+            0x2 => TevColorArg::Color0,
+            0x3 => TevColorArg::Alpha0,
+            0x4 => TevColorArg::Color1,
+            0x5 => TevColorArg::Alpha1,
+            0x6 => TevColorArg::Color2,
+            0x7 => TevColorArg::Alpha2,
+            0x8 => TevColorArg::TextureColor,
+            0x9 => TevColorArg::TextureAlpha,
+            0xa => TevColorArg::RasterizedColor,
+            0xb => TevColorArg::RasterizedAlpha,
+            0xc => TevColorArg::One,
+            0xd => TevColorArg::Half,
+            0xe => TevColorArg::Konst,
+            _ => TevColorArg::Zero,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TevAlphaArg {
+    PrevAlpha,
+    Alpha0,
+    Alpha1,
+    Alpha2,
+    TextureAlpha,
+    RasterizedAlpha,
+    Konst,
+    Zero,
+}
+
+impl TevAlphaArg {
+    fn new(value: u32) -> TevAlphaArg {
+        match value & 0b111 {
+            0 => TevAlphaArg::PrevAlpha,
+            1 => TevAlphaArg::Alpha0,
+            2 => TevAlphaArg::Alpha1,
+            3 => TevAlphaArg::Alpha2,
+            4 => TevAlphaArg::TextureAlpha,
+            5 => TevAlphaArg::RasterizedAlpha,
+            6 => TevAlphaArg::Konst,
+            _ => TevAlphaArg::Zero,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TevBias {
+    Zero,
+    AddHalf,
+    SubtractHalf,
+    /// The stage compares a and b instead of interpolating between them
+    Compare,
+}
+
+impl TevBias {
+    fn new(value: u32) -> TevBias {
+        match value & 0b11 {
+            0 => TevBias::Zero,
+            1 => TevBias::AddHalf,
+            2 => TevBias::SubtractHalf,
+            _ => TevBias::Compare,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TevScale {
+    One,
+    Two,
+    Four,
+    Half,
+}
+
+impl TevScale {
+    fn new(value: u32) -> TevScale {
+        match value & 0b11 {
+            0 => TevScale::One,
+            1 => TevScale::Two,
+            2 => TevScale::Four,
+            _ => TevScale::Half,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TevRegister {
+    /// The register read by the next stage and output by the last stage
+    Prev,
+    Register0,
+    Register1,
+    Register2,
+}
+
+impl TevRegister {
+    fn new(value: u32) -> TevRegister {
+        match value & 0b11 {
+            0 => TevRegister::Prev,
+            1 => TevRegister::Register0,
+            2 => TevRegister::Register1,
+            _ => TevRegister::Register2,
+        }
+    }
+}
@@ -1,16 +1,29 @@
 use fancy_slice::FancySlice;
 
+use crate::parse_error::{ParseError, check_len, try_offset};
 use crate::resources::Resource;
 
-pub(crate) fn textures(data: FancySlice, resources: Vec<Resource>) -> Vec<Texture> {
+/// `group_offset` is the offset of the resource group from the start of the MDL0
+pub(crate) fn textures(
+    data: FancySlice,
+    group_offset: usize,
+    resources: Vec<Resource>,
+) -> Result<Vec<Texture>, ParseError> {
     let mut textures = vec![];
     for resource in resources {
         let mut references = vec![];
 
-        let num_children = data.i32_be(resource.data_offset as usize);
-        for i in 0..num_children as usize {
-            let data = data
-                .relative_fancy_slice(resource.data_offset as usize + 4 + TEXTURE_REF_SIZE * i..);
+        let resource_offset = resource.data_offset as i64;
+        let list = try_offset(&data, resource_offset, 4, "MDL0 texture reference")?;
+        let num_children = list.u32_be(0) as usize;
+        check_len(
+            list,
+            4 + TEXTURE_REF_SIZE * num_children,
+            "MDL0 texture reference",
+        )
+        .map_err(|err| err.offset_by(resource_offset as usize))?;
+        for i in 0..num_children {
+            let data = list.relative_fancy_slice(4 + TEXTURE_REF_SIZE * i..);
             let material_offset = data.i32_be(0x00);
             let reference_offset = data.i32_be(0x04);
 
@@ -20,15 +33,41 @@ pub(crate) fn textures(data: FancySlice, resources: Vec<Resource>) -> Vec<Textur
             });
         }
         let name = resource.string;
-        textures.push(Texture { name, references });
+        let offset = group_offset + resource.data_offset as usize;
+        textures.push(Texture {
+            name,
+            references,
+            offset,
+        });
     }
-    textures
+    Ok(textures)
 }
 
 #[derive(Clone, Debug)]
 pub struct Texture {
     pub name: String,
     pub references: Vec<TextureRef>,
+    /// Offset from the start of the MDL0 the list was read from, the references are relative to it.
+    offset: usize,
+}
+
+impl Texture {
+    /// Writes the list of references to the end of `output`, the MDL0 being compiled, returning the offset it was written to.
+    /// `relocate` maps an offset in the original MDL0 to where that data was written in `output`.
+    pub(crate) fn compile(&self, output: &mut Vec<u8>, relocate: impl Fn(usize) -> usize) -> usize {
+        let offset = output.len();
+        let move_reference = |reference: i32| {
+            relocate((self.offset as i32 + reference) as usize) as i32 - offset as i32
+        };
+
+        output.extend(i32::to_be_bytes(self.references.len() as i32));
+        for reference in &self.references {
+            output.extend(i32::to_be_bytes(move_reference(reference.material_offset)));
+            output.extend(i32::to_be_bytes(move_reference(reference.reference_offset)));
+        }
+
+        offset
+    }
 }
 
 const TEXTURE_REF_SIZE: usize = 0x8;
@@ -1,13 +1,15 @@
 use cgmath::Vector3;
 use fancy_slice::FancySlice;
 
-use crate::resources::Resource;
+use crate::parse_error::{ParseError, ParseErrorKind, try_offset};
+use crate::resources::{Resource, StringTable};
 
 #[rustfmt::skip]
-pub(crate) fn vertices(data: FancySlice, resources: Vec<Resource>) -> Vec<Vertices> {
+pub(crate) fn vertices(data: FancySlice, resources: Vec<Resource>) -> Result<Vec<Vertices>, ParseError> {
     let mut vertices = vec!();
     for resource in resources {
-        let data = data.relative_fancy_slice(resource.data_offset as usize..);
+        let resource_offset = resource.data_offset as i64;
+        let data = try_offset(&data, resource_offset, VERTICES_SIZE, "MDL0 vertices")?;
 
         let size           = data.i32_be(0x00); // including header
         let _mdl0_offset   = data.i32_be(0x04);
@@ -32,6 +34,13 @@ pub(crate) fn vertices(data: FancySlice, resources: Vec<Resource>) -> Vec<Vertic
 
         // 16 bytes of padding before data starts
 
+        if data_offset < 0 || data_offset > size || size as usize > data.len() {
+            return Err(ParseError::new(
+                "MDL0 vertices",
+                resource_offset as usize,
+                ParseErrorKind::Malformed(format!("vertex data 0x{data_offset:x}..0x{size:x} is outside of the data")),
+            ));
+        }
         let data = data.relative_slice(data_offset as usize .. size as usize).to_vec();
 
         vertices.push(Vertices {
@@ -48,10 +57,10 @@ pub(crate) fn vertices(data: FancySlice, resources: Vec<Resource>) -> Vec<Vertic
             e_max,
         });
     }
-    vertices
+    Ok(vertices)
 }
 
-const _VERTICES_SIZE: usize = 0x40;
+const VERTICES_SIZE: usize = 0x40;
 #[derive(Clone, Debug)]
 pub struct Vertices {
     pub name: String,
@@ -67,6 +76,37 @@ pub struct Vertices {
     pub e_max: Vector3<f32>,
 }
 
+impl Vertices {
+    /// Writes the vertices to the end of `output`, the MDL0 being compiled, returning the offset it was written to.
+    pub(crate) fn compile(&self, output: &mut Vec<u8>, strings: &mut StringTable) -> usize {
+        while output.len() % 0x20 != 0 {
+            output.push(0x00);
+        }
+        let offset = output.len();
+
+        output.extend(i32::to_be_bytes((VERTICES_SIZE + self.data.len()) as i32));
+        output.extend(i32::to_be_bytes(-(offset as i32))); // mdl0_offset
+        output.extend(i32::to_be_bytes(VERTICES_SIZE as i32)); // data_offset
+        strings.reference(output.len(), offset, &self.name);
+        output.extend(i32::to_be_bytes(0)); // string_offset
+        output.extend(i32::to_be_bytes(self.index));
+        output.extend(i32::to_be_bytes(self.is_xyz as i32));
+        output.extend(i32::to_be_bytes(self.component_type.value()));
+        output.push(self.divisor);
+        output.push(self.entry_stride);
+        output.extend(u16::to_be_bytes(self.num_vertices));
+        for value in [self.e_min, self.e_max] {
+            output.extend(f32::to_be_bytes(value.x));
+            output.extend(f32::to_be_bytes(value.y));
+            output.extend(f32::to_be_bytes(value.z));
+        }
+        output.resize(offset + VERTICES_SIZE, 0x00);
+        output.extend(&self.data);
+
+        offset
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum VertexComponentType {
     U8,
@@ -78,7 +118,7 @@ pub enum VertexComponentType {
 }
 
 impl VertexComponentType {
-    fn new(value: i32) -> VertexComponentType {
+    pub(crate) fn new(value: i32) -> VertexComponentType {
         match value {
             0 => VertexComponentType::U8,
             1 => VertexComponentType::I8,
@@ -88,4 +128,25 @@ impl VertexComponentType {
             _ => VertexComponentType::Unknown(value),
         }
     }
+
+    fn value(&self) -> i32 {
+        match self {
+            VertexComponentType::U8 => 0,
+            VertexComponentType::I8 => 1,
+            VertexComponentType::U16 => 2,
+            VertexComponentType::I16 => 3,
+            VertexComponentType::F32 => 4,
+            VertexComponentType::Unknown(value) => *value,
+        }
+    }
+
+    /// Size of a single component in bytes
+    pub fn size(&self) -> Option<usize> {
+        match self {
+            VertexComponentType::U8 | VertexComponentType::I8 => Some(1),
+            VertexComponentType::U16 | VertexComponentType::I16 => Some(2),
+            VertexComponentType::F32 => Some(4),
+            VertexComponentType::Unknown(_) => None,
+        }
+    }
 }
@@ -0,0 +1,144 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use fancy_slice::FancySlice;
+
+/// Describes why and where parsing failed.
+///
+/// Returned by the `try_*` entry points, e.g. `arc::try_arc`, so that bad files can be skipped instead of aborting.
+#[derive(Clone, Debug)]
+pub struct ParseError {
+    /// The file being parsed, only known when the caller provided it via `ParseError::with_file`
+    pub file: Option<PathBuf>,
+    /// Byte offset where the failing structure starts.
+    /// Relative to the start of the data passed to the `try_*` function.
+    /// If the data was compressed this is an offset into the decompressed data.
+    pub offset: usize,
+    /// Name of the structure that was being parsed e.g. "PLT0"
+    pub structure: &'static str,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum ParseErrorKind {
+    /// The structure needs `needed` bytes but only `len` bytes were available
+    UnexpectedEnd {
+        needed: usize,
+        len: usize,
+    },
+    UnknownVersion(i32),
+    UnknownCompressionType(u8),
+    InvalidString(String),
+    /// The data does not make sense for the structure
+    Malformed(String),
+}
+
+impl ParseError {
+    pub(crate) fn new(structure: &'static str, offset: usize, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            file: None,
+            offset,
+            structure,
+            kind,
+        }
+    }
+
+    /// Records the file that was being parsed
+    pub fn with_file(mut self, file: &Path) -> ParseError {
+        self.file = Some(file.to_path_buf());
+        self
+    }
+
+    /// Makes the offset relative to a parent structure that starts `base` bytes earlier
+    pub(crate) fn offset_by(mut self, base: usize) -> ParseError {
+        self.offset = self.offset.saturating_add(base);
+        self
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+        write!(
+            f,
+            "Failed to parse {} at 0x{:x}: ",
+            self.structure, self.offset
+        )?;
+        match &self.kind {
+            ParseErrorKind::UnexpectedEnd { needed, len } => write!(
+                f,
+                "data ended unexpectedly, needed 0x{needed:x} bytes but only 0x{len:x} bytes remain"
+            ),
+            ParseErrorKind::UnknownVersion(version) => write!(f, "unknown version {version}"),
+            ParseErrorKind::UnknownCompressionType(value) => {
+                write!(f, "unknown compression type {value}")
+            }
+            ParseErrorKind::InvalidString(message) => write!(f, "invalid string: {message}"),
+            ParseErrorKind::Malformed(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Returns an error if `data` is shorter than `needed` bytes
+pub(crate) fn check_len(
+    data: FancySlice,
+    needed: usize,
+    structure: &'static str,
+) -> Result<(), ParseError> {
+    if data.len() < needed {
+        Err(ParseError::new(
+            structure,
+            0,
+            ParseErrorKind::UnexpectedEnd {
+                needed,
+                len: data.len(),
+            },
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads the null terminated string at `offset`
+pub(crate) fn try_str<'a>(
+    data: &'a FancySlice,
+    offset: usize,
+    structure: &'static str,
+) -> Result<&'a str, ParseError> {
+    if offset >= data.len() {
+        return Err(ParseError::new(
+            structure,
+            0,
+            ParseErrorKind::UnexpectedEnd {
+                needed: offset + 1,
+                len: data.len(),
+            },
+        ));
+    }
+    data.str(offset)
+        .map_err(|err| ParseError::new(structure, 0, ParseErrorKind::InvalidString(err)))
+}
+
+/// Returns the data starting at `offset`, which must be followed by at least `needed` bytes
+pub(crate) fn try_offset<'a>(
+    data: &'a FancySlice,
+    offset: i64,
+    needed: usize,
+    structure: &'static str,
+) -> Result<FancySlice<'a>, ParseError> {
+    if offset < 0 || offset as u64 > data.len() as u64 {
+        return Err(ParseError::new(
+            structure,
+            0,
+            ParseErrorKind::Malformed(format!("offset {offset} is outside of the data")),
+        ));
+    }
+    let offset = offset as usize;
+    let data = data.relative_fancy_slice(offset..);
+    check_len(data, needed, structure).map_err(|err| err.offset_by(offset))?;
+    Ok(data)
+}
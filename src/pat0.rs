@@ -0,0 +1,207 @@
+use fancy_slice::FancySlice;
+
+use crate::bres::RawBresChild;
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
+use crate::resources;
+
+/// Parses a PAT0, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_pat0(data: FancySlice) -> Result<Pat0, ParseError> {
+    check_len(data, 0x24, "PAT0")?;
+    let _size                 = data.i32_be(0x4);
+    let version               = data.i32_be(0x8);
+    let _bres_offset          = data.i32_be(0xc);
+    let resources_offset      = data.i32_be(0x10);
+    let texture_table_offset  = data.i32_be(0x14);
+    let palette_table_offset  = data.i32_be(0x18);
+    let _texture_ptr_offset   = data.i32_be(0x1c);
+    let _palette_ptr_offset   = data.i32_be(0x20);
+    // version 4 adds a user data offset, shifting every following field along
+    let offset = match version {
+        3 => 0x24,
+        4 => 0x28,
+        _ => return Err(ParseError::new("PAT0", 0, ParseErrorKind::UnknownVersion(version))),
+    };
+    check_len(data, offset + 0x14, "PAT0")?;
+    let string_offset         = data.i32_be(offset);
+    let _orig_path_offset     = data.i32_be(offset + 0x4);
+    let num_frames            = data.u16_be(offset + 0x8);
+    let _num_children         = data.u16_be(offset + 0xa);
+    let num_textures          = data.u16_be(offset + 0xc);
+    let num_palettes          = data.u16_be(offset + 0xe);
+    let loop_value            = data.i32_be(offset + 0x10);
+
+    let name = try_str(&data, string_offset as usize, "PAT0 name")
+        .map_err(|err| err.offset_by(string_offset as usize))?
+        .to_string();
+    let textures = name_table(&data, texture_table_offset, num_textures)?;
+    let palettes = name_table(&data, palette_table_offset, num_palettes)?;
+
+    let resources_data = try_offset(&data, resources_offset as i64, 0, "PAT0")?;
+    let resources = resources::try_resources(resources_data)
+        .map_err(|err| err.offset_by(resources_offset as usize))?;
+
+    let mut materials = vec!();
+    for resource in resources {
+        let material_offset = resources_offset as i64 + resource.data_offset as i64;
+        let material_data = try_offset(&data, material_offset, PAT0_MATERIAL_SIZE, "PAT0 material")?;
+        let flags = material_data.u32_be(0x4);
+
+        let mut material_textures = vec!();
+        let mut texture_offset = PAT0_MATERIAL_SIZE;
+        for index in 0..8 {
+            let texture_flags = flags >> (index * 4);
+            let exists      = texture_flags & 0b0001 != 0;
+            let fixed       = texture_flags & 0b0010 != 0;
+            let has_texture = texture_flags & 0b0100 != 0;
+            let has_palette = texture_flags & 0b1000 != 0;
+            if !exists {
+                continue;
+            }
+
+            let keyframe = |data: FancySlice, frame| {
+                let texture_index = data.u16_be(0x0) as usize;
+                let palette_index = data.u16_be(0x2) as usize;
+                Pat0Keyframe {
+                    frame,
+                    texture: if has_texture { textures.get(texture_index).cloned() } else { None },
+                    palette: if has_palette { palettes.get(palette_index).cloned() } else { None },
+                }
+            };
+
+            check_len(material_data, texture_offset + 4, "PAT0 material")
+                .map_err(|err| err.offset_by(material_offset as usize))?;
+            let keyframes = if fixed {
+                vec!(keyframe(material_data.relative_fancy_slice(texture_offset ..), 0.0))
+            } else {
+                let table_offset = material_data.i32_be(texture_offset) as i64;
+                let table_data = try_offset(&material_data, table_offset, PAT0_TABLE_HEADER_SIZE, "PAT0 keyframes")
+                    .map_err(|err| err.offset_by(material_offset as usize))?;
+                let num_keyframes = table_data.u16_be(0x0);
+                let _frame_scale  = table_data.f32_be(0x4);
+                check_len(table_data, PAT0_TABLE_HEADER_SIZE + PAT0_KEYFRAME_SIZE * num_keyframes as usize, "PAT0 keyframes")
+                    .map_err(|err| err.offset_by((material_offset + table_offset) as usize))?;
+                (0..num_keyframes as usize).map(|i| {
+                    let keyframe_data = table_data.relative_fancy_slice(PAT0_TABLE_HEADER_SIZE + PAT0_KEYFRAME_SIZE * i ..);
+                    keyframe(keyframe_data.relative_fancy_slice(0x4 ..), keyframe_data.f32_be(0x0))
+                }).collect()
+            };
+            texture_offset += 4;
+
+            material_textures.push(Pat0Texture {
+                index,
+                keyframes,
+            });
+        }
+
+        materials.push(Pat0Material {
+            name: resource.string,
+            textures: material_textures,
+        });
+    }
+
+    let mut raw = RawBresChild::animation(data, resources_offset as usize, offset, 0x0, offset != 0x24, "PAT0")?;
+    for (table_offset, count) in [(texture_table_offset, num_textures), (palette_table_offset, num_palettes)] {
+        for i in 0..count as usize {
+            raw.string(data, table_offset as usize + i * 4, table_offset as usize, "PAT0 name table")?;
+        }
+    }
+
+    Ok(Pat0 {
+        name,
+        num_frames,
+        loop_value: loop_value != 0,
+        materials,
+        raw,
+    })
+}
+
+/// Each name is pointed to by an offset relative to the start of the table
+fn name_table(data: &FancySlice, table_offset: i32, count: u16) -> Result<Vec<String>, ParseError> {
+    let table = try_offset(
+        data,
+        table_offset as i64,
+        count as usize * 4,
+        "PAT0 name table",
+    )?;
+    (0..count as usize)
+        .map(|i| {
+            let offset = table.i32_be(i * 4);
+            try_str(&table, offset as usize, "PAT0 name table")
+                .map(|x| x.to_string())
+                .map_err(|err| err.offset_by(table_offset as usize))
+        })
+        .collect()
+}
+
+/// Texture swapping animation
+#[derive(Clone, Debug)]
+pub struct Pat0 {
+    name: String,
+    num_frames: u16,
+    loop_value: bool,
+    materials: Vec<Pat0Material>,
+    raw: RawBresChild,
+}
+
+impl Pat0 {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn num_frames(&self) -> u16 {
+        self.num_frames
+    }
+
+    pub fn loop_value(&self) -> bool {
+        self.loop_value
+    }
+
+    pub fn materials(&self) -> &[Pat0Material] {
+        &self.materials
+    }
+
+    /// Returns the bytes the PAT0 was parsed from, only the names are rewritten to point into the new string table.
+    pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
+        self.raw.compile(bres_offset)
+    }
+}
+
+const PAT0_MATERIAL_SIZE: usize = 0x8;
+#[derive(Clone, Debug)]
+pub struct Pat0Material {
+    /// Name of the MDL0 material being animated
+    pub name: String,
+    pub textures: Vec<Pat0Texture>,
+}
+
+const PAT0_TABLE_HEADER_SIZE: usize = 0x8;
+#[derive(Clone, Debug)]
+pub struct Pat0Texture {
+    /// Index into the materials texture references
+    pub index: u8,
+    /// Sorted by frame
+    pub keyframes: Vec<Pat0Keyframe>,
+}
+
+impl Pat0Texture {
+    /// Returns the keyframe in effect at the specified frame.
+    /// Unlike other animations, textures are not interpolated, the most recent keyframe is used as is.
+    pub fn get_keyframe(&self, frame: i32) -> Option<&Pat0Keyframe> {
+        self.keyframes
+            .iter()
+            .take_while(|x| x.frame <= frame as f32)
+            .last()
+            .or_else(|| self.keyframes.first())
+    }
+}
+
+const PAT0_KEYFRAME_SIZE: usize = 0x8;
+#[derive(Clone, Debug)]
+pub struct Pat0Keyframe {
+    pub frame: f32,
+    /// Name of the TEX0 to use, None when the texture is left unchanged
+    pub texture: Option<String>,
+    /// Name of the PLT0 to use, None when the palette is left unchanged
+    pub palette: Option<String>,
+}
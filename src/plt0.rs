@@ -1,10 +1,14 @@
 use fancy_slice::FancySlice;
 
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_str};
+use crate::resources::StringTable;
 use crate::user_data::{UserData, UserDataValue};
 use crate::wii_texture_formats::WiiPaletteFormat;
 
+/// Parses a PLT0, returning an error instead of panicking on malformed data.
 #[rustfmt::skip]
-pub(crate) fn plt0(data: FancySlice) -> Plt0 {
+pub fn try_plt0(data: FancySlice) -> Result<Plt0, ParseError> {
+    check_len(data, 0x28, "PLT0")?;
     let size               = data.i32_be(0x4);
     let version            = data.i32_be(0x8);
     //let bres_offset      = data.i32_be(0xc);
@@ -28,10 +32,17 @@ pub(crate) fn plt0(data: FancySlice) -> Plt0 {
     } else if version == 1 {
         vec!()
     } else {
-        panic!("Unknown PLT0 verison: {}", version)
+        return Err(ParseError::new("PLT0", 0, ParseErrorKind::UnknownVersion(version)));
     };
 
-    let name = data.str(string_offset as usize).unwrap().to_string();
+    let name = try_str(&data, string_offset as usize, "PLT0 name")
+        .map_err(|err| err.offset_by(string_offset as usize))?
+        .to_string();
+
+    if size < PLT0_HEADER_SIZE as i32 {
+        return Err(ParseError::new("PLT0", 0, ParseErrorKind::Malformed(format!("size {size} is smaller than the header"))));
+    }
+    check_len(data, size as usize, "PLT0")?;
 
     // TODO: This doesnt necasarily start at PLT0_HEADER_SIZE, maybe the offset is stored in the
     // resources which I havent parsed yet??
@@ -40,7 +51,7 @@ pub(crate) fn plt0(data: FancySlice) -> Plt0 {
         .chunks_exact(2)
         .map(|x| u16::from_be_bytes([x[0], x[1]])).collect();
 
-    Plt0 { name, pixel_format, orig_path_offset, user_data, color_data }
+    Ok(Plt0 { name, pixel_format, orig_path_offset, user_data, color_data })
 }
 
 const PLT0_HEADER_SIZE: usize = 0x40;
@@ -55,8 +66,31 @@ pub struct Plt0 {
 }
 
 impl Plt0 {
+    /// Creates a palette by encoding each RGBA8 color into `pixel_format`
+    pub fn new(name: &str, pixel_format: WiiPaletteFormat, colors: &[[u8; 4]]) -> Plt0 {
+        Plt0 {
+            name: name.to_string(),
+            color_data: colors
+                .iter()
+                .map(|x| pixel_format.encode_color(*x))
+                .collect(),
+            pixel_format,
+            user_data: vec![],
+            orig_path_offset: 0,
+        }
+    }
+
+    /// Decodes every color in the palette into RGBA8
+    pub fn colors(&self) -> Vec<[u8; 4]> {
+        self.color_data
+            .iter()
+            .map(|x| self.pixel_format.decode_color(*x))
+            .collect()
+    }
+
     pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
         let mut output = vec![];
+        let mut strings = StringTable::new();
 
         let size = PLT0_HEADER_SIZE + self.color_data.len() * 2;
         let version = if !self.user_data.is_empty() { 3 } else { 1 };
@@ -67,8 +101,9 @@ impl Plt0 {
         output.extend(i32::to_be_bytes(size as i32));
         output.extend(i32::to_be_bytes(version));
         output.extend(i32::to_be_bytes(bres_offset));
-        output.extend(i32::to_be_bytes(0)); // TODO: resources_offset
-        output.extend(u32::to_be_bytes(0)); // TODO: string_offset
+        output.extend(i32::to_be_bytes(PLT0_HEADER_SIZE as i32)); // data_offset
+        strings.reference(output.len(), 0, &self.name);
+        output.extend(u32::to_be_bytes(0)); // string_offset
         output.extend(u32::to_be_bytes(self.pixel_format.value()));
         output.extend(u16::to_be_bytes(num_entries as u16));
         output.extend(u16::to_be_bytes(0)); // padding
@@ -93,6 +128,12 @@ impl Plt0 {
             output.extend(u16::to_be_bytes(*color));
         }
 
+        // The strings are not included in the size, otherwise they would be read as color data.
+        while output.len() % 4 != 0 {
+            output.push(0x00);
+        }
+        strings.compile(&mut output);
+
         output
     }
 }
@@ -0,0 +1,186 @@
+use fancy_slice::FancySlice;
+
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_str};
+
+/// Parses a REFF, returning an error instead of panicking on malformed data.
+pub fn try_reff(data: FancySlice) -> Result<Reff, ParseError> {
+    let file = effect_file(data, "REFF")?;
+    let emitters = file
+        .objects
+        .into_iter()
+        .map(|object| ReffEmitter {
+            texture_names: texture_names(&object.data),
+            name: object.name,
+            data: object.data,
+        })
+        .collect();
+
+    Ok(Reff {
+        name: file.name,
+        version: file.version,
+        emitters,
+        raw: data.relative_slice(..).to_vec(),
+    })
+}
+
+/// Particle effects, referred to by name from the EFLS
+#[derive(Clone, Debug)]
+pub struct Reff {
+    name: String,
+    version: u16,
+    emitters: Vec<ReffEmitter>,
+    raw: Vec<u8>,
+}
+
+impl Reff {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn emitters(&self) -> &[ReffEmitter] {
+        &self.emitters
+    }
+
+    /// The emitters are read only, so this is the REFF exactly as it was read.
+    pub fn compile(&self) -> Vec<u8> {
+        self.raw.clone()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ReffEmitter {
+    pub name: String,
+    /// Names of the REFT textures drawn on each particle, at most 3.
+    pub texture_names: Vec<String>,
+    /// The raw emitter, particle and animation data
+    pub data: Vec<u8>,
+}
+
+/// The emitter data starts with the emitter parameters then the particle parameters.
+/// Each is prefixed by its size and the particle parameters end with the 3 texture names.
+fn texture_names(emitter: &[u8]) -> Vec<String> {
+    let u32_at = |offset: usize| -> Option<usize> {
+        emitter
+            .get(offset..offset + 4)
+            .map(|x| u32::from_be_bytes(x.try_into().unwrap()) as usize)
+    };
+    let Some(emitter_size) = u32_at(0) else {
+        return vec![];
+    };
+    let particle_offset = 4 + emitter_size;
+    let Some(particle_size) = u32_at(particle_offset) else {
+        return vec![];
+    };
+    let Some(particle) = emitter.get(particle_offset + 4..particle_offset + 4 + particle_size)
+    else {
+        return vec![];
+    };
+
+    // TODO: The size of the fixed particle parameters before the names changes between versions and is not known for each version.
+    // So instead we search for the only position where three length prefixed names end exactly at the end of the particle parameters.
+    (0..particle.len())
+        .find_map(|start| length_prefixed_names(&particle[start..]))
+        .unwrap_or_default()
+}
+
+/// Returns the names if `data` consists entirely of 3 length prefixed, null terminated names.
+/// A length of 0 or 1 is used when there is no texture.
+fn length_prefixed_names(data: &[u8]) -> Option<Vec<String>> {
+    let mut names = vec![];
+    let mut offset = 0;
+    for _ in 0..3 {
+        let len = u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().unwrap()) as usize;
+        offset += 2;
+        if len > 1 {
+            let name = data.get(offset..offset + len)?;
+            let (null, name) = name.split_last()?;
+            if *null != 0 || !name.iter().all(|x| x.is_ascii_graphic() || *x == b' ') {
+                return None;
+            }
+            names.push(String::from_utf8_lossy(name).into_owned());
+        }
+        offset += len;
+    }
+
+    // allow for padding
+    if data.len() >= offset && data.len() - offset < 4 && data[offset..].iter().all(|x| *x == 0) {
+        Some(names)
+    } else {
+        None
+    }
+}
+
+/// The container shared by REFF and REFT files
+pub(crate) struct EffectFile {
+    pub name: String,
+    pub version: u16,
+    pub objects: Vec<EffectObject>,
+}
+
+pub(crate) struct EffectObject {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[rustfmt::skip]
+pub(crate) fn effect_file(data: FancySlice, structure: &'static str) -> Result<EffectFile, ParseError> {
+    check_len(data, EFFECT_HEADER_SIZE, structure)?;
+    let version     = data.u16_be(0x6);
+    let header_size = data.u16_be(0xc) as usize;
+
+    // Only has one section, which has the same tag as the file.
+    let block = header_size;
+    check_len(data, block + EFFECT_BLOCK_SIZE, structure)?;
+    let data_offset = data.u32_be(block + 0x8) as usize;
+    let name_len    = data.u16_be(block + 0x14) as usize;
+    let name = if name_len > 0 {
+        try_str(&data, block + EFFECT_BLOCK_SIZE, structure)
+            .map_err(|err| err.offset_by(block + EFFECT_BLOCK_SIZE))?
+            .to_string()
+    } else {
+        String::new()
+    };
+
+    // The data offset is relative to itself
+    let table = block + 0x8 + data_offset;
+    check_len(data, table + EFFECT_TABLE_HEADER_SIZE, structure)?;
+    let num_objects = data.u16_be(table + 0x4);
+
+    let mut objects = vec!();
+    let mut entry = table + EFFECT_TABLE_HEADER_SIZE;
+    for _ in 0..num_objects {
+        check_len(data, entry + 2, structure)?;
+        let name_len = data.u16_be(entry) as usize;
+        let name = try_str(&data, entry + 2, structure)
+            .map_err(|err| err.offset_by(entry + 2))?
+            .to_string();
+        entry += 2 + name_len;
+
+        check_len(data, entry + 8, structure)?;
+        let object_offset = data.u32_be(entry) as usize;
+        let object_len    = data.u32_be(entry + 4) as usize;
+        entry += 8;
+
+        // The object offset is relative to the start of the table
+        let start = table + object_offset;
+        if data.len() < start + object_len {
+            return Err(ParseError::new(structure, start, ParseErrorKind::UnexpectedEnd {
+                needed: object_len,
+                len: data.len().saturating_sub(start),
+            }));
+        }
+        let data = data.relative_slice(start .. start + object_len).to_vec();
+
+        objects.push(EffectObject { name, data });
+    }
+
+    Ok(EffectFile { name, version, objects })
+}
+
+const EFFECT_HEADER_SIZE: usize = 0x10;
+const EFFECT_BLOCK_SIZE: usize = 0x18;
+const EFFECT_TABLE_HEADER_SIZE: usize = 0x8;
@@ -0,0 +1,143 @@
+use anyhow::{Error, bail};
+use fancy_slice::FancySlice;
+
+use crate::parse_error::{ParseError, ParseErrorKind};
+use crate::reff::effect_file;
+use crate::wii_texture_formats::{WiiPaletteFormat, WiiPixelFormat};
+
+/// Parses a REFT, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_reft(data: FancySlice) -> Result<Reft, ParseError> {
+    let file = effect_file(data, "REFT")?;
+
+    let mut textures = vec!();
+    for object in file.objects {
+        if object.data.len() < REFT_TEXTURE_HEADER_SIZE {
+            return Err(ParseError::new("REFT texture", 0, ParseErrorKind::UnexpectedEnd {
+                needed: REFT_TEXTURE_HEADER_SIZE,
+                len: object.data.len(),
+            }));
+        }
+        let texture = FancySlice::new(&object.data);
+        let width          = texture.u16_be(0x4);
+        let height         = texture.u16_be(0x6);
+        let image_len      = texture.u32_be(0x8) as usize;
+        let pixel_format   = texture.u8(0xc);
+        let palette_format = texture.u8(0xd);
+        let num_colors     = texture.u16_be(0xe);
+        let palette_len    = texture.u32_be(0x10) as usize;
+        let num_mipmaps    = texture.u8(0x14);
+        let min_filter     = texture.u8(0x15);
+        let mag_filter     = texture.u8(0x16);
+        let lod_bias       = texture.f32_be(0x18);
+
+        let image_end = REFT_TEXTURE_HEADER_SIZE + image_len;
+        let palette_end = image_end + palette_len;
+        if object.data.len() < palette_end {
+            return Err(ParseError::new("REFT texture", 0, ParseErrorKind::UnexpectedEnd {
+                needed: palette_end,
+                len: object.data.len(),
+            }));
+        }
+        let image = object.data[REFT_TEXTURE_HEADER_SIZE..image_end].to_vec();
+        let palette = (0..(num_colors as usize).min(palette_len / 2))
+            .map(|i| texture.u16_be(image_end + i * 2))
+            .collect();
+
+        textures.push(ReftTexture {
+            name: object.name,
+            width,
+            height,
+            pixel_format: WiiPixelFormat::new(pixel_format as u32),
+            palette_format: WiiPaletteFormat::new(palette_format as u32),
+            palette,
+            num_mipmaps,
+            min_filter,
+            mag_filter,
+            lod_bias,
+            image,
+        });
+    }
+
+    Ok(Reft {
+        name: file.name,
+        version: file.version,
+        textures,
+        raw: data.relative_slice(..).to_vec(),
+    })
+}
+
+/// Textures used by the particles in the REFF
+#[derive(Clone, Debug)]
+pub struct Reft {
+    name: String,
+    version: u16,
+    textures: Vec<ReftTexture>,
+    raw: Vec<u8>,
+}
+
+impl Reft {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    pub fn textures(&self) -> &[ReftTexture] {
+        &self.textures
+    }
+
+    /// Returns the bytes the REFT was parsed from, the textures cannot be modified.
+    pub fn compile(&self) -> Vec<u8> {
+        self.raw.clone()
+    }
+}
+
+const REFT_TEXTURE_HEADER_SIZE: usize = 0x20;
+#[derive(Clone, Debug)]
+pub struct ReftTexture {
+    /// Referred to by `ReffEmitter::texture_names`
+    pub name: String,
+    pub width: u16,
+    pub height: u16,
+    pub pixel_format: WiiPixelFormat,
+    pub palette_format: WiiPaletteFormat,
+    /// Only used by the indexed pixel formats
+    pub palette: Vec<u16>,
+    pub num_mipmaps: u8,
+    pub min_filter: u8,
+    pub mag_filter: u8,
+    pub lod_bias: f32,
+    /// Every mipmap, stored one after the other
+    pub image: Vec<u8>,
+}
+
+impl ReftTexture {
+    /// Decodes the full size image into RGBA8, stored row by row starting from the top left
+    pub fn decode(&self) -> Result<Vec<u8>, Error> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let Some(size) = self.pixel_format.data_size(width, height) else {
+            bail!(
+                "Cannot decode REFT texture with pixel format {:?}",
+                self.pixel_format
+            );
+        };
+        if self.image.len() < size {
+            bail!(
+                "REFT texture needs 0x{:x} bytes of image data but only has 0x{:x}",
+                size,
+                self.image.len()
+            );
+        }
+        let palette: Vec<[u8; 4]> = self
+            .palette
+            .iter()
+            .map(|x| self.palette_format.decode_color(*x))
+            .collect();
+        self.pixel_format
+            .decode(&self.image[..size], width, height, &palette)
+    }
+}
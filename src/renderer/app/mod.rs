@@ -7,7 +7,7 @@ use winit::event_loop::{EventLoop, EventLoopWindowTarget};
 use winit::window::Window;
 use winit_input_helper::WinitInputHelper;
 
-use crate::high_level_fighter::HighLevelSubaction;
+use crate::high_level_fighter::{HighLevelModel, HighLevelSubaction};
 use crate::renderer::camera::Camera;
 use crate::renderer::draw::draw_frame;
 use crate::renderer::wgpu_state::{CompatibleSurface, WgpuState};
@@ -30,6 +30,7 @@ pub struct App {
     surface: wgpu::Surface<'static>,
     surface_configuration: wgpu::SurfaceConfiguration,
     subaction: HighLevelSubaction,
+    model: Option<HighLevelModel>,
     event_tx: Sender<AppEventIncoming>,
     event_loop: Option<EventLoop<()>>,
 }
@@ -123,6 +124,7 @@ impl App {
             surface,
             surface_configuration,
             subaction,
+            model: None,
             event_tx,
             event_loop: Some(event_loop),
         }
@@ -140,6 +142,12 @@ impl App {
             .unwrap();
     }
 
+    /// Sets the model to draw, posed by the `bone_matrices` of each frame.
+    /// Use `HighLevelFighter::new_with_model` to generate both.
+    pub fn set_model(&mut self, model: Option<HighLevelModel>) {
+        self.model = model;
+    }
+
     /// Sets a function that will be called when various internal events occur within the app
     pub fn set_event_handler(&mut self, event_handler: AppEventOutgoingHandler) {
         self.app_state.set_event_handler(event_handler);
@@ -173,6 +181,11 @@ impl App {
                 self.app_state.render_ecb,
                 &self.app_state.invulnerable_type,
                 &self.subaction,
+                if self.app_state.render_model {
+                    self.model.as_ref()
+                } else {
+                    None
+                },
                 self.app_state.frame_index,
                 &self.app_state.camera,
             );
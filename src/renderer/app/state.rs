@@ -16,6 +16,7 @@ pub enum AppEventIncoming {
     SetInvulnerableType(InvulnerableType),
     SetWireframe(bool),
     SetRenderEcb(bool),
+    SetRenderModel(bool),
     SetPerspective(bool),
     ResetCamera(CharacterFacing),
 }
@@ -26,6 +27,7 @@ pub enum AppEventOutgoing {
     NewInvulnerableType(InvulnerableType),
     NewWireframe(bool),
     NewRenderEcb(bool),
+    NewRenderModel(bool),
     NewPerspective(bool),
 }
 
@@ -49,6 +51,7 @@ pub struct AppState {
     pub wireframe: bool,
     pub perspective: bool,
     pub render_ecb: bool,
+    pub render_model: bool,
     pub invulnerable_type: InvulnerableType,
     pub camera: Camera,
     state: State,
@@ -69,6 +72,7 @@ impl AppState {
             wireframe: false,
             perspective: false,
             render_ecb: false,
+            render_model: true,
             invulnerable_type: InvulnerableType::Hit,
             camera,
             state: State::Play,
@@ -112,6 +116,10 @@ impl AppState {
                     self.render_ecb = ecb;
                     self.send_event(AppEventOutgoing::NewRenderEcb(ecb));
                 }
+                AppEventIncoming::SetRenderModel(model) => {
+                    self.render_model = model;
+                    self.send_event(AppEventOutgoing::NewRenderModel(model));
+                }
                 AppEventIncoming::SetPerspective(perspective) => {
                     self.perspective = perspective;
                     self.send_event(AppEventOutgoing::NewPerspective(perspective));
@@ -134,6 +142,10 @@ impl AppState {
             self.render_ecb = !self.render_ecb;
             self.send_event(AppEventOutgoing::NewRenderEcb(self.render_ecb));
         }
+        if input.key_pressed_os(KeyCode::Digit4) {
+            self.render_model = !self.render_model;
+            self.send_event(AppEventOutgoing::NewRenderModel(self.render_model));
+        }
         if input.key_pressed_os(KeyCode::Backspace) {
             self.camera
                 .reset(window_width, window_height, CharacterFacing::Right);
@@ -1,20 +1,24 @@
 use std::f32::consts;
 use std::mem;
 
-use cgmath::{InnerSpace, Matrix4, MetricSpace, Quaternion, Rad, SquareMatrix, Vector3};
+use cgmath::{InnerSpace, Matrix4, MetricSpace, Quaternion, Rad, SquareMatrix, Vector3, Zero};
 use wgpu::StoreOp;
 use wgpu::util::DeviceExt;
 
-use crate::high_level_fighter::{CollisionBoxValues, Extent, HighLevelSubaction};
+use crate::high_level_fighter::{
+    CollisionBoxValues, Extent, HighLevelModel, HighLevelSubaction, HighLevelTexture,
+};
 use crate::renderer::app::state::InvulnerableType;
 use crate::renderer::camera::Camera;
-use crate::renderer::wgpu_state::{SAMPLE_COUNT, Vertex, WgpuState};
+use crate::renderer::wgpu_state::{ModelVertex, SAMPLE_COUNT, Vertex, WgpuState};
 
 struct Draw {
     uniform: Matrix4<f32>,
     vertices: wgpu::Buffer,
     indices: wgpu::Buffer,
     indices_len: usize,
+    /// Only used when drawing the model
+    texture: Option<wgpu::BindGroup>,
 }
 
 pub(crate) fn draw_frame(
@@ -27,6 +31,7 @@ pub(crate) fn draw_frame(
     render_ecb: bool,
     invulnerable_type: &InvulnerableType,
     subaction: &HighLevelSubaction,
+    model: Option<&HighLevelModel>,
     frame_index: usize,
     camera: &Camera,
 ) -> wgpu::CommandEncoder {
@@ -41,6 +46,9 @@ pub(crate) fn draw_frame(
         state.multisampled_framebuffer = state
             .device
             .create_texture(&state.multisampled_framebuffer_descriptor);
+
+        state.depth_texture_descriptor.size = state.multisampled_framebuffer_descriptor.size;
+        state.depth_texture = state.device.create_texture(&state.depth_texture_descriptor);
     }
 
     let draws = create_draws(
@@ -52,6 +60,7 @@ pub(crate) fn draw_frame(
         render_ecb,
         invulnerable_type,
         subaction,
+        model,
         frame_index,
         camera,
     );
@@ -74,6 +83,9 @@ pub(crate) fn draw_frame(
         let attachment = state
             .multisampled_framebuffer
             .create_view(&wgpu::TextureViewDescriptor::default());
+        let depth = state
+            .depth_texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
         let mut rpass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                 view: if SAMPLE_COUNT == 1 {
@@ -91,20 +103,33 @@ pub(crate) fn draw_frame(
                     store: StoreOp::Store,
                 },
             })],
-            depth_stencil_attachment: None,
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
             label: None,
             timestamp_writes: None,
             occlusion_query_set: None,
         });
-        if wireframe {
-            rpass.set_pipeline(&state.render_pipeline_line);
-        } else {
-            rpass.set_pipeline(&state.render_pipeline_fill);
-        }
 
         for (i, draw) in draws.iter().enumerate() {
             rpass.set_bind_group(0, &state.bind_groups[i], &[]);
-            rpass.set_index_buffer(draw.indices.slice(..), wgpu::IndexFormat::Uint16);
+            if let Some(texture) = &draw.texture {
+                rpass.set_pipeline(&state.render_pipeline_model);
+                rpass.set_bind_group(1, texture, &[]);
+                rpass.set_index_buffer(draw.indices.slice(..), wgpu::IndexFormat::Uint32);
+            } else {
+                if wireframe {
+                    rpass.set_pipeline(&state.render_pipeline_line);
+                } else {
+                    rpass.set_pipeline(&state.render_pipeline_fill);
+                }
+                rpass.set_index_buffer(draw.indices.slice(..), wgpu::IndexFormat::Uint16);
+            }
             rpass.set_vertex_buffer(0, draw.vertices.slice(..));
             rpass.draw_indexed(0..draw.indices_len as u32, 0, 0..1);
         }
@@ -122,6 +147,7 @@ fn create_draws(
     render_ecb: bool,
     invulnerable_type: &InvulnerableType,
     subaction: &HighLevelSubaction,
+    model: Option<&HighLevelModel>,
     frame_index: usize,
     camera: &Camera,
 ) -> Vec<Draw> {
@@ -170,6 +196,12 @@ fn create_draws(
     let transform_translation_frame =
         Matrix4::from_translation(Vector3::new(0.0, frame.y_pos, frame.x_pos));
 
+    // The model is drawn first so the depth buffer it writes to does not hide the boxes
+    if let Some(model) = model {
+        let transform = projection * view * transform_translation_frame;
+        draws.extend(draw_model(state, model, &frame.bone_matrices, transform));
+    }
+
     for hurt_box in &frame.hurt_boxes {
         let color = if hurt_box.state.is_intangible() {
             [0.0, 0.0, 1.0, 0.15]
@@ -283,6 +315,7 @@ fn create_draws(
             vertices,
             indices,
             indices_len,
+            texture: None,
         });
     }
 
@@ -345,6 +378,7 @@ fn create_draws(
             vertices,
             indices,
             indices_len,
+            texture: None,
         });
 
         // ECB
@@ -395,12 +429,160 @@ fn create_draws(
             vertices,
             indices,
             indices_len,
+            texture: None,
         });
     }
 
     draws
 }
 
+/// Poses the model on the CPU, as the uniforms only have room for a single matrix
+fn draw_model(
+    state: &WgpuState,
+    model: &HighLevelModel,
+    bone_matrices: &[Matrix4<f32>],
+    uniform: Matrix4<f32>,
+) -> Vec<Draw> {
+    let textures: Vec<_> = model
+        .textures
+        .iter()
+        .map(|texture| texture_bind_group(state, texture))
+        .collect();
+    // Untextured meshes sample a single white pixel so they can share the model pipeline
+    let white = texture_bind_group(
+        state,
+        &HighLevelTexture {
+            name: String::new(),
+            width: 1,
+            height: 1,
+            rgba: vec![0xff; 4],
+        },
+    );
+
+    // Shade the model with a light from above the camera
+    let light = Vector3::new(-1.0, 1.0, 0.5).normalize();
+
+    let mut draws = vec![];
+    for mesh in &model.meshes {
+        if mesh.mesh.indices.is_empty() {
+            continue;
+        }
+
+        let vertices_vec: Vec<_> = mesh
+            .mesh
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let mut skin = Matrix4::zero();
+                for weight in &vertex.weights {
+                    let index = weight.bone_index.max(0) as usize;
+                    if let (Some(bone), Some(bone_inv)) = (
+                        bone_matrices.get(index),
+                        model.bone_transforms_inv.get(index),
+                    ) {
+                        skin += bone * bone_inv * weight.weight;
+                    }
+                }
+                if skin.is_zero() {
+                    skin = Matrix4::identity();
+                }
+
+                let shade = vertex
+                    .normal
+                    .map(|normal| (skin * normal.extend(0.0)).truncate())
+                    .filter(|normal| normal.magnitude2() > 0.0)
+                    .map(|normal| 0.5 + 0.5 * normal.normalize().dot(light).max(0.0))
+                    .unwrap_or(1.0);
+                let color = vertex
+                    .colors
+                    .first()
+                    .map(|color| color.map(|x| x as f32 / 255.0))
+                    .unwrap_or([1.0; 4]);
+
+                ModelVertex {
+                    _pos: (skin * vertex.position.extend(1.0)).into(),
+                    _color: [
+                        color[0] * shade,
+                        color[1] * shade,
+                        color[2] * shade,
+                        color[3],
+                    ],
+                    _uv: vertex.uvs.first().map(|x| [x.x, x.y]).unwrap_or([0.0; 2]),
+                }
+            })
+            .collect();
+
+        let vertices = state
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&vertices_vec),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        let indices = state
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(&mesh.mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+
+        let texture = mesh
+            .texture
+            .and_then(|x| textures.get(x))
+            .unwrap_or(&white)
+            .clone();
+
+        draws.push(Draw {
+            uniform,
+            vertices,
+            indices,
+            indices_len: mesh.mesh.indices.len(),
+            texture: Some(texture),
+        });
+    }
+    draws
+}
+
+fn texture_bind_group(state: &WgpuState, texture: &HighLevelTexture) -> wgpu::BindGroup {
+    let size = wgpu::Extent3d {
+        width: texture.width as u32,
+        height: texture.height as u32,
+        depth_or_array_layers: 1,
+    };
+    let gpu_texture = state.device.create_texture_with_data(
+        &state.queue,
+        &wgpu::TextureDescriptor {
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            label: None,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        &texture.rgba,
+    );
+    let view = gpu_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    state.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: &state.texture_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&state.sampler),
+            },
+        ],
+        label: None,
+    })
+}
+
 /// Keep this around, handy for debugging extent generation
 /// Generating the full debug extent generation must be disabled during normal use as it would be too expensive.
 /// Use like:
@@ -503,6 +685,7 @@ fn _draw_extent(state: &WgpuState, extent: &Extent, external_transform: &Matrix4
         vertices,
         indices,
         indices_len,
+        texture: None,
     }
 }
 
@@ -594,5 +777,6 @@ fn draw_cylinder(
         vertices,
         indices,
         indices_len,
+        texture: None,
     }
 }
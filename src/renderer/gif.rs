@@ -9,6 +9,7 @@ use crate::renderer::draw::draw_frame;
 use crate::renderer::wgpu_state::WgpuState;
 
 /// Returns a receiver of the bytes of a gif displaying hitbox and hurtboxes
+/// The fighters model is also displayed if the HighLevelFighter was created by `HighLevelFighter::new_with_model`
 ///
 /// Most of the time is spent CPU side waiting for the color quantization thread to finish.
 /// So if you are batch rendering gifs you will get a massive speedup by running multiple `render_gif`s concurrently.
@@ -112,6 +113,7 @@ pub fn render_gif(
             false,
             &InvulnerableType::Hit,
             subaction,
+            high_level_fighter.model.as_ref(),
             frame_index,
             &camera,
         );
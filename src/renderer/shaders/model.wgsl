@@ -0,0 +1,40 @@
+struct VertexOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+    @builtin(position) position: vec4<f32>,
+}
+
+struct Locals {
+    transform: mat4x4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> u_locals: Locals;
+
+@group(1) @binding(0)
+var t_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var s_texture: sampler;
+
+@vertex
+fn vs_main(
+    @location(0) position: vec4<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) uv: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.color = color;
+    out.uv = uv;
+    out.position = u_locals.transform * position;
+    return out;
+}
+
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = in.color * textureSample(t_texture, s_texture, in.uv);
+    // The depth buffer would hide anything behind transparent pixels
+    if color.a < 0.5 {
+        discard;
+    }
+    return color;
+}
@@ -18,16 +18,31 @@ pub(crate) struct Vertex {
     pub _pos: [f32; 4],
     pub _color: [f32; 4],
 }
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+pub(crate) struct ModelVertex {
+    pub _pos: [f32; 4],
+    pub _color: [f32; 4],
+    pub _uv: [f32; 2],
+}
+
+pub(crate) const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 pub struct WgpuState {
     pub(crate) device: wgpu::Device,
     pub(crate) queue: wgpu::Queue,
     pub(crate) _bind_group_layout: wgpu::BindGroupLayout,
     pub(crate) render_pipeline_fill: wgpu::RenderPipeline,
     pub(crate) render_pipeline_line: wgpu::RenderPipeline,
+    pub(crate) render_pipeline_model: wgpu::RenderPipeline,
+    pub(crate) texture_bind_group_layout: wgpu::BindGroupLayout,
+    pub(crate) sampler: wgpu::Sampler,
     pub(crate) uniforms_buffer: wgpu::Buffer,
     pub(crate) bind_groups: Vec<wgpu::BindGroup>,
     pub(crate) multisampled_framebuffer_descriptor: wgpu::TextureDescriptor<'static>,
     pub(crate) multisampled_framebuffer: wgpu::Texture,
+    pub(crate) depth_texture_descriptor: wgpu::TextureDescriptor<'static>,
+    pub(crate) depth_texture: wgpu::Texture,
     pub(crate) format: wgpu::TextureFormat,
     pub(crate) background_color: wgpu::Color,
 }
@@ -125,6 +140,56 @@ impl WgpuState {
             push_constant_ranges: &[],
         });
 
+        let model_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("shaders/model.wgsl"))),
+        });
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: None,
+            });
+
+        let model_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout, &texture_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline_model = WgpuState::render_pipeline_model(
+            &device,
+            format,
+            &model_shader_module,
+            &model_pipeline_layout,
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
         let render_pipeline_line = WgpuState::render_pipeline(
             &device,
             format,
@@ -187,16 +252,28 @@ impl WgpuState {
         };
         let multisampled_framebuffer = device.create_texture(&multisampled_framebuffer_descriptor);
 
+        let depth_texture_descriptor = wgpu::TextureDescriptor {
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            ..multisampled_framebuffer_descriptor.clone()
+        };
+        let depth_texture = device.create_texture(&depth_texture_descriptor);
+
         WgpuState {
             device,
             queue,
             _bind_group_layout: bind_group_layout,
             render_pipeline_fill,
             render_pipeline_line,
+            render_pipeline_model,
+            texture_bind_group_layout,
+            sampler,
             uniforms_buffer,
             bind_groups,
             multisampled_framebuffer_descriptor,
             multisampled_framebuffer,
+            depth_texture_descriptor,
+            depth_texture,
             format,
             background_color,
         }
@@ -250,7 +327,83 @@ impl WgpuState {
                 polygon_mode,
                 ..Default::default()
             },
-            depth_stencil: None,
+            // The boxes are always drawn over the model
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: SAMPLE_COUNT,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        })
+    }
+
+    fn render_pipeline_model(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        shader_module: &wgpu::ShaderModule,
+        pipeline_layout: &wgpu::PipelineLayout,
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader_module,
+                entry_point: Some("vs_main"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: mem::size_of::<ModelVertex>() as u64,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &[
+                        wgpu::VertexAttribute {
+                            shader_location: 0,
+                            format: wgpu::VertexFormat::Float32x4,
+                            offset: 0,
+                        },
+                        wgpu::VertexAttribute {
+                            shader_location: 1,
+                            format: wgpu::VertexFormat::Float32x4,
+                            offset: 4 * 4,
+                        },
+                        wgpu::VertexAttribute {
+                            shader_location: 2,
+                            format: wgpu::VertexFormat::Float32x2,
+                            offset: 4 * 8,
+                        },
+                    ],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader_module,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                front_face: wgpu::FrontFace::Ccw,
+                // Some materials are double sided and materials aren't parsed yet, so draw both sides
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
             multisample: wgpu::MultisampleState {
                 count: SAMPLE_COUNT,
                 mask: !0,
@@ -1,18 +1,33 @@
 use fancy_slice::FancySlice;
+use std::collections::HashMap;
+
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_str};
 
 // ResourceGroup in brawlbox
 #[rustfmt::skip]
-pub(crate) fn resources(data: FancySlice) -> Vec<Resource> {
+pub(crate) fn try_resources(data: FancySlice) -> Result<Vec<Resource>, ParseError> {
+    check_len(data, RESOURCE_HEADER_SIZE, "resource group")?;
     let total_size   = data.i32_be(0);
     let num_children = data.i32_be(4);
 
-    assert_eq!(total_size, (num_children + 1) * RESOURCE_SIZE as i32 + RESOURCE_HEADER_SIZE as i32);
+    let expected_size = num_children.checked_add(1)
+        .and_then(|x| x.checked_mul(RESOURCE_SIZE as i32))
+        .and_then(|x| x.checked_add(RESOURCE_HEADER_SIZE as i32));
+    if num_children < 0 || expected_size != Some(total_size) {
+        return Err(ParseError::new("resource group", 0, ParseErrorKind::Malformed(format!(
+            "total size {total_size} does not match {num_children} children"
+        ))));
+    }
+    check_len(data, total_size as usize, "resource group")?;
 
     let mut resources = vec!();
     for i in 1..=num_children { // the first child is a dummy so we skip it.
         let child_index = RESOURCE_HEADER_SIZE + RESOURCE_SIZE * i as usize;
 
         let string_offset = data.i32_be(child_index + 8);
+        let string = try_str(&data, string_offset as usize, "resource name")
+            .map_err(|err| err.offset_by(string_offset as usize))?
+            .to_string();
 
         resources.push(Resource {
             //id:          data.u16_be(child_index as usize),
@@ -20,11 +35,11 @@ pub(crate) fn resources(data: FancySlice) -> Vec<Resource> {
             //left_index:  data.u16_be(child_index as usize + 0x4),
             //right_index: data.u16_be(child_index as usize + 0x6),
             data_offset:   data.i32_be(child_index + 0xc),
-            string:        data.str(string_offset as usize).unwrap().to_string(),
+            string,
         });
     }
 
-    resources
+    Ok(resources)
 }
 
 pub(crate) const RESOURCE_HEADER_SIZE: usize = 0x8;
@@ -36,3 +51,193 @@ pub struct Resource {
     pub data_offset: i32,
     pub string: String,
 }
+
+/// Writes a resource group for entries with the given names to the end of `output`, returning the offset of the group.
+///
+/// The data offsets are left as 0, fill them in with `set_resource_data_offset` once the data has been written.
+pub(crate) fn compile_resources(
+    output: &mut Vec<u8>,
+    strings: &mut StringTable,
+    names: &[&str],
+) -> usize {
+    let group = output.len();
+    let tree = resource_tree(names);
+
+    let resources_size = (names.len() + 1) * RESOURCE_SIZE + RESOURCE_HEADER_SIZE; // includes the dummy child
+    output.extend(i32::to_be_bytes(resources_size as i32));
+    output.extend(i32::to_be_bytes(names.len() as i32)); // num_children
+
+    for (i, node) in tree.iter().enumerate() {
+        output.extend(u16::to_be_bytes(node.id));
+        output.extend(u16::to_be_bytes(0)); // flag
+        output.extend(u16::to_be_bytes(node.left));
+        output.extend(u16::to_be_bytes(node.right));
+        if i == 0 {
+            output.extend(i32::to_be_bytes(0)); // string_offset
+        } else {
+            strings.reference(output.len(), group, names[i - 1]);
+            output.extend(i32::to_be_bytes(0)); // string_offset, filled in by the StringTable
+        }
+        output.extend(i32::to_be_bytes(0)); // data_offset
+    }
+
+    group
+}
+
+/// Points the resource at `index` in the group at `group` to the data at `data`.
+/// All offsets are relative to the start of `output`.
+pub(crate) fn set_resource_data_offset(output: &mut [u8], group: usize, index: usize, data: usize) {
+    let field = group + RESOURCE_HEADER_SIZE + RESOURCE_SIZE * (index + 1) + 0xc;
+    output[field..field + 4].copy_from_slice(&i32::to_be_bytes(data as i32 - group as i32));
+}
+
+struct ResourceNode {
+    id: u16,
+    left: u16,
+    right: u16,
+}
+
+/// The game finds resources by name via a patricia tree stored in the resource entries.
+/// Each id selects the bit of the name to branch on: `(char_index << 3) | bit_index`.
+/// The ids decrease while descending the tree, a non-decreasing id means a leaf was reached.
+///
+/// The first node is the root which is not associated with any name.
+fn resource_tree(names: &[&str]) -> Vec<ResourceNode> {
+    let mut tree = vec![ResourceNode {
+        id: 0xffff,
+        left: 0,
+        right: 0,
+    }];
+
+    for (i, name) in names.iter().enumerate() {
+        let name = name.as_bytes();
+        let index = i as u16 + 1;
+
+        // find the closest existing name
+        let mut prev = 0;
+        let mut current = tree[0].left as usize;
+        while tree[current].id < tree[prev].id {
+            prev = current;
+            current = resource_branch(&tree[current], name) as usize;
+        }
+        let closest = if current == 0 {
+            &[][..]
+        } else {
+            names[current - 1].as_bytes()
+        };
+
+        let id = match most_significant_different_bit(name, closest) {
+            Some(id) => id,
+            None => {
+                // Duplicate names cant be found by name anyway, so just keep it out of the tree.
+                tree.push(ResourceNode {
+                    id: 0,
+                    left: index,
+                    right: index,
+                });
+                continue;
+            }
+        };
+
+        // find where the new node branches off
+        let mut prev = 0;
+        let mut current = tree[0].left as usize;
+        while tree[current].id < tree[prev].id && tree[current].id > id {
+            prev = current;
+            current = resource_branch(&tree[current], name) as usize;
+        }
+
+        let (left, right) = if name_bit(name, id) {
+            (current as u16, index)
+        } else {
+            (index, current as u16)
+        };
+        tree.push(ResourceNode { id, left, right });
+
+        if prev == 0 || !name_bit(name, tree[prev].id) {
+            tree[prev].left = index;
+        } else {
+            tree[prev].right = index;
+        }
+    }
+
+    tree
+}
+
+fn resource_branch(node: &ResourceNode, name: &[u8]) -> u16 {
+    if name_bit(name, node.id) {
+        node.right
+    } else {
+        node.left
+    }
+}
+
+fn name_bit(name: &[u8], id: u16) -> bool {
+    let char_index = (id >> 3) as usize;
+    char_index < name.len() && (name[char_index] >> (id & 7)) & 1 != 0
+}
+
+/// Returns the id of the highest bit that differs between the names, treating the shorter name as padded with zeroes.
+fn most_significant_different_bit(a: &[u8], b: &[u8]) -> Option<u16> {
+    for i in (0..a.len().max(b.len())).rev() {
+        let different = a.get(i).unwrap_or(&0) ^ b.get(i).unwrap_or(&0);
+        if different != 0 {
+            let bit = 7 - different.leading_zeros() as u16;
+            return Some(((i as u16) << 3) | bit);
+        }
+    }
+    None
+}
+
+/// Collects the strings referenced by a BRES or one of its children so they can be written out once, at the end.
+pub(crate) struct StringTable {
+    references: Vec<StringReference>,
+}
+
+struct StringReference {
+    field: usize,
+    base: usize,
+    string: String,
+}
+
+impl StringTable {
+    pub fn new() -> StringTable {
+        StringTable { references: vec![] }
+    }
+
+    /// Records that the i32 at `field` needs to be set to the offset of `string` relative to `base`.
+    /// All offsets are relative to the start of the output passed to `StringTable::compile`.
+    pub fn reference(&mut self, field: usize, base: usize, string: &str) {
+        self.references.push(StringReference {
+            field,
+            base,
+            string: string.to_string(),
+        });
+    }
+
+    /// Writes the strings to the end of `output` and fills in every reference to them.
+    ///
+    /// Each string is prefixed with its length, null terminated and padded to 4 bytes.
+    /// The references point at the first character, not the length.
+    pub fn compile(self, output: &mut Vec<u8>) {
+        let mut strings: Vec<&str> = self.references.iter().map(|x| x.string.as_str()).collect();
+        strings.sort_unstable();
+        strings.dedup();
+
+        let mut offsets = HashMap::new();
+        for string in strings {
+            output.extend(u32::to_be_bytes(string.len() as u32));
+            offsets.insert(string, output.len());
+            output.extend(string.as_bytes());
+            output.push(0x00);
+            while output.len() % 4 != 0 {
+                output.push(0x00);
+            }
+        }
+
+        for reference in &self.references {
+            let offset = offsets[reference.string.as_str()] as i32 - reference.base as i32;
+            output[reference.field..reference.field + 4].copy_from_slice(&i32::to_be_bytes(offset));
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use crate::parse_error::ParseError;
+use crate::sakurai::ScriptSlot;
+use crate::sakurai::fighter_data::{
+    self, ACTION_FLAGS_SIZE, ActionFlags, ModelVisibility, SUB_ACTION_FLAGS_SIZE, SubactionFlags,
+};
+use crate::sakurai::writer::SakuraiWriter;
+use crate::script;
+use crate::script::Script;
+use crate::wii_memory::WiiMemory;
+
+use fancy_slice::FancySlice;
+
+/// Parses the article header at `offset` in `parent_data`.
+///
+/// The size of the articles tables is determined by where the next piece of data starts.
+/// `boundaries` contains the offsets of any other data that may be stored after the tables.
+///
+/// Returns None if the header does not look like an article.
+/// This happens for data sections that use a different layout e.g. kirby hats.
+/// Returns an error if the header looks like an article but its tables are malformed.
+#[rustfmt::skip]
+pub(crate) fn article(parent_data: FancySlice, offset: usize, boundaries: &[usize], wii_memory: &WiiMemory) -> Result<Option<Article>, ParseError> {
+    if offset % 4 != 0 || offset + ARTICLE_SIZE > parent_data.len() {
+        return Ok(None);
+    }
+
+    let arc_group              = parent_data.i32_be(offset);
+    let bone                   = parent_data.i32_be(offset + 0x04);
+    let arc_entry_group        = parent_data.i32_be(offset + 0x08);
+    let actions_start          = parent_data.i32_be(offset + 0x0c);
+    let action_flags_start     = parent_data.i32_be(offset + 0x10);
+    let subaction_flags_start  = parent_data.i32_be(offset + 0x14);
+    let subaction_main_start   = parent_data.i32_be(offset + 0x18);
+    let subaction_gfx_start    = parent_data.i32_be(offset + 0x1c);
+    let subaction_sfx_start    = parent_data.i32_be(offset + 0x20);
+    let model_visibility_start = parent_data.i32_be(offset + 0x24);
+    let _collision_data        = parent_data.i32_be(offset + 0x28);
+    let _unknown_d2            = parent_data.i32_be(offset + 0x2c);
+    let _unknown_d3            = parent_data.i32_be(offset + 0x30);
+
+    let starts = [
+        actions_start,
+        action_flags_start,
+        subaction_flags_start,
+        subaction_main_start,
+        subaction_gfx_start,
+        subaction_sfx_start,
+        model_visibility_start,
+    ];
+    if starts.iter().any(|x| *x < 0 || *x % 4 != 0 || *x as usize >= parent_data.len()) {
+        return Ok(None);
+    }
+
+    // The tables are stored sequentially, so a table ends where the next thing starts.
+    let mut boundaries = boundaries.to_vec();
+    boundaries.extend(starts.iter().filter(|x| **x != 0).map(|x| *x as usize));
+    boundaries.push(offset);
+    boundaries.push(parent_data.len());
+    let table_len = |start: i32, size: usize| {
+        if start == 0 {
+            None
+        } else {
+            let start = start as usize;
+            boundaries.iter().filter(|x| **x > start).min().map(|end| (end - start) / size)
+        }
+    };
+
+    let actions_num = [table_len(actions_start, 4), table_len(action_flags_start, ACTION_FLAGS_SIZE)]
+        .into_iter().flatten().min().unwrap_or(0);
+    let subactions_num = [
+        table_len(subaction_flags_start, SUB_ACTION_FLAGS_SIZE),
+        table_len(subaction_main_start, 4),
+        table_len(subaction_gfx_start, 4),
+        table_len(subaction_sfx_start, 4),
+    ].into_iter().flatten().min().unwrap_or(0);
+
+    let scripts = |start: i32, num: usize| {
+        if start == 0 {
+            Ok(vec!())
+        } else {
+            script::scripts(parent_data, start as usize, num, wii_memory)
+        }
+    };
+
+    let action_flags = if action_flags_start == 0 {
+        vec!()
+    } else {
+        fighter_data::action_flags(parent_data, action_flags_start as usize, actions_num)?
+    };
+    let subaction_flags = if subaction_flags_start == 0 {
+        vec!()
+    } else {
+        fighter_data::subaction_flags(parent_data, subaction_flags_start as usize, subactions_num)?
+    };
+    let model_visibility = if model_visibility_start == 0 {
+        ModelVisibility { references: vec!(), defaults: vec!() }
+    } else {
+        fighter_data::model_visibility(parent_data, model_visibility_start)?
+    };
+
+    Ok(Some(Article {
+        arc_group,
+        bone,
+        arc_entry_group,
+        actions:        scripts(actions_start, actions_num)?,
+        action_flags,
+        subaction_flags,
+        subaction_main: scripts(subaction_main_start, subactions_num)?,
+        subaction_gfx:  scripts(subaction_gfx_start, subactions_num)?,
+        subaction_sfx:  scripts(subaction_sfx_start, subactions_num)?,
+        model_visibility,
+        _collision_data,
+        _unknown_d2,
+        _unknown_d3,
+        actions_start,
+        action_flags_start,
+        subaction_flags_start,
+        subaction_main_start,
+        subaction_gfx_start,
+        subaction_sfx_start,
+    }))
+}
+
+/// The offset of the article header at `offset` and the offsets of all of its tables
+pub(crate) fn article_offsets(parent_data: FancySlice, offset: usize) -> Vec<usize> {
+    let mut offsets = vec![offset];
+    if offset + ARTICLE_SIZE <= parent_data.len() {
+        for field in (0x0c..ARTICLE_SIZE).step_by(4) {
+            let value = parent_data.i32_be(offset + field);
+            if value > 0 && (value as usize) < parent_data.len() {
+                offsets.push(value as usize);
+            }
+        }
+    }
+    offsets
+}
+
+const ARTICLE_SIZE: usize = 0x34;
+/// An object with its own model and scripts that is spawned by a fighter or item e.g. projectiles.
+#[derive(Clone, Debug)]
+pub struct Article {
+    /// Index of the group in the model arc containing the articles model and animations
+    pub arc_group: i32,
+    /// The bone the article is spawned at
+    pub bone: i32,
+    pub arc_entry_group: i32,
+    pub actions: Vec<Script>,
+    pub action_flags: Vec<ActionFlags>,
+    pub subaction_flags: Vec<SubactionFlags>,
+    pub subaction_main: Vec<Script>,
+    pub subaction_gfx: Vec<Script>,
+    pub subaction_sfx: Vec<Script>,
+    pub model_visibility: ModelVisibility,
+    _collision_data: i32,
+    _unknown_d2: i32,
+    _unknown_d3: i32,
+    actions_start: i32,
+    action_flags_start: i32,
+    subaction_flags_start: i32,
+    subaction_main_start: i32,
+    subaction_gfx_start: i32,
+    subaction_sfx_start: i32,
+}
+
+impl Article {
+    /// Writes the parsed data over the top of the original data.
+    /// Scripts are handled separately via `Article::script_slots`
+    pub(crate) fn compile(&self, writer: &mut SakuraiWriter) {
+        // The names are left untouched as we have no way to allocate new strings
+        for (i, flags) in self.subaction_flags.iter().enumerate() {
+            let offset = self.subaction_flags_start as usize + i * SUB_ACTION_FLAGS_SIZE;
+            writer.write_u8(offset, flags.in_translation_time);
+            writer.write_u8(offset + 1, flags.animation_flags.bits());
+        }
+
+        for (i, flags) in self.action_flags.iter().enumerate() {
+            let offset = self.action_flags_start as usize + i * ACTION_FLAGS_SIZE;
+            writer.write_u32(offset, flags.flag1);
+            writer.write_u32(offset + 0x4, flags.flag2);
+            writer.write_u32(offset + 0x8, flags.flag3);
+            writer.write_u32(offset + 0xc, flags.flag4);
+        }
+    }
+
+    /// Returns every script along with the location of the pointer to it
+    pub(crate) fn script_slots(&self) -> Vec<ScriptSlot<'_>> {
+        let mut slots = vec![];
+        let tables = [
+            (self.actions_start, &self.actions),
+            (self.subaction_main_start, &self.subaction_main),
+            (self.subaction_gfx_start, &self.subaction_gfx),
+            (self.subaction_sfx_start, &self.subaction_sfx),
+        ];
+        for (start, scripts) in tables {
+            for (i, script) in scripts.iter().enumerate() {
+                slots.push(ScriptSlot {
+                    pointer_offset: start as usize + i * 4,
+                    script,
+                });
+            }
+        }
+        slots
+    }
+
+    /// Every script directly referenced by the article, used to locate the fragment scripts they call
+    pub(crate) fn scripts(&self) -> [&[Script]; 4] {
+        [
+            &self.actions,
+            &self.subaction_main,
+            &self.subaction_gfx,
+            &self.subaction_sfx,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An article header at 0 followed by tables for 2 actions and 3 subactions, then 0x10 bytes of unrelated data
+    fn article_fixture(actions_start: i32) -> Vec<u8> {
+        let mut data = vec![];
+        data.extend(i32::to_be_bytes(1)); // arc_group
+        data.extend(i32::to_be_bytes(2)); // bone
+        data.extend(i32::to_be_bytes(3)); // arc_entry_group
+        data.extend(i32::to_be_bytes(actions_start));
+        data.extend(i32::to_be_bytes(0x3c)); // action_flags_start
+        data.extend(i32::to_be_bytes(0x5c)); // subaction_flags_start
+        data.extend(i32::to_be_bytes(0x74)); // subaction_main_start
+        data.extend(i32::to_be_bytes(0x80)); // subaction_gfx_start
+        data.extend(i32::to_be_bytes(0x8c)); // subaction_sfx_start
+        data.resize(ARTICLE_SIZE, 0);
+
+        data.extend([0; 2 * 4]); // actions, scripts at offset 0 are treated as empty
+        data.extend([0; 2 * ACTION_FLAGS_SIZE]);
+        data.extend([0; 3 * SUB_ACTION_FLAGS_SIZE]);
+        data.extend([0; 3 * 4]); // subaction main
+        data.extend([0; 3 * 4]); // subaction gfx
+        data.extend([0; 3 * 4]); // subaction sfx
+        assert_eq!(data.len(), 0x98);
+        data.extend([0xff; 0x10]);
+        data
+    }
+
+    #[test]
+    fn article_table_lengths() {
+        let data = article_fixture(0x34);
+        let article = article(FancySlice::new(&data), 0, &[0x98], &WiiMemory::new())
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(article.arc_group, 1);
+        assert_eq!(article.actions.len(), 2);
+        assert_eq!(article.action_flags.len(), 2);
+        assert_eq!(article.subaction_flags.len(), 3);
+        assert_eq!(article.subaction_main.len(), 3);
+        assert_eq!(article.subaction_gfx.len(), 3);
+        assert_eq!(article.subaction_sfx.len(), 3);
+    }
+
+    #[test]
+    fn article_table_lengths_use_shortest_table() {
+        // Without the boundary the subaction sfx table would run to the end of the data,
+        // but every subaction table has the same length so the shortest is used.
+        let data = article_fixture(0x34);
+        let article = article(FancySlice::new(&data), 0, &[], &WiiMemory::new())
+            .unwrap()
+            .unwrap();
+        assert_eq!(article.subaction_sfx.len(), 3);
+    }
+
+    #[test]
+    fn article_table_lengths_missing_table() {
+        let data = article_fixture(0);
+        let article = article(FancySlice::new(&data), 0, &[0x98], &WiiMemory::new())
+            .unwrap()
+            .unwrap();
+        assert!(article.actions.is_empty());
+        assert_eq!(article.action_flags.len(), 2);
+        assert_eq!(article.subaction_main.len(), 3);
+    }
+
+    #[test]
+    fn article_invalid_header() {
+        let mut data = article_fixture(0x34);
+        // misaligned action_flags_start
+        data[0x10..0x14].copy_from_slice(&i32::to_be_bytes(0x3d));
+        assert!(
+            article(FancySlice::new(&data), 0, &[0x98], &WiiMemory::new())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -1,122 +1,391 @@
 use cgmath::Vector3;
 use fancy_slice::FancySlice;
 
+use crate::parse_error::{ParseError, check_len, try_offset};
+use crate::sakurai::writer::SakuraiWriter;
 use crate::util;
 
 #[rustfmt::skip]
-pub fn misc_section(data: FancySlice, parent_data: FancySlice) -> MiscSection {
+pub fn misc_section(data: FancySlice, parent_data: FancySlice) -> Result<MiscSection, ParseError> {
+    check_len(data, MISC_SECTION_HEADER_SIZE, "misc section")?;
     let _unk0_offset          = data.i32_be(0);
-    let final_smash_aura_list = util::list_offset(data.relative_fancy_slice(0x04..));
-    let hurt_box_list         = util::list_offset(data.relative_fancy_slice(0x0c..));
-    let ledge_grab_list       = util::list_offset(data.relative_fancy_slice(0x14..));
-    let unk7_list             = util::list_offset(data.relative_fancy_slice(0x1c..));
+    let final_smash_aura_list = util::list_offset(&data, 0x04, "misc section")?;
+    let hurt_box_list         = util::list_offset(&data, 0x0c, "misc section")?;
+    let ledge_grab_list       = util::list_offset(&data, 0x14, "misc section")?;
+    let unk7_list             = util::list_offset(&data, 0x1c, "misc section")?;
     let bone_refs_offset      = data.i32_be(0x24);
-    let _item_bones           = data.i32_be(0x28);
-    let _sound_data_offset    = data.i32_be(0x2c);
+    let item_bones_offset     = data.i32_be(0x28);
+    let sound_data_offset     = data.i32_be(0x2c);
     let _unk12_offset         = data.i32_be(0x30);
-    let _multi_jump_offset    = data.i32_be(0x34);
-    let _glide_offset         = data.i32_be(0x38);
+    let multi_jump_offset     = data.i32_be(0x34);
+    let glide_offset          = data.i32_be(0x38);
     let crawl_offset          = data.i32_be(0x3c);
     let ecbs_offset           = data.i32_be(0x40);
     let tether_offset         = data.i32_be(0x44);
     let _unk18_offset         = data.i32_be(0x48);
 
-    let mut final_smash_auras = vec!();
-    for i in 0..final_smash_aura_list.count {
-        let offset = final_smash_aura_list.start_offset as usize + i as usize * FINAL_SMASH_AURA_SIZE;
-        final_smash_auras.push(final_smash_aura(parent_data.relative_fancy_slice(offset..)));
-    }
+    let final_smash_auras = util::list_offsets(parent_data, &final_smash_aura_list, FINAL_SMASH_AURA_SIZE, "final smash aura")?
+        .map(|offset| final_smash_aura(parent_data.relative_fancy_slice(offset..)))
+        .collect();
 
-    let mut hurt_boxes = vec!();
-    for i in 0..hurt_box_list.count {
-        let offset = hurt_box_list.start_offset as usize + i as usize * HURTBOX_SIZE;
-        hurt_boxes.push(hurtbox(parent_data.relative_fancy_slice(offset..)));
-    }
+    let hurt_boxes = util::list_offsets(parent_data, &hurt_box_list, HURTBOX_SIZE, "hurtbox")?
+        .map(|offset| hurtbox(parent_data.relative_fancy_slice(offset..)))
+        .collect();
 
-    let mut ledge_grab_boxes = vec!();
-    for i in 0..ledge_grab_list.count {
-        let offset = ledge_grab_list.start_offset as usize + i as usize * LEDGE_GRAB_SIZE;
-        ledge_grab_boxes.push(ledge_grab_box(parent_data.relative_fancy_slice(offset ..)));
-    }
+    let ledge_grab_boxes = util::list_offsets(parent_data, &ledge_grab_list, LEDGE_GRAB_SIZE, "ledge grab box")?
+        .map(|offset| ledge_grab_box(parent_data.relative_fancy_slice(offset..)))
+        .collect();
 
-    let mut unk7s = vec!();
-    for i in 0..unk7_list.count {
-        let offset = unk7_list.start_offset as usize + i as usize * UNK7_SIZE;
-        unk7s.push(unk7(parent_data.relative_fancy_slice(offset ..)));
-    }
+    let unk7s = util::list_offsets(parent_data, &unk7_list, UNK7_SIZE, "misc section unk7")?
+        .map(|offset| unk7(parent_data.relative_fancy_slice(offset..)))
+        .collect();
 
+    let bone_refs = try_offset(&parent_data, bone_refs_offset as i64, BONE_REFS_SIZE, "bone refs")?;
     let bone_refs = BoneRefs {
-        unk0:    parent_data.i32_be(bone_refs_offset as usize),
-        unk1:    parent_data.i32_be(bone_refs_offset as usize + 0x04),
-        unk2:    parent_data.i32_be(bone_refs_offset as usize + 0x08),
-        unk3:    parent_data.i32_be(bone_refs_offset as usize + 0x0c),
-        trans_n: parent_data.i32_be(bone_refs_offset as usize + 0x10),
-        unk5:    parent_data.i32_be(bone_refs_offset as usize + 0x14),
-        unk6:    parent_data.i32_be(bone_refs_offset as usize + 0x18),
-        unk7:    parent_data.i32_be(bone_refs_offset as usize + 0x1c),
-        unk8:    parent_data.i32_be(bone_refs_offset as usize + 0x20),
-        unk9:    parent_data.i32_be(bone_refs_offset as usize + 0x24),
+        unk0:    bone_refs.i32_be(0x00),
+        unk1:    bone_refs.i32_be(0x04),
+        unk2:    bone_refs.i32_be(0x08),
+        unk3:    bone_refs.i32_be(0x0c),
+        trans_n: bone_refs.i32_be(0x10),
+        unk5:    bone_refs.i32_be(0x14),
+        unk6:    bone_refs.i32_be(0x18),
+        unk7:    bone_refs.i32_be(0x1c),
+        unk8:    bone_refs.i32_be(0x20),
+        unk9:    bone_refs.i32_be(0x24),
+    };
+
+    let item_bones = if item_bones_offset == 0 {
+        None
+    } else {
+        let offset = item_bones_offset as usize;
+        let item_bones = try_offset(&parent_data, item_bones_offset as i64, 0x10, "item bones")?;
+        Some(ItemBones {
+            have_n:  item_bones.i32_be(0x00),
+            throw_n: item_bones.i32_be(0x04),
+            bones:   i32_list(parent_data, util::list_offset(&parent_data, offset + 0x08, "item bones")?, "item bones")?,
+        })
+    };
+
+    // A list of sound tables, each table is a list of sound ids
+    let sound_tables = if sound_data_offset == 0 {
+        vec!()
+    } else {
+        let tables_list = util::list_offset(&parent_data, sound_data_offset as usize, "sound data")?;
+        util::list_offsets(parent_data, &tables_list, util::LIST_OFFSET_SIZE, "sound data")?
+            .map(|offset| i32_list(parent_data, util::list_offset(&parent_data, offset, "sound table")?, "sound table"))
+            .collect::<Result<_, _>>()?
+    };
+
+    let multi_jump = if multi_jump_offset == 0 {
+        None
+    } else {
+        let offset = multi_jump_offset as usize;
+        let multi_jump = try_offset(&parent_data, multi_jump_offset as i64, MULTI_JUMP_SIZE, "multi jump")?;
+        let hops_list = util::list_offset(&parent_data, offset + 0x10, "multi jump")?;
+        let unks_list = util::list_offset(&parent_data, offset + 0x18, "multi jump")?;
+        Some(MultiJump {
+            unk0:             multi_jump.f32_be(0x00),
+            unk1:             multi_jump.f32_be(0x04),
+            unk2:             multi_jump.f32_be(0x08),
+            horizontal_boost: multi_jump.f32_be(0x0c),
+            hops:             util::list_offsets(parent_data, &hops_list, 4, "multi jump hops")?.map(|x| parent_data.f32_be(x)).collect(),
+            unks:             util::list_offsets(parent_data, &unks_list, 4, "multi jump unks")?.map(|x| parent_data.f32_be(x)).collect(),
+            turn_frames:      multi_jump.f32_be(0x20),
+        })
+    };
+
+    let glide = if glide_offset == 0 {
+        None
+    } else {
+        let glide = try_offset(&parent_data, glide_offset as i64, GLIDE_SIZE, "glide")?;
+        Some(Glide {
+            values: std::array::from_fn(|i| glide.f32_be(i * 4)),
+        })
     };
 
     let crawl = if crawl_offset == 0 {
         None
     } else {
+        let crawl = try_offset(&parent_data, crawl_offset as i64, 0x8, "crawl")?;
         Some(Crawl {
-            forward:  parent_data.f32_be(crawl_offset as usize),
-            backward: parent_data.f32_be(crawl_offset as usize + 0x4),
+            forward:  crawl.f32_be(0x0),
+            backward: crawl.f32_be(0x4),
         })
     };
 
-    let ecbs_list = util::list_offset(parent_data.relative_fancy_slice(ecbs_offset as usize..));
+    let ecbs_list = util::list_offset(&parent_data, ecbs_offset as usize, "ECB")?;
 
     // it looks like this same structure is used elsewhere as well. Check the DataSection.cs and ExtraDataOffsets.cs files in brawlbox.
     let mut ecbs = vec!();
-    for i in 0..ecbs_list.count {
-        let pointer  = parent_data.i32_be(ecbs_list.start_offset as usize + i as usize * ECB_SIZE); // TODO: Is this indirection for anything? Maybe the list is supposed to occur here instead?
-        let ecb_type = parent_data.i32_be(pointer as usize);
-        if ecb_type == 0 {
-            let ecb_bones_offset = parent_data.i32_be(pointer as usize + 0x04);
-            let ecb_bones_count  = parent_data.i32_be(pointer as usize + 0x08);
-            let min_height       = parent_data.f32_be(pointer as usize + 0x0C);
-            let min_width        = parent_data.f32_be(pointer as usize + 0x10);
-            let unk              = parent_data.f32_be(pointer as usize + 0x14);
-
-            let mut bones = vec!();
-            for i in 0..ecb_bones_count {
-                bones.push(parent_data.i32_be(ecb_bones_offset as usize + i as usize * 4));
+    for pointer_offset in util::list_offsets(parent_data, &ecbs_list, ECB_SIZE, "ECB")? {
+        let pointer  = parent_data.i32_be(pointer_offset) as usize; // TODO: Is this indirection for anything? Maybe the list is supposed to occur here instead?
+        let ecb = try_offset(&parent_data, pointer as i64, 0x4, "ECB")?;
+        let ecb_type = ecb.i32_be(0);
+        let (kind, values_offset) = match ecb_type {
+            0 => (ECBKind::Bones, pointer + 0x04),
+            1 => (ECBKind::Fixed, pointer + 0x04),
+            2 => {
+                check_len(ecb, 0x8, "ECB").map_err(|err| err.offset_by(pointer))?;
+                (ECBKind::BonesWithFlags { flags: ecb.u32_be(0x04) }, pointer + 0x08)
             }
-
-            ecbs.push(ECB { bones, min_height, min_width, unk });
+            _ => {
+                error!("Unknown ECB type {}", ecb_type);
+                continue;
+            }
+        };
+        let (bones, values_offset) = if let ECBKind::Fixed = kind {
+            (vec!(), values_offset)
         } else {
-            error!("ECB type unimplemented")
-        }
+            let bones_list = util::list_offset(&parent_data, values_offset, "ECB")?;
+            (i32_list(parent_data, bones_list, "ECB")?, values_offset + util::LIST_OFFSET_SIZE)
+        };
+        let values = try_offset(&parent_data, values_offset as i64, 0xc, "ECB")?;
+        let min_height = values.f32_be(0x00);
+        let min_width  = values.f32_be(0x04);
+        let unk        = values.f32_be(0x08);
+
+        ecbs.push(ECB { kind, bones, min_height, min_width, unk });
     }
 
     let tether = if tether_offset == 0 {
         None
     } else {
+        let tether = try_offset(&parent_data, tether_offset as i64, 0x8, "tether")?;
         Some(Tether {
-            num_hang_frame: parent_data.i32_be(tether_offset as usize),
-            _unk1:          parent_data.f32_be(tether_offset as usize + 0x4),
+            num_hang_frame: tether.i32_be(0x0),
+            _unk1:          tether.f32_be(0x4),
         })
     };
 
-    MiscSection {
+    Ok(MiscSection {
         final_smash_auras,
         hurt_boxes,
         ledge_grab_boxes,
         unk7s,
         bone_refs,
-        _item_bones,
-        _sound_data_offset,
+        item_bones,
+        sound_tables,
         _unk12_offset,
-        _multi_jump_offset,
-        _glide_offset,
+        multi_jump,
+        glide,
         crawl,
         ecbs,
         tether,
         _unk18_offset,
+    })
+}
+
+impl MiscSection {
+    /// Writes the parsed data over the top of the original misc section at `offset`
+    pub(crate) fn compile(&self, writer: &mut SakuraiWriter, offset: usize) {
+        compile_list(
+            writer,
+            offset + 0x04,
+            &self.final_smash_auras,
+            FINAL_SMASH_AURA_SIZE,
+            compile_final_smash_aura,
+        );
+        compile_list(
+            writer,
+            offset + 0x0c,
+            &self.hurt_boxes,
+            HURTBOX_SIZE,
+            compile_hurtbox,
+        );
+        compile_list(
+            writer,
+            offset + 0x14,
+            &self.ledge_grab_boxes,
+            LEDGE_GRAB_SIZE,
+            compile_ledge_grab_box,
+        );
+
+        let bone_refs_offset = writer.i32(offset + 0x24) as usize;
+        let bone_refs = &self.bone_refs;
+        for (i, value) in [
+            bone_refs.unk0,
+            bone_refs.unk1,
+            bone_refs.unk2,
+            bone_refs.unk3,
+            bone_refs.trans_n,
+            bone_refs.unk5,
+            bone_refs.unk6,
+            bone_refs.unk7,
+            bone_refs.unk8,
+            bone_refs.unk9,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            writer.write_i32(bone_refs_offset + i * 4, value);
+        }
+
+        let item_bones_offset = writer.i32(offset + 0x28) as usize;
+        if let (Some(item_bones), true) = (&self.item_bones, item_bones_offset != 0) {
+            writer.write_i32(item_bones_offset, item_bones.have_n);
+            writer.write_i32(item_bones_offset + 0x04, item_bones.throw_n);
+            compile_list(
+                writer,
+                item_bones_offset + 0x08,
+                &item_bones.bones,
+                4,
+                |bone, writer, offset| writer.write_i32(offset, *bone),
+            );
+        }
+
+        let sound_data_offset = writer.i32(offset + 0x2c) as usize;
+        if sound_data_offset != 0 {
+            compile_list(
+                writer,
+                sound_data_offset,
+                &self.sound_tables,
+                util::LIST_OFFSET_SIZE,
+                |table, writer, offset| {
+                    compile_list(writer, offset, table, 4, |sound, writer, offset| {
+                        writer.write_i32(offset, *sound)
+                    })
+                },
+            );
+        }
+
+        let multi_jump_offset = writer.i32(offset + 0x34) as usize;
+        if let (Some(multi_jump), true) = (&self.multi_jump, multi_jump_offset != 0) {
+            writer.write_f32(multi_jump_offset, multi_jump.unk0);
+            writer.write_f32(multi_jump_offset + 0x04, multi_jump.unk1);
+            writer.write_f32(multi_jump_offset + 0x08, multi_jump.unk2);
+            writer.write_f32(multi_jump_offset + 0x0c, multi_jump.horizontal_boost);
+            for (list_offset, values) in [(0x10, &multi_jump.hops), (0x18, &multi_jump.unks)] {
+                compile_list(
+                    writer,
+                    multi_jump_offset + list_offset,
+                    values,
+                    4,
+                    |value, writer, offset| writer.write_f32(offset, *value),
+                );
+            }
+            writer.write_f32(multi_jump_offset + 0x20, multi_jump.turn_frames);
+        }
+
+        let glide_offset = writer.i32(offset + 0x38) as usize;
+        if let (Some(glide), true) = (&self.glide, glide_offset != 0) {
+            for (i, value) in glide.values.iter().enumerate() {
+                writer.write_f32(glide_offset + i * 4, *value);
+            }
+        }
+
+        let crawl_offset = writer.i32(offset + 0x3c) as usize;
+        if let (Some(crawl), true) = (&self.crawl, crawl_offset != 0) {
+            writer.write_f32(crawl_offset, crawl.forward);
+            writer.write_f32(crawl_offset + 0x4, crawl.backward);
+        }
+
+        let ecbs_offset = writer.i32(offset + 0x40) as usize;
+        let ecbs_start = writer.i32(ecbs_offset) as usize;
+        let ecbs_count = writer.i32(ecbs_offset + 0x4) as usize;
+        // Unknown ECB types are not parsed so they are skipped over here too.
+        let ecb_pointers = (0..ecbs_count)
+            .map(|i| writer.i32(ecbs_start + i * ECB_SIZE) as usize)
+            .filter(|pointer| (0..=2).contains(&writer.i32(*pointer)));
+        for (pointer, ecb) in ecb_pointers.collect::<Vec<_>>().into_iter().zip(&self.ecbs) {
+            // The layout of the other types is inferred, so they are left untouched rather than risk corrupting them.
+            if let ECBKind::Bones = ecb.kind {
+                compile_list(
+                    writer,
+                    pointer + 0x04,
+                    &ecb.bones,
+                    4,
+                    |bone, writer, offset| writer.write_i32(offset, *bone),
+                );
+                let values_offset = pointer + 0x04 + util::LIST_OFFSET_SIZE;
+                writer.write_f32(values_offset, ecb.min_height);
+                writer.write_f32(values_offset + 0x04, ecb.min_width);
+                writer.write_f32(values_offset + 0x08, ecb.unk);
+            }
+        }
+
+        let tether_offset = writer.i32(offset + 0x44) as usize;
+        if let (Some(tether), true) = (&self.tether, tether_offset != 0) {
+            writer.write_i32(tether_offset, tether.num_hang_frame);
+        }
+    }
+}
+
+fn i32_list(
+    parent_data: FancySlice,
+    list: util::ListOffset,
+    structure: &'static str,
+) -> Result<Vec<i32>, ParseError> {
+    Ok(util::list_offsets(parent_data, &list, 4, structure)?
+        .map(|offset| parent_data.i32_be(offset))
+        .collect())
+}
+
+/// Writes the items over the list pointed to by the `ListOffset` at `list_offset`.
+/// If the number of items has changed, a new list is allocated at the end of the data instead.
+fn compile_list<T>(
+    writer: &mut SakuraiWriter,
+    list_offset: usize,
+    items: &[T],
+    item_size: usize,
+    compile: impl Fn(&T, &mut SakuraiWriter, usize),
+) {
+    let mut start = writer.i32(list_offset) as usize;
+    let count = writer.i32(list_offset + 0x4) as usize;
+    if count != items.len() {
+        start = writer.allocate(items.len() * item_size);
+        writer.write_pointer(list_offset, start as i32);
+        writer.write_i32(list_offset + 0x4, items.len() as i32);
     }
+
+    for (i, item) in items.iter().enumerate() {
+        compile(item, writer, start + i * item_size);
+    }
+}
+
+#[rustfmt::skip]
+fn compile_final_smash_aura(aura: &FinalSmashAura, writer: &mut SakuraiWriter, offset: usize) {
+    writer.write_i32(offset,        aura.bone_index);
+    writer.write_f32(offset + 0x04, aura.x);
+    writer.write_f32(offset + 0x08, aura.y);
+    writer.write_f32(offset + 0x0c, aura.width);
+    writer.write_f32(offset + 0x10, aura.height);
+}
+
+#[rustfmt::skip]
+fn compile_hurtbox(hurt_box: &HurtBox, writer: &mut SakuraiWriter, offset: usize) {
+    writer.write_f32(offset,        hurt_box.offset.x);
+    writer.write_f32(offset + 0x04, hurt_box.offset.y);
+    writer.write_f32(offset + 0x08, hurt_box.offset.z);
+    writer.write_f32(offset + 0x0c, hurt_box.stretch.x);
+    writer.write_f32(offset + 0x10, hurt_box.stretch.y);
+    writer.write_f32(offset + 0x14, hurt_box.stretch.z);
+    writer.write_f32(offset + 0x18, hurt_box.radius);
+
+    let zone = match hurt_box.zone {
+        HurtBoxZone::Low        => 0,
+        HurtBoxZone::Middle     => 1,
+        HurtBoxZone::High       => 2,
+        HurtBoxZone::Unknown(x) => x as u16,
+    };
+    let region = match (hurt_box.grabbable, hurt_box.trap_item_hittable) {
+        (true,  false) => 0,
+        (false, false) => 1,
+        (false, true)  => 2,
+        (true,  true)  => 3,
+    };
+    let flags = hurt_box.enabled as u16
+        | (zone << 3)
+        | (region << 5)
+        | (hurt_box.bone_index << 7);
+    writer.write_u16(offset + 0x1c, flags);
+}
+
+#[rustfmt::skip]
+fn compile_ledge_grab_box(ledge_grab_box: &LedgeGrabBox, writer: &mut SakuraiWriter, offset: usize) {
+    writer.write_f32(offset,       ledge_grab_box.x_left);
+    writer.write_f32(offset + 0x4, ledge_grab_box.y);
+    writer.write_f32(offset + 0x8, ledge_grab_box.x_padding);
+    writer.write_f32(offset + 0xc, ledge_grab_box.height);
 }
 
 #[rustfmt::skip]
@@ -202,6 +471,7 @@ fn unk7(data: FancySlice) -> Unk7 {
     Unk7 { _unk1, _unk2, _unk3, _unk4, _unk5, _unk6, _unk7, _unk8, _unk9, _unk10, _unk11, _unk12, _unk13, _unk14 }
 }
 
+pub(crate) const MISC_SECTION_HEADER_SIZE: usize = 0x4c;
 #[derive(Clone, Debug)]
 pub struct MiscSection {
     pub final_smash_auras: Vec<FinalSmashAura>,
@@ -209,14 +479,19 @@ pub struct MiscSection {
     pub ledge_grab_boxes: Vec<LedgeGrabBox>,
     pub unk7s: Vec<Unk7>,
     pub bone_refs: BoneRefs,
-    _item_bones: i32,
-    _sound_data_offset: i32,
+    pub item_bones: Option<ItemBones>,
+    /// Each table is a list of sound ids
+    pub sound_tables: Vec<Vec<i32>>,
+    /// The layout of this data is unknown
     _unk12_offset: i32,
-    _multi_jump_offset: i32,
-    _glide_offset: i32,
+    /// Only used by fighters with more than one midair jump e.g. Kirby, Jigglypuff and Meta Knight
+    pub multi_jump: Option<MultiJump>,
+    /// Only used by fighters that can glide e.g. Pit, Charizard and Meta Knight
+    pub glide: Option<Glide>,
     pub crawl: Option<Crawl>,
     pub ecbs: Vec<ECB>,
     pub tether: Option<Tether>,
+    /// The layout of this data is unknown
     _unk18_offset: i32,
 }
 
@@ -296,6 +571,7 @@ pub struct Unk7 {
     _unk14: f32,
 }
 
+const BONE_REFS_SIZE: usize = 0x28;
 #[derive(Clone, Debug)]
 pub struct BoneRefs {
     pub unk0: i32,
@@ -310,6 +586,36 @@ pub struct BoneRefs {
     pub unk9: i32,
 }
 
+/// The bones items are attached to
+#[derive(Clone, Debug)]
+pub struct ItemBones {
+    /// The bone held items are attached to
+    pub have_n: i32,
+    /// The bone thrown items are released from
+    pub throw_n: i32,
+    pub bones: Vec<i32>,
+}
+
+const MULTI_JUMP_SIZE: usize = 0x24;
+#[derive(Clone, Debug)]
+pub struct MultiJump {
+    pub unk0: f32,
+    pub unk1: f32,
+    pub unk2: f32,
+    pub horizontal_boost: f32,
+    /// The vertical velocity of each midair jump
+    pub hops: Vec<f32>,
+    pub unks: Vec<f32>,
+    pub turn_frames: f32,
+}
+
+pub const GLIDE_SIZE: usize = 0x58;
+/// The meaning of the individual glide parameters is not known yet
+#[derive(Clone, Debug)]
+pub struct Glide {
+    pub values: [f32; GLIDE_SIZE / 4],
+}
+
 #[derive(Clone, Debug)]
 pub struct Crawl {
     pub forward: f32,
@@ -318,16 +624,177 @@ pub struct Crawl {
 
 pub const ECB_SIZE: usize = 0x4; // TODO
 #[derive(Clone, Debug)]
-/// TODO: Currently just ECB type 0, maybe change to enum or maybe change the fields to Options
 pub struct ECB {
+    pub kind: ECBKind,
+    /// The ECB stretches to contain these bones, always empty for `ECBKind::Fixed`
     pub bones: Vec<i32>,
     pub min_height: f32,
     pub min_width: f32,
     pub unk: f32, // Is this even part of the ecb, might just be padding...? always 0 and changing doesnt seem to do anything
 }
 
+/// Only the layout of type 0 is known.
+/// The layouts of types 1 and 2 are inferred from where their values line up with type 0,
+/// so `MiscSection::compile` does not write changes to them back.
+#[derive(Clone, Debug)]
+pub enum ECBKind {
+    /// ECB type 0
+    Bones,
+    /// ECB type 1, read as having no bones so the ECB is always the minimum size
+    Fixed,
+    /// ECB type 2, read as a type 0 ECB preceded by `flags`
+    BonesWithFlags { flags: u32 },
+}
+
 #[derive(Clone, Debug)]
 pub struct Tether {
     pub num_hang_frame: i32,
     _unk1: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_f32s(data: &mut Vec<u8>, values: &[f32]) {
+        for value in values {
+            data.extend(f32::to_be_bytes(*value));
+        }
+    }
+
+    /// A misc section at 0 with a multi jump, a glide and one ECB of each type
+    #[rustfmt::skip]
+    fn misc_fixture() -> Vec<u8> {
+        let mut data = vec![0; MISC_SECTION_HEADER_SIZE];
+        data[0x24..0x28].copy_from_slice(&i32::to_be_bytes(0x4c)); // bone refs
+        data[0x34..0x38].copy_from_slice(&i32::to_be_bytes(0x74)); // multi jump
+        data[0x38..0x3c].copy_from_slice(&i32::to_be_bytes(0xa8)); // glide
+        data[0x40..0x44].copy_from_slice(&i32::to_be_bytes(0x100)); // ECB list
+
+        // bone refs
+        data.resize(0x74, 0);
+
+        // multi jump
+        push_f32s(&mut data, &[1.0, 2.0, 3.0, 0.5]);
+        data.extend(i32::to_be_bytes(0x98)); // hops
+        data.extend(i32::to_be_bytes(2));
+        data.extend(i32::to_be_bytes(0xa0)); // unks
+        data.extend(i32::to_be_bytes(2));
+        push_f32s(&mut data, &[4.0]); // turn frames
+        push_f32s(&mut data, &[2.5, 2.0]); // hops
+        push_f32s(&mut data, &[0.25, 0.75]); // unks
+        assert_eq!(data.len(), 0xa8);
+
+        // glide
+        let glide: Vec<f32> = (0..GLIDE_SIZE / 4).map(|i| i as f32).collect();
+        push_f32s(&mut data, &glide);
+
+        // ECB list
+        data.extend(i32::to_be_bytes(0x108));
+        data.extend(i32::to_be_bytes(3));
+        for pointer in [0x114, 0x12c, 0x13c] {
+            data.extend(i32::to_be_bytes(pointer));
+        }
+
+        // type 0: bones, values
+        data.extend(i32::to_be_bytes(0));
+        data.extend(i32::to_be_bytes(0x158));
+        data.extend(i32::to_be_bytes(2));
+        push_f32s(&mut data, &[10.0, 4.0, 0.0]);
+        // type 1: values
+        data.extend(i32::to_be_bytes(1));
+        push_f32s(&mut data, &[11.0, 5.0, 0.0]);
+        // type 2: flags, bones, values
+        data.extend(i32::to_be_bytes(2));
+        data.extend(u32::to_be_bytes(0x80));
+        data.extend(i32::to_be_bytes(0x158));
+        data.extend(i32::to_be_bytes(1));
+        push_f32s(&mut data, &[12.0, 6.0, 0.0]);
+        assert_eq!(data.len(), 0x158);
+
+        // ECB bones
+        data.extend(i32::to_be_bytes(3));
+        data.extend(i32::to_be_bytes(7));
+        data
+    }
+
+    #[test]
+    fn parse_misc_section() {
+        let data = misc_fixture();
+        let data = FancySlice::new(&data);
+        let misc = misc_section(data, data).unwrap();
+
+        let multi_jump = misc.multi_jump.unwrap();
+        assert_eq!(
+            [multi_jump.unk0, multi_jump.unk1, multi_jump.unk2],
+            [1.0, 2.0, 3.0]
+        );
+        assert_eq!(multi_jump.horizontal_boost, 0.5);
+        assert_eq!(multi_jump.hops, [2.5, 2.0]);
+        assert_eq!(multi_jump.unks, [0.25, 0.75]);
+        assert_eq!(multi_jump.turn_frames, 4.0);
+
+        let glide = misc.glide.unwrap();
+        assert_eq!(glide.values[0], 0.0);
+        assert_eq!(
+            glide.values[GLIDE_SIZE / 4 - 1],
+            (GLIDE_SIZE / 4 - 1) as f32
+        );
+
+        assert_eq!(misc.ecbs.len(), 3);
+        assert!(matches!(misc.ecbs[0].kind, ECBKind::Bones));
+        assert_eq!(misc.ecbs[0].bones, [3, 7]);
+        assert_eq!(
+            (misc.ecbs[0].min_height, misc.ecbs[0].min_width),
+            (10.0, 4.0)
+        );
+        assert!(matches!(misc.ecbs[1].kind, ECBKind::Fixed));
+        assert!(misc.ecbs[1].bones.is_empty());
+        assert_eq!(
+            (misc.ecbs[1].min_height, misc.ecbs[1].min_width),
+            (11.0, 5.0)
+        );
+        assert!(matches!(
+            misc.ecbs[2].kind,
+            ECBKind::BonesWithFlags { flags: 0x80 }
+        ));
+        assert_eq!(misc.ecbs[2].bones, [3]);
+        assert_eq!(
+            (misc.ecbs[2].min_height, misc.ecbs[2].min_width),
+            (12.0, 6.0)
+        );
+    }
+
+    #[test]
+    fn compile_unmodified_misc_section() {
+        let data = misc_fixture();
+        let misc = misc_section(FancySlice::new(&data), FancySlice::new(&data)).unwrap();
+
+        let mut writer = SakuraiWriter::new(data.clone(), vec![]);
+        misc.compile(&mut writer, 0);
+        assert_eq!(writer.data, data);
+    }
+
+    #[test]
+    fn compile_modified_misc_section() {
+        let data = misc_fixture();
+        let mut misc = misc_section(FancySlice::new(&data), FancySlice::new(&data)).unwrap();
+        misc.multi_jump.as_mut().unwrap().hops[1] = 1.5;
+        misc.glide.as_mut().unwrap().values[3] = -1.0;
+        for ecb in &mut misc.ecbs {
+            ecb.min_height = 20.0;
+        }
+
+        let mut writer = SakuraiWriter::new(data.clone(), vec![]);
+        misc.compile(&mut writer, 0);
+        let compiled =
+            misc_section(FancySlice::new(&writer.data), FancySlice::new(&writer.data)).unwrap();
+
+        assert_eq!(compiled.multi_jump.unwrap().hops, [2.5, 1.5]);
+        assert_eq!(compiled.glide.unwrap().values[3], -1.0);
+        // only type 0 ECBs are written back
+        assert_eq!(compiled.ecbs[0].min_height, 20.0);
+        assert_eq!(compiled.ecbs[1].min_height, 11.0);
+        assert_eq!(compiled.ecbs[2].min_height, 12.0);
+    }
+}
@@ -1,5 +1,9 @@
 pub mod misc_section;
 
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
+use crate::sakurai::ScriptSlot;
+use crate::sakurai::article::{self, Article};
+use crate::sakurai::writer::SakuraiWriter;
 use crate::script;
 use crate::script::Script;
 use crate::util;
@@ -9,7 +13,8 @@ use misc_section::MiscSection;
 use fancy_slice::FancySlice;
 
 #[rustfmt::skip]
-pub(crate) fn arc_fighter_data(parent_data: FancySlice, data: FancySlice, wii_memory: &WiiMemory) -> ArcFighterData {
+pub(crate) fn arc_fighter_data(parent_data: FancySlice, data: FancySlice, wii_memory: &WiiMemory) -> Result<ArcFighterData, ParseError> {
+    check_len(data, ARC_FIGHTER_DATA_HEADER_SIZE, "fighter data")?;
     let subaction_flags_start        = data.i32_be(0);
     let model_visibility_start       = data.i32_be(4);
     let attribute_start              = data.i32_be(8);
@@ -35,47 +40,91 @@ pub(crate) fn arc_fighter_data(parent_data: FancySlice, data: FancySlice, wii_me
     let _unknown1                    = data.i32_be(88);
     let _samus_arm_cannon_positions  = data.i32_be(92);
     let _unknown2                    = data.i32_be(96);
-    let _static_articles_start       = data.i32_be(100);
-    let _entry_articles_start        = data.i32_be(104);
+    let static_articles_start        = data.i32_be(100);
+    let entry_article_start          = data.i32_be(104);
     let _flags1                      = data.u32_be(116);
     let _flags2                      = data.i32_be(120);
 
     let sizes = get_sizes(data);
+    let table_size = |start: i32, structure: &'static str| {
+        sizes.iter().find(|x| x.offset == start as usize).map(|x| x.size).ok_or_else(|| {
+            ParseError::new(structure, start.max(0) as usize, ParseErrorKind::Malformed(String::from("the size of the table could not be determined")))
+        })
+    };
 
-    let subaction_flags_num = sizes.iter().find(|x| x.offset == subaction_flags_start as usize).unwrap().size / SUB_ACTION_FLAGS_SIZE;
-    let subaction_flags = subaction_flags(parent_data, parent_data.relative_fancy_slice(subaction_flags_start as usize ..), subaction_flags_num);
+    let subaction_flags_num = table_size(subaction_flags_start, "subaction flags")? / SUB_ACTION_FLAGS_SIZE;
+    let subaction_flags = subaction_flags(parent_data, subaction_flags_start as usize, subaction_flags_num + 1)?;
 
-    let model_visibility = model_visibility(parent_data, model_visibility_start);
+    let model_visibility = model_visibility(parent_data, model_visibility_start)?;
 
-    let action_flags_num = sizes.iter().find(|x| x.offset == action_flags_start as usize).unwrap().size / ACTION_FLAGS_SIZE;
-    let action_flags = action_flags(parent_data.relative_fancy_slice(action_flags_start as usize ..), action_flags_num);
+    let action_flags_num = table_size(action_flags_start, "action flags")? / ACTION_FLAGS_SIZE;
+    let action_flags = action_flags(parent_data, action_flags_start as usize, action_flags_num)?;
 
-    let entry_actions_num = sizes.iter().find(|x| x.offset == entry_actions_start as usize).unwrap().size / 4; // divide by integer size
-    let entry_actions = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(entry_actions_start as usize ..), entry_actions_num, wii_memory);
-    let exit_actions = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(exit_actions_start as usize ..), entry_actions_num, wii_memory);
+    let entry_actions_num = table_size(entry_actions_start, "entry actions")? / 4; // divide by integer size
+    let entry_actions = script::scripts(parent_data, entry_actions_start as usize, entry_actions_num, wii_memory)?;
+    let exit_actions = script::scripts(parent_data, exit_actions_start as usize, entry_actions_num, wii_memory)?;
 
-    let subaction_main_num = sizes.iter().find(|x| x.offset == subaction_main_start as usize).unwrap().size / 4; // divide by integer size
-    let subaction_main = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(subaction_main_start as usize ..), subaction_main_num, wii_memory);
-    let subaction_gfx = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(subaction_gfx_start as usize ..), subaction_main_num, wii_memory);
-    let subaction_sfx = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(subaction_sfx_start as usize ..), subaction_main_num, wii_memory);
-    let subaction_other = script::scripts(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(subaction_other_start as usize ..), subaction_main_num, wii_memory);
+    let subaction_main_num = table_size(subaction_main_start, "subaction main")? / 4; // divide by integer size
+    let subaction_main = script::scripts(parent_data, subaction_main_start as usize, subaction_main_num, wii_memory)?;
+    let subaction_gfx = script::scripts(parent_data, subaction_gfx_start as usize, subaction_main_num, wii_memory)?;
+    let subaction_sfx = script::scripts(parent_data, subaction_sfx_start as usize, subaction_main_num, wii_memory)?;
+    let subaction_other = script::scripts(parent_data, subaction_other_start as usize, subaction_main_num, wii_memory)?;
 
-    let attributes = fighter_attributes(parent_data.relative_fancy_slice(attribute_start as usize ..));
-    let misc = misc_section::misc_section(parent_data.relative_fancy_slice(misc_section_offset as usize ..), parent_data);
+    let attributes = fighter_attributes(try_offset(&parent_data, attribute_start as i64, FIGHTER_ATTRIBUTES_SIZE, "fighter attributes")?);
+    let misc_data = try_offset(&parent_data, misc_section_offset as i64, misc_section::MISC_SECTION_HEADER_SIZE, "misc section")?;
+    let misc = misc_section::misc_section(misc_data, parent_data)?;
 
     let entry_action_overrides = if entry_action_overrides_start != 0 {
-        action_overrides(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(entry_action_overrides_start as usize ..), wii_memory)
+        action_overrides(parent_data, entry_action_overrides_start as usize, wii_memory)?
     } else {
         vec!()
     };
 
     let exit_action_overrides = if exit_action_overrides_start != 0 {
-        action_overrides(parent_data.relative_fancy_slice(..), parent_data.relative_fancy_slice(exit_action_overrides_start as usize ..), wii_memory)
+        action_overrides(parent_data, exit_action_overrides_start as usize, wii_memory)?
     } else {
         vec!()
     };
 
-    ArcFighterData {
+    // The article tables are interleaved with the rest of the fighter data, so every known offset is needed to find where they end.
+    let mut boundaries: Vec<usize> = sizes.iter().map(|x| x.offset).collect();
+    let mut static_article_offsets = vec!();
+    if static_articles_start != 0 {
+        let list = util::list_offset(&parent_data, static_articles_start as usize, "static articles")?;
+        for offset in util::list_offsets(parent_data, &list, 4, "static articles")? {
+            let offset = parent_data.i32_be(offset) as usize;
+            if offset != 0 {
+                static_article_offsets.push(offset);
+            }
+        }
+        boundaries.push(static_articles_start as usize);
+        boundaries.push(list.start_offset as usize);
+    }
+    for offset in &static_article_offsets {
+        boundaries.extend(article::article_offsets(parent_data, *offset));
+    }
+    if entry_article_start != 0 {
+        boundaries.extend(article::article_offsets(parent_data, entry_article_start as usize));
+    }
+
+    let mut static_articles = vec!();
+    for offset in &static_article_offsets {
+        match article::article(parent_data, *offset, &boundaries, wii_memory)? {
+            Some(article) => static_articles.push(article),
+            None => error!("Static article at 0x{:x} is malformed", offset),
+        }
+    }
+    let entry_article = if entry_article_start != 0 {
+        let article = article::article(parent_data, entry_article_start as usize, &boundaries, wii_memory)?;
+        if article.is_none() {
+            error!("Entry article at 0x{:x} is malformed", entry_article_start);
+        }
+        article
+    } else {
+        None
+    };
+
+    Ok(ArcFighterData {
         subaction_flags,
         attributes,
         misc,
@@ -97,12 +146,24 @@ pub(crate) fn arc_fighter_data(parent_data: FancySlice, data: FancySlice, wii_me
         _bone_ref2,
         entry_action_overrides,
         exit_action_overrides,
+        static_articles,
+        entry_article,
         _samus_arm_cannon_positions,
-        _static_articles_start,
-        _entry_articles_start,
         _flags1,
         _flags2,
-    }
+        subaction_flags_start,
+        attribute_start,
+        misc_section_offset,
+        action_flags_start,
+        entry_actions_start,
+        exit_actions_start,
+        subaction_main_start,
+        subaction_gfx_start,
+        subaction_sfx_start,
+        subaction_other_start,
+        entry_action_overrides_start,
+        exit_action_overrides_start,
+    })
 }
 
 #[rustfmt::skip]
@@ -200,7 +261,104 @@ fn fighter_attributes(data: FancySlice) -> FighterAttributes {
     }
 }
 
-const _ARC_FIGHTER_DATA_HEADER_SIZE: usize = 0x7c;
+/// Writes the attributes over the top of the original attributes at `offset`, leaving the unknown values untouched
+#[rustfmt::skip]
+fn compile_fighter_attributes(attributes: &FighterAttributes, writer: &mut SakuraiWriter, offset: usize) {
+    writer.write_f32(offset,        attributes.walk_init_vel);
+    writer.write_f32(offset + 0x04, attributes.walk_acc);
+    writer.write_f32(offset + 0x08, attributes.walk_max_vel);
+    writer.write_f32(offset + 0x0c, attributes.ground_friction);
+    writer.write_f32(offset + 0x10, attributes.dash_init_vel);
+    writer.write_f32(offset + 0x14, attributes.dash_run_acc_a);
+    writer.write_f32(offset + 0x18, attributes.dash_run_acc_b);
+    writer.write_f32(offset + 0x1c, attributes.dash_run_term_vel);
+    writer.write_f32(offset + 0x24, attributes.grounded_max_x_vel);
+    writer.write_i32(offset + 0x28, attributes.dash_cancel_frame_window);
+    writer.write_f32(offset + 0x2c, attributes.guard_on_max_momentum);
+    writer.write_i32(offset + 0x30, attributes.jump_squat_frames);
+    writer.write_f32(offset + 0x34, attributes.jump_x_init_vel);
+    writer.write_f32(offset + 0x38, attributes.jump_y_init_vel);
+    writer.write_f32(offset + 0x3c, attributes.jump_x_vel_ground_mult);
+    writer.write_f32(offset + 0x40, attributes.jump_x_init_term_vel);
+    writer.write_f32(offset + 0x44, attributes.jump_y_init_vel_short);
+    writer.write_f32(offset + 0x48, attributes.air_jump_x_mult);
+    writer.write_f32(offset + 0x4c, attributes.air_jump_y_mult);
+    writer.write_f32(offset + 0x50, attributes.footstool_init_vel);
+    writer.write_f32(offset + 0x54, attributes.footstool_init_vel_short);
+    writer.write_f32(offset + 0x5c, attributes.meteor_cancel_delay);
+    writer.write_u32(offset + 0x60, attributes.num_jumps);
+    writer.write_f32(offset + 0x64, attributes.gravity);
+    writer.write_f32(offset + 0x68, attributes.term_vel);
+    writer.write_f32(offset + 0x6c, attributes.air_friction_y);
+    writer.write_f32(offset + 0x70, attributes.air_y_term_vel);
+    writer.write_f32(offset + 0x74, attributes.air_mobility_a);
+    writer.write_f32(offset + 0x78, attributes.air_mobility_b);
+    writer.write_f32(offset + 0x7c, attributes.air_x_term_vel);
+    writer.write_f32(offset + 0x80, attributes.air_friction_x);
+    writer.write_f32(offset + 0x84, attributes.fastfall_velocity);
+    writer.write_f32(offset + 0x88, attributes.air_x_term_vel_hard);
+    writer.write_u32(offset + 0x8c, attributes.glide_frame_window);
+    writer.write_f32(offset + 0x94, attributes.jab2_window);
+    writer.write_f32(offset + 0x98, attributes.jab3_window);
+    writer.write_f32(offset + 0x9c, attributes.ftilt2_window);
+    writer.write_f32(offset + 0xa0, attributes.ftilt3_window);
+    writer.write_f32(offset + 0xa4, attributes.fsmash2_window);
+    writer.write_f32(offset + 0xa8, attributes.flip_dir_frame);
+    writer.write_f32(offset + 0xb0, attributes.weight);
+    writer.write_f32(offset + 0xb4, attributes.size);
+    writer.write_f32(offset + 0xb8, attributes.results_screen_size);
+    writer.write_f32(offset + 0xc4, attributes.shield_size);
+    writer.write_f32(offset + 0xc8, attributes.shield_break_vel);
+    writer.write_f32(offset + 0xcc, attributes.shield_strength);
+    writer.write_f32(offset + 0xd4, attributes.respawn_platform_size);
+    writer.write_f32(offset + 0xf4, attributes.edge_jump_x_vel);
+    writer.write_f32(offset + 0xfc, attributes.edge_jump_y_vel);
+    writer.write_f32(offset + 0x118, attributes.item_throw_strength);
+    writer.write_f32(offset + 0x128, attributes.projectile_item_move_speed);
+    writer.write_f32(offset + 0x12c, attributes.projectile_item_move_speed_dash_f);
+    writer.write_f32(offset + 0x130, attributes.projectile_item_move_speed_dash_b);
+    writer.write_f32(offset + 0x138, attributes.light_landing_lag);
+    writer.write_f32(offset + 0x13c, attributes.normal_landing_lag);
+    writer.write_f32(offset + 0x140, attributes.nair_landing_lag);
+    writer.write_f32(offset + 0x144, attributes.fair_landing_lag);
+    writer.write_f32(offset + 0x148, attributes.bair_landing_lag);
+    writer.write_f32(offset + 0x14c, attributes.uair_landing_lag);
+    writer.write_f32(offset + 0x150, attributes.dair_landing_lag);
+    writer.write_u32(offset + 0x154, attributes.term_vel_hard_frames);
+    writer.write_u32(offset + 0x158, attributes.hip_n_bone);
+    writer.write_f32(offset + 0x15c, attributes.tag_height_value);
+    writer.write_f32(offset + 0x164, attributes.walljump_x_vel);
+    writer.write_f32(offset + 0x168, attributes.walljump_y_vel);
+    writer.write_u32(offset + 0x180, attributes.lhand_n_bone);
+    writer.write_u32(offset + 0x184, attributes.rhand_n_bone);
+    writer.write_f32(offset + 0x18c, attributes.water_y_acc);
+    writer.write_f32(offset + 0x1a4, attributes.spit_star_size);
+    writer.write_u32(offset + 0x1a8, attributes.spit_star_damage);
+    writer.write_f32(offset + 0x1ac, attributes.egg_size);
+    writer.write_u32(offset + 0x1cc, attributes.hip_n_bone2);
+    writer.write_u32(offset + 0x1e0, attributes.x_rot_n_bone);
+    writer.write_f32(offset + 0x1f8, attributes.camera_initial_y_offset);
+    writer.write_f32(offset + 0x1fc, attributes.camera_size_front);
+    writer.write_f32(offset + 0x200, attributes.camera_size_back);
+    writer.write_f32(offset + 0x204, attributes.camera_size_top);
+    writer.write_f32(offset + 0x208, attributes.camera_size_bottom);
+    writer.write_f32(offset + 0x210, attributes.zoom_camera_size_front);
+    writer.write_f32(offset + 0x214, attributes.zoom_camera_size_back);
+    writer.write_f32(offset + 0x218, attributes.zoom_camera_size_top);
+    writer.write_f32(offset + 0x21c, attributes.zoom_camera_size_bottom);
+    writer.write_u32(offset + 0x220, attributes.head_n_bone);
+    writer.write_f32(offset + 0x244, attributes.pause_camera_zoom_distance);
+    // TODO: magnifying_glass_size is read from the same offset as pause_camera_zoom_distance, one of them is wrong
+
+    let mut weight_dependent_throws = writer.i32(offset + 0x2dc) as u32 & !0b1111;
+    if !attributes.weight_dependent_throw_backward { weight_dependent_throws |= 0b0001 }
+    if !attributes.weight_dependent_throw_forward  { weight_dependent_throws |= 0b0010 }
+    if !attributes.weight_dependent_throw_up       { weight_dependent_throws |= 0b0100 }
+    if !attributes.weight_dependent_throw_down     { weight_dependent_throws |= 0b1000 }
+    writer.write_u32(offset + 0x2dc, weight_dependent_throws);
+}
+
+const ARC_FIGHTER_DATA_HEADER_SIZE: usize = 0x7c;
 #[derive(Clone, Debug)]
 pub struct ArcFighterData {
     pub subaction_flags: Vec<SubactionFlags>,
@@ -216,6 +374,10 @@ pub struct ArcFighterData {
     pub model_visibility: ModelVisibility,
     pub entry_action_overrides: Vec<ActionOverride>,
     pub exit_action_overrides: Vec<ActionOverride>,
+    /// Articles that exist for the whole match e.g. projectiles
+    pub static_articles: Vec<Article>,
+    /// The article used during the fighters entrance
+    pub entry_article: Option<Article>,
     _sse_attribute_start: i32,
     _common_action_flags_start: i32,
     _action_interrupts: i32,
@@ -225,12 +387,102 @@ pub struct ArcFighterData {
     _bone_ref1: i32,
     _bone_ref2: i32,
     _samus_arm_cannon_positions: i32,
-    _static_articles_start: i32,
-    _entry_articles_start: i32,
     _flags1: u32,
     _flags2: i32,
+    subaction_flags_start: i32,
+    attribute_start: i32,
+    misc_section_offset: i32,
+    action_flags_start: i32,
+    entry_actions_start: i32,
+    exit_actions_start: i32,
+    subaction_main_start: i32,
+    subaction_gfx_start: i32,
+    subaction_sfx_start: i32,
+    subaction_other_start: i32,
+    entry_action_overrides_start: i32,
+    exit_action_overrides_start: i32,
+}
+
+impl ArcFighterData {
+    /// Writes the parsed data over the top of the original data.
+    /// Scripts are handled separately via `ArcFighterData::script_slots`
+    pub(crate) fn compile(&self, writer: &mut SakuraiWriter) {
+        compile_fighter_attributes(&self.attributes, writer, self.attribute_start as usize);
+        self.misc.compile(writer, self.misc_section_offset as usize);
+
+        // The names are left untouched as we have no way to allocate new strings
+        for (i, flags) in self.subaction_flags.iter().enumerate() {
+            let offset = self.subaction_flags_start as usize + i * SUB_ACTION_FLAGS_SIZE;
+            writer.write_u8(offset, flags.in_translation_time);
+            writer.write_u8(offset + 1, flags.animation_flags.bits());
+        }
+
+        for (i, flags) in self.action_flags.iter().enumerate() {
+            let offset = self.action_flags_start as usize + i * ACTION_FLAGS_SIZE;
+            writer.write_u32(offset, flags.flag1);
+            writer.write_u32(offset + 0x4, flags.flag2);
+            writer.write_u32(offset + 0x8, flags.flag3);
+            writer.write_u32(offset + 0xc, flags.flag4);
+        }
+
+        for article in self.articles() {
+            article.compile(writer);
+        }
+    }
+
+    /// The static articles followed by the entry article
+    pub fn articles(&self) -> impl Iterator<Item = &Article> {
+        self.static_articles.iter().chain(self.entry_article.iter())
+    }
+
+    /// Returns every script along with the location of the pointer to it
+    pub(crate) fn script_slots(&self) -> Vec<ScriptSlot<'_>> {
+        let mut slots = vec![];
+        let tables = [
+            (self.entry_actions_start, &self.entry_actions),
+            (self.exit_actions_start, &self.exit_actions),
+            (self.subaction_main_start, &self.subaction_main),
+            (self.subaction_gfx_start, &self.subaction_gfx),
+            (self.subaction_sfx_start, &self.subaction_sfx),
+            (self.subaction_other_start, &self.subaction_other),
+        ];
+        for (start, scripts) in tables {
+            for (i, script) in scripts.iter().enumerate() {
+                slots.push(ScriptSlot {
+                    pointer_offset: start as usize + i * 4,
+                    script,
+                });
+            }
+        }
+
+        let overrides = [
+            (
+                self.entry_action_overrides_start,
+                &self.entry_action_overrides,
+            ),
+            (
+                self.exit_action_overrides_start,
+                &self.exit_action_overrides,
+            ),
+        ];
+        for (start, overrides) in overrides {
+            for (i, action_override) in overrides.iter().enumerate() {
+                slots.push(ScriptSlot {
+                    pointer_offset: start as usize + i * OVERRIDE_SIZE + 4,
+                    script: &action_override.script,
+                });
+            }
+        }
+
+        for article in self.articles() {
+            slots.extend(article.script_slots());
+        }
+
+        slots
+    }
 }
 
+pub(crate) const FIGHTER_ATTRIBUTES_SIZE: usize = 0x2e0;
 #[derive(Serialize, Clone, Debug)]
 pub struct FighterAttributes {
     pub walk_init_vel: f32,
@@ -340,9 +592,18 @@ bitflags! {
     }
 }
 
-fn subaction_flags(parent_data: FancySlice, data: FancySlice, num: usize) -> Vec<SubactionFlags> {
+pub(crate) fn subaction_flags(
+    parent_data: FancySlice,
+    start: usize,
+    num: usize,
+) -> Result<Vec<SubactionFlags>, ParseError> {
+    let data = try_offset(
+        &parent_data,
+        start as i64,
+        num * SUB_ACTION_FLAGS_SIZE,
+        "subaction flags",
+    )?;
     let mut result = vec![];
-    let num = num + 1;
     for i in 0..num {
         let in_translation_time = data.u8(i * SUB_ACTION_FLAGS_SIZE);
         let animation_flags_int = data.u8(i * SUB_ACTION_FLAGS_SIZE + 1);
@@ -353,7 +614,7 @@ fn subaction_flags(parent_data: FancySlice, data: FancySlice, num: usize) -> Vec
         let name = if string_offset == 0 {
             String::new()
         } else {
-            parent_data.str(string_offset as usize).unwrap().to_string()
+            try_str(&parent_data, string_offset as usize, "subaction name")?.to_string()
         };
 
         result.push(SubactionFlags {
@@ -362,10 +623,10 @@ fn subaction_flags(parent_data: FancySlice, data: FancySlice, num: usize) -> Vec
             name,
         });
     }
-    result
+    Ok(result)
 }
 
-const SUB_ACTION_FLAGS_SIZE: usize = 0x8;
+pub(crate) const SUB_ACTION_FLAGS_SIZE: usize = 0x8;
 #[derive(Clone, Debug)]
 pub struct SubactionFlags {
     pub in_translation_time: u8,
@@ -373,11 +634,20 @@ pub struct SubactionFlags {
     pub name: String,
 }
 
-fn model_visibility(parent_data: FancySlice, model_visibility_start: i32) -> ModelVisibility {
-    let reference_offset = parent_data.i32_be(model_visibility_start as usize);
-    let bone_switch_count = parent_data.i32_be(model_visibility_start as usize + 0x04);
-    let defaults_offset = parent_data.i32_be(model_visibility_start as usize + 0x08);
-    let defaults_count = parent_data.i32_be(model_visibility_start as usize + 0x0c);
+pub(crate) fn model_visibility(
+    parent_data: FancySlice,
+    model_visibility_start: i32,
+) -> Result<ModelVisibility, ParseError> {
+    let header = try_offset(
+        &parent_data,
+        model_visibility_start as i64,
+        0x10,
+        "model visibility",
+    )?;
+    let reference_offset = header.i32_be(0x00);
+    let bone_switch_count = header.i32_be(0x04);
+    let defaults_offset = header.i32_be(0x08);
+    let defaults_count = header.i32_be(0x0c);
 
     let mut references = vec![];
     if reference_offset != 0 {
@@ -391,37 +661,58 @@ fn model_visibility(parent_data: FancySlice, model_visibility_start: i32) -> Mod
             error!(
                 "Oh no the reference_count calculation is messed up, please handle this case properly"
             );
-            return ModelVisibility {
+            return Ok(ModelVisibility {
                 references: vec![],
                 defaults: vec![],
-            };
+            });
         }
 
-        for reference_i in 0..reference_count as usize {
-            let bone_switch_offset = parent_data
-                .i32_be(reference_offset as usize + VISIBILITY_REFERENCE_SIZE * reference_i)
-                as usize;
+        let reference_list = util::ListOffset {
+            start_offset: reference_offset,
+            count: reference_count,
+        };
+        for reference in util::list_offsets(
+            parent_data,
+            &reference_list,
+            VISIBILITY_REFERENCE_SIZE,
+            "model visibility reference",
+        )? {
+            let bone_switch_offset = parent_data.i32_be(reference);
             let mut bone_switches = vec![];
             if bone_switch_offset != 0 {
-                for bone_switch_i in 0..bone_switch_count as usize {
+                let bone_switch_list = util::ListOffset {
+                    start_offset: bone_switch_offset,
+                    count: bone_switch_count,
+                };
+                for bone_switch in util::list_offsets(
+                    parent_data,
+                    &bone_switch_list,
+                    util::LIST_OFFSET_SIZE,
+                    "model visibility bone switch",
+                )? {
                     let visibility_group_list =
-                        util::list_offset(parent_data.relative_fancy_slice(
-                            bone_switch_offset + util::LIST_OFFSET_SIZE * bone_switch_i..,
-                        ));
+                        util::list_offset(&parent_data, bone_switch, "model visibility group")?;
                     let mut groups = vec![];
 
-                    for visibility_group_i in 0..visibility_group_list.count as usize {
-                        let bone_list = util::list_offset(parent_data.relative_fancy_slice(
-                            visibility_group_list.start_offset as usize
-                                + util::LIST_OFFSET_SIZE * visibility_group_i..,
-                        ));
-                        let mut bones = vec![];
-
-                        for bone_i in 0..bone_list.count as usize {
-                            let bone =
-                                parent_data.i32_be(bone_list.start_offset as usize + 4 * bone_i);
-                            bones.push(bone);
-                        }
+                    for visibility_group in util::list_offsets(
+                        parent_data,
+                        &visibility_group_list,
+                        util::LIST_OFFSET_SIZE,
+                        "model visibility group",
+                    )? {
+                        let bone_list = util::list_offset(
+                            &parent_data,
+                            visibility_group,
+                            "model visibility bone",
+                        )?;
+                        let bones = util::list_offsets(
+                            parent_data,
+                            &bone_list,
+                            4,
+                            "model visibility bone",
+                        )?
+                        .map(|bone| parent_data.i32_be(bone))
+                        .collect();
 
                         groups.push(VisibilityGroup { bones });
                     }
@@ -434,22 +725,28 @@ fn model_visibility(parent_data: FancySlice, model_visibility_start: i32) -> Mod
     }
 
     let mut defaults = vec![];
-    for i in 0..defaults_count as usize {
-        let switch_index =
-            parent_data.i32_be(defaults_offset as usize + VISIBILITY_DEFAULT_SIZE * i);
-        let group_index =
-            parent_data.i32_be(defaults_offset as usize + VISIBILITY_DEFAULT_SIZE * i + 4);
-
-        defaults.push(VisibilityDefault {
-            switch_index,
-            group_index,
-        });
+    if defaults_count > 0 {
+        let defaults_list = util::ListOffset {
+            start_offset: defaults_offset,
+            count: defaults_count,
+        };
+        for default in util::list_offsets(
+            parent_data,
+            &defaults_list,
+            VISIBILITY_DEFAULT_SIZE,
+            "model visibility default",
+        )? {
+            defaults.push(VisibilityDefault {
+                switch_index: parent_data.i32_be(default),
+                group_index: parent_data.i32_be(default + 4),
+            });
+        }
     }
 
-    ModelVisibility {
+    Ok(ModelVisibility {
         references,
         defaults,
-    }
+    })
 }
 
 #[derive(Clone, Debug)]
@@ -484,7 +781,17 @@ pub struct VisibilityDefault {
     pub group_index: i32,
 }
 
-fn action_flags(data: FancySlice, num: usize) -> Vec<ActionFlags> {
+pub(crate) fn action_flags(
+    parent_data: FancySlice,
+    start: usize,
+    num: usize,
+) -> Result<Vec<ActionFlags>, ParseError> {
+    let data = try_offset(
+        &parent_data,
+        start as i64,
+        num * ACTION_FLAGS_SIZE,
+        "action flags",
+    )?;
     let mut result = vec![];
     for i in 0..num {
         result.push(ActionFlags {
@@ -494,10 +801,10 @@ fn action_flags(data: FancySlice, num: usize) -> Vec<ActionFlags> {
             flag4: data.u32_be(i * ACTION_FLAGS_SIZE + 0xc),
         });
     }
-    result
+    Ok(result)
 }
 
-const ACTION_FLAGS_SIZE: usize = 0x10;
+pub(crate) const ACTION_FLAGS_SIZE: usize = 0x10;
 #[derive(Clone, Debug)]
 pub struct ActionFlags {
     pub flag1: u32,
@@ -523,11 +830,11 @@ fn get_sizes(data: FancySlice) -> Vec<OffsetSizePair> {
     pairs.sort_by_key(|x| x.offset);
 
     // fill in size for most elements
-    for i in 0..pairs.len() - 1 {
+    for i in 0..pairs.len().saturating_sub(1) {
         pairs[i].size = pairs[i + 1].offset - pairs[i].offset
     }
 
-    // Just pop the last element, so if we try to access it we get a panic
+    // The size of the last element is unknown, so it is removed to make looking it up fail
     pairs.pop();
 
     pairs
@@ -535,21 +842,27 @@ fn get_sizes(data: FancySlice) -> Vec<OffsetSizePair> {
 
 fn action_overrides(
     parent_data: FancySlice,
-    data: FancySlice,
+    start: usize,
     wii_memory: &WiiMemory,
-) -> Vec<ActionOverride> {
+) -> Result<Vec<ActionOverride>, ParseError> {
     let mut overrides = vec![];
     for i in 0..10 {
-        let action_id = data.u32_be(i * OVERRIDE_SIZE);
-        let offset = data.u32_be(i * OVERRIDE_SIZE + 4);
-        let script = script::new_script(parent_data.relative_fancy_slice(..), offset, wii_memory);
+        let data = try_offset(
+            &parent_data,
+            (start + i * OVERRIDE_SIZE) as i64,
+            OVERRIDE_SIZE,
+            "action override",
+        )?;
+        let action_id = data.u32_be(0);
+        let offset = data.u32_be(4);
+        let script = script::new_script(parent_data, offset, wii_memory)?;
 
         if action_id == !0u32 {
             break;
         }
         overrides.push(ActionOverride { action_id, script });
     }
-    overrides
+    Ok(overrides)
 }
 
 const OVERRIDE_SIZE: usize = 0x8;
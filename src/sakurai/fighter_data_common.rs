@@ -1,12 +1,15 @@
 use fancy_slice::FancySlice;
 
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_str};
+use crate::sakurai::ScriptSlot;
 use crate::script;
 use crate::script::Script;
 use crate::util;
 use crate::wii_memory::WiiMemory;
 
 #[rustfmt::skip]
-pub(crate) fn arc_fighter_data_common(parent_data: FancySlice, data: FancySlice, wii_memory: &WiiMemory) -> ArcFighterDataCommon {
+pub(crate) fn arc_fighter_data_common(parent_data: FancySlice, data: FancySlice, wii_memory: &WiiMemory) -> Result<ArcFighterDataCommon, ParseError> {
+    check_len(data, ARC_FIGHTER_DATA_COMMON_HEADER_SIZE, "fighter data common")?;
     let global_ics           = data.i32_be(0x00);
     let global_ics_sse       = data.i32_be(0x04);
     let ics                  = data.i32_be(0x08);
@@ -36,25 +39,17 @@ pub(crate) fn arc_fighter_data_common(parent_data: FancySlice, data: FancySlice,
 
     let sizes = get_sizes(data);
 
-    let entry_actions_num = sizes.iter().find(|x| x.offset == entry_actions_start as usize).unwrap().size / 4; // divide by integer size
-    let entry_actions = script::scripts(parent_data, parent_data.relative_fancy_slice(entry_actions_start as usize ..), entry_actions_num, wii_memory);
-    let exit_actions = script::scripts(parent_data, parent_data.relative_fancy_slice(exit_actions_start as usize ..), entry_actions_num, wii_memory);
+    let entry_actions_size = sizes.iter().find(|x| x.offset == entry_actions_start as usize).map(|x| x.size).ok_or_else(|| {
+        ParseError::new("entry actions", entry_actions_start.max(0) as usize, ParseErrorKind::Malformed(String::from("the size of the table could not be determined")))
+    })?;
+    let entry_actions_num = entry_actions_size / 4; // divide by integer size
+    let entry_actions = script::scripts(parent_data, entry_actions_start as usize, entry_actions_num, wii_memory)?;
+    let exit_actions = script::scripts(parent_data, exit_actions_start as usize, entry_actions_num, wii_memory)?;
 
-    let leg_bones_left_list = util::list_offset(parent_data.relative_fancy_slice(leg_bones as usize..));
-    let mut leg_bones_left = vec!();
-    for i in 0..leg_bones_left_list.count as usize {
-        let string_offset = parent_data.i32_be(leg_bones_left_list.start_offset as usize + i * 4);
-        leg_bones_left.push(parent_data.str(string_offset as usize).unwrap().to_string());
-    }
-
-    let leg_bones_right_list = util::list_offset(parent_data.relative_fancy_slice(leg_bones as usize + util::LIST_OFFSET_SIZE ..));
-    let mut leg_bones_right = vec!();
-    for i in 0..leg_bones_right_list.count as usize {
-        let string_offset = parent_data.i32_be(leg_bones_right_list.start_offset as usize + i * 4);
-        leg_bones_right.push(parent_data.str(string_offset as usize).unwrap().to_string());
-    }
+    let leg_bones_left = leg_bone_names(parent_data, leg_bones as usize)?;
+    let leg_bones_right = leg_bone_names(parent_data, leg_bones as usize + util::LIST_OFFSET_SIZE)?;
 
-    ArcFighterDataCommon {
+    Ok(ArcFighterDataCommon {
         global_ics,
         global_ics_sse,
         ics,
@@ -82,10 +77,22 @@ pub(crate) fn arc_fighter_data_common(parent_data: FancySlice, data: FancySlice,
         unk14,
         unk15,
         unk16,
+        entry_actions_start,
+        exit_actions_start,
+    })
+}
+
+fn leg_bone_names(parent_data: FancySlice, offset: usize) -> Result<Vec<String>, ParseError> {
+    let list = util::list_offset(&parent_data, offset, "leg bones")?;
+    let mut names = vec![];
+    for name in util::list_offsets(parent_data, &list, 4, "leg bones")? {
+        let string_offset = parent_data.i32_be(name);
+        names.push(try_str(&parent_data, string_offset as usize, "leg bone name")?.to_string());
     }
+    Ok(names)
 }
 
-const _ARC_FIGHTER_DATA_COMMON_HEADER_SIZE: usize = 0x68;
+const ARC_FIGHTER_DATA_COMMON_HEADER_SIZE: usize = 0x68;
 #[derive(Clone, Debug)]
 pub struct ArcFighterDataCommon {
     pub global_ics: i32,
@@ -115,6 +122,28 @@ pub struct ArcFighterDataCommon {
     pub unk14: i32,
     pub unk15: i32,
     pub unk16: i32,
+    entry_actions_start: i32,
+    exit_actions_start: i32,
+}
+
+impl ArcFighterDataCommon {
+    /// Returns every script along with the location of the pointer to it
+    pub(crate) fn script_slots(&self) -> Vec<ScriptSlot<'_>> {
+        let mut slots = vec![];
+        let tables = [
+            (self.entry_actions_start, &self.entry_actions),
+            (self.exit_actions_start, &self.exit_actions),
+        ];
+        for (start, scripts) in tables {
+            for (i, script) in scripts.iter().enumerate() {
+                slots.push(ScriptSlot {
+                    pointer_offset: start as usize + i * 4,
+                    script,
+                });
+            }
+        }
+        slots
+    }
 }
 
 struct OffsetSizePair {
@@ -131,17 +160,21 @@ fn get_sizes(data: FancySlice) -> Vec<OffsetSizePair> {
         }
     }
 
+    if pairs.len() < 3 {
+        return vec![];
+    }
+
     // TODO: Document WHY we modify these offsets, I just copied it from brawlbox
     pairs[2].offset = 1; // Set ICs offset to 1
     pairs.sort_by_key(|x| x.offset);
     pairs[2].offset -= 1; // Set unk4 offset to -= 1
 
     // fill in size for most elements
-    for i in 0..pairs.len() - 1 {
+    for i in 0..pairs.len().saturating_sub(1) {
         pairs[i].size = pairs[i + 1].offset - pairs[i].offset
     }
 
-    // Just pop the last element, so if we try to access it we get a panic
+    // The size of the last element is unknown, so it is removed to make looking it up fail
     pairs.pop();
 
     pairs
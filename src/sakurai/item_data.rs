@@ -1,14 +1,27 @@
+use crate::sakurai::article::{self, Article};
 use crate::wii_memory::WiiMemory;
 
 use fancy_slice::FancySlice;
 
+/// `data_offset` is the offset of the data section within `parent_data`
 pub(crate) fn arc_item_data(
-    _parent_data: FancySlice,
-    _data: FancySlice,
-    _wii_memory: &WiiMemory,
+    parent_data: FancySlice,
+    data_offset: usize,
+    wii_memory: &WiiMemory,
 ) -> ArcItemData {
-    ArcItemData {}
+    // The article layout is validated before parsing, but the tables it points to can still be garbage.
+    let article =
+        article::article(parent_data, data_offset, &[], wii_memory).unwrap_or_else(|err| {
+            error!("Failed to parse item data as an article: {}", err);
+            None
+        });
+
+    ArcItemData { article }
 }
 
 #[derive(Clone, Debug)]
-pub struct ArcItemData {}
+pub struct ArcItemData {
+    /// The scripts and flags of the item, laid out the same as the articles spawned by fighters.
+    /// None when the data section uses some other layout e.g. kirby hats.
+    pub article: Option<Article>,
+}
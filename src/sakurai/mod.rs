@@ -1,10 +1,14 @@
+pub mod article;
 pub mod fighter_data;
 pub mod fighter_data_common;
 pub mod item_data;
+mod writer;
 
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
 use crate::script;
-use crate::script::Script;
+use crate::script::{ARGUMENT_SIZE, Argument, EVENT_SIZE, Offset, Script};
 use crate::wii_memory::WiiMemory;
+use writer::SakuraiWriter;
 
 use fighter_data::ArcFighterData;
 use fighter_data_common::ArcFighterDataCommon;
@@ -12,25 +16,61 @@ use item_data::ArcItemData;
 
 use fancy_slice::FancySlice;
 
-pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool) -> ArcSakurai {
+use std::collections::{HashMap, HashSet};
+
+/// Parses a sakurai archive, returning an error instead of panicking when the data is malformed
+pub(crate) fn try_arc_sakurai(
+    data: FancySlice,
+    wii_memory: &WiiMemory,
+    item: bool,
+) -> Result<ArcSakurai, ParseError> {
+    check_len(data, ARC_SAKURAI_HEADER_SIZE, "ARC sakurai")?;
     let size = data.i32_be(0x00);
     let lookup_entry_offset = data.i32_be(0x04);
     let lookup_entry_count = data.i32_be(0x08);
     let section_count = data.i32_be(0x0c);
     let external_subroutine_count = data.i32_be(0x10);
 
+    if [
+        lookup_entry_offset,
+        lookup_entry_count,
+        section_count,
+        external_subroutine_count,
+    ]
+    .iter()
+    .any(|x| *x < 0)
+    {
+        return Err(ParseError::new(
+            "ARC sakurai",
+            0,
+            ParseErrorKind::Malformed(String::from(
+                "the header contains a negative offset or count",
+            )),
+        ));
+    }
+
     let lookup_entries_offset = ARC_SAKURAI_HEADER_SIZE + lookup_entry_offset as usize;
     let sections_offset = lookup_entries_offset + lookup_entry_count as usize * 4;
     let external_subroutines_offset =
         sections_offset + section_count as usize * ARC_SAKURAI_SECTION_HEADER_SIZE;
     let string_table_offset =
         external_subroutines_offset + external_subroutine_count as usize * EXTERNAL_SUBROUTINE_SIZE;
+    check_len(data, string_table_offset, "ARC sakurai tables")?;
 
     let parent_data = data.relative_fancy_slice(ARC_SAKURAI_HEADER_SIZE..);
+    let name = |string_offset: i32, structure| {
+        let string = try_offset(
+            &data,
+            string_table_offset as i64 + string_offset as i64,
+            0,
+            structure,
+        )?;
+        Ok::<_, ParseError>(try_str(&string, 0, structure)?.to_string())
+    };
 
     let mut lookup_entries = vec![];
     for i in 0..lookup_entry_count {
-        let offset = lookup_entry_offset as usize + i as usize * 4;
+        let offset = lookup_entries_offset + i as usize * 4;
         let entry_offset = data.i32_be(offset);
         lookup_entries.push(entry_offset);
     }
@@ -41,17 +81,28 @@ pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool)
         let offset = external_subroutines_offset + i as usize * EXTERNAL_SUBROUTINE_SIZE;
         let mut offset_linked_list = data.i32_be(offset);
         let string_offset = data.i32_be(offset + 4);
-        let name = data
-            .str(string_table_offset + string_offset as usize)
-            .unwrap()
-            .to_string();
+        let name = name(string_offset, "external subroutine name")?;
 
         // The offset_linked_list is a pointer to the offset argument used by a subroutine/goto call that is making an external call.
         // However since the value in subroutine/goto offset argument has no purpose as its an external call, it is instead used to point to another value subroutine/goto offset argument.
         // This forms a linked list between all the subroutine/goto offset arguments that make the same external call.
         while offset_linked_list > 0 && offset_linked_list < size {
+            if offsets.contains(&offset_linked_list) {
+                return Err(ParseError::new(
+                    "external subroutine",
+                    offset,
+                    ParseErrorKind::Malformed(format!("the call list of {name} loops")),
+                ));
+            }
             offsets.push(offset_linked_list);
-            offset_linked_list = data.i32_be(ARC_SAKURAI_HEADER_SIZE + offset_linked_list as usize);
+            let next = try_offset(
+                &parent_data,
+                offset_linked_list as i64,
+                4,
+                "external subroutine call",
+            )
+            .map_err(in_data)?;
+            offset_linked_list = next.i32_be(0);
         }
 
         external_subroutines.push(ExternalSubroutine { name, offsets });
@@ -62,23 +113,23 @@ pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool)
         let offset = sections_offset + i as usize * ARC_SAKURAI_SECTION_HEADER_SIZE;
         let data_offset = data.u32_be(offset);
         let string_offset = data.i32_be(offset + 4);
-        let name = data
-            .str(string_table_offset + string_offset as usize)
-            .unwrap()
-            .to_string();
+        let name = name(string_offset, "section name")?;
 
-        let data = data.relative_fancy_slice(ARC_SAKURAI_HEADER_SIZE + data_offset as usize..);
+        let section =
+            || try_offset(&parent_data, data_offset as i64, 0, "section").map_err(in_data);
         let mut section_data = match name.as_str() {
-            "data" if item => {
-                SectionData::ItemData(item_data::arc_item_data(parent_data, data, wii_memory))
-            }
-            "data" => SectionData::FighterData(fighter_data::arc_fighter_data(
+            "data" if item => SectionData::ItemData(item_data::arc_item_data(
                 parent_data,
-                data,
+                data_offset as usize,
                 wii_memory,
             )),
+            "data" => SectionData::FighterData(Box::new(
+                fighter_data::arc_fighter_data(parent_data, section()?, wii_memory)
+                    .map_err(in_data)?,
+            )),
             "dataCommon" => SectionData::FighterDataCommon(
-                fighter_data_common::arc_fighter_data_common(parent_data, data, wii_memory),
+                fighter_data_common::arc_fighter_data_common(parent_data, section()?, wii_memory)
+                    .map_err(in_data)?,
             ),
             _ => SectionData::None,
         };
@@ -90,11 +141,8 @@ pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool)
         {
             section_data = SectionData::Script(SectionScript {
                 name: name.clone(),
-                script: script::new_script(
-                    parent_data.relative_fancy_slice(..),
-                    data_offset,
-                    wii_memory,
-                ),
+                script: script::new_script(parent_data, data_offset, wii_memory)
+                    .map_err(in_data)?,
             });
         }
         sections.push(ArcSakuraiSection {
@@ -121,6 +169,9 @@ pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool)
                 for override_script in &data.exit_action_overrides {
                     all_scripts_sub.push(override_script.script.clone());
                 }
+                for article in data.articles() {
+                    all_scripts.extend(article.scripts());
+                }
             }
             SectionData::FighterDataCommon(data_common) => {
                 all_scripts.push(data_common.entry_actions.as_slice());
@@ -129,8 +180,10 @@ pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool)
             SectionData::Script(script) => {
                 all_scripts_sub.push(script.script.clone());
             }
-            SectionData::ItemData(_item_data) => {
-                // TODO
+            SectionData::ItemData(item_data) => {
+                if let Some(article) = &item_data.article {
+                    all_scripts.extend(article.scripts());
+                }
             }
             _ => {}
         }
@@ -142,28 +195,353 @@ pub(crate) fn arc_sakurai(data: FancySlice, wii_memory: &WiiMemory, item: bool)
         .flat_map(|x| x.offsets.iter().cloned())
         .collect();
     let mut fragment_scripts = script::fragment_scripts(
-        parent_data.relative_fancy_slice(..),
+        parent_data,
         all_scripts.as_slice(),
         ignore_origins.as_slice(),
         wii_memory,
-    );
+    )
+    .map_err(in_data)?;
     fragment_scripts.sort_by_key(|x| x.offset);
 
-    ArcSakurai {
-        _lookup_entries: lookup_entries,
+    let raw = data
+        .relative_slice(..(size as usize).min(data.len()))
+        .to_vec();
+
+    Ok(ArcSakurai {
+        lookup_entries,
         sections,
         external_subroutines,
         fragment_scripts,
-    }
+        raw,
+    })
+}
+
+/// Makes an error offset relative to the data section relative to the start of the archive instead
+fn in_data(err: ParseError) -> ParseError {
+    err.offset_by(ARC_SAKURAI_HEADER_SIZE)
 }
 
 const ARC_SAKURAI_HEADER_SIZE: usize = 0x20;
 #[derive(Clone, Debug)]
 pub struct ArcSakurai {
-    _lookup_entries: Vec<i32>,
+    lookup_entries: Vec<i32>,
     pub sections: Vec<ArcSakuraiSection>,
     pub external_subroutines: Vec<ExternalSubroutine>,
     pub fragment_scripts: Vec<Script>,
+    /// We dont parse everything yet, so compiling writes over the top of the original data
+    raw: Vec<u8>,
+}
+
+impl ArcSakurai {
+    /// Compiles the sakurai archive, this is the first child of a Fit{}.pac `Arc`.
+    ///
+    /// Most data is written over the top of the original data.
+    /// Scripts that have been modified are written to the end of the data section and everything pointing to them is updated.
+    /// The original copies of modified scripts are left in place unused.
+    pub fn compile(&self) -> Vec<u8> {
+        let raw = FancySlice::new(&self.raw);
+        let size = raw.i32_be(0x00);
+        let lookup_entry_offset = raw.i32_be(0x04);
+        let section_count = raw.i32_be(0x0c) as usize;
+        let external_subroutine_count = raw.i32_be(0x10) as usize;
+
+        let lookup_entries_offset = ARC_SAKURAI_HEADER_SIZE + lookup_entry_offset as usize;
+        let sections_offset = lookup_entries_offset + self.lookup_entries.len() * 4;
+        let external_subroutines_offset =
+            sections_offset + section_count * ARC_SAKURAI_SECTION_HEADER_SIZE;
+        let string_table_offset =
+            external_subroutines_offset + external_subroutine_count * EXTERNAL_SUBROUTINE_SIZE;
+
+        let parent_data = raw.relative_fancy_slice(ARC_SAKURAI_HEADER_SIZE..lookup_entries_offset);
+        let mut writer = SakuraiWriter::new(
+            parent_data.relative_slice(..).to_vec(),
+            self.lookup_entries.clone(),
+        );
+
+        let mut script_slots = vec![];
+        let mut section_scripts = vec![];
+        for (i, section) in self.sections.iter().enumerate() {
+            match &section.data {
+                SectionData::FighterData(data) => {
+                    data.compile(&mut writer);
+                    script_slots.extend(data.script_slots());
+                }
+                SectionData::FighterDataCommon(data) => {
+                    script_slots.extend(data.script_slots());
+                }
+                SectionData::ItemData(data) => {
+                    if let Some(article) = &data.article {
+                        article.compile(&mut writer);
+                        script_slots.extend(article.script_slots());
+                    }
+                }
+                SectionData::Script(script) => section_scripts.push((i, &script.script)),
+                _ => {}
+            }
+        }
+
+        // Scripts that are not pointed to by a table can be pointed to by any number of offset arguments.
+        // So we move them all at once.
+        let mut relocations = HashMap::new();
+        let mut to_write = vec![];
+        let global_scripts = section_scripts
+            .iter()
+            .map(|(_, script)| *script)
+            .chain(self.fragment_scripts.iter());
+        for script in global_scripts {
+            if !script_unchanged(parent_data, script) && !relocations.contains_key(&script.offset) {
+                let new_offset = writer.allocate(script_size(script));
+                relocations.insert(script.offset, new_offset as i32);
+                to_write.push((script, new_offset));
+            }
+        }
+
+        // Table entries can share a script, so we give each modified table entry its own copy
+        let mut slot_copies: Vec<(&Script, usize)> = vec![];
+        for slot in &script_slots {
+            if script_unchanged(parent_data, slot.script) {
+                continue;
+            }
+            let existing = slot_copies
+                .iter()
+                .find(|(script, _)| script.offset == slot.script.offset && *script == slot.script);
+            let new_offset = if let Some((_, new_offset)) = existing {
+                *new_offset
+            } else {
+                let new_offset = writer.allocate(script_size(slot.script));
+                slot_copies.push((slot.script, new_offset));
+                to_write.push((slot.script, new_offset));
+                new_offset
+            };
+            writer.write_pointer(slot.pointer_offset, new_offset as i32);
+        }
+
+        let external_origins: HashSet<i32> = self
+            .external_subroutines
+            .iter()
+            .flat_map(|x| x.offsets.iter().cloned())
+            .collect();
+        let mut moved_external_origins: HashMap<i32, Vec<i32>> = HashMap::new();
+        for (script, new_offset) in &to_write {
+            write_script(
+                &mut writer,
+                script,
+                *new_offset,
+                &relocations,
+                &external_origins,
+                &mut moved_external_origins,
+            );
+        }
+
+        // update the offset arguments of unmodified scripts that point to moved scripts
+        if !relocations.is_empty() {
+            let unchanged_scripts = script_slots
+                .iter()
+                .map(|x| x.script)
+                .chain(section_scripts.iter().map(|(_, script)| *script))
+                .chain(self.fragment_scripts.iter())
+                .filter(|script| script_unchanged(parent_data, script));
+            for script in unchanged_scripts {
+                for event in &script.events {
+                    for argument in &event.arguments {
+                        if let Argument::Offset(Offset { offset, origin }) = argument
+                            && let Some(new_offset) = relocations.get(offset)
+                            && !external_origins.contains(origin)
+                            && *origin > 0
+                            && (*origin as usize) + 4 <= parent_data.len()
+                        {
+                            writer.write_i32(*origin as usize, *new_offset);
+                        }
+                    }
+                }
+            }
+        }
+
+        while writer.data.len() % 4 != 0 {
+            writer.data.push(0x00);
+        }
+
+        // create sakurai header
+        let mut output = Vec::with_capacity(self.raw.len());
+        output.extend(raw.relative_slice(..ARC_SAKURAI_HEADER_SIZE));
+        output[0x04..0x08].copy_from_slice(&i32::to_be_bytes(writer.data.len() as i32));
+        output[0x08..0x0c].copy_from_slice(&i32::to_be_bytes(writer.lookup_entries.len() as i32));
+
+        output.extend(&writer.data);
+
+        for lookup_entry in &writer.lookup_entries {
+            output.extend(i32::to_be_bytes(*lookup_entry));
+        }
+
+        for i in 0..section_count {
+            let offset = sections_offset + i * ARC_SAKURAI_SECTION_HEADER_SIZE;
+            let data_offset = section_scripts
+                .iter()
+                .find(|(section_i, _)| *section_i == i)
+                .and_then(|(_, script)| relocations.get(&script.offset))
+                .cloned()
+                .unwrap_or_else(|| raw.i32_be(offset));
+            output.extend(i32::to_be_bytes(data_offset));
+            output.extend(raw.relative_slice(offset + 4..offset + ARC_SAKURAI_SECTION_HEADER_SIZE));
+        }
+
+        for (i, external_subroutine) in self.external_subroutines.iter().enumerate() {
+            let offset = external_subroutines_offset + i * EXTERNAL_SUBROUTINE_SIZE;
+            let mut head = raw.i32_be(offset);
+
+            // Add the external calls in moved scripts to the linked list of calls
+            let mut chain = external_subroutine.offsets.clone();
+            for origin in &external_subroutine.offsets {
+                if let Some(moved) = moved_external_origins.get(origin) {
+                    chain.extend(moved);
+                }
+            }
+            if chain.len() != external_subroutine.offsets.len() {
+                let last = *external_subroutine.offsets.last().unwrap();
+                let terminator = i32::from_be_bytes(
+                    self.raw[ARC_SAKURAI_HEADER_SIZE + last as usize..][..4]
+                        .try_into()
+                        .unwrap(),
+                );
+                head = chain[0];
+                for pair in chain.windows(2) {
+                    write_i32_at(&mut output, pair[0], pair[1]);
+                }
+                write_i32_at(&mut output, *chain.last().unwrap(), terminator);
+            }
+
+            output.extend(i32::to_be_bytes(head));
+            output.extend(raw.relative_slice(offset + 4..offset + EXTERNAL_SUBROUTINE_SIZE));
+        }
+
+        output.extend(raw.relative_slice(string_table_offset..));
+
+        let new_size = size + output.len() as i32 - self.raw.len() as i32;
+        output[0x00..0x04].copy_from_slice(&i32::to_be_bytes(new_size));
+
+        output
+    }
+}
+
+/// Writes a value into a compiled sakurai archive at an offset relative to the start of the data section
+fn write_i32_at(output: &mut [u8], offset: i32, value: i32) {
+    let offset = ARC_SAKURAI_HEADER_SIZE + offset as usize;
+    output[offset..offset + 4].copy_from_slice(&i32::to_be_bytes(value));
+}
+
+/// A location in the data section that points to a script
+pub(crate) struct ScriptSlot<'a> {
+    pub pointer_offset: usize,
+    pub script: &'a Script,
+}
+
+fn script_size(script: &Script) -> usize {
+    let arguments: usize = script.events.iter().map(|x| x.arguments.len()).sum();
+    (script.events.len() + 1) * EVENT_SIZE + arguments * ARGUMENT_SIZE
+}
+
+/// Returns true if the script is identical to the script stored at `script.offset` in the original data.
+fn script_unchanged(parent_data: FancySlice, script: &Script) -> bool {
+    let offset = script.offset;
+    if offset == 0 || offset == -1 {
+        return script.events.is_empty();
+    }
+    if offset < 0 || offset as usize >= parent_data.len() {
+        // Script lives in wii memory, we cant compile these so just treat them as unchanged.
+        return true;
+    }
+
+    let mut events = script.events.iter();
+    let mut event_offset = offset as usize;
+    while event_offset + EVENT_SIZE <= parent_data.len() {
+        let namespace = parent_data.u8(event_offset);
+        let code = parent_data.u8(event_offset + 1);
+        let num_arguments = parent_data.u8(event_offset + 2) as usize;
+        let unk1 = parent_data.u8(event_offset + 3);
+        let raw_id = parent_data.u32_be(event_offset);
+        if code == 0 && namespace == 0 {
+            return events.next().is_none();
+        }
+
+        if raw_id != 0xFADEF00D && raw_id != 0xFADE0D8A {
+            let event = match events.next() {
+                Some(event) => event,
+                None => return false,
+            };
+            let argument_offset = parent_data.u32_be(event_offset + 4) as usize;
+            if argument_offset + num_arguments * ARGUMENT_SIZE > parent_data.len() {
+                // Arguments live in wii memory, we cant compile these so just treat them as unchanged.
+                return true;
+            }
+            if event.namespace != namespace
+                || event.code != code
+                || event.unk1 != unk1
+                || event.arguments.len() != num_arguments
+            {
+                return false;
+            }
+            for (i, argument) in event.arguments.iter().enumerate() {
+                let offset = argument_offset + i * ARGUMENT_SIZE;
+                let ty = parent_data.i32_be(offset);
+                let value = parent_data.i32_be(offset + 4);
+                if script::argument(ty, value, offset as i32 + 4) != *argument {
+                    return false;
+                }
+            }
+        }
+        event_offset += EVENT_SIZE;
+    }
+    false
+}
+
+/// Writes the script to `new_offset` which must already be allocated with `script_size`
+fn write_script(
+    writer: &mut SakuraiWriter,
+    script: &Script,
+    new_offset: usize,
+    relocations: &HashMap<i32, i32>,
+    external_origins: &HashSet<i32>,
+    moved_external_origins: &mut HashMap<i32, Vec<i32>>,
+) {
+    let mut argument_offset = new_offset + (script.events.len() + 1) * EVENT_SIZE;
+    for (i, event) in script.events.iter().enumerate() {
+        let event_offset = new_offset + i * EVENT_SIZE;
+        writer.write_u8(event_offset, event.namespace);
+        writer.write_u8(event_offset + 1, event.code);
+        writer.write_u8(event_offset + 2, event.arguments.len() as u8);
+        writer.write_u8(event_offset + 3, event.unk1);
+        if event.arguments.is_empty() {
+            writer.write_i32(event_offset + 4, 0);
+        } else {
+            writer.write_pointer(event_offset + 4, argument_offset as i32);
+        }
+
+        for argument in &event.arguments {
+            let (ty, value) = argument.compile();
+            let value_offset = argument_offset + 4;
+            writer.write_i32(argument_offset, ty);
+            if let Argument::Offset(Offset { offset, origin }) = argument {
+                if external_origins.contains(origin) {
+                    // The value is part of a linked list that is rebuilt later
+                    moved_external_origins
+                        .entry(*origin)
+                        .or_default()
+                        .push(value_offset as i32);
+                    writer.write_i32(value_offset, value);
+                } else {
+                    let value = relocations.get(offset).cloned().unwrap_or(value);
+                    writer.write_i32(value_offset, value);
+                    // origin is 0 when the argument was created by the user rather than parsed
+                    if value != 0 && (*origin == 0 || writer.is_pointer(*origin)) {
+                        writer.add_lookup_entry(value_offset as i32);
+                    }
+                }
+            } else {
+                writer.write_i32(value_offset, value);
+            }
+            argument_offset += ARGUMENT_SIZE;
+        }
+    }
 }
 
 const ARC_SAKURAI_SECTION_HEADER_SIZE: usize = 0x8;
@@ -177,7 +555,7 @@ pub struct ArcSakuraiSection {
 #[derive(Clone, Debug)]
 pub enum SectionData {
     ItemData(ArcItemData),
-    FighterData(ArcFighterData),
+    FighterData(Box<ArcFighterData>),
     FighterDataCommon(ArcFighterDataCommon),
     Script(SectionScript),
     None,
@@ -195,3 +573,203 @@ pub struct ExternalSubroutine {
     pub name: String,
     pub offsets: Vec<i32>,
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::arc::tests::arc_fixture;
+    use crate::arc::{ArcChildData, try_arc};
+    use crate::sakurai::fighter_data::{FIGHTER_ATTRIBUTES_SIZE, SUB_ACTION_FLAGS_SIZE};
+
+    /// A sakurai archive containing a single section at `section_offset` in the data section
+    fn archive(
+        data_section: &[u8],
+        lookup_entries: &[i32],
+        section_offset: i32,
+        section_name: &[u8],
+    ) -> Vec<u8> {
+        let mut output = vec![];
+        let size = ARC_SAKURAI_HEADER_SIZE
+            + data_section.len()
+            + lookup_entries.len() * 4
+            + ARC_SAKURAI_SECTION_HEADER_SIZE
+            + section_name.len();
+        output.extend(i32::to_be_bytes(size as i32));
+        output.extend(i32::to_be_bytes(data_section.len() as i32));
+        output.extend(i32::to_be_bytes(lookup_entries.len() as i32));
+        output.extend(i32::to_be_bytes(1)); // section_count
+        output.extend(i32::to_be_bytes(0)); // external_subroutine_count
+        output.resize(ARC_SAKURAI_HEADER_SIZE, 0);
+
+        output.extend(data_section);
+        for lookup_entry in lookup_entries {
+            output.extend(i32::to_be_bytes(*lookup_entry));
+        }
+        output.extend(i32::to_be_bytes(section_offset)); // data_offset
+        output.extend(i32::to_be_bytes(0)); // string_offset
+        output.extend(section_name);
+        output
+    }
+
+    /// The data section of the script fixtures, starts with a script at 0x8 that waits `wait * 60000` frames
+    fn script_data_section(wait: i32) -> Vec<u8> {
+        let mut data_section = vec![0; 8]; // scripts at offset 0 are treated as empty
+        data_section.extend([0x00, 0x01, 0x01, 0x00]); // synchronous timer with 1 argument
+        data_section.extend(i32::to_be_bytes(0x18)); // argument offset
+        data_section.extend([0; 8]); // end of script
+        data_section.extend(i32::to_be_bytes(1)); // scalar
+        data_section.extend(i32::to_be_bytes(wait));
+        data_section
+    }
+
+    /// A sakurai archive containing a single script section that waits 3 frames
+    fn fixture() -> Vec<u8> {
+        archive(
+            &script_data_section(3 * 60000),
+            &[0x0c],
+            0x8,
+            b"gameAnimCmd_Test\0\0\0\0",
+        )
+    }
+
+    /// A sakurai archive laid out like a Fit{}.pac, with a fighter data section that has two actions and two subactions.
+    /// The first action and subaction run the script at 0x8.
+    pub(crate) fn fighter_fixture() -> Vec<u8> {
+        let mut data_section = script_data_section(3 * 60000);
+        let mut lookup_entries = vec![0x0c];
+        data_section.resize(0x46c, 0);
+        let mut write = |offset: usize, value: i32, pointer: bool| {
+            data_section[offset..offset + 4].copy_from_slice(&i32::to_be_bytes(value));
+            if pointer {
+                lookup_entries.push(offset as i32);
+            }
+        };
+
+        // fighter data header at 0x20
+        let header = 0x20;
+        write(header, 0xa0, true); // subaction flags
+        write(header + 0x04, 0x100, true); // model visibility
+        write(header + 0x08, 0x110, true); // attributes
+        write(header + 0x10, 0x3f0, true); // misc section
+        write(header + 0x18, 0xb0, true); // action flags
+        write(header + 0x24, 0xd0, true); // entry actions
+        write(header + 0x28, 0xd8, true); // exit actions
+        for (i, start) in [0xe0, 0xe8, 0xf0, 0xf8].into_iter().enumerate() {
+            write(header + 0x30 + i * 4, start, true); // subaction main, gfx, sfx and other
+        }
+
+        // Every subaction flags entry is read, including the one after the last subaction
+        for i in 0..3 {
+            write(
+                0xa0 + i * SUB_ACTION_FLAGS_SIZE,
+                0x0403_0000 + i as i32,
+                false,
+            );
+        }
+        for i in 0..8 {
+            write(0xb0 + i * 4, i as i32 + 1, false); // action flags
+        }
+        for table in [0xd0, 0xe0] {
+            write(table, 0x8, true); // entry action 0 and subaction 0 run the script
+        }
+        for i in 0..FIGHTER_ATTRIBUTES_SIZE / 4 {
+            write(0x110 + i * 4, (i as f32 * 0.5).to_bits() as i32, false);
+        }
+
+        // misc section at 0x3f0, with empty lists and no optional data
+        write(0x3f0 + 0x24, 0x43c, true); // bone refs
+        write(0x3f0 + 0x40, 0x464, true); // ECB list
+        for i in 0..10 {
+            write(0x43c + i * 4, i as i32, false);
+        }
+
+        archive(
+            &data_section,
+            &lookup_entries,
+            header as i32,
+            b"data\0\0\0\0",
+        )
+    }
+
+    fn parse(data: &[u8]) -> ArcSakurai {
+        try_arc_sakurai(FancySlice::new(data), &WiiMemory::new(), false).unwrap()
+    }
+
+    fn section_script(sakurai: &ArcSakurai) -> &Script {
+        match &sakurai.sections[0].data {
+            SectionData::Script(script) => &script.script,
+            data => panic!("expected a script section, got {data:?}"),
+        }
+    }
+
+    #[test]
+    fn compile_unmodified() {
+        let data = fixture();
+        let sakurai = parse(&data);
+        assert_eq!(section_script(&sakurai).events.len(), 1);
+        assert_eq!(sakurai.compile(), data);
+    }
+
+    #[test]
+    fn compile_modified_script() {
+        let mut sakurai = parse(&fixture());
+        let SectionData::Script(script) = &mut sakurai.sections[0].data else {
+            unreachable!()
+        };
+        let mut event = script.script.events[0].clone();
+        event.arguments = vec![Argument::scalar(5.0)];
+        script.script.events.push(event);
+        let expected = script.script.events.clone();
+
+        let compiled = sakurai.compile();
+        let reparsed = parse(&compiled);
+        assert_eq!(section_script(&reparsed).events, expected);
+        assert_eq!(reparsed.compile(), compiled);
+    }
+
+    #[test]
+    fn compile_modified_script_keeps_scalars() {
+        // Not representable as an f32, so it would change if it was compiled from the parsed value
+        let wait = 0x7fff_fff1;
+        let data = archive(
+            &script_data_section(wait),
+            &[0x0c],
+            0x8,
+            b"gameAnimCmd_Test\0\0\0\0",
+        );
+        let mut sakurai = parse(&data);
+        let SectionData::Script(script) = &mut sakurai.sections[0].data else {
+            unreachable!()
+        };
+        let event = script.script.events[0].clone();
+        script.script.events.push(event);
+
+        let reparsed = parse(&sakurai.compile());
+        for event in &section_script(&reparsed).events {
+            assert_eq!(event.arguments[0].compile(), (1, wait));
+        }
+    }
+
+    #[test]
+    fn compile_unmodified_fighter_arc() {
+        let sakurai = fighter_fixture();
+        let motion = b"MOTN\0\0\0\0";
+
+        let data = arc_fixture("FitTest", &[&sakurai, motion]);
+
+        let arc = try_arc(FancySlice::new(&data), &WiiMemory::new(), false).unwrap();
+        let ArcChildData::Sakurai(sakurai) = &arc.children[0].data else {
+            panic!("expected the first child to be sakurai data");
+        };
+        let SectionData::FighterData(fighter_data) = &sakurai.sections[0].data else {
+            panic!("expected a fighter data section");
+        };
+        assert_eq!(fighter_data.subaction_flags.len(), 3);
+        assert_eq!(fighter_data.action_flags.len(), 2);
+        assert_eq!(fighter_data.subaction_main[0].events.len(), 1);
+        assert_eq!(fighter_data.attributes.walk_init_vel, 0.0);
+        assert_eq!(fighter_data.misc.bone_refs.unk1, 1);
+
+        assert_eq!(arc.compile().unwrap(), data);
+    }
+}
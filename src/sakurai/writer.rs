@@ -0,0 +1,79 @@
+use std::collections::HashSet;
+
+/// Modifies the data section of a sakurai archive, keeping track of which values are pointers.
+///
+/// All offsets are relative to the start of the data section, the same as the offsets stored within it.
+pub(crate) struct SakuraiWriter {
+    pub data: Vec<u8>,
+    /// Offsets of every pointer in the data section, the game relocates these when loading the file.
+    pub lookup_entries: Vec<i32>,
+    lookup_entries_set: HashSet<i32>,
+}
+
+impl SakuraiWriter {
+    pub fn new(data: Vec<u8>, lookup_entries: Vec<i32>) -> SakuraiWriter {
+        let lookup_entries_set = lookup_entries.iter().cloned().collect();
+        SakuraiWriter {
+            data,
+            lookup_entries,
+            lookup_entries_set,
+        }
+    }
+
+    pub fn i32(&self, offset: usize) -> i32 {
+        i32::from_be_bytes([
+            self.data[offset],
+            self.data[offset + 1],
+            self.data[offset + 2],
+            self.data[offset + 3],
+        ])
+    }
+
+    pub fn write_u8(&mut self, offset: usize, value: u8) {
+        self.data[offset] = value;
+    }
+
+    pub fn write_u16(&mut self, offset: usize, value: u16) {
+        self.data[offset..offset + 2].copy_from_slice(&u16::to_be_bytes(value));
+    }
+
+    pub fn write_i32(&mut self, offset: usize, value: i32) {
+        self.data[offset..offset + 4].copy_from_slice(&i32::to_be_bytes(value));
+    }
+
+    pub fn write_u32(&mut self, offset: usize, value: u32) {
+        self.data[offset..offset + 4].copy_from_slice(&u32::to_be_bytes(value));
+    }
+
+    pub fn write_f32(&mut self, offset: usize, value: f32) {
+        self.data[offset..offset + 4].copy_from_slice(&f32::to_be_bytes(value));
+    }
+
+    /// Writes an offset to elsewhere in the data section, registering it as a pointer if it isnt null.
+    pub fn write_pointer(&mut self, offset: usize, value: i32) {
+        self.write_i32(offset, value);
+        if value != 0 {
+            self.add_lookup_entry(offset as i32);
+        }
+    }
+
+    pub fn is_pointer(&self, offset: i32) -> bool {
+        self.lookup_entries_set.contains(&offset)
+    }
+
+    pub fn add_lookup_entry(&mut self, offset: i32) {
+        if self.lookup_entries_set.insert(offset) {
+            self.lookup_entries.push(offset);
+        }
+    }
+
+    /// Allocates `size` zeroed bytes at the end of the data section, returning the offset to them.
+    pub fn allocate(&mut self, size: usize) -> usize {
+        while self.data.len() % 4 != 0 {
+            self.data.push(0x00);
+        }
+        let offset = self.data.len();
+        self.data.resize(offset + size, 0x00);
+        offset
+    }
+}
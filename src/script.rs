@@ -1,19 +1,22 @@
 use fancy_slice::FancySlice;
 
+use crate::parse_error::{ParseError, check_len, try_offset};
 use crate::wii_memory::WiiMemory;
 
+/// Parses the `num` scripts pointed to by the table of offsets at `start`
 pub(crate) fn scripts(
     parent_data: FancySlice,
-    offset_data: FancySlice,
+    start: usize,
     num: usize,
     wii_memory: &WiiMemory,
-) -> Vec<Script> {
+) -> Result<Vec<Script>, ParseError> {
+    let offset_data = try_offset(&parent_data, start as i64, num * 4, "script table")?;
     let mut result = vec![];
     for i in 0..num {
         let offset = offset_data.u32_be(i * 4);
-        result.push(new_script(parent_data, offset, wii_memory));
+        result.push(new_script(parent_data, offset, wii_memory)?);
     }
-    result
+    Ok(result)
 }
 
 /// finds any scripts that are pointed to by Goto's and Subroutines but dont exist yet.
@@ -22,7 +25,7 @@ pub(crate) fn fragment_scripts(
     known_scripts: &[&[Script]],
     ignore_origins: &[i32],
     wii_memory: &WiiMemory,
-) -> Vec<Script> {
+) -> Result<Vec<Script>, ParseError> {
     let mut fragments: Vec<Script> = vec![];
     for scripts in known_scripts.iter() {
         for script in scripts.iter() {
@@ -63,7 +66,7 @@ pub(crate) fn fragment_scripts(
                     let already_added = fragments.iter().any(|x| x.offset == offset);
 
                     if !is_action && !already_added {
-                        fragments.push(new_script(parent_data, offset as u32, wii_memory));
+                        fragments.push(new_script(parent_data, offset as u32, wii_memory)?);
                     }
                 }
             }
@@ -74,32 +77,45 @@ pub(crate) fn fragment_scripts(
         // the fragment scripts may refer to their own fragment scripts
         let mut all = known_scripts.to_vec();
         all.push(&fragments);
-        let inner_fragments = fragment_scripts(parent_data, &all, ignore_origins, wii_memory);
+        let inner_fragments = fragment_scripts(parent_data, &all, ignore_origins, wii_memory)?;
         fragments.extend(inner_fragments);
     }
-    fragments
+    Ok(fragments)
 }
 
-pub fn new_script(parent_data: FancySlice, offset: u32, wii_memory: &WiiMemory) -> Script {
-    let buffer = if offset == 0 || offset as i32 == -1 {
-        return Script {
+/// Parses the script at `offset`, which is either an offset into `parent_data` or an address in `wii_memory`.
+///
+/// Returns an error if the script runs past the end of the data before reaching its terminating event.
+pub fn new_script(
+    parent_data: FancySlice,
+    offset: u32,
+    wii_memory: &WiiMemory,
+) -> Result<Script, ParseError> {
+    let (buffer, error_offset) = if offset == 0 || offset as i32 == -1 {
+        return Ok(Script {
             events: vec![],
             offset: offset as i32,
-        };
+        });
     } else if offset > 0 && offset < (parent_data.len() as u32) {
-        parent_data.relative_fancy_slice(offset as usize..)
+        (
+            parent_data.relative_fancy_slice(offset as usize..),
+            offset as usize,
+        )
     } else if offset < 0x8000_0000 {
-        return Script {
+        return Ok(Script {
             events: vec![],
             offset: offset as i32,
-        };
+        });
     } else {
-        wii_memory.fancy_slice_from(offset as usize)
+        // wii memory has no offset within the data
+        (wii_memory.fancy_slice_from(offset as usize), 0)
     };
 
     let mut events = vec![];
     let mut event_offset = 0;
     loop {
+        check_len(buffer, event_offset as usize + 4, "script")
+            .map_err(|err| err.offset_by(error_offset))?;
         let namespace = buffer.u8(event_offset as usize);
         let code = buffer.u8(event_offset as usize + 1);
         let num_arguments = buffer.u8(event_offset as usize + 2);
@@ -115,6 +131,8 @@ pub fn new_script(parent_data: FancySlice, offset: u32, wii_memory: &WiiMemory)
         // const long FADEDATA = 0xFADE0D8A; // Constant for the tag FADE0D8A representing the end of useable space.
         // const long FADEFOOD = 0xFADEF00D; // Constant for the tag FADEF00D representing empty, useable space.
         if raw_id != 0xFADEF00D && raw_id != 0xFADE0D8A {
+            check_len(buffer, event_offset as usize + EVENT_SIZE, "script")
+                .map_err(|err| err.offset_by(error_offset))?;
             let argument_offset = buffer.u32_be(event_offset as usize + 4);
 
             let argument_buffer = if argument_offset as usize >= parent_data.len() {
@@ -134,10 +152,10 @@ pub fn new_script(parent_data: FancySlice, offset: u32, wii_memory: &WiiMemory)
 
         event_offset += EVENT_SIZE as u32;
     }
-    Script {
+    Ok(Script {
         events,
         offset: offset as i32,
-    }
+    })
 }
 
 #[rustfmt::skip]
@@ -154,41 +172,51 @@ fn arguments(data: FancySlice, origin: u32, num_arguments: usize) -> Vec<Argumen
         let ty    = data.i32_be(argument_offset as usize    );
         let value = data.i32_be(argument_offset as usize + 4);
 
-        let argument = match ty {
-            0 => Argument::Value (value),
-            1 => Argument::Scalar (value as f32 / 60000.0),
-            2 => Argument::Offset (Offset { offset: value, origin: origin as i32 + argument_offset + 4}),
-            3 => Argument::Bool (value == 1),
-            4 => Argument::File (value),
-            5 => {
-                let value = value as u32;
-                let memory_type = ((value & 0xF0000000) >> 28) as u8;
-                let data_type   = ((value & 0x0F000000) >> 24) as u8;
-                let address     =  value & 0x00FFFFFF;
-
-                let memory_type = VariableMemoryType::new(memory_type);
-                let data_type = VariableDataType::new(data_type);
-
-                Argument::Variable (Variable { memory_type, data_type, address })
-            }
-            6 => Argument::new(value as u32),
-            _ => Argument::Unknown (ty, value),
-        };
+        let argument = argument(ty, value, origin as i32 + argument_offset + 4);
         arguments.push(argument);
     }
 
     arguments
 }
 
-#[derive(Clone, Debug)]
+/// `origin` is the location of the value, used to locate the value of offset arguments
+#[rustfmt::skip]
+pub(crate) fn argument(ty: i32, value: i32, origin: i32) -> Argument {
+    match ty {
+        0 => Argument::Value (value),
+        1 => Argument::Scalar (scalar_value(value), value),
+        2 => Argument::Offset (Offset { offset: value, origin }),
+        3 => Argument::Bool (value == 1),
+        4 => Argument::File (value),
+        5 => {
+            let value = value as u32;
+            let memory_type = ((value & 0xF0000000) >> 28) as u8;
+            let data_type   = ((value & 0x0F000000) >> 24) as u8;
+            let address     =  value & 0x00FFFFFF;
+
+            let memory_type = VariableMemoryType::new(memory_type);
+            let data_type = VariableDataType::new(data_type);
+
+            Argument::Variable (Variable { memory_type, data_type, address })
+        }
+        6 => Argument::new(value as u32),
+        _ => Argument::Unknown (ty, value),
+    }
+}
+
+fn scalar_value(raw: i32) -> f32 {
+    raw as f32 / 60000.0
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Script {
     pub events: Vec<Event>,
     pub offset: i32,
 }
 
 // Events are like lines of code in a script
-const EVENT_SIZE: usize = 0x8;
-#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) const EVENT_SIZE: usize = 0x8;
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Event {
     pub namespace: u8,
     pub code: u8,
@@ -204,27 +232,32 @@ impl Event {
     }
 }
 
-const ARGUMENT_SIZE: usize = 0x8;
-#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) const ARGUMENT_SIZE: usize = 0x8;
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum Argument {
     Value(i32),
-    Scalar(f32),
+    /// The value and the fixed point value it was parsed from.
+    /// The fixed point value is compiled as is while it still matches the value, so unmodified scalars compile losslessly.
+    Scalar(f32, i32),
     Offset(Offset),
     Bool(bool),
     File(i32),
     Variable(Variable),
-    Requirement { flip: bool, ty: Requirement },
+    Requirement {
+        flip: bool,
+        ty: Requirement,
+    },
     Unknown(i32, i32),
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Variable {
     pub memory_type: VariableMemoryType,
     pub data_type: VariableDataType,
     pub address: u32,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Offset {
     pub offset: i32,
     pub origin: i32,
@@ -236,7 +269,7 @@ pub enum OffsetType {
     External(String),
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum VariableMemoryType {
     /// Known as IC in existing tools
     InternalConstant,
@@ -256,9 +289,18 @@ impl VariableMemoryType {
             _ => VariableMemoryType::Unknown(value),
         }
     }
+
+    fn value(&self) -> u8 {
+        match self {
+            VariableMemoryType::InternalConstant => 0,
+            VariableMemoryType::LongtermAccess => 1,
+            VariableMemoryType::RandomAccess => 2,
+            VariableMemoryType::Unknown(value) => *value,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum VariableDataType {
     /// Known as Basic in existing tools
     Int,
@@ -278,9 +320,18 @@ impl VariableDataType {
             _ => VariableDataType::Unknown(value),
         }
     }
+
+    fn value(&self) -> u8 {
+        match self {
+            VariableDataType::Int => 0,
+            VariableDataType::Float => 1,
+            VariableDataType::Bool => 2,
+            VariableDataType::Unknown(value) => *value,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum Requirement {
     CharacterExists,
     AnimationEnd,
@@ -355,6 +406,11 @@ pub enum Requirement {
 }
 
 impl Argument {
+    /// Creates a scalar argument that compiles to the fixed point value closest to `value`
+    pub fn scalar(value: f32) -> Argument {
+        Argument::Scalar(value, (value * 60000.0).round() as i32)
+    }
+
     fn new(value: u32) -> Argument {
         let flip = value >> 31 == 1;
         let ty = match value & 0xFFFF {
@@ -425,4 +481,107 @@ impl Argument {
         };
         Argument::Requirement { ty, flip }
     }
+
+    /// Returns the type and value that the argument is stored as
+    pub(crate) fn compile(&self) -> (i32, i32) {
+        match self {
+            Argument::Value(value) => (0, *value),
+            Argument::Scalar(value, raw) => {
+                if scalar_value(*raw) == *value {
+                    (1, *raw)
+                } else {
+                    (1, (value * 60000.0).round() as i32)
+                }
+            }
+            Argument::Offset(Offset { offset, .. }) => (2, *offset),
+            Argument::Bool(value) => (3, *value as i32),
+            Argument::File(value) => (4, *value),
+            Argument::Variable(Variable {
+                memory_type,
+                data_type,
+                address,
+            }) => {
+                let value = ((memory_type.value() as u32) << 28)
+                    | ((data_type.value() as u32) << 24)
+                    | (address & 0x00FFFFFF);
+                (5, value as i32)
+            }
+            Argument::Requirement { ty, flip } => {
+                let value = ((*flip as u32) << 31) | ty.value();
+                (6, value as i32)
+            }
+            Argument::Unknown(ty, value) => (*ty, *value),
+        }
+    }
+}
+
+impl Requirement {
+    fn value(&self) -> u32 {
+        match self {
+            Requirement::CharacterExists => 0x0000,
+            Requirement::AnimationEnd => 0x0001,
+            Requirement::AnimationHasLooped => 0x0002,
+            Requirement::OnGround => 0x0003,
+            Requirement::InAir => 0x0004,
+            Requirement::HoldingALedge => 0x0005,
+            Requirement::OnAPassableFloor => 0x0006,
+            Requirement::Comparison => 0x0007,
+            Requirement::BoolIsTrue => 0x0008,
+            Requirement::FacingRight => 0x0009,
+            Requirement::FacingLeft => 0x000A,
+            Requirement::HitboxConnects => 0x000B,
+            Requirement::TouchingAFloorWallOrCeiling => 0x000C,
+            Requirement::IsThrowingSomeone => 0x000D,
+            Requirement::ButtonTap => 0x000F,
+            Requirement::EnteringOrIsInHitLag => 0x0014,
+            Requirement::ArticleExists => 0x0015,
+            Requirement::IsOversteppingAnEdge => 0x0016,
+            Requirement::HasAFloorBelowThePlayer => 0x0017,
+            Requirement::ChangeInAirGroundState => 0x001B,
+            Requirement::ArticleAvailable => 0x001C,
+            Requirement::CurrentTriggeredStatusID => 0x001D,
+            Requirement::HoldingItem => 0x001F,
+            Requirement::HoldingItemOfType => 0x0020,
+            Requirement::LightItemIsInGrabRange => 0x0021,
+            Requirement::HeavyItemIsInGrabRange => 0x0022,
+            Requirement::ItemOfTypeIsInGrabbingRange => 0x0023,
+            Requirement::TurningWithItem => 0x0024,
+            Requirement::InWater => 0x002A,
+            Requirement::RollADie => 0x002B,
+            Requirement::SubactionExists => 0x002C,
+            Requirement::ButtonMashingOrStatusExpiredSleepBuryFreeze => 0x002E,
+            Requirement::IsNotInDamagingLens => 0x002F,
+            Requirement::ButtonPress => 0x0030,
+            Requirement::ButtonRelease => 0x0031,
+            Requirement::ButtonHeld => 0x0032,
+            Requirement::ButtonNotPressed => 0x0033,
+            Requirement::StickDirectionPressed => 0x0034,
+            Requirement::StickDirectionNotPressed => 0x0035,
+            Requirement::IsBeingThrownBySomeone1 => 0x0037,
+            Requirement::IsBeingThrownBySomeone2 => 0x0038,
+            Requirement::HasntTethered3Times => 0x0039,
+            Requirement::HasPassedOverAnEdgeForward => 0x003a,
+            Requirement::HasPassedOverAnEdgeBackward => 0x003b,
+            Requirement::IsHoldingSomeoneInGrab => 0x003c,
+            Requirement::HitboxHasConnected => 0x003d,
+            Requirement::PickUpItem => 0x0047,
+            Requirement::HitByCapeEffect => 0x004C,
+            Requirement::SDIInput => 0x004D,
+            Requirement::ShieldInputPress => 0x004E,
+            Requirement::ShieldInputHeld => 0x004f,
+            Requirement::TauntInputPress => 0x0050,
+            Requirement::TauntInputHeld => 0x0051,
+            Requirement::ThreadIsNull => 0x0060,
+            Requirement::Always => 0x00FF,
+            Requirement::InWalljump => 0x2711,
+            Requirement::InWallCling => 0x2712,
+            Requirement::InFootstoolRange => 0x2713,
+            Requirement::IsFallingOrHitDown => 0x2716,
+            Requirement::HasSmashBall => 0x2717,
+            Requirement::CanPickupAnotherItem => 0x2719,
+            Requirement::FSmashShortcut => 0x271D,
+            Requirement::TapJumpOn => 0x2725,
+            Requirement::Unknown(value) => *value,
+        }
+    }
 }
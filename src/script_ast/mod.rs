@@ -40,9 +40,9 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
         let args = &event.arguments;
         use crate::script::Argument::*;
         let event_ast = match (event.namespace, event.code, args.first(), args.get(1), args.get(2)) {
-            (0x00, 0x01, Some(Scalar(v0)), None, None) => EventAst::SyncWait (*v0),
+            (0x00, 0x01, Some(Scalar(v0, _)), None, None) => EventAst::SyncWait (*v0),
             (0x00, 0x02, None,             None, None) => EventAst::Nop,
-            (0x00, 0x02, Some(Scalar(v0)), None, None) => EventAst::AsyncWait (*v0),
+            (0x00, 0x02, Some(Scalar(v0, _)), None, None) => EventAst::AsyncWait (*v0),
             (0x00, 0x04, Some(Value(v0)),  None, None) => { // Loop
                 let iterations = if *v0 == -1 {
                     Iterations::Infinite
@@ -144,7 +144,14 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                 }
             }
             (0x00, 0x0F, None, None, None) => { return ProcessedBlock::EndIf { then_branch: Block { events: event_asts } } }
-            (0x00, 0x10, Some(Value(v0)), Some(Value(v1)),      None) => EventAst::Switch (*v0, *v1),
+            (0x00, 0x10, Some(Value(v0)), Some(v1),             None) => { // Switch
+                match v1 {
+                    Scalar(v1, _)   => EventAst::Switch (*v0, Expression::Scalar(*v1)),
+                    Variable(v1) => EventAst::Switch (*v0, Expression::Variable(VariableAst::new(v1))),
+                    Value(v1)    => EventAst::Switch (*v0, Expression::Value(*v1)),
+                    _            => EventAst::Unknown (event.clone()),
+                }
+            }
             (0x00, 0x11, Some(Value(v0)), None,                 None) => EventAst::Case (*v0),
             (0x00, 0x11, None,            None,                 None) => EventAst::DefaultCase,
             (0x00, 0x13, None,            None,                 None) => EventAst::EndSwitch,
@@ -202,10 +209,10 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x04, 0x00, Some(Value(v0)),  Some(Bool(v1)),  None) => if *v1 { EventAst::ChangeSubaction (*v0) } else { EventAst::ChangeSubactionRestartFrame (*v0) }
 
             // timing
-            (0x04, 0x06, Some(Scalar(v0)), None,            None) => EventAst::SetAnimationFrame (*v0),
-            (0x04, 0x07, Some(Scalar(v0)), None,            None) => EventAst::FrameSpeedModifier { multiplier: *v0, unk: 0 },
-            (0x04, 0x07, Some(Scalar(v0)), Some(Value(v1)), None) => EventAst::FrameSpeedModifier { multiplier: *v0, unk: *v1 },
-            (0x04, 0x14, Some(Scalar(v0)), None,            None) => EventAst::SetAnimationAndTimerFrame (*v0),
+            (0x04, 0x06, Some(Scalar(v0, _)), None,            None) => EventAst::SetAnimationFrame (*v0),
+            (0x04, 0x07, Some(Scalar(v0, _)), None,            None) => EventAst::FrameSpeedModifier { multiplier: *v0, unk: 0 },
+            (0x04, 0x07, Some(Scalar(v0, _)), Some(Value(v1)), None) => EventAst::FrameSpeedModifier { multiplier: *v0, unk: *v1 },
+            (0x04, 0x14, Some(Scalar(v0, _)), None,            None) => EventAst::SetAnimationAndTimerFrame (*v0),
             (0x0c, 0x23, Some(Value(v0)),  Some(Value(v1)), None) => EventAst::TimeManipulation (*v0, *v1),
 
             // misc state
@@ -217,7 +224,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x06, 0x04, None,             None,     None) => EventAst::DeleteAllHitBoxes,
             (0x06, 0x00, Some(Value(v0)), Some(v1), Some(Value(v2))) => {
                 match (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7), args.get(8), args.get(9), args.get(10), args.get(11), args.get(12)) {
-                    (Some(Value(v3)), Some(Value(v4)), Some(Scalar(v5)), Some(Scalar(v6)), Some(Scalar(v7)), Some(Scalar(v8)), Some(Scalar(v9)), Some(Scalar(v10)), Some(Scalar(v11)), Some(Value(v12))) => {
+                    (Some(Value(v3)), Some(Value(v4)), Some(Scalar(v5, _)), Some(Scalar(v6, _)), Some(Scalar(v7, _)), Some(Scalar(v8, _)), Some(Scalar(v9, _)), Some(Scalar(v10, _)), Some(Scalar(v11, _)), Some(Value(v12))) => {
                         let damage = match v1 {
                             Value(constant)        => Some(FloatValue::Constant (*constant as f32)),
                             Variable(variable) => Some(FloatValue::Variable (VariableAst::new(variable))),
@@ -267,7 +274,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             }
             (0x06, 0x2B, Some(Value(v0)), Some(Value(v1)), Some(Value(v2))) => {
                 match (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7), args.get(8), args.get(9), args.get(10), args.get(11), args.get(12)) {
-                    (Some(Value(v3)), Some(Value(v4)), Some(Scalar(v5)), Some(Scalar(v6)), Some(Scalar(v7)), Some(Scalar(v8)), Some(Scalar(v9)), Some(Scalar(v10)), Some(Scalar(v11)), Some(Value(v12))) => {
+                    (Some(Value(v3)), Some(Value(v4)), Some(Scalar(v5, _)), Some(Scalar(v6, _)), Some(Scalar(v7, _)), Some(Scalar(v8, _)), Some(Scalar(v9, _)), Some(Scalar(v10, _)), Some(Scalar(v11, _)), Some(Value(v12))) => {
                         let v12u = *v12 as u32;
                         EventAst::ThrownHitBox (HitBoxArguments {
                             bone_index:                  (*v0 >> 16) as i16,
@@ -307,7 +314,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             }
             (0x06, 0x15, Some(Value(v0)), Some(v1), Some(Value(v2))) => {
                 match (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7), args.get(8), args.get(9), args.get(10), args.get(11), args.get(12), args.get(13), args.get(14)) {
-                    (Some(Value(v3)), Some(Value(v4)), Some(Scalar(v5)), Some(Scalar(v6)), Some(Scalar(v7)), Some(Scalar(v8)), Some(Scalar(v9)), Some(Scalar(v10)), Some(Scalar(v11)), Some(Value(v12)), Some(Value(v13)), Some(Value(v14))) => {
+                    (Some(Value(v3)), Some(Value(v4)), Some(Scalar(v5, _)), Some(Scalar(v6, _)), Some(Scalar(v7, _)), Some(Scalar(v8, _)), Some(Scalar(v9, _)), Some(Scalar(v10, _)), Some(Scalar(v11, _)), Some(Value(v12)), Some(Value(v13)), Some(Value(v14))) => {
                         let damage = match v1 {
                             Value(constant)    => Some(FloatValue::Constant (*constant as f32)),
                             Variable(variable) => Some(FloatValue::Variable (VariableAst::new(variable))),
@@ -394,8 +401,8 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     direction: DefensiveCollisionDirection::new(*v2),
                 }
             }
-            (0x06, 0x1B, Some(Value(v0)), Some(Value(v1)), Some(Scalar(v2))) => {
-                if let (Some(Scalar(v3)), Some(Scalar(v4))) = (args.get(3), args.get(4)) {
+            (0x06, 0x1B, Some(Value(v0)), Some(Value(v1)), Some(Scalar(v2, _))) => {
+                if let (Some(Scalar(v3, _)), Some(Scalar(v4, _))) = (args.get(3), args.get(4)) {
                     EventAst::MoveHitBox (MoveHitBox {
                         hitbox_id:    *v0,
                         new_bone:     *v1,
@@ -410,8 +417,8 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x06, 0x01, Some(Value(v0)), Some(Value(v1)), None) => EventAst::ChangeHitBoxDamage { hitbox_id: *v0, new_damage: *v1 },
             (0x06, 0x02, Some(Value(v0)), Some(Value(v1)), None) => EventAst::ChangeHitBoxSize   { hitbox_id: *v0, new_size:   *v1 },
             (0x06, 0x03, Some(Value(v0)), None,            None) => EventAst::DeleteHitBox (*v0),
-            (0x06, 0x0A, Some(Value(v0)), Some(Value(v1)), Some(Scalar(v2))) => {
-                if let (Some(Scalar(v3)), Some(Scalar(v4)), Some(Scalar(v5)), Some(Value(v6)), Some(Value(v7))) =
+            (0x06, 0x0A, Some(Value(v0)), Some(Value(v1)), Some(Scalar(v2, _))) => {
+                if let (Some(Scalar(v3, _)), Some(Scalar(v4, _)), Some(Scalar(v5, _)), Some(Value(v6)), Some(Value(v7))) =
                     (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7))
                 {
                     let unk = if let Some(Value(value)) = args.get(8) { Some(*value) } else { None };
@@ -437,7 +444,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x06, 0x0D, None,             None, None) => EventAst::DeleteAllGrabBoxes,
             (0x06, 0x0E, Some(Value(v0)), Some(Value(v1)), Some(Value(v2))) => {
                 match (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7), args.get(8), args.get(9), args.get(10), args.get(11), args.get(12), args.get(13), args.get(14), args.get(15), args.get(16)) {
-                    (Some(Value(v3)), Some(Value(v4)), Some(Value(v5)), Some(Value(v6)), Some(Value(v7)), Some(Scalar(v8)), Some(Scalar(v9)), Some(Scalar(v10)), Some(Value(v11)), Some(Value(v12)), Some(Value(v13)), Some(Bool(v14)), Some(Bool(v15)), Some(Value(v16))) => {
+                    (Some(Value(v3)), Some(Value(v4)), Some(Value(v5)), Some(Value(v6)), Some(Value(v7)), Some(Scalar(v8, _)), Some(Scalar(v9, _)), Some(Scalar(v10, _)), Some(Value(v11)), Some(Value(v12)), Some(Value(v13)), Some(Bool(v14)), Some(Bool(v15)), Some(Value(v16))) => {
                         EventAst::SpecifyThrow (SpecifyThrow {
                             throw_use:   ThrowUse::new(*v0),
                             bone:        *v1,
@@ -521,8 +528,8 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x0C, 0x08, None,             None,             None) => EventAst::TerminateSelf,
             (0x0C, 0x09, Some(Value(v0)),  None,             None) => EventAst::LedgeGrabEnable (LedgeGrabEnable::new(*v0)),
             (0x0C, 0x25, Some(Bool(v0)),   None,             None) => EventAst::TagDisplay (*v0),
-            (0x1E, 0x00, Some(Value(v0)),  Some(Scalar(v1)), None) => EventAst::Armor { armor_type: ArmorType::new(*v0), tolerance: *v1 },
-            (0x1E, 0x03, Some(Scalar(v0)), None,             None) => EventAst::AddDamage (*v0),
+            (0x1E, 0x00, Some(Value(v0)),  Some(Scalar(v1, _)), None) => EventAst::Armor { armor_type: ArmorType::new(*v0), tolerance: *v1 },
+            (0x1E, 0x03, Some(Scalar(v0, _)), None,             None) => EventAst::AddDamage (*v0),
 
             // posture
             (0x05, 0x01, None, None, None) => EventAst::Posture (0x01),
@@ -533,7 +540,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x05, 0x0D, None, None, None) => EventAst::Posture (0x0D),
 
             // movement
-            (0x0E, 0x08, Some(&Scalar(v0)), Some(&Scalar(v1)), Some(&Value(v2))) => {
+            (0x0E, 0x08, Some(&Scalar(v0, _)), Some(&Scalar(v1, _)), Some(&Value(v2))) => {
                 if let Some(&Value(v3)) = args.get(3) {
                     EventAst::SetOrAddVelocity (SetOrAddVelocity {
                         x_vel: v0,
@@ -545,10 +552,10 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     EventAst::Unknown (event.clone())
                 }
             }
-            (0x0E, 0x08, Some(Scalar(v0)),   Some(Scalar(v1)),   None) => EventAst::SetVelocity { x_vel: *v0, y_vel: *v1 },
-            (0x0E, 0x01, Some(Scalar(v0)),   Some(Scalar(v1)),   None) => EventAst::AddVelocity { x_vel: FloatValue::Constant(*v0),                  y_vel: FloatValue::Constant(*v1) },
-            (0x0E, 0x01, Some(Variable(v0)), Some(Scalar(v1)),   None) => EventAst::AddVelocity { x_vel: FloatValue::Variable(VariableAst::new(v0)), y_vel: FloatValue::Constant(*v1) },
-            (0x0E, 0x01, Some(Scalar(v0)),   Some(Variable(v1)), None) => EventAst::AddVelocity { x_vel: FloatValue::Constant(*v0),                  y_vel: FloatValue::Variable(VariableAst::new(v1)) },
+            (0x0E, 0x08, Some(Scalar(v0, _)),   Some(Scalar(v1, _)),   None) => EventAst::SetVelocity { x_vel: *v0, y_vel: *v1 },
+            (0x0E, 0x01, Some(Scalar(v0, _)),   Some(Scalar(v1, _)),   None) => EventAst::AddVelocity { x_vel: FloatValue::Constant(*v0),                  y_vel: FloatValue::Constant(*v1) },
+            (0x0E, 0x01, Some(Variable(v0)), Some(Scalar(v1, _)),   None) => EventAst::AddVelocity { x_vel: FloatValue::Variable(VariableAst::new(v0)), y_vel: FloatValue::Constant(*v1) },
+            (0x0E, 0x01, Some(Scalar(v0, _)),   Some(Variable(v1)), None) => EventAst::AddVelocity { x_vel: FloatValue::Constant(*v0),                  y_vel: FloatValue::Variable(VariableAst::new(v1)) },
             (0x0E, 0x01, Some(Variable(v0)), Some(Variable(v1)), None) => EventAst::AddVelocity { x_vel: FloatValue::Variable(VariableAst::new(v0)), y_vel: FloatValue::Variable(VariableAst::new(v1)) },
             (0x0E, 0x06, Some(Value(v0)),    None,               None) => EventAst::DisableMovement (DisableMovement::new(*v0)),
             (0x0E, 0x07, Some(Value(v0)),    None,               None) => EventAst::DisableMovement2 (DisableMovement::new(*v0)),
@@ -575,15 +582,15 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             (0x12, 0x02, Some(Value(v0)),    Some(Variable(v1)), None) => EventAst::IntVariableSubtract   { value: *v0, variable: VariableAst::new(v1) },
             (0x12, 0x03, Some(Variable(v0)), None,               None) => EventAst::IntVariableIncrement  { variable: VariableAst::new(v0) },
             (0x12, 0x04, Some(Variable(v0)), None,               None) => EventAst::IntVariableDecrement  { variable: VariableAst::new(v0) },
-            (0x12, 0x06, Some(Scalar(v0)),   Some(Variable(v1)), None) => EventAst::FloatVariableSet      { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x06, Some(Scalar(v0, _)),   Some(Variable(v1)), None) => EventAst::FloatVariableSet      { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
             (0x12, 0x06, Some(Variable(v0)), Some(Variable(v1)), None) => EventAst::FloatVariableSet      { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
-            (0x12, 0x07, Some(Scalar(v0)),   Some(Variable(v1)), None) => EventAst::FloatVariableAdd      { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x07, Some(Scalar(v0, _)),   Some(Variable(v1)), None) => EventAst::FloatVariableAdd      { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
             (0x12, 0x07, Some(Variable(v0)), Some(Variable(v1)), None) => EventAst::FloatVariableAdd      { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
-            (0x12, 0x08, Some(Scalar(v0)),   Some(Variable(v1)), None) => EventAst::FloatVariableSubtract { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x08, Some(Scalar(v0, _)),   Some(Variable(v1)), None) => EventAst::FloatVariableSubtract { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
             (0x12, 0x08, Some(Variable(v0)), Some(Variable(v1)), None) => EventAst::FloatVariableSubtract { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
-            (0x12, 0x0F, Some(Scalar(v0)),   Some(Variable(v1)), None) => EventAst::FloatVariableMultiply { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x0F, Some(Scalar(v0, _)),   Some(Variable(v1)), None) => EventAst::FloatVariableMultiply { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
             (0x12, 0x0F, Some(Variable(v0)), Some(Variable(v1)), None) => EventAst::FloatVariableMultiply { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
-            (0x12, 0x10, Some(Scalar(v0)),   Some(Variable(v1)), None) => EventAst::FloatVariableDivide   { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
+            (0x12, 0x10, Some(Scalar(v0, _)),   Some(Variable(v1)), None) => EventAst::FloatVariableDivide   { value: FloatValue::Constant(*v0),                   variable: VariableAst::new(v1) },
             (0x12, 0x10, Some(Variable(v0)), Some(Variable(v1)), None) => EventAst::FloatVariableDivide   { value: FloatValue::Variable(VariableAst::new(v0)), variable: VariableAst::new(v1) },
             (0x12, 0x0A, Some(Variable(v0)), None,               None) => EventAst::BoolVariableSetTrue   { variable: VariableAst::new(v0) },
             (0x12, 0x0B, Some(Variable(v0)), None,               None) => EventAst::BoolVariableSetFalse  { variable: VariableAst::new(v0) },
@@ -591,10 +598,10 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             // graphics
             (0x0B, 0x00, Some(&Value(v0)), Some(&Value(v1)), None) => EventAst::ModelChanger { reference: 0, switch_index: v0, bone_group_index: v1 },
             (0x0B, 0x01, Some(&Value(v0)), Some(&Value(v1)), None) => EventAst::ModelChanger { reference: 1, switch_index: v0, bone_group_index: v1 },
-            (0x11, 0x1A, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2))) |
-            (0x11, 0x1B, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2))) => {
+            (0x11, 0x1A, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2, _))) |
+            (0x11, 0x1B, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2, _))) => {
                 match (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7), args.get(8), args.get(9), args.get(10), args.get(11), args.get(12), args.get(13), args.get(14), args.get(15)) {
-                    (Some(&Scalar(v3)), Some(&Scalar(v4)), Some(&Scalar(v5)), Some(&Scalar(v6)), Some(&Scalar(v7)), Some(&Scalar(v8)), Some(&Scalar(v9)), Some(&Scalar(v10)), Some(&Scalar(v11)), Some(&Scalar(v12)), Some(&Scalar(v13)), Some(&Scalar(v14)), Some(&Bool(v15))) => {
+                    (Some(&Scalar(v3, _)), Some(&Scalar(v4, _)), Some(&Scalar(v5, _)), Some(&Scalar(v6, _)), Some(&Scalar(v7, _)), Some(&Scalar(v8, _)), Some(&Scalar(v9, _)), Some(&Scalar(v10, _)), Some(&Scalar(v11, _)), Some(&Scalar(v12, _)), Some(&Scalar(v13, _)), Some(&Scalar(v14, _)), Some(&Bool(v15))) => {
                         EventAst::GraphicEffect (GraphicEffect {
                             graphic:                  v0,
                             bone:                     v1,
@@ -617,8 +624,8 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     _ => EventAst::Unknown (event.clone())
                 }
             }
-            (0x11, 0x00, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2))) => {
-                if let (Some(&Scalar(v3)), Some(&Scalar(v4)), Some(&Scalar(v5)), Some(&Scalar(v6)), Some(&Scalar(v7)), Some(&Scalar(v8)), Some(&Scalar(v9)), Some(&Scalar(v10)), Some(&Scalar(v11)), Some(&Scalar(v12)), Some(&Scalar(v13)), Some(&Scalar(v14)), Some(&Bool(v15))) =
+            (0x11, 0x00, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2, _))) => {
+                if let (Some(&Scalar(v3, _)), Some(&Scalar(v4, _)), Some(&Scalar(v5, _)), Some(&Scalar(v6, _)), Some(&Scalar(v7, _)), Some(&Scalar(v8, _)), Some(&Scalar(v9, _)), Some(&Scalar(v10, _)), Some(&Scalar(v11, _)), Some(&Scalar(v12, _)), Some(&Scalar(v13, _)), Some(&Scalar(v14, _)), Some(&Bool(v15))) =
                     (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7), args.get(8), args.get(9), args.get(10), args.get(11), args.get(12), args.get(13), args.get(14), args.get(15))
                 {
                     EventAst::ExternalGraphicEffect (ExternalGraphicEffect {
@@ -646,9 +653,9 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                     EventAst::Unknown (event.clone())
                 }
             }
-            (0x11, 0x01, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2))) |
-            (0x11, 0x02, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2))) => {
-                if let (Some(&Scalar(v3)), Some(&Scalar(v4)), Some(&Scalar(v5)), Some(&Scalar(v6)), Some(&Scalar(v7)), Some(&Scalar(v8)), Some(&Bool(v9))) =
+            (0x11, 0x01, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2, _))) |
+            (0x11, 0x02, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2, _))) => {
+                if let (Some(&Scalar(v3, _)), Some(&Scalar(v4, _)), Some(&Scalar(v5, _)), Some(&Scalar(v6, _)), Some(&Scalar(v7, _)), Some(&Scalar(v8, _)), Some(&Bool(v9))) =
                     (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7), args.get(8), args.get(9))
                 {
                     EventAst::ExternalGraphicEffect (ExternalGraphicEffect {
@@ -697,7 +704,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             }
             (0x11, 0x18, Some(&Value(v0)), Some(&Value(v1)), None) => EventAst::EndUnlimitedScreenTint { tint_id: v0, transition_out_time: v1 },
             (0x11, 0x03, Some(&Value(v0)), Some(&Value(v1)), Some(&Value(v2))) => {
-                if let (Some(&Scalar(v3)), Some(&Scalar(v4)), Some(&Scalar(v5)), Some(&Value(v6)), Some(&Scalar(v7)), Some(&Scalar(v8)), Some(&Scalar(v9)), Some(&Bool(v10)), Some(&Value(v11)), Some(&Value(v12)), Some(&Scalar(v13)), Some(&Scalar(v14)), Some(&Scalar(v15)), Some(&Scalar(v16)), Some(&Scalar(v17)), Some(&Scalar(v18)), Some(&Scalar(v19))) =
+                if let (Some(&Scalar(v3, _)), Some(&Scalar(v4, _)), Some(&Scalar(v5, _)), Some(&Value(v6)), Some(&Scalar(v7, _)), Some(&Scalar(v8, _)), Some(&Scalar(v9, _)), Some(&Bool(v10)), Some(&Value(v11)), Some(&Value(v12)), Some(&Scalar(v13, _)), Some(&Scalar(v14, _)), Some(&Scalar(v15, _)), Some(&Scalar(v16, _)), Some(&Scalar(v17, _)), Some(&Scalar(v18, _)), Some(&Scalar(v19, _))) =
                     (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7), args.get(8), args.get(9), args.get(10), args.get(11), args.get(12), args.get(13), args.get(14), args.get(15), args.get(16), args.get(17), args.get(18), args.get(19))
                 {
                     EventAst::SwordGlow (SwordGlow {
@@ -730,9 +737,9 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                 }
             }
             (0x11, 0x05, Some(&Value(v0)), None,              None) => EventAst::DeleteSwordGlow { fade_time: v0 },
-            (0x14, 0x07, Some(&Value(v0)), Some(&Scalar(v1)), Some(&Scalar(v2))) => {
+            (0x14, 0x07, Some(&Value(v0)), Some(&Scalar(v1, _)), Some(&Scalar(v2, _))) => {
                 match (args.get(3), args.get(4), args.get(5), args.get(6), args.get(7), args.get(8), args.get(9)) {
-                    (Some(&Scalar(v3)), Some(&Scalar(v4)), Some(&Scalar(v5)), Some(&Scalar(v6)), Some(&Scalar(v7)), Some(&Scalar(v8)), Some(&Value(v9))) => {
+                    (Some(&Scalar(v3, _)), Some(&Scalar(v4, _)), Some(&Scalar(v5, _)), Some(&Scalar(v6, _)), Some(&Scalar(v7, _)), Some(&Scalar(v8, _)), Some(&Value(v9))) => {
                         EventAst::AestheticWindEffect (AestheticWindEffect {
                             unk1:    v0,
                             unk2:    v1,
@@ -752,8 +759,8 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
             }
             (0x14, 0x04, Some(&Value(v0)), None, None) => EventAst::EndAestheticWindEffect { unk: v0 },
             (0x1A, 0x00, Some(&Value(v0)), None, None) => EventAst::ScreenShake { magnitude: v0 },
-            (0x1A, 0x04, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2))) => {
-                if let (Some(&Scalar(v3)), Some(&Scalar(v4))) = (args.get(3), args.get(4)) {
+            (0x1A, 0x04, Some(&Value(v0)), Some(&Value(v1)), Some(&Scalar(v2, _))) => {
+                if let (Some(&Scalar(v3, _)), Some(&Scalar(v4, _))) = (args.get(3), args.get(4)) {
                     EventAst::CameraCloseup (CameraCloseup {
                         zoom_time: v0,
                         unk: v1,
@@ -782,7 +789,7 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                 }
             }
             (0x21, 0x05, Some(&Value(v0)), Some(&Value(v1)), Some(&Value(v2))) => {
-                if let (Some(&Value(v3)), Some(&Scalar(v4)), Some(&Scalar(v5))) = (args.get(3), args.get(4), args.get(5)) {
+                if let (Some(&Value(v3)), Some(&Scalar(v4, _)), Some(&Scalar(v5, _))) = (args.get(3), args.get(4), args.get(5)) {
                     EventAst::FlashEffectLight { red: v0, green: v1, blue: v2, alpha: v3, light_source_x: v4, light_source_y: v5 }
                 } else {
                     EventAst::Unknown (event.clone())
@@ -813,11 +820,11 @@ fn process_block(events: &mut std::iter::Peekable<slice::Iter<Event>>) -> Proces
                 },
             (0x1F, 0x0E, Some(Variable(v0)), Some(Variable(v1)), Some(Variable(v2)))
                 => EventAst::ItemThrow { unk1: VariableAst::new(v0), unk2: VariableAst::new(v1), unk3: VariableAst::new(v2), unk4: None, unk5: None },
-            (0x1F, 0x01, Some(Scalar(v0)), Some(Scalar(v1)), Some(Variable(v2))) =>
+            (0x1F, 0x01, Some(Scalar(v0, _)), Some(Scalar(v1, _)), Some(Variable(v2))) =>
                     EventAst::ItemThrow2 { unk1: *v0, unk2: *v1, unk3: VariableAst::new(v2) },
             (0x1F, 0x02, None,            None,             None) => EventAst::ItemDrop,
             (0x1F, 0x03, Some(Value(v0)), None,             None) => EventAst::ItemConsume { unk: *v0 },
-            (0x1F, 0x04, Some(Value(v0)), Some(Scalar(v1)), None) => EventAst::ItemSetProperty { unk1: *v0, unk2: *v1 },
+            (0x1F, 0x04, Some(Value(v0)), Some(Scalar(v1, _)), None) => EventAst::ItemSetProperty { unk1: *v0, unk2: *v1 },
             (0x1F, 0x05, None,            None,             None) => EventAst::FireWeapon,
             (0x1F, 0x06, None,            None,             None) => EventAst::FireProjectile,
             (0x1F, 0x07, Some(Value(v0)), None,             None) => EventAst::Item1F { unk: *v0 },
@@ -859,7 +866,7 @@ impl Expression {
             (None, None, None) => Expression::Nullary(requirement.clone()),
             (Some(v1), None, None) => {
                 let value = Box::new(match v1 {
-                    Argument::Scalar(v1) => Expression::Scalar(*v1),
+                    Argument::Scalar(v1, _) => Expression::Scalar(*v1),
                     Argument::Variable(v1) => Expression::Variable(VariableAst::new(v1)),
                     Argument::Value(v1) => Expression::Value(*v1),
                     _ => {
@@ -874,7 +881,7 @@ impl Expression {
             }
             (Some(v1), Some(Argument::Value(v2)), Some(v3)) => {
                 let left = Box::new(match v1 {
-                    Argument::Scalar(v1) => Expression::Scalar(*v1),
+                    Argument::Scalar(v1, _) => Expression::Scalar(*v1),
                     Argument::Variable(v1) => Expression::Variable(VariableAst::new(v1)),
                     Argument::Value(v1) => Expression::Value(*v1),
                     _ => {
@@ -883,7 +890,7 @@ impl Expression {
                     }
                 });
                 let right = Box::new(match v3 {
-                    Argument::Scalar(v3) => Expression::Scalar(*v3),
+                    Argument::Scalar(v3, _) => Expression::Scalar(*v3),
                     Argument::Variable(v3) => Expression::Variable(VariableAst::new(v3)),
                     Argument::Value(v3) => Expression::Value(*v3),
                     _ => {
@@ -967,9 +974,11 @@ pub enum EventAst {
     /// Has no effect outside of an IfStatement
     /// Havent tested if it would affect execution when called within a subroutine, but I would assume it is.
     IfStatementOr(Expression),
-    /// Begin a multiple case Switch block.
-    Switch(i32, i32),
+    /// Begin a multiple case Switch block over the value of the expression.
+    /// Events before the first case are not executed.
+    Switch(i32, Expression),
     /// Handler for if the variable in the switch statement equals the specified value.
+    /// Execution does not fall through into the next case, but consecutive cases share the events that follow them.
     Case(i32),
     /// The case chosen if none of the others are executed.
     DefaultCase,
@@ -2117,7 +2126,7 @@ pub struct Interrupt {
     pub test: Expression,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum InterruptType {
     Main,
     GroundSpecial,
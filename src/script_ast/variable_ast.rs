@@ -75,7 +75,7 @@ impl VariableAst {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum InternalConstantInt {
     CurrentFrame,
     Damage,
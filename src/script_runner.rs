@@ -1,7 +1,9 @@
 use crate::fighter::WiiRDFrameSpeedModifier;
 use crate::high_level_fighter;
-use crate::high_level_fighter::{CollisionBoxValues, SectionScriptAst};
-use crate::sakurai::fighter_data::ArcFighterData;
+use crate::high_level_fighter::{
+    CollisionBoxValues, HighLevelAction, HighLevelSubaction, SectionScriptAst,
+};
+use crate::sakurai::fighter_data::ModelVisibility;
 use crate::script::{Requirement, VariableDataType};
 use crate::script_ast::variable_ast::{
     InternalConstantInt, LongtermAccessBool, LongtermAccessFloat, LongtermAccessInt,
@@ -9,8 +11,8 @@ use crate::script_ast::variable_ast::{
 };
 use crate::script_ast::{
     ArmorType, BinaryExpression, Block, ComparisonOperator, DisableMovement, EdgeSlide, EventAst,
-    Expression, FloatValue, GrabBoxArguments, HitBoxArguments, HurtBoxState, Interrupt, Iterations,
-    LedgeGrabEnable, ScriptAst, SpecialHitBoxArguments, SpecifyThrow, ThrowUse,
+    Expression, FloatValue, GrabBoxArguments, HitBoxArguments, HurtBoxState, InterruptType,
+    Iterations, LedgeGrabEnable, ScriptAst, SpecialHitBoxArguments, SpecifyThrow, ThrowUse,
 };
 
 use std::collections::HashMap;
@@ -23,12 +25,28 @@ pub struct ScriptRunner<'a> {
     pub common_scripts: &'a [&'a ScriptAst],
     pub section_scripts: &'a [SectionScriptAst],
     pub call_every_frame: HashMap<i32, CallEveryFrame<'a>>,
+    /// Threads created by IndependentSubroutine, they keep their state across frames and subaction changes
+    pub independent_threads: Vec<IndependentThread<'a>>,
     pub visited_gotos: Vec<i32>,
     pub subaction_index: usize,
+    /// The action being run, None when the runner only runs a single subaction
+    pub action_index: Option<usize>,
+    /// Used to follow subaction changes and action transitions when running an action
+    pub actions: &'a [HighLevelAction],
+    /// Used to follow subaction changes and action transitions when running an action
+    pub subactions: &'a [HighLevelSubaction],
+    /// Set when the last frame started a new subaction, only occurs when running an action
+    pub subaction_changed: bool,
+    /// affected by frame speed modifiers, reset when the action changes
+    pub action_frame_index: f32,
     pub frame_index: f32,     // affected by frame speed modifiers
     pub animation_index: f32, // affected by frame speed modifiers, usually in sync with frame_index but not always because some commands affect only animation_index
     pub frame_count: usize, // goes up by exactly 1 every frame, only used for external statistics like iasa
+    /// Set by AllowInterrupts, enables the interrupts in the common interrupt groups e.g. GroundAttack.
+    /// Use `ScriptRunner::can_interrupt` to check if anything can actually interrupt the subaction.
     pub interruptible: bool,
+    /// The inputs and state that the scripts are run against
+    pub context: SimulationContext,
     pub hitboxes: [Option<ScriptCollisionBox>; 7],
     pub hurtbox_state_all: HurtBoxState,
     pub hurtbox_states: HashMap<i32, HurtBoxState>,
@@ -58,15 +76,22 @@ pub struct ScriptRunner<'a> {
     /// Children of these bones are also visible
     pub invisible_bones: Vec<i32>,
     /// Each Interrupt is rechecked every frame after being created
-    pub interrupts: Vec<Interrupt>,
-    /// As a hack to get some subactions working we store "bad interrupts" here instead.
-    pub bad_interrupts: Vec<Interrupt>,
+    pub interrupts: Vec<ScriptInterrupt>,
+    /// Index into `interrupts` of the last CreateInterrupt, modified by PreviousInterruptAddRequirement.
+    /// None if there is no such interrupt or it has since been cleared.
+    previous_interrupt: Option<usize>,
+    /// Interrupts in these groups cannot occur until the group is enabled again
+    pub disabled_interrupt_groups: Vec<InterruptType>,
     pub hitbox_sets_rehit: [bool; 10],
     pub slope_contour_stand: Option<i32>,
     pub slope_contour_full: Option<(i32, i32)>,
     pub rumble: Option<(i32, i32)>,
     pub rumble_loop: Option<(i32, i32)>,
     pub grab_interrupt_damage: Option<i32>,
+    /// Set by the caller when a hitbox on the current frame connects with the opponent, checked by HitboxConnects
+    pub hitbox_connected: bool,
+    /// Set once a hitbox connects, reset when the subaction changes
+    pub hitbox_has_connected: bool,
     pub throw: Option<SpecifyThrow>,
     /// Reset to false before processing each frame.
     pub throw_activate: bool,
@@ -153,6 +178,51 @@ pub struct CallStack<'a> {
     pub wait_until: f32,
 }
 
+impl<'a> CallStack<'a> {
+    fn new(block: &'a Block) -> CallStack<'a> {
+        let calls = vec![Call {
+            block,
+            else_branch: None,
+            index: 0,
+            subroutine: false,
+            external: false,
+            if_statement: false,
+            execute: true,
+            switches: vec![],
+        }];
+        CallStack {
+            calls,
+            wait_until: -1.0,
+        }
+    }
+
+    fn empty() -> CallStack<'a> {
+        CallStack {
+            calls: vec![],
+            wait_until: -1.0,
+        }
+    }
+}
+
+/// When running an action, the entry script is run in the call stack after the main, gfx, sfx and other call stacks
+const ACTION_CALL_STACK: usize = 4;
+
+/// The maximum number of subaction changes and action transitions that are followed in a single frame
+const MAX_CHANGES_PER_FRAME: usize = 10;
+
+/// The values of `InterruptType::new` for every group except `InterruptType::Main`.
+/// The game fills these groups in from the current action, so they are enabled by AllowInterrupts rather than CreateInterrupt.
+const COMMON_INTERRUPT_GROUPS: std::ops::RangeInclusive<i32> = 0x01..=0x13;
+
+fn default_ledge_grab_enable(subaction_name: &str) -> LedgeGrabEnable {
+    match subaction_name {
+        "JumpF" | "JumpB" | "JumpAerialF" | "JumpAerialB" | "FallF" | "FallB" | "Fall"
+        | "FallAerialF" | "FallAerialB" | "FallAerial" | "FallSpecialF" | "FallSpecialB"
+        | "FallSpecial" => LedgeGrabEnable::EnableInFront,
+        _ => LedgeGrabEnable::Disable,
+    }
+}
+
 pub struct Call<'a> {
     pub block: &'a Block,
     pub else_branch: Option<&'a Block>,
@@ -162,6 +232,94 @@ pub struct Call<'a> {
     /// I have tested the `And` and `Or` events to have no effect outside of any if statement
     pub if_statement: bool,
     pub execute: bool,
+    /// The switch statements this call is currently inside of, innermost last
+    pub switches: Vec<SwitchState>,
+}
+
+pub struct SwitchState {
+    value: ExprResult,
+    /// Set once a case has been executed, no later cases in the switch will execute
+    matched: bool,
+    /// true while the events of a case are being executed
+    active: bool,
+    /// Whether the call was executing when the switch began, restored at the end of the switch
+    parent_execute: bool,
+}
+
+/// The inputs and the state of the fighter and its surroundings that the scripts are run against.
+#[derive(Clone, Debug)]
+pub struct SimulationContext {
+    /// The inputs made on each frame, indexed by `ScriptRunner::frame_count`.
+    /// There are no inputs on frames past the end.
+    pub inputs: Vec<FrameInput>,
+    /// The damage percent of the fighter when the simulation starts
+    pub damage: f32,
+    pub facing_right: bool,
+    /// Whether the fighter starts on the ground, the scripts can change this with SetAirGround
+    pub on_ground: bool,
+    /// The position of the opponent, relative to where the fighter starts.
+    /// x is in the direction the fighter is facing.
+    /// When set, a hitbox covering this point connects.
+    pub opponent_position: Option<(f32, f32)>,
+    /// The values of variables when the simulation starts e.g. to choose between Zelda and Sheik
+    /// Addresses past the end of the variable storage are logged and ignored.
+    pub variables: Vec<(VariableAst, VariableValue)>,
+    /// Overrides the value of internal constants that depend on the character
+    pub internal_constants: Vec<(InternalConstantInt, i32)>,
+    /// When true, an interrupt with its requirements met changes the subaction.
+    /// When false, interrupts are only recorded so that every frame of the subaction can be inspected.
+    pub follow_interrupts: bool,
+}
+
+impl SimulationContext {
+    fn input(&self, frame: Option<usize>) -> Option<&FrameInput> {
+        frame.and_then(|frame| self.inputs.get(frame))
+    }
+}
+
+impl Default for SimulationContext {
+    /// A grounded fighter facing right at 0% with no inputs
+    fn default() -> Self {
+        SimulationContext {
+            inputs: vec![],
+            damage: 0.0,
+            facing_right: true,
+            on_ground: true,
+            opponent_position: None,
+            variables: vec![],
+            internal_constants: vec![],
+            follow_interrupts: false,
+        }
+    }
+}
+
+/// The inputs made on a single frame
+#[derive(Clone, Debug, Default)]
+pub struct FrameInput {
+    /// Buttons held down, a button is pressed on the first frame it is held and released on the first frame it is not.
+    pub buttons: Vec<i32>,
+    /// Stick directions held, checked by StickDirectionPressed and StickDirectionNotPressed
+    pub stick_directions: Vec<i32>,
+}
+
+#[derive(Clone, Debug)]
+pub enum VariableValue {
+    Int(i32),
+    Float(f32),
+    Bool(bool),
+}
+
+/// An interrupt created by the subaction scripts
+#[derive(Clone, Debug)]
+pub struct ScriptInterrupt {
+    pub interrupt_type: InterruptType,
+    /// None when created without an ID, such interrupts can only be modified by PreviousInterruptAddRequirement
+    pub interrupt_id: Option<i32>,
+    pub action: i32,
+    pub test: Expression,
+    pub enabled: bool,
+    /// Whether `test` passed against the `SimulationContext` on the current frame
+    pub requirement_met: bool,
 }
 
 pub enum ChangeSubaction {
@@ -169,7 +327,8 @@ pub enum ChangeSubaction {
     InfiniteLoop,
     ChangeSubaction(i32),
     ChangeSubactionRestartFrame(i32),
-    // TODO: Because we currently only operate at the subaction level, this is the best we can do.
+    /// Only occurs when `SimulationContext::follow_interrupts` is set.
+    /// When running an action, this is followed by changing to the action instead.
     Interrupt(i32),
 }
 
@@ -257,32 +416,15 @@ impl<'a> ScriptRunner<'a> {
         common_scripts: &'a [&'a ScriptAst],
         section_scripts: &'a [SectionScriptAst],
         init_hack_script: &Block,
-        fighter_data: &ArcFighterData,
+        model_visibility: &ModelVisibility,
         subaction_name: String,
+        context: SimulationContext,
     ) -> ScriptRunner<'a> {
-        let mut call_stacks = vec![];
-        for script in subaction_scripts {
-            let calls = vec![Call {
-                block: &script.block,
-                else_branch: None,
-                index: 0,
-                subroutine: false,
-                external: false,
-                if_statement: false,
-                execute: true,
-            }];
-            call_stacks.push(CallStack {
-                calls,
-                wait_until: -1.0,
-            });
-        }
-
-        let ledge_grab_enable = match subaction_name.as_ref() {
-            "JumpF" | "JumpB" | "JumpAerialF" | "JumpAerialB" | "FallF" | "FallB" | "Fall"
-            | "FallAerialF" | "FallAerialB" | "FallAerial" | "FallSpecialF" | "FallSpecialB"
-            | "FallSpecial" => LedgeGrabEnable::EnableInFront,
-            _ => LedgeGrabEnable::Disable,
-        };
+        let call_stacks = subaction_scripts
+            .iter()
+            .map(|script| CallStack::new(&script.block))
+            .collect();
+        let ledge_grab_enable = default_ledge_grab_enable(&subaction_name);
 
         // TODO: Need to understand what ModelVisibility.cs:269 is doing (ResetVisibility)
         //
@@ -302,8 +444,8 @@ impl<'a> ScriptRunner<'a> {
         // This isnt actually part of the visibility reset that occurs at the start of a subaction
         // and should be only called during initialization if such a refactor occurs.
 
-        for reference in &fighter_data.model_visibility.references {
-            for default in &fighter_data.model_visibility.defaults {
+        for reference in &model_visibility.references {
+            for default in &model_visibility.defaults {
                 if let Some(bone_switch) =
                     reference.bone_switches.get(default.switch_index as usize)
                     && let Some(group) = bone_switch.groups.get(default.group_index as usize)
@@ -334,8 +476,8 @@ impl<'a> ScriptRunner<'a> {
         // The two cases where this could affect things in the future are:
         // *   ScriptRunner is extended to run at the action level, in which case new subactions would cause the bones to reset after being potentially modified
         // *   invisible_bones is populated with the visible bone flags from the MDL0 bone data.
-        for reference in &fighter_data.model_visibility.references {
-            for default in &fighter_data.model_visibility.defaults {
+        for reference in &model_visibility.references {
+            for default in &model_visibility.defaults {
                 if let Some(bone_switch) =
                     reference.bone_switches.get(default.switch_index as usize)
                     && let Some(group) = bone_switch.groups.get(default.group_index as usize)
@@ -402,13 +544,20 @@ impl<'a> ScriptRunner<'a> {
             common_scripts,
             section_scripts,
             subaction_index,
+            action_index: None,
+            actions: &[],
+            subactions: &[],
+            subaction_changed: false,
+            action_frame_index: 0.0,
             ledge_grab_enable,
             call_every_frame: HashMap::new(),
+            independent_threads: vec![],
             visited_gotos: vec![],
             frame_index: 0.0,
             animation_index: 0.0,
             frame_count: 0,
             interruptible: false,
+            context: context.clone(),
             hitboxes: [None, None, None, None, None, None, None],
             hurtbox_state_all: HurtBoxState::Normal,
             hurtbox_states: HashMap::new(),
@@ -423,19 +572,22 @@ impl<'a> ScriptRunner<'a> {
             disable_movement: DisableMovement::Enable,
             armor_type: ArmorType::None,
             armor_tolerance: 0.0,
-            damage: 0.0,
-            airbourne: false,
+            damage: context.damage,
+            airbourne: !context.on_ground,
             edge_slide: EdgeSlide::SlideOff,
             reverse_direction: false,
             change_subaction: ChangeSubaction::Continue,
             interrupts: vec![],
-            bad_interrupts: vec![],
+            previous_interrupt: None,
+            disabled_interrupt_groups: vec![],
             hitbox_sets_rehit: [false; 10],
             slope_contour_stand: None,
             slope_contour_full: None,
             rumble: None,
             rumble_loop: None,
             grab_interrupt_damage: None,
+            hitbox_connected: false,
+            hitbox_has_connected: false,
             throw: None,
             throw_activate: false,
             invisible_bones,
@@ -521,6 +673,24 @@ impl<'a> ScriptRunner<'a> {
             runner.step_event(event, false, &[], &[], &[]);
         }
 
+        for (variable, value) in &context.variables {
+            match (variable.data_type(), value) {
+                (VariableDataType::Int, VariableValue::Int(value)) => {
+                    runner.set_variable_int_inner(variable, *value)
+                }
+                (VariableDataType::Float, VariableValue::Float(value)) => {
+                    runner.set_variable_float_inner(variable, *value)
+                }
+                (VariableDataType::Bool, VariableValue::Bool(value)) => {
+                    runner.set_variable_bool_inner(variable, *value)
+                }
+                _ => error!(
+                    "Initial value {:?} does not match the type of variable {:?}",
+                    value, variable
+                ),
+            }
+        }
+
         // Need to run the script until the first wait, so that the script is in the valid state
         // for the first frame.
         runner.step_script();
@@ -528,15 +698,64 @@ impl<'a> ScriptRunner<'a> {
         runner
     }
 
+    /// Runs the action at `action_index` starting from its entry script.
+    /// Unlike `ScriptRunner::new`, subaction changes are followed by switching to the scripts of the new subaction.
+    /// Interrupts that are followed, as configured by `SimulationContext::follow_interrupts`, run the exit script of the current action and then the entry script of the new action.
+    ///
+    /// The returned runner has completed the first frame.
+    /// Returns None if `action_index` is not in `actions`.
+    pub fn new_action(
+        action_index: usize,
+        wiird_frame_speed_modifiers: &'a [WiiRDFrameSpeedModifier],
+        actions: &'a [HighLevelAction],
+        subactions: &'a [HighLevelSubaction],
+        fighter_scripts: &'a [&'a ScriptAst],
+        common_scripts: &'a [&'a ScriptAst],
+        section_scripts: &'a [SectionScriptAst],
+        model_visibility: &ModelVisibility,
+        context: SimulationContext,
+    ) -> Option<ScriptRunner<'a>> {
+        let action = actions.get(action_index)?;
+        let mut runner = ScriptRunner::new(
+            0,
+            wiird_frame_speed_modifiers,
+            &[],
+            fighter_scripts,
+            common_scripts,
+            section_scripts,
+            &Block { events: vec![] },
+            model_visibility,
+            String::new(),
+            context,
+        );
+        runner.actions = actions;
+        runner.subactions = subactions;
+        runner.action_index = Some(action_index);
+
+        // The subaction call stacks are empty until the entry script changes the subaction
+        runner.call_stacks = (0..4).map(|_| CallStack::empty()).collect();
+        runner
+            .call_stacks
+            .push(CallStack::new(&action.script_entry.block));
+        runner.run_call_stack(ACTION_CALL_STACK);
+        runner.run_independent_threads();
+        runner.follow_changes();
+
+        Some(runner)
+    }
+
     /// Steps the main, gfx, sfx and other scripts by 1 game frame.
     pub fn step(&mut self) {
         let mut fsms = vec![];
         for fsm in self.wiird_frame_speed_modifiers {
-            // TODO: Because we currently only operate at the subaction level, this is the best we can do.
-            if !fsm.action
-                && fsm.action_subaction_id as usize == self.subaction_index
-                && (self.frame_index as u16) >= fsm.frame as u16
-            {
+            let applies = if fsm.action {
+                self.action_index == Some(fsm.action_subaction_id as usize)
+                    && (self.action_frame_index as u16) >= fsm.frame as u16
+            } else {
+                fsm.action_subaction_id as usize == self.subaction_index
+                    && (self.frame_index as u16) >= fsm.frame as u16
+            };
+            if applies {
                 fsms.push(fsm);
             }
         }
@@ -547,11 +766,174 @@ impl<'a> ScriptRunner<'a> {
         }
         self.frame_index += self.frame_speed_modifier;
         self.animation_index += self.frame_speed_modifier;
+        self.action_frame_index += self.frame_speed_modifier;
         self.frame_count += 1;
         self.step_script();
     }
 
+    /// Interrupts that are enabled and not in a disabled group
+    pub fn active_interrupts(&self) -> impl Iterator<Item = &ScriptInterrupt> {
+        self.interrupts
+            .iter()
+            .filter(|x| x.enabled && !self.disabled_interrupt_groups.contains(&x.interrupt_type))
+    }
+
+    /// Returns true if the subaction can be interrupted on the current frame.
+    /// This happens when interrupts are allowed and a common interrupt group is enabled,
+    /// or when the requirements of an active interrupt are met by the `SimulationContext`.
+    pub fn can_interrupt(&self) -> bool {
+        let common_group_enabled = COMMON_INTERRUPT_GROUPS
+            .map(InterruptType::new)
+            .any(|x| !self.disabled_interrupt_groups.contains(&x));
+        (self.interruptible && common_group_enabled)
+            || self.active_interrupts().any(|x| x.requirement_met)
+    }
+
+    /// The interrupts with `interrupt_id`, in `interrupt_type` or in any group when None
+    fn find_interrupts(
+        &mut self,
+        interrupt_type: Option<&InterruptType>,
+        interrupt_id: i32,
+    ) -> impl Iterator<Item = &mut ScriptInterrupt> {
+        self.interrupts.iter_mut().filter(move |x| {
+            interrupt_type.is_none_or(|ty| &x.interrupt_type == ty)
+                && x.interrupt_id == Some(interrupt_id)
+        })
+    }
+
+    /// Rechecks the requirements of every interrupt against the state at the end of the frame
+    fn check_interrupts(&mut self) {
+        for i in 0..self.interrupts.len() {
+            let test = self.interrupts[i].test.clone();
+            self.interrupts[i].requirement_met = self.evaluate_expression(&test).unwrap_bool();
+        }
+
+        if self.context.follow_interrupts
+            && let ChangeSubaction::Continue = self.change_subaction
+        {
+            let action = self
+                .active_interrupts()
+                .find(|x| x.requirement_met)
+                .map(|x| x.action);
+            if let Some(action) = action {
+                self.change_subaction = ChangeSubaction::Interrupt(action);
+            }
+        }
+    }
+
+    /// Applies any subaction change or followed interrupt, running the scripts they start within the current frame.
+    /// Does nothing unless an action is being run.
+    fn follow_changes(&mut self) {
+        if self.action_index.is_none() {
+            return;
+        }
+
+        // Scripts can keep changing back and forth, so give up after a while.
+        for _ in 0..MAX_CHANGES_PER_FRAME {
+            match self.change_subaction {
+                ChangeSubaction::ChangeSubaction(subaction)
+                | ChangeSubaction::ChangeSubactionRestartFrame(subaction) => {
+                    let restart_frame = matches!(
+                        self.change_subaction,
+                        ChangeSubaction::ChangeSubactionRestartFrame(_)
+                    );
+                    if !self.start_subaction(subaction, restart_frame) {
+                        return;
+                    }
+                    for i in 0..4 {
+                        self.run_call_stack(i);
+                    }
+                }
+                ChangeSubaction::Interrupt(action) => {
+                    if !self.start_action(action) {
+                        return;
+                    }
+                }
+                ChangeSubaction::Continue | ChangeSubaction::InfiniteLoop => return,
+            }
+            // Threads started by the new scripts run on this frame too
+            self.run_independent_threads();
+        }
+        self.change_subaction = ChangeSubaction::InfiniteLoop;
+    }
+
+    /// Replaces the subaction scripts with those of the subaction at `subaction_index`.
+    /// Returns false if there is no such subaction.
+    fn start_subaction(&mut self, subaction_index: i32, restart_frame: bool) -> bool {
+        let Some(subaction) = usize::try_from(subaction_index)
+            .ok()
+            .and_then(|x| self.subactions.get(x))
+        else {
+            return false;
+        };
+        let scripts = &subaction.scripts;
+        for (i, script) in [
+            &scripts.script_main,
+            &scripts.script_gfx,
+            &scripts.script_sfx,
+            &scripts.script_other,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            self.call_stacks[i] = CallStack::new(&script.block);
+        }
+
+        self.subaction_index = subaction_index as usize;
+        self.subaction_name = subaction.name.clone();
+        self.subaction_changed = true;
+        self.change_subaction = ChangeSubaction::Continue;
+        self.ledge_grab_enable = default_ledge_grab_enable(&subaction.name);
+        self.hitboxes = [None, None, None, None, None, None, None];
+        self.hitbox_has_connected = false;
+        self.frame_speed_modifier = 1.0;
+        if restart_frame {
+            // Independent threads keep waiting for the same number of frames
+            for thread in &mut self.independent_threads {
+                thread.call_stack.wait_until -= self.frame_index;
+            }
+            self.frame_index = 0.0;
+            self.animation_index = 0.0;
+        }
+        self.remove_independent_threads(false);
+        true
+    }
+
+    /// Runs the exit script of the current action and then the entry script of the action at `action_index`.
+    /// Returns false if there is no such action.
+    fn start_action(&mut self, action_index: i32) -> bool {
+        let Some(action) = usize::try_from(action_index)
+            .ok()
+            .and_then(|x| self.actions.get(x))
+        else {
+            return false;
+        };
+
+        if let Some(prev_action) = self.action_index.and_then(|x| self.actions.get(x)) {
+            self.call_stacks[ACTION_CALL_STACK] = CallStack::new(&prev_action.script_exit.block);
+            self.run_call_stack(ACTION_CALL_STACK);
+        }
+
+        // Interrupts and the enabled interrupt groups belong to the action
+        self.action_index = Some(action_index as usize);
+        self.action_frame_index = 0.0;
+        self.change_subaction = ChangeSubaction::Continue;
+        self.interrupts.clear();
+        self.previous_interrupt = None;
+        self.disabled_interrupt_groups.clear();
+        self.interruptible = false;
+        self.call_every_frame.clear();
+        self.hitboxes = [None, None, None, None, None, None, None];
+        self.remove_independent_threads(true);
+
+        self.call_stacks[ACTION_CALL_STACK] = CallStack::new(&action.script_entry.block);
+        self.run_call_stack(ACTION_CALL_STACK);
+        true
+    }
+
     fn step_script(&mut self) {
+        self.subaction_changed = false;
+        self.hitbox_has_connected |= self.hitbox_connected;
         for rehit in self.hitbox_sets_rehit.iter_mut() {
             *rehit = false;
         }
@@ -561,6 +943,9 @@ impl<'a> ScriptRunner<'a> {
         self.x_vel_modify = VelModify::None;
         self.y_vel_modify = VelModify::None;
         self.reverse_direction = false;
+        for thread in &mut self.independent_threads {
+            thread.stepped = false;
+        }
 
         // The hitbox existed last frame so should be interpolated.
         // (Unless it gets overwritten, but that will be handled when that happens)
@@ -568,13 +953,6 @@ impl<'a> ScriptRunner<'a> {
             hitbox.interpolate = true;
         }
 
-        for interrupt in self.interrupts.clone() {
-            if self.evaluate_expression(&interrupt.test).unwrap_bool() {
-                // TODO: Because we currently only operate at the subaction level, this is the best we can do.
-                self.change_subaction = ChangeSubaction::Interrupt(interrupt.action);
-            }
-        }
-
         // create a callstack for CallEveryFrame block
         for call_every_frame in self.call_every_frame.values() {
             let calls = vec![Call {
@@ -585,6 +963,7 @@ impl<'a> ScriptRunner<'a> {
                 external: call_every_frame.external,
                 if_statement: false,
                 execute: true,
+                switches: vec![],
             }];
             self.call_stacks.push(CallStack {
                 calls,
@@ -594,44 +973,165 @@ impl<'a> ScriptRunner<'a> {
 
         // run the main, gfx, sfx and other scripts
         for i in 0..self.call_stacks.len() {
-            while !self.call_stacks[i].calls.is_empty() {
-                // reached the end of the script
-                // Handle wait events
-                if self.frame_index < self.call_stacks[i].wait_until {
-                    break;
-                }
+            self.run_call_stack(i);
+        }
+        self.run_independent_threads();
 
-                // Process the next event in the call_stack
-                let call = self.call_stacks[i].calls.last().unwrap();
-                if let Some(event) = call.block.events.get(call.index) {
-                    self.call_stacks[i].calls.last_mut().unwrap().index += 1;
-                    let external = self.call_stacks[i].calls.last().unwrap().external;
+        // CallEveryFrame call stacks only get one frame to complete so remove them now.
+        // keep main, gfx, sfx and other call stacks, along with the action call stack when running an action
+        if self.action_index.is_some() {
+            self.call_stacks.truncate(ACTION_CALL_STACK + 1);
+        } else {
+            self.call_stacks.truncate(4);
+        }
 
-                    if self.call_stacks[i].calls.last().unwrap().execute {
-                        match self.step_event(
-                            event,
-                            external,
-                            self.fighter_scripts,
-                            self.common_scripts,
-                            self.section_scripts,
-                        ) {
-                            StepEventResult::WaitUntil(value) => {
-                                self.call_stacks[i].wait_until = value;
-                            }
-                            StepEventResult::NewForLoop { block, iterations } => {
-                                for _ in 0..iterations {
-                                    self.call_stacks[i].calls.push(Call {
-                                        block,
-                                        else_branch: None,
-                                        index: 0,
-                                        subroutine: false,
-                                        if_statement: false,
-                                        execute: true,
-                                        external,
-                                    });
-                                }
+        if self.frame_speed_modifier == 0.0 {
+            self.change_subaction = ChangeSubaction::InfiniteLoop
+        }
+
+        self.check_interrupts();
+        self.follow_changes();
+
+        match self.disable_movement {
+            DisableMovement::Enable => {
+                self.x += self.x_vel;
+                self.y += self.y_vel;
+            }
+            DisableMovement::DisableVertical => {
+                self.x += self.x_vel;
+            }
+            DisableMovement::DisableHorizontal => {
+                self.y += self.y_vel;
+            }
+            _ => error!("Unknown DisableMovement value"),
+        }
+    }
+
+    /// Runs every independent thread that has not run on this frame, including any threads started while doing so.
+    fn run_independent_threads(&mut self) {
+        while let Some(thread) = self.independent_threads.iter_mut().find(|x| !x.stepped) {
+            thread.stepped = true;
+            thread.running = true;
+            let call_stack = std::mem::replace(&mut thread.call_stack, CallStack::empty());
+
+            self.call_stacks.push(call_stack);
+            self.run_call_stack(self.call_stacks.len() - 1);
+            let call_stack = self.call_stacks.pop().unwrap();
+
+            // The thread may have removed or replaced itself while running
+            if let Some(thread) = self.independent_threads.iter_mut().find(|x| x.running) {
+                thread.call_stack = call_stack;
+                thread.running = false;
+            }
+        }
+
+        self.independent_threads
+            .retain(|x| !x.call_stack.calls.is_empty());
+    }
+
+    /// Removes the independent threads that do not outlive a change to the subaction or action
+    fn remove_independent_threads(&mut self, action_changed: bool) {
+        self.independent_threads
+            .retain(|thread| match thread.thread_type {
+                ThreadType::Subaction => false,
+                ThreadType::Action => !action_changed,
+                ThreadType::Independent | ThreadType::Unknown(_) => true,
+            });
+    }
+
+    /// The frame that waits in the call stack at index `i` are measured against.
+    /// The action scripts wait on the action frame, so restarting the frame of the subaction does not affect them.
+    fn call_stack_frame_index(&self, i: usize) -> f32 {
+        if self.action_index.is_some() && i == ACTION_CALL_STACK {
+            self.action_frame_index
+        } else {
+            self.frame_index
+        }
+    }
+
+    /// Runs the call stack at index `i` until it waits or finishes
+    fn run_call_stack(&mut self, i: usize) {
+        while !self.call_stacks[i].calls.is_empty() {
+            // reached the end of the script
+            // Handle wait events
+            if self.call_stack_frame_index(i) < self.call_stacks[i].wait_until {
+                break;
+            }
+
+            // Process the next event in the call_stack
+            let call = self.call_stacks[i].calls.last().unwrap();
+            if let Some(event) = call.block.events.get(call.index) {
+                self.call_stacks[i].calls.last_mut().unwrap().index += 1;
+                let external = self.call_stacks[i].calls.last().unwrap().external;
+
+                // Switch events decide whether execution is enabled so they run even when execution is disabled
+                match event {
+                    EventAst::Switch(_, value) => {
+                        let parent_execute = self.call_stacks[i].calls.last().unwrap().execute;
+                        let value = if parent_execute {
+                            self.evaluate_expression(value)
+                        } else {
+                            ExprResult::Int(0)
+                        };
+                        let call = self.call_stacks[i].calls.last_mut().unwrap();
+                        call.switches.push(SwitchState {
+                            value,
+                            matched: false,
+                            active: false,
+                            parent_execute,
+                        });
+                        call.execute = false;
+                        continue;
+                    }
+                    EventAst::Case(_) | EventAst::DefaultCase => {
+                        let call = self.call_stacks[i].calls.last_mut().unwrap();
+                        // consecutive cases share the events that follow them
+                        let follows_case = call.index >= 2
+                            && matches!(
+                                call.block.events[call.index - 2],
+                                EventAst::Case(_) | EventAst::DefaultCase
+                            );
+                        if let Some(switch) = call.switches.last_mut()
+                            && switch.parent_execute
+                        {
+                            if !(follows_case && switch.active) {
+                                switch.active = !switch.matched
+                                    && match event {
+                                        EventAst::Case(case) => switch.value.equals(*case),
+                                        _ => true,
+                                    };
                             }
-                            StepEventResult::NewCall { block } => {
+                            switch.matched |= switch.active;
+                            call.execute = switch.active;
+                        }
+                        continue;
+                    }
+                    EventAst::EndSwitch => {
+                        let call = self.call_stacks[i].calls.last_mut().unwrap();
+                        if let Some(switch) = call.switches.pop() {
+                            call.execute = switch.parent_execute;
+                        }
+                        continue;
+                    }
+                    _ => {}
+                }
+
+                if self.call_stacks[i].calls.last().unwrap().execute {
+                    match self.step_event(
+                        event,
+                        external,
+                        self.fighter_scripts,
+                        self.common_scripts,
+                        self.section_scripts,
+                    ) {
+                        StepEventResult::Wait(value) => {
+                            self.call_stacks[i].wait_until = self.call_stack_frame_index(i) + value;
+                        }
+                        StepEventResult::WaitUntil(value) => {
+                            self.call_stacks[i].wait_until = value;
+                        }
+                        StepEventResult::NewForLoop { block, iterations } => {
+                            for _ in 0..iterations {
                                 self.call_stacks[i].calls.push(Call {
                                     block,
                                     else_branch: None,
@@ -639,118 +1139,135 @@ impl<'a> ScriptRunner<'a> {
                                     subroutine: false,
                                     if_statement: false,
                                     execute: true,
+                                    switches: vec![],
                                     external,
                                 });
                             }
-                            StepEventResult::NewIfStatement {
-                                then_branch,
+                        }
+                        StepEventResult::NewCall { block } => {
+                            self.call_stacks[i].calls.push(Call {
+                                block,
+                                else_branch: None,
+                                index: 0,
+                                subroutine: false,
+                                if_statement: false,
+                                execute: true,
+                                switches: vec![],
+                                external,
+                            });
+                        }
+                        StepEventResult::NewIfStatement {
+                            then_branch,
+                            else_branch,
+                            execute,
+                        } => {
+                            self.call_stacks[i].calls.push(Call {
+                                block: then_branch,
                                 else_branch,
+                                index: 0,
+                                subroutine: false,
+                                if_statement: true,
                                 execute,
-                            } => {
-                                self.call_stacks[i].calls.push(Call {
-                                    block: then_branch,
-                                    else_branch,
-                                    index: 0,
-                                    subroutine: false,
-                                    if_statement: true,
-                                    execute,
-                                    external,
-                                });
-                            }
-                            StepEventResult::IfStatementDisableExecution => {
-                                if self.call_stacks[i].calls.last().unwrap().if_statement {
-                                    self.call_stacks[i].calls.last_mut().unwrap().execute = false;
-                                }
-                            }
-                            StepEventResult::Subroutine { block, external } => {
-                                self.call_stacks[i].calls.push(Call {
-                                    block,
-                                    else_branch: None,
-                                    index: 0,
-                                    subroutine: true,
-                                    if_statement: false,
-                                    execute: true,
-                                    external,
-                                });
+                                switches: vec![],
+                                external,
+                            });
+                        }
+                        StepEventResult::IfStatementDisableExecution => {
+                            if self.call_stacks[i].calls.last().unwrap().if_statement {
+                                self.call_stacks[i].calls.last_mut().unwrap().execute = false;
                             }
-                            StepEventResult::CallEveryFrame {
+                        }
+                        StepEventResult::Subroutine { block, external } => {
+                            self.call_stacks[i].calls.push(Call {
                                 block,
-                                thread_id,
+                                else_branch: None,
+                                index: 0,
+                                subroutine: true,
+                                if_statement: false,
+                                execute: true,
+                                switches: vec![],
                                 external,
-                            } => {
-                                self.call_every_frame
-                                    .insert(thread_id, CallEveryFrame { block, external });
-                            }
-                            StepEventResult::Return => {
-                                let mut run = false;
-                                while run {
-                                    run = self.call_stacks[i]
-                                        .calls
-                                        .pop()
-                                        .map(|x| !x.subroutine)
-                                        .unwrap_or(false);
-                                }
-                            }
-                            StepEventResult::Goto { block, external } => {
-                                self.call_stacks[i].calls.pop();
-                                self.call_stacks[i].calls.push(Call {
-                                    block,
-                                    else_branch: None,
-                                    index: 0,
-                                    subroutine: false,
-                                    if_statement: false,
-                                    execute: true,
-                                    external,
-                                });
-                            }
-                            StepEventResult::None => {}
+                            });
                         }
-                    } else {
-                        // when execution is disabled we run events that may resume execution, otherwise we do nothing
-                        let new_execution = match event {
-                            EventAst::IfStatementOr(test) => {
-                                self.evaluate_expression(test).unwrap_bool()
-                                    && self.call_stacks[i].calls.last().unwrap().if_statement
+                        StepEventResult::CallEveryFrame {
+                            block,
+                            thread_id,
+                            external,
+                        } => {
+                            self.call_every_frame
+                                .insert(thread_id, CallEveryFrame { block, external });
+                        }
+                        StepEventResult::IndependentSubroutine {
+                            block,
+                            thread_id,
+                            external,
+                        } => {
+                            // Starting a thread with the id of an existing thread replaces it
+                            self.independent_threads
+                                .retain(|x| x.thread_id != thread_id);
+                            let mut call_stack = CallStack::new(block);
+                            call_stack.calls[0].external = external;
+                            self.independent_threads.push(IndependentThread {
+                                thread_id,
+                                thread_type: ThreadType::Independent,
+                                call_stack,
+                                running: false,
+                                stepped: false,
+                            });
+                        }
+                        StepEventResult::Return => {
+                            let mut run = false;
+                            while run {
+                                run = self.call_stacks[i]
+                                    .calls
+                                    .pop()
+                                    .map(|x| !x.subroutine)
+                                    .unwrap_or(false);
                             }
-                            _ => false,
-                        };
-                        self.call_stacks[i].calls.last_mut().unwrap().execute = new_execution;
+                        }
+                        StepEventResult::Goto { block, external } => {
+                            self.call_stacks[i].calls.pop();
+                            self.call_stacks[i].calls.push(Call {
+                                block,
+                                else_branch: None,
+                                index: 0,
+                                subroutine: false,
+                                if_statement: false,
+                                execute: true,
+                                switches: vec![],
+                                external,
+                            });
+                        }
+                        StepEventResult::None => {}
                     }
                 } else {
-                    // If there is an else branch, begin processing that as the main block, otherwise pop the call
-                    let call = self.call_stacks[i].calls.last_mut().unwrap();
-                    if let Some(else_branch) = call.else_branch {
-                        call.block = else_branch;
-                        call.index = 0;
-                        call.else_branch = None;
-                        call.execute = !call.execute;
-                    } else {
-                        self.call_stacks[i].calls.pop();
-                    }
+                    // when execution is disabled we run events that may resume execution, otherwise we do nothing
+                    let new_execution = match event {
+                        EventAst::IfStatementOr(test) => {
+                            let call = self.call_stacks[i].calls.last().unwrap();
+                            // An Or cannot enable the events of a case that was not chosen
+                            let in_skipped_case = call.switches.last().is_some_and(|x| !x.active);
+                            call.if_statement
+                                && !in_skipped_case
+                                && self.evaluate_expression(test).unwrap_bool()
+                        }
+                        _ => false,
+                    };
+                    self.call_stacks[i].calls.last_mut().unwrap().execute = new_execution;
+                }
+            } else {
+                // If there is an else branch, begin processing that as the main block, otherwise pop the call
+                let call = self.call_stacks[i].calls.last_mut().unwrap();
+                if let Some(else_branch) = call.else_branch {
+                    call.block = else_branch;
+                    call.index = 0;
+                    call.else_branch = None;
+                    call.execute = !call.execute;
+                } else {
+                    self.call_stacks[i].calls.pop();
                 }
             }
         }
-
-        // CallEveryFrame call stacks only get one frame to complete so remove them now.
-        self.call_stacks.truncate(4); // keep main, gfx, sfx and other call stacks
-
-        if self.frame_speed_modifier == 0.0 {
-            self.change_subaction = ChangeSubaction::InfiniteLoop
-        }
-
-        match self.disable_movement {
-            DisableMovement::Enable => {
-                self.x += self.x_vel;
-                self.y += self.y_vel;
-            }
-            DisableMovement::DisableVertical => {
-                self.x += self.x_vel;
-            }
-            DisableMovement::DisableHorizontal => {
-                self.y += self.y_vel;
-            }
-            _ => error!("Unknown DisableMovement value"),
-        }
     }
 
     /// Returns the wait_until value
@@ -764,7 +1281,7 @@ impl<'a> ScriptRunner<'a> {
     ) -> StepEventResult<'b> {
         match event {
             EventAst::SyncWait(value) => {
-                return StepEventResult::WaitUntil(self.frame_index + *value);
+                return StepEventResult::Wait(*value);
             }
             EventAst::AsyncWait(value) => {
                 return StepEventResult::WaitUntil(*value);
@@ -872,7 +1389,8 @@ impl<'a> ScriptRunner<'a> {
                 }
             }
             EventAst::IfStatementOr(_) => {} // This is handled in the !execution branch
-            EventAst::Switch(_, _) => {}     // TODO
+            // Switch events are handled before execution is checked
+            EventAst::Switch(_, _) => {}
             EventAst::EndSwitch => {}
             EventAst::Case(_) => {}
             EventAst::DefaultCase => {}
@@ -910,28 +1428,134 @@ impl<'a> ScriptRunner<'a> {
             EventAst::RemoveCallEveryFrame { thread_id } => {
                 self.call_every_frame.remove(thread_id);
             }
-            EventAst::IndependentSubroutine { .. } => {} // TODO
-            EventAst::RemoveIndependentSubroutine { .. } => {} // TODO
-            EventAst::SetIndependentSubroutineThreadType { .. } => {} // TODO
-            EventAst::DisableInterrupt(_) => {}          // TODO
-            EventAst::EnableInterrupt(_) => {}           // TODO
-            EventAst::ToggleInterrupt { .. } => {}       // TODO
-            EventAst::EnableInterruptGroup(_) => {}      // TODO
-            EventAst::DisableInterruptGroup(_) => {}     // TODO
-            EventAst::ClearInterruptGroup(_) => {}       // TODO
-            EventAst::CreateInterrupt(interrupt) => {
-                // If the interrupt would succeed on the first frame then ignore it.
-                // This is a super hacky hack to get moves like DK's dash attack working.
-                if !(self.frame_index == 0.0
-                    && self.evaluate_expression(&interrupt.test).unwrap_bool())
+            EventAst::IndependentSubroutine { thread_id, offset } => {
+                if !external
+                    && let Some(script) = section_scripts
+                        .iter()
+                        .find(|x| x.callers.contains(&offset.origin))
                 {
-                    self.interrupts.push(interrupt.clone());
+                    return StepEventResult::IndependentSubroutine {
+                        thread_id: *thread_id,
+                        block: &script.script.block,
+                        external: true,
+                    };
+                }
+
+                let all_scripts = if external {
+                    common_scripts
                 } else {
-                    self.bad_interrupts.push(interrupt.clone());
+                    fighter_scripts
+                };
+                for script in all_scripts.iter() {
+                    if script.offset == offset.offset {
+                        return StepEventResult::IndependentSubroutine {
+                            thread_id: *thread_id,
+                            block: &script.block,
+                            external,
+                        };
+                    }
+                }
+                error!("Couldnt find IndependentSubroutine offset");
+            }
+            EventAst::RemoveIndependentSubroutine { thread_id } => {
+                self.independent_threads
+                    .retain(|x| x.thread_id != *thread_id);
+            }
+            EventAst::SetIndependentSubroutineThreadType {
+                thread_id,
+                thread_type,
+            } => {
+                let thread_type = ThreadType::new(*thread_type);
+                match self
+                    .independent_threads
+                    .iter_mut()
+                    .find(|x| x.thread_id == *thread_id)
+                {
+                    Some(thread) => thread.thread_type = thread_type,
+                    None => warn!(
+                        "SetIndependentSubroutineThreadType used on thread {} which is not running",
+                        thread_id
+                    ),
+                }
+            }
+            EventAst::DisableInterrupt(interrupt_id) => {
+                for interrupt in self.find_interrupts(None, *interrupt_id) {
+                    interrupt.enabled = false;
+                }
+            }
+            EventAst::EnableInterrupt(interrupt_id) => {
+                for interrupt in self.find_interrupts(None, *interrupt_id) {
+                    interrupt.enabled = true;
+                }
+            }
+            EventAst::ToggleInterrupt {
+                interrupt_type,
+                interrupt_id,
+            } => {
+                for interrupt in self.find_interrupts(Some(interrupt_type), *interrupt_id) {
+                    interrupt.enabled = !interrupt.enabled;
+                }
+            }
+            EventAst::EnableInterruptGroup(interrupt_type) => {
+                self.disabled_interrupt_groups
+                    .retain(|x| x != interrupt_type);
+            }
+            EventAst::DisableInterruptGroup(interrupt_type) => {
+                if !self.disabled_interrupt_groups.contains(interrupt_type) {
+                    self.disabled_interrupt_groups.push(interrupt_type.clone());
+                }
+            }
+            EventAst::ClearInterruptGroup(interrupt_type) => {
+                if let Some(previous) = self.previous_interrupt {
+                    self.previous_interrupt =
+                        if &self.interrupts[previous].interrupt_type == interrupt_type {
+                            None
+                        } else {
+                            let removed_before = self.interrupts[..previous]
+                                .iter()
+                                .filter(|x| &x.interrupt_type == interrupt_type)
+                                .count();
+                            Some(previous - removed_before)
+                        };
                 }
+                self.interrupts
+                    .retain(|x| &x.interrupt_type != interrupt_type);
+            }
+            EventAst::CreateInterrupt(interrupt) => {
+                // Interrupts created by the subaction always belong to the main group.
+                // The other groups are filled in by the game from the current action.
+                self.interrupts.push(ScriptInterrupt {
+                    interrupt_type: InterruptType::Main,
+                    interrupt_id: interrupt.interrupt_id,
+                    action: interrupt.action,
+                    test: interrupt.test.clone(),
+                    enabled: true,
+                    requirement_met: false,
+                });
+                self.previous_interrupt = Some(self.interrupts.len() - 1);
             }
             EventAst::PreviousInterruptAddRequirement { test } => {
-                if let Some(interrupt) = self.interrupts.last_mut() {
+                if let Some(interrupt) = self
+                    .previous_interrupt
+                    .and_then(|x| self.interrupts.get_mut(x))
+                {
+                    let left = Box::new(interrupt.test.clone());
+                    let right = Box::new(test.clone());
+                    let operator = ComparisonOperator::And;
+                    interrupt.test = Expression::Binary(BinaryExpression {
+                        left,
+                        operator,
+                        right,
+                    });
+                }
+            }
+            EventAst::InterruptAddRequirement {
+                interrupt_type,
+                interrupt_id,
+                test,
+            } => {
+                // Interrupts the game fills in for the common groups are not simulated, so only created interrupts can be modified.
+                for interrupt in self.find_interrupts(Some(interrupt_type), *interrupt_id) {
                     let left = Box::new(interrupt.test.clone());
                     let right = Box::new(test.clone());
                     let operator = ComparisonOperator::And;
@@ -942,7 +1566,6 @@ impl<'a> ScriptRunner<'a> {
                     });
                 }
             }
-            EventAst::InterruptAddRequirement { .. } => {} // TODO
             EventAst::AllowInterrupts => {
                 self.interruptible = true;
             }
@@ -1422,28 +2045,10 @@ impl<'a> ScriptRunner<'a> {
     #[rustfmt::skip]
     fn evaluate_expression(&mut self, expression: &Expression) -> ExprResult {
         match expression {
-            Expression::Nullary (requirement) => {
-                ExprResult::Bool (match requirement {
-                    Requirement::CharacterExists => true,
-                    Requirement::OnGround => true,
-                    Requirement::InAir => false,
-                    Requirement::FacingRight => true,
-                    Requirement::HasntTethered3Times => true,
-                    Requirement::IsNotInDamagingLens => true,
-                    _ => false
-                })
-            }
+            Expression::Nullary (requirement) => ExprResult::Bool (self.evaluate_requirement(requirement, None)),
             Expression::Unary (unary) => {
-                ExprResult::Bool (match unary.requirement {
-                    Requirement::CharacterExists => true,
-                    Requirement::OnGround => true,
-                    Requirement::InAir => false,
-                    Requirement::FacingRight => true,
-                    Requirement::HasntTethered3Times => true,
-                    Requirement::IsNotInDamagingLens => true,
-                    Requirement::BoolIsTrue => self.evaluate_expression(&unary.value).unwrap_bool(),
-                    _ => false
-                })
+                let value = self.evaluate_expression(&unary.value);
+                ExprResult::Bool (self.evaluate_requirement(&unary.requirement, Some(value)))
             }
             #[allow(clippy::float_cmp)]
             Expression::Binary (binary) => {
@@ -1522,6 +2127,40 @@ impl<'a> ScriptRunner<'a> {
         }
     }
 
+    fn evaluate_requirement(&self, requirement: &Requirement, value: Option<ExprResult>) -> bool {
+        let id = match value {
+            Some(ExprResult::Int(value)) => Some(value),
+            Some(ExprResult::Float(value)) => Some(value as i32),
+            Some(ExprResult::Bool(_)) | None => None,
+        };
+        let input = self.context.input(Some(self.frame_count));
+        let prev_input = self.context.input(self.frame_count.checked_sub(1));
+        let held = |input: Option<&FrameInput>| {
+            id.is_some_and(|id| input.is_some_and(|x| x.buttons.contains(&id)))
+        };
+        let stick = id.is_some_and(|id| input.is_some_and(|x| x.stick_directions.contains(&id)));
+        match requirement {
+            Requirement::CharacterExists => true,
+            Requirement::Always => true,
+            Requirement::OnGround => !self.airbourne,
+            Requirement::InAir => self.airbourne,
+            Requirement::FacingRight => self.context.facing_right,
+            Requirement::FacingLeft => !self.context.facing_right,
+            Requirement::HasntTethered3Times => true,
+            Requirement::IsNotInDamagingLens => true,
+            Requirement::HitboxConnects => self.hitbox_connected,
+            Requirement::HitboxHasConnected => self.hitbox_has_connected,
+            Requirement::BoolIsTrue => value.map(|x| x.unwrap_bool()).unwrap_or(false),
+            Requirement::ButtonPress | Requirement::ButtonTap => held(input) && !held(prev_input),
+            Requirement::ButtonRelease => !held(input) && held(prev_input),
+            Requirement::ButtonHeld => held(input),
+            Requirement::ButtonNotPressed => !held(input),
+            Requirement::StickDirectionPressed => stick,
+            Requirement::StickDirectionNotPressed => !stick,
+            _ => false,
+        }
+    }
+
     fn get_variable_int(&self, variable: &VariableAst) -> i32 {
         match variable.data_type() {
             VariableDataType::Int => self.get_variable_int_inner(variable),
@@ -1572,23 +2211,47 @@ impl<'a> ScriptRunner<'a> {
     }
 
     fn get_variable_int_inner(&self, variable: &VariableAst) -> i32 {
+        if let VariableAst::InternalConstantInt(constant) = variable
+            && let Some((_, value)) = self
+                .context
+                .internal_constants
+                .iter()
+                .find(|x| &x.0 == constant)
+        {
+            return *value;
+        }
+
         match variable {
             VariableAst::InternalConstantInt(InternalConstantInt::CurrentFrame) => {
                 self.frame_index as i32
             }
-            VariableAst::InternalConstantInt(InternalConstantInt::CharacterDirection) => 1,
-            VariableAst::InternalConstantInt(InternalConstantInt::CharacterDirectionOpposite) => -1,
+            VariableAst::InternalConstantInt(InternalConstantInt::Damage) => self.damage as i32,
+            VariableAst::InternalConstantInt(InternalConstantInt::CharacterDirection) => {
+                if self.context.facing_right { 1 } else { -1 }
+            }
+            VariableAst::InternalConstantInt(InternalConstantInt::CharacterDirectionOpposite) => {
+                if self.context.facing_right {
+                    -1
+                } else {
+                    1
+                }
+            }
             VariableAst::InternalConstantInt(InternalConstantInt::CurrentFrameSpeed) => {
                 self.frame_speed_modifier as i32
             }
-            VariableAst::InternalConstantInt(InternalConstantInt::CurrentSubaction) => 0, // TODO: Get this passed as an argument to ScriptRunner::new
-            VariableAst::InternalConstantInt(InternalConstantInt::CurrentAction) => 0, // TODO: Get this passed as an argument to ScriptRunner::new
+            VariableAst::InternalConstantInt(InternalConstantInt::CurrentSubaction) => {
+                self.subaction_index as i32
+            }
+            // When only running a subaction, the action is set through SimulationContext::internal_constants instead
+            VariableAst::InternalConstantInt(InternalConstantInt::CurrentAction) => {
+                self.action_index.unwrap_or(0) as i32
+            }
             VariableAst::InternalConstantInt(InternalConstantInt::CrawlControlStickXOffsetMax) => {
                 20
-            } // TODO: probably character dependent?
+            } // TODO: probably character dependent? Can be overridden by SimulationContext::internal_constants
             VariableAst::InternalConstantInt(InternalConstantInt::CrawlControlStickXOffsetMin) => {
                 -20
-            } // TODO: probably character dependent?
+            } // TODO: probably character dependent? Can be overridden by SimulationContext::internal_constants
             VariableAst::InternalConstantInt(_) => 0, // Best we can do for everything else is 0
             VariableAst::LongtermAccessInt(LongtermAccessInt::JumpsUsed) => self.jumps_used,
             VariableAst::LongtermAccessInt(LongtermAccessInt::WallJumpCount) => {
@@ -1730,11 +2393,12 @@ impl<'a> ScriptRunner<'a> {
             }
             VariableAst::LongtermAccessInt(LongtermAccessInt::Temp1) => self.temp1 = value,
             VariableAst::LongtermAccessInt(LongtermAccessInt::Temp2) => self.temp2 = value,
-            VariableAst::LongtermAccessInt(LongtermAccessInt::Address(address)) => {
-                if (*address as usize) < self.longterm_access_int.len() {
-                    self.longterm_access_int[*address as usize] = value;
-                }
-            }
+            VariableAst::LongtermAccessInt(LongtermAccessInt::Address(address)) => set_address(
+                &mut self.longterm_access_int,
+                *address,
+                value,
+                "LongtermAccessInt",
+            ),
 
             VariableAst::RandomAccessInt(RandomAccessInt::ThrowDataParam1) => {
                 self.throw_data_param1 = value
@@ -1745,11 +2409,12 @@ impl<'a> ScriptRunner<'a> {
             VariableAst::RandomAccessInt(RandomAccessInt::ThrowDataParam3) => {
                 self.throw_data_param3 = value
             }
-            VariableAst::RandomAccessInt(RandomAccessInt::Address(address)) => {
-                if (*address as usize) < self.random_access_int.len() {
-                    self.random_access_int[*address as usize] = value;
-                }
-            }
+            VariableAst::RandomAccessInt(RandomAccessInt::Address(address)) => set_address(
+                &mut self.random_access_int,
+                *address,
+                value,
+                "RandomAccessInt",
+            ),
 
             VariableAst::Unknown { .. } => {} // Likely from garbage data
 
@@ -1821,19 +2486,21 @@ impl<'a> ScriptRunner<'a> {
             VariableAst::LongtermAccessFloat(LongtermAccessFloat::CurryRandomness) => {
                 self.curry_randomness = value
             }
-            VariableAst::LongtermAccessFloat(LongtermAccessFloat::Address(address)) => {
-                if (*address as usize) < self.longterm_access_float.len() {
-                    self.longterm_access_float[*address as usize] = value;
-                }
-            }
+            VariableAst::LongtermAccessFloat(LongtermAccessFloat::Address(address)) => set_address(
+                &mut self.longterm_access_float,
+                *address,
+                value,
+                "LongtermAccessFloat",
+            ),
             VariableAst::RandomAccessFloat(RandomAccessFloat::EnableTurnWhenBelowZero) => {
                 self.enable_turn_when_below_zero = value
             }
-            VariableAst::RandomAccessFloat(RandomAccessFloat::Address(address)) => {
-                if (*address as usize) < self.random_access_float.len() {
-                    self.random_access_float[*address as usize] = value;
-                }
-            }
+            VariableAst::RandomAccessFloat(RandomAccessFloat::Address(address)) => set_address(
+                &mut self.random_access_float,
+                *address,
+                value,
+                "RandomAccessFloat",
+            ),
             VariableAst::Unknown { .. } => {} // Likely from garbage data
 
             VariableAst::LongtermAccessInt(_)
@@ -1957,11 +2624,12 @@ impl<'a> ScriptRunner<'a> {
             VariableAst::LongtermAccessBool(LongtermAccessBool::Deflection) => {
                 self.deflection = value
             }
-            VariableAst::LongtermAccessBool(LongtermAccessBool::Address(address)) => {
-                if (*address as usize) < self.longterm_access_bool.len() {
-                    self.longterm_access_bool[*address as usize] = value;
-                }
-            }
+            VariableAst::LongtermAccessBool(LongtermAccessBool::Address(address)) => set_address(
+                &mut self.longterm_access_bool,
+                *address,
+                value,
+                "LongtermAccessBool",
+            ),
 
             VariableAst::RandomAccessBool(RandomAccessBool::CharacterFloat) => {
                 self.character_float = value
@@ -1991,11 +2659,12 @@ impl<'a> ScriptRunner<'a> {
             VariableAst::RandomAccessBool(RandomAccessBool::EnableLandingLag) => {
                 self.landing_lag = value
             }
-            VariableAst::RandomAccessBool(RandomAccessBool::Address(address)) => {
-                if (*address as usize) < self.random_access_bool.len() {
-                    self.random_access_bool[*address as usize] = value;
-                }
-            }
+            VariableAst::RandomAccessBool(RandomAccessBool::Address(address)) => set_address(
+                &mut self.random_access_bool,
+                *address,
+                value,
+                "RandomAccessBool",
+            ),
             VariableAst::Unknown { .. } => {} // Likely from garbage data
 
             VariableAst::LongtermAccessInt(_)
@@ -2010,7 +2679,18 @@ impl<'a> ScriptRunner<'a> {
     }
 }
 
+/// Sets the variable at `address`, addresses past the end of `variables` are logged and ignored
+fn set_address<T>(variables: &mut [T], address: u32, value: T, name: &str) {
+    match variables.get_mut(address as usize) {
+        Some(variable) => *variable = value,
+        None => error!("{name} address {address} is out of range, the value was not set"),
+    }
+}
+
 enum StepEventResult<'a> {
+    /// Wait for this many frames
+    Wait(f32),
+    /// Wait until this frame
     WaitUntil(f32),
     NewForLoop {
         block: &'a Block,
@@ -2038,6 +2718,11 @@ enum StepEventResult<'a> {
         external: bool,
         thread_id: i32,
     },
+    IndependentSubroutine {
+        block: &'a Block,
+        external: bool,
+        thread_id: i32,
+    },
     Return,
     None,
 }
@@ -2047,6 +2732,45 @@ pub struct CallEveryFrame<'a> {
     pub external: bool,
 }
 
+/// A subroutine started by IndependentSubroutine.
+/// It runs alongside the subaction scripts until it finishes or is removed.
+pub struct IndependentThread<'a> {
+    pub thread_id: i32,
+    /// Set by SetIndependentSubroutineThreadType, a thread started with the id of an existing thread starts as `ThreadType::Independent` again
+    pub thread_type: ThreadType,
+    pub call_stack: CallStack<'a>,
+    /// true while the call_stack is being run, it is temporarily moved into `ScriptRunner::call_stacks` during that time.
+    running: bool,
+    /// Set once the thread has been run on the current frame
+    stepped: bool,
+}
+
+/// Determines when an independent subroutine thread is removed.
+/// The values are the thread type argument of SetIndependentSubroutineThreadType from Mawootad's Independent Subroutines code.
+/// 0 is the default type, the meanings of 1 and 2 have not been verified against that code.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ThreadType {
+    /// The thread runs until its subroutine finishes or it is removed, this is the default
+    Independent,
+    /// The thread is also removed when the action changes
+    Action,
+    /// The thread is also removed when the subaction or action changes
+    Subaction,
+    /// Treated the same as Independent
+    Unknown(i32),
+}
+
+impl ThreadType {
+    pub fn new(value: i32) -> Self {
+        match value {
+            0x00 => ThreadType::Independent,
+            0x01 => ThreadType::Action,
+            0x02 => ThreadType::Subaction,
+            _ => ThreadType::Unknown(value),
+        }
+    }
+}
+
 #[derive(Debug)]
 enum ExprResult {
     Int(i32),
@@ -2062,6 +2786,15 @@ impl ExprResult {
             ExprResult::Float(value) => *value != 0.0,
         }
     }
+
+    /// Compares against the value of a switch case
+    fn equals(&self, case: i32) -> bool {
+        match self {
+            ExprResult::Bool(result) => *result as i32 == case,
+            ExprResult::Int(value) => *value == case,
+            ExprResult::Float(value) => *value == case as f32,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -2080,3 +2813,637 @@ impl VelModify {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::high_level_fighter::tests::{action, subaction};
+    use crate::script::Offset;
+    use crate::script_ast::{Interrupt, UnaryExpression};
+
+    pub(crate) fn variable(address: u32) -> VariableAst {
+        VariableAst::RandomAccessInt(RandomAccessInt::Address(address))
+    }
+
+    pub(crate) fn set(address: u32, value: i32) -> EventAst {
+        EventAst::IntVariableSet {
+            value,
+            variable: variable(address),
+        }
+    }
+
+    pub(crate) fn script(events: Vec<EventAst>) -> ScriptAst {
+        ScriptAst {
+            block: Block { events },
+            offset: 0,
+        }
+    }
+
+    /// Runs the first frame of `script` as the main subaction script
+    fn runner(script: &ScriptAst, context: SimulationContext) -> ScriptRunner<'_> {
+        ScriptRunner::new(
+            0,
+            &[],
+            &[script],
+            &[],
+            &[],
+            &[],
+            &Block { events: vec![] },
+            &ModelVisibility {
+                references: vec![],
+                defaults: vec![],
+            },
+            String::new(),
+            context,
+        )
+    }
+
+    /// A switch over `value` that sets variable 0 to 1, 2 or 3 depending on whether the first, second or default case is chosen
+    fn switch_script(value: i32) -> ScriptAst {
+        script(vec![
+            EventAst::Switch(6, Expression::Value(value)),
+            set(1, 1), // before the first case
+            EventAst::Case(1),
+            set(0, 1),
+            EventAst::Case(2),
+            set(0, 2),
+            EventAst::DefaultCase,
+            set(0, 3),
+            EventAst::EndSwitch,
+            set(2, 1), // after the switch
+        ])
+    }
+
+    #[test]
+    fn switch_case() {
+        for (value, expected) in [(1, 1), (2, 2), (5, 3)] {
+            let script = switch_script(value);
+            let runner = runner(&script, SimulationContext::default());
+            assert_eq!(runner.random_access_int[0], expected, "switch over {value}");
+            assert_eq!(runner.random_access_int[1], 0);
+            assert_eq!(runner.random_access_int[2], 1);
+        }
+    }
+
+    #[test]
+    fn switch_over_variable() {
+        let script = script(vec![
+            EventAst::Switch(6, Expression::Variable(variable(3))),
+            EventAst::Case(4),
+            set(0, 4),
+            EventAst::DefaultCase,
+            set(0, 1),
+            EventAst::EndSwitch,
+        ]);
+        let context = SimulationContext {
+            variables: vec![(variable(3), VariableValue::Int(4))],
+            ..SimulationContext::default()
+        };
+        let runner = runner(&script, context);
+        assert_eq!(runner.random_access_int[0], 4);
+    }
+
+    #[test]
+    fn switch_case_does_not_fall_through() {
+        let script = script(vec![
+            EventAst::Switch(6, Expression::Value(1)),
+            EventAst::Case(1),
+            EventAst::IntVariableAdd {
+                value: 1,
+                variable: variable(0),
+            },
+            EventAst::Case(2),
+            EventAst::IntVariableAdd {
+                value: 10,
+                variable: variable(0),
+            },
+            EventAst::DefaultCase,
+            EventAst::IntVariableAdd {
+                value: 100,
+                variable: variable(0),
+            },
+            EventAst::EndSwitch,
+        ]);
+        let runner = runner(&script, SimulationContext::default());
+        assert_eq!(runner.random_access_int[0], 1);
+    }
+
+    #[test]
+    fn switch_consecutive_cases() {
+        for value in [1, 2, 3] {
+            let script = script(vec![
+                EventAst::Switch(6, Expression::Value(value)),
+                EventAst::Case(1),
+                EventAst::Case(2),
+                set(0, 1),
+                EventAst::Case(3),
+                EventAst::DefaultCase,
+                set(1, 1),
+                EventAst::EndSwitch,
+            ]);
+            let runner = runner(&script, SimulationContext::default());
+            let expected = if value == 3 { [0, 1] } else { [1, 0] };
+            assert_eq!(
+                runner.random_access_int[..2],
+                expected,
+                "switch over {value}"
+            );
+        }
+    }
+
+    #[test]
+    fn nested_switch() {
+        let script = script(vec![
+            EventAst::Switch(6, Expression::Value(1)),
+            EventAst::Case(1),
+            EventAst::Switch(6, Expression::Value(5)),
+            EventAst::Case(5),
+            set(0, 5),
+            EventAst::DefaultCase,
+            set(0, 6),
+            EventAst::EndSwitch,
+            set(1, 1), // the rest of the outer case runs after the inner switch
+            EventAst::Case(2),
+            EventAst::Switch(6, Expression::Value(5)),
+            EventAst::Case(5),
+            set(2, 1),
+            EventAst::EndSwitch,
+            set(3, 1),
+            EventAst::EndSwitch,
+        ]);
+        let runner = runner(&script, SimulationContext::default());
+        assert_eq!(runner.random_access_int[..4], [5, 1, 0, 0]);
+    }
+
+    /// A requirement that variable `address` equals `value`
+    pub(crate) fn equals(address: u32, value: i32) -> Expression {
+        Expression::Binary(BinaryExpression {
+            left: Box::new(Expression::Variable(variable(address))),
+            operator: ComparisonOperator::Equal,
+            right: Box::new(Expression::Value(value)),
+        })
+    }
+
+    pub(crate) fn create_interrupt(interrupt_id: i32, action: i32, test: Expression) -> EventAst {
+        EventAst::CreateInterrupt(Interrupt {
+            interrupt_id: Some(interrupt_id),
+            action,
+            test,
+        })
+    }
+
+    /// An interrupt in a group filled in by the game, which the scripts cannot create
+    fn common_interrupt(interrupt_id: i32) -> ScriptInterrupt {
+        ScriptInterrupt {
+            interrupt_type: InterruptType::GroundAttack,
+            interrupt_id: Some(interrupt_id),
+            action: 0x24,
+            test: equals(0, 0),
+            enabled: false,
+            requirement_met: false,
+        }
+    }
+
+    #[test]
+    fn interrupt_requirement() {
+        let script = script(vec![
+            create_interrupt(1, 5, equals(0, 1)),
+            EventAst::SyncWait(1.0),
+            set(0, 1),
+        ]);
+        let mut runner = runner(&script, SimulationContext::default());
+        assert!(!runner.interrupts[0].requirement_met);
+        assert!(!runner.can_interrupt());
+
+        runner.step();
+        assert!(runner.interrupts[0].requirement_met);
+        assert!(runner.can_interrupt());
+        // the interrupt is only recorded
+        assert!(matches!(runner.change_subaction, ChangeSubaction::Continue));
+    }
+
+    #[test]
+    fn follow_interrupt() {
+        let script = script(vec![create_interrupt(1, 5, equals(0, 0))]);
+        let context = SimulationContext {
+            follow_interrupts: true,
+            ..SimulationContext::default()
+        };
+        let runner = runner(&script, context);
+        assert!(matches!(
+            runner.change_subaction,
+            ChangeSubaction::Interrupt(5)
+        ));
+    }
+
+    #[test]
+    fn enable_disable_interrupt() {
+        let script = script(vec![
+            create_interrupt(1, 5, equals(0, 0)),
+            EventAst::DisableInterrupt(1),
+            EventAst::SyncWait(1.0),
+            EventAst::EnableInterrupt(1),
+            EventAst::EnableInterrupt(2),
+            EventAst::SyncWait(1.0),
+            EventAst::ToggleInterrupt {
+                interrupt_type: InterruptType::Main,
+                interrupt_id: 1,
+            },
+        ]);
+        let mut runner = runner(&script, SimulationContext::default());
+        assert!(!runner.interrupts[0].enabled);
+        assert!(!runner.can_interrupt());
+
+        runner.interrupts.push(common_interrupt(2));
+        runner.step();
+        assert!(runner.interrupts[0].enabled);
+        // EnableInterrupt is not limited to the main group
+        assert!(runner.interrupts[1].enabled);
+        assert!(runner.can_interrupt());
+
+        runner.step();
+        assert!(!runner.interrupts[0].enabled);
+        assert!(runner.interrupts[1].enabled);
+    }
+
+    #[test]
+    fn interrupt_groups() {
+        let script = script(vec![
+            create_interrupt(1, 5, equals(0, 0)),
+            EventAst::DisableInterruptGroup(InterruptType::Main),
+            EventAst::SyncWait(1.0),
+            EventAst::EnableInterruptGroup(InterruptType::Main),
+            EventAst::SyncWait(1.0),
+            EventAst::ClearInterruptGroup(InterruptType::Main),
+        ]);
+        let mut runner = runner(&script, SimulationContext::default());
+        assert!(runner.interrupts[0].requirement_met);
+        assert_eq!(runner.active_interrupts().count(), 0);
+        assert!(!runner.can_interrupt());
+
+        runner.step();
+        assert_eq!(runner.active_interrupts().count(), 1);
+        assert!(runner.can_interrupt());
+
+        runner.step();
+        assert!(runner.interrupts.is_empty());
+    }
+
+    #[test]
+    fn allow_interrupts_needs_common_group() {
+        let mut events = vec![EventAst::AllowInterrupts];
+        events.extend(
+            COMMON_INTERRUPT_GROUPS.map(|x| EventAst::DisableInterruptGroup(InterruptType::new(x))),
+        );
+        events.push(EventAst::SyncWait(1.0));
+        events.push(EventAst::EnableInterruptGroup(InterruptType::GroundAttack));
+        let script = script(events);
+        let mut runner = runner(&script, SimulationContext::default());
+        assert!(!runner.can_interrupt());
+
+        runner.step();
+        assert!(runner.can_interrupt());
+    }
+
+    #[test]
+    fn interrupt_add_requirement() {
+        let script = script(vec![
+            create_interrupt(1, 5, equals(0, 0)),
+            create_interrupt(2, 6, equals(0, 0)),
+            EventAst::PreviousInterruptAddRequirement { test: equals(1, 1) },
+            EventAst::InterruptAddRequirement {
+                interrupt_type: InterruptType::Main,
+                interrupt_id: 1,
+                test: equals(2, 1),
+            },
+            EventAst::SyncWait(1.0),
+            set(1, 1),
+            EventAst::SyncWait(1.0),
+            set(2, 1),
+        ]);
+        let mut runner = runner(&script, SimulationContext::default());
+        assert!(!runner.interrupts[0].requirement_met);
+        assert!(!runner.interrupts[1].requirement_met);
+
+        runner.step();
+        assert!(!runner.interrupts[0].requirement_met);
+        assert!(runner.interrupts[1].requirement_met);
+
+        runner.step();
+        assert!(runner.interrupts[0].requirement_met);
+    }
+
+    #[test]
+    fn previous_interrupt_add_requirement_after_clear() {
+        let script = script(vec![
+            EventAst::SyncWait(1.0),
+            create_interrupt(1, 5, equals(0, 0)),
+            EventAst::ClearInterruptGroup(InterruptType::Main),
+            // the interrupt it belongs to was cleared, so the common interrupt must not be modified
+            EventAst::PreviousInterruptAddRequirement { test: equals(0, 1) },
+        ]);
+        let mut runner = runner(&script, SimulationContext::default());
+        runner.interrupts.push(common_interrupt(2));
+        runner.interrupts[0].enabled = true;
+
+        runner.step();
+        assert_eq!(runner.interrupts.len(), 1);
+        assert!(runner.interrupts[0].requirement_met);
+    }
+
+    /// A script at `offset` for an IndependentSubroutine to run
+    fn subroutine(offset: i32, events: Vec<EventAst>) -> ScriptAst {
+        ScriptAst {
+            block: Block { events },
+            offset,
+        }
+    }
+
+    fn independent_subroutine(thread_id: i32, offset: i32) -> EventAst {
+        EventAst::IndependentSubroutine {
+            thread_id,
+            offset: Offset { offset, origin: 0 },
+        }
+    }
+
+    /// Runs the first frame of action 0
+    fn action_runner<'a>(
+        actions: &'a [HighLevelAction],
+        subactions: &'a [HighLevelSubaction],
+        fighter_scripts: &'a [&'a ScriptAst],
+    ) -> ScriptRunner<'a> {
+        let context = SimulationContext {
+            follow_interrupts: true,
+            ..SimulationContext::default()
+        };
+        ScriptRunner::new_action(
+            0,
+            &[],
+            actions,
+            subactions,
+            fighter_scripts,
+            &[],
+            &[],
+            &ModelVisibility {
+                references: vec![],
+                defaults: vec![],
+            },
+            context,
+        )
+        .unwrap()
+    }
+
+    fn thread_ids(runner: &ScriptRunner) -> Vec<i32> {
+        runner
+            .independent_threads
+            .iter()
+            .map(|x| x.thread_id)
+            .collect()
+    }
+
+    /// Action 0 runs subaction 0 for a frame and then subaction 1, which interrupts into action 1 on its second frame
+    fn thread_actions(
+        start_threads: Vec<EventAst>,
+    ) -> (Vec<HighLevelAction>, Vec<HighLevelSubaction>) {
+        let mut subaction_0 = start_threads;
+        subaction_0.extend([
+            EventAst::SyncWait(1.0),
+            EventAst::ChangeSubactionRestartFrame(1),
+        ]);
+        let actions = vec![
+            action(vec![EventAst::ChangeSubactionRestartFrame(0)]),
+            action(vec![]),
+        ];
+        let subactions = vec![
+            subaction(subaction_0),
+            subaction(vec![
+                EventAst::SyncWait(1.0),
+                create_interrupt(1, 1, equals(0, 0)),
+            ]),
+        ];
+        (actions, subactions)
+    }
+
+    #[test]
+    fn independent_thread_survives_subaction_change() {
+        // counts the frames it has run for in variable 1
+        let events = (0..10)
+            .flat_map(|_| {
+                [
+                    EventAst::IntVariableAdd {
+                        value: 1,
+                        variable: variable(1),
+                    },
+                    EventAst::SyncWait(1.0),
+                ]
+            })
+            .collect();
+        let counter = subroutine(0x100, events);
+        let fighter_scripts = [&counter];
+        let (actions, subactions) = thread_actions(vec![independent_subroutine(1, 0x100)]);
+        let mut runner = action_runner(&actions, &subactions, &fighter_scripts);
+        assert_eq!(thread_ids(&runner), [1]);
+        assert_eq!(runner.random_access_int[1], 1);
+
+        runner.step();
+        assert_eq!(runner.subaction_index, 1);
+        assert_eq!(thread_ids(&runner), [1]);
+        assert_eq!(runner.random_access_int[1], 2);
+
+        runner.step();
+        assert_eq!(runner.action_index, Some(1));
+        assert_eq!(thread_ids(&runner), [1]);
+        assert_eq!(runner.random_access_int[1], 3);
+    }
+
+    #[test]
+    fn independent_thread_types() {
+        let wait = subroutine(0x100, vec![EventAst::SyncWait(10.0)]);
+        let fighter_scripts = [&wait];
+        let (actions, subactions) = thread_actions(vec![
+            independent_subroutine(1, 0x100),
+            independent_subroutine(2, 0x100),
+            EventAst::SetIndependentSubroutineThreadType {
+                thread_id: 2,
+                thread_type: 1,
+            },
+            independent_subroutine(3, 0x100),
+            EventAst::SetIndependentSubroutineThreadType {
+                thread_id: 3,
+                thread_type: 2,
+            },
+        ]);
+        let mut runner = action_runner(&actions, &subactions, &fighter_scripts);
+        assert_eq!(thread_ids(&runner), [1, 2, 3]);
+        assert_eq!(
+            runner.independent_threads[2].thread_type,
+            ThreadType::Subaction
+        );
+
+        runner.step();
+        assert_eq!(runner.subaction_index, 1);
+        assert_eq!(thread_ids(&runner), [1, 2]);
+
+        runner.step();
+        assert_eq!(runner.action_index, Some(1));
+        assert_eq!(thread_ids(&runner), [1]);
+    }
+
+    #[test]
+    fn independent_thread_replaces_itself() {
+        // restarts itself on its second frame, so never reaches its last event
+        let restart = subroutine(
+            0x100,
+            vec![
+                EventAst::IntVariableAdd {
+                    value: 1,
+                    variable: variable(1),
+                },
+                EventAst::SyncWait(1.0),
+                independent_subroutine(1, 0x100),
+                EventAst::SyncWait(1.0),
+                set(2, 1),
+            ],
+        );
+        let fighter_scripts = [&restart];
+        let (actions, subactions) = thread_actions(vec![independent_subroutine(1, 0x100)]);
+        let mut runner = action_runner(&actions, &subactions, &fighter_scripts);
+        assert_eq!(runner.random_access_int[1], 1);
+
+        for frame in 2..5 {
+            runner.step();
+            assert_eq!(thread_ids(&runner), [1]);
+            assert_eq!(runner.random_access_int[1], frame);
+        }
+        assert_eq!(runner.random_access_int[2], 0);
+    }
+
+    fn requirement(requirement: Requirement, value: i32) -> Expression {
+        Expression::Unary(UnaryExpression {
+            requirement,
+            value: Box::new(Expression::Value(value)),
+        })
+    }
+
+    fn hold(buttons: Vec<i32>) -> FrameInput {
+        FrameInput {
+            buttons,
+            stick_directions: vec![],
+        }
+    }
+
+    #[test]
+    fn button_press_release() {
+        let script = script(vec![
+            create_interrupt(1, 5, requirement(Requirement::ButtonPress, 1)),
+            create_interrupt(2, 5, requirement(Requirement::ButtonRelease, 1)),
+            create_interrupt(3, 5, requirement(Requirement::ButtonHeld, 1)),
+        ]);
+        let context = SimulationContext {
+            inputs: vec![hold(vec![]), hold(vec![1]), hold(vec![1, 2]), hold(vec![2])],
+            ..SimulationContext::default()
+        };
+        let mut runner = runner(&script, context);
+        let mut met = vec![];
+        for _ in 0..5 {
+            met.push(
+                runner
+                    .interrupts
+                    .iter()
+                    .map(|x| x.requirement_met)
+                    .collect::<Vec<_>>(),
+            );
+            runner.step();
+        }
+        assert_eq!(
+            met,
+            [
+                [false, false, false],
+                [true, false, true],
+                [false, false, true],
+                [false, true, false],
+                // there are no inputs past the end
+                [false, false, false],
+            ]
+        );
+    }
+
+    #[test]
+    fn button_press_on_first_frame() {
+        let script = script(vec![create_interrupt(
+            1,
+            5,
+            requirement(Requirement::ButtonPress, 1),
+        )]);
+        let context = SimulationContext {
+            inputs: vec![hold(vec![1])],
+            ..SimulationContext::default()
+        };
+        let runner = runner(&script, context);
+        assert!(runner.interrupts[0].requirement_met);
+    }
+
+    #[test]
+    fn seed_variables() {
+        let script = script(vec![]);
+        let context = SimulationContext {
+            variables: vec![
+                (variable(1), VariableValue::Int(7)),
+                (
+                    VariableAst::RandomAccessFloat(RandomAccessFloat::Address(2)),
+                    VariableValue::Float(1.5),
+                ),
+                (
+                    VariableAst::RandomAccessBool(RandomAccessBool::Address(3)),
+                    VariableValue::Bool(true),
+                ),
+                // the wrong type and an out of range address are ignored
+                (variable(4), VariableValue::Float(2.0)),
+                (variable(0x1000), VariableValue::Int(1)),
+            ],
+            ..SimulationContext::default()
+        };
+        let runner = runner(&script, context);
+        assert_eq!(runner.random_access_int[1], 7);
+        assert_eq!(runner.random_access_float[2], 1.5);
+        assert!(runner.random_access_bool[3]);
+        assert_eq!(runner.random_access_int[4], 0);
+    }
+
+    #[test]
+    fn seed_variables_before_first_frame() {
+        let script = script(vec![EventAst::IntVariableAdd {
+            value: 1,
+            variable: variable(1),
+        }]);
+        let context = SimulationContext {
+            variables: vec![(variable(1), VariableValue::Int(7))],
+            ..SimulationContext::default()
+        };
+        let runner = runner(&script, context);
+        assert_eq!(runner.random_access_int[1], 8);
+    }
+
+    #[test]
+    fn hitbox_connects() {
+        let script = script(vec![
+            create_interrupt(1, 5, Expression::Nullary(Requirement::HitboxConnects)),
+            create_interrupt(2, 5, Expression::Nullary(Requirement::HitboxHasConnected)),
+        ]);
+        let mut runner = runner(&script, SimulationContext::default());
+        runner.step();
+        assert!(!runner.interrupts[0].requirement_met);
+        assert!(!runner.interrupts[1].requirement_met);
+
+        runner.hitbox_connected = true;
+        runner.step();
+        assert!(runner.interrupts[0].requirement_met);
+        assert!(runner.interrupts[1].requirement_met);
+
+        // HitboxConnects only lasts a frame but HitboxHasConnected lasts until the subaction changes
+        runner.hitbox_connected = false;
+        runner.step();
+        assert!(!runner.interrupts[0].requirement_met);
+        assert!(runner.interrupts[1].requirement_met);
+    }
+}
@@ -0,0 +1,167 @@
+use fancy_slice::FancySlice;
+
+use crate::bres::RawBresChild;
+use crate::chr0::{Keyframe, try_interpolated12_keyframe};
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
+use crate::resources;
+
+/// Parses a SHP0, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_shp0(data: FancySlice) -> Result<Shp0, ParseError> {
+    check_len(data, 0x18, "SHP0")?;
+    let _size              = data.i32_be(0x4);
+    let version            = data.i32_be(0x8);
+    let _bres_offset       = data.i32_be(0xc);
+    let resources_offset   = data.i32_be(0x10);
+    let string_list_offset = data.i32_be(0x14);
+    // version 4 adds a user data offset, shifting every following field along
+    let offset = match version {
+        3 => 0x18,
+        4 => 0x1c,
+        _ => return Err(ParseError::new("SHP0", 0, ParseErrorKind::UnknownVersion(version))),
+    };
+    check_len(data, offset + 0x10, "SHP0")?;
+    let string_offset      = data.i32_be(offset);
+    let _orig_path_offset  = data.i32_be(offset + 0x4);
+    let num_frames         = data.u16_be(offset + 0x8);
+    let _num_children      = data.u16_be(offset + 0xa);
+    let loop_value         = data.i32_be(offset + 0xc);
+
+    let name = try_str(&data, string_offset as usize, "SHP0 name")
+        .map_err(|err| err.offset_by(string_offset as usize))?
+        .to_string();
+    let string_list = try_offset(&data, string_list_offset as i64, 0, "SHP0 string list")?;
+
+    let resources_data = try_offset(&data, resources_offset as i64, 0, "SHP0")?;
+    let resources = resources::try_resources(resources_data)
+        .map_err(|err| err.offset_by(resources_offset as usize))?;
+
+    let mut children = vec!();
+    let mut string_list_indices = vec!();
+    for resource in resources {
+        let child_offset = resources_offset as i64 + resource.data_offset as i64;
+        let child_data = try_offset(&data, child_offset, SHP0_CHILD_SIZE, "SHP0 child")?;
+        let flags          = child_data.u32_be(0x0);
+        let _string_offset = child_data.i32_be(0x4);
+        let _base_index    = child_data.u16_be(0x8);
+        let num_targets    = child_data.u16_be(0xa);
+        let fixed_flags    = child_data.u32_be(0xc);
+        let indices_offset = child_data.i32_be(0x10);
+
+        let child_error = |err: ParseError| err.offset_by(child_offset as usize);
+        check_len(child_data, SHP0_CHILD_SIZE + num_targets as usize * 4, "SHP0 child").map_err(child_error)?;
+        let indices = try_offset(&child_data, indices_offset as i64, num_targets as usize * 2, "SHP0 child").map_err(child_error)?;
+
+        let mut targets = vec!();
+        for i in 0..num_targets as usize {
+            // Each index refers to a name in the string list, which is pointed to by an offset relative to the start of the list
+            let index = indices.u16_be(i * 2);
+            string_list_indices.push(index);
+            check_len(string_list, index as usize * 4 + 4, "SHP0 string list")
+                .map_err(|err| err.offset_by(string_list_offset as usize))?;
+            let target_string_offset = string_list.i32_be(index as usize * 4);
+            let name = try_str(&string_list, target_string_offset as usize, "SHP0 string list")
+                .map_err(|err| err.offset_by(string_list_offset as usize))?
+                .to_string();
+
+            let weight_offset = SHP0_CHILD_SIZE + i * 4;
+            let weight = if i < 32 && fixed_flags & (1 << i) != 0 {
+                Keyframe::Fixed(child_data.f32_be(weight_offset))
+            } else {
+                let keyframe_offset = child_data.i32_be(weight_offset) as i64;
+                let keyframe_data = try_offset(&child_data, keyframe_offset, 0, "SHP0 keyframe").map_err(child_error)?;
+                try_interpolated12_keyframe(keyframe_data, "SHP0 keyframe")
+                    .map_err(|err| err.offset_by((child_offset + keyframe_offset) as usize))?
+            };
+
+            targets.push(Shp0Target { name, weight });
+        }
+
+        children.push(Shp0Child {
+            name: resource.string,
+            enabled:          flags & 0b0001 != 0,
+            update_positions: flags & 0b0010 != 0,
+            update_normals:   flags & 0b0100 != 0,
+            update_colors:    flags & 0b1000 != 0,
+            targets,
+        });
+    }
+
+    let mut raw = RawBresChild::animation(data, resources_offset as usize, offset, 0x4, offset != 0x18, "SHP0")?;
+    string_list_indices.sort();
+    string_list_indices.dedup();
+    for index in string_list_indices {
+        raw.string(data, string_list_offset as usize + index as usize * 4, string_list_offset as usize, "SHP0 string list")?;
+    }
+
+    Ok(Shp0 {
+        name,
+        num_frames,
+        loop_value: loop_value != 0,
+        children,
+        raw,
+    })
+}
+
+/// Shape (morph target) animation, blending vertex arrays together
+#[derive(Clone, Debug)]
+pub struct Shp0 {
+    name: String,
+    num_frames: u16,
+    loop_value: bool,
+    children: Vec<Shp0Child>,
+    raw: RawBresChild,
+}
+
+impl Shp0 {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn num_frames(&self) -> u16 {
+        self.num_frames
+    }
+
+    pub fn loop_value(&self) -> bool {
+        self.loop_value
+    }
+
+    pub fn children(&self) -> &[Shp0Child] {
+        &self.children
+    }
+
+    /// Returns the bytes the SHP0 was parsed from, only the names are rewritten to point into the new string table.
+    pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
+        self.raw.compile(bres_offset)
+    }
+}
+
+const SHP0_CHILD_SIZE: usize = 0x14;
+#[derive(Clone, Debug)]
+pub struct Shp0Child {
+    /// Name of the MDL0 vertex array that is morphed
+    pub name: String,
+    pub enabled: bool,
+    pub update_positions: bool,
+    pub update_normals: bool,
+    pub update_colors: bool,
+    pub targets: Vec<Shp0Target>,
+}
+
+impl Shp0Child {
+    /// Returns the name and weight of every morph target at the specified frame
+    pub fn get_weights(&self, loop_value: bool, frame: i32) -> Vec<(&str, f32)> {
+        self.targets
+            .iter()
+            .map(|x| (x.name.as_str(), x.weight.get_value(loop_value, frame)))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Shp0Target {
+    /// Name of the MDL0 vertex array that is blended into the base vertex array
+    pub name: String,
+    /// Ranges from 0.0 (only the base vertex array) to 1.0 (only this vertex array)
+    pub weight: Keyframe,
+}
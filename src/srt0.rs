@@ -0,0 +1,257 @@
+use cgmath::Vector2;
+use fancy_slice::FancySlice;
+
+use crate::bres::RawBresChild;
+use crate::chr0::{Keyframe, try_interpolated12_keyframe};
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
+use crate::resources;
+
+/// Parses a SRT0, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_srt0(data: FancySlice) -> Result<Srt0, ParseError> {
+    check_len(data, 0x14, "SRT0")?;
+    let _size             = data.i32_be(0x4);
+    let version           = data.i32_be(0x8);
+    let _bres_offset      = data.i32_be(0xc);
+    let resources_offset  = data.i32_be(0x10);
+    // version 5 adds a user data offset, shifting every following field along
+    let offset = match version {
+        4 => 0x14,
+        5 => 0x18,
+        _ => return Err(ParseError::new("SRT0", 0, ParseErrorKind::UnknownVersion(version))),
+    };
+    check_len(data, offset + 0x14, "SRT0")?;
+    let string_offset     = data.i32_be(offset);
+    let _orig_path_offset = data.i32_be(offset + 0x4);
+    let num_frames        = data.u16_be(offset + 0x8);
+    let _num_children     = data.u16_be(offset + 0xa);
+    let matrix_mode       = data.i32_be(offset + 0xc);
+    let loop_value        = data.i32_be(offset + 0x10);
+
+    let name = try_str(&data, string_offset as usize, "SRT0 name")
+        .map_err(|err| err.offset_by(string_offset as usize))?
+        .to_string();
+
+    let resources_data = try_offset(&data, resources_offset as i64, 0, "SRT0")?;
+    let resources = resources::try_resources(resources_data)
+        .map_err(|err| err.offset_by(resources_offset as usize))?;
+
+    let mut materials = vec!();
+    for resource in resources {
+        let material_offset = resources_offset as i64 + resource.data_offset as i64;
+        let material_data = try_offset(&data, material_offset, SRT0_MATERIAL_SIZE, "SRT0 material")?;
+
+        let texture_flags  = material_data.u32_be(0x4);
+        let indirect_flags = material_data.u32_be(0x8);
+
+        let mut textures = vec!();
+        let mut indirect_textures = vec!();
+        let mut entry_offset = SRT0_MATERIAL_SIZE;
+        for (flags, count, textures) in [(texture_flags, 8, &mut textures), (indirect_flags, 3, &mut indirect_textures)] {
+            for index in 0..count {
+                if flags & (1 << index) != 0 {
+                    check_len(material_data, entry_offset + 4, "SRT0 material")
+                        .map_err(|err| err.offset_by(material_offset as usize))?;
+                    let texture_offset = material_data.i32_be(entry_offset) as i64;
+                    let texture_data = try_offset(&material_data, texture_offset, SRT0_TEXTURE_SIZE, "SRT0 texture")
+                        .map_err(|err| err.offset_by(material_offset as usize))?;
+                    textures.push(
+                        srt0_texture(texture_data, index as u8)
+                            .map_err(|err| err.offset_by((material_offset + texture_offset) as usize))?
+                    );
+                    entry_offset += 4;
+                }
+            }
+        }
+
+        materials.push(Srt0Material {
+            name: resource.string,
+            textures,
+            indirect_textures,
+        });
+    }
+
+    let raw = RawBresChild::animation(data, resources_offset as usize, offset, 0x0, offset != 0x14, "SRT0")?;
+
+    Ok(Srt0 {
+        name,
+        num_frames,
+        loop_value: loop_value != 0,
+        matrix_mode: Srt0MatrixMode::new(matrix_mode),
+        materials,
+        raw,
+    })
+}
+
+#[rustfmt::skip]
+fn srt0_texture(data: FancySlice, index: u8) -> Result<Srt0Texture, ParseError> {
+    let code = Srt0TextureCode::new(data.u32_be(0));
+
+    let mut data_offset = SRT0_TEXTURE_SIZE;
+    let mut next_keyframe = |fixed| {
+        check_len(data, data_offset + 4, "SRT0 texture")?;
+        let value = if fixed {
+            Keyframe::Fixed(data.f32_be(data_offset))
+        } else {
+            let offset = data.i32_be(data_offset) as i64;
+            let keyframe_data = try_offset(&data, offset, 0, "SRT0 keyframe")?;
+            try_interpolated12_keyframe(keyframe_data, "SRT0 keyframe")
+                .map_err(|err| err.offset_by(offset as usize))?
+        };
+        data_offset += 4;
+        Ok(value)
+    };
+
+    let (scale_x, scale_y) = if code.scale_one() {
+        (Keyframe::Fixed(1.0), Keyframe::Fixed(1.0))
+    } else if code.scale_isotropic() {
+        let scale = next_keyframe(code.scale_fixed_x())?;
+        (scale.clone(), scale)
+    } else {
+        let x = next_keyframe(code.scale_fixed_x())?;
+        let y = next_keyframe(code.scale_fixed_y())?;
+        (x, y)
+    };
+
+    let rotation = if code.rot_zero() {
+        Keyframe::Fixed(0.0)
+    } else {
+        next_keyframe(code.rot_fixed())?
+    };
+
+    let (translation_x, translation_y) = if code.translation_zero() {
+        (Keyframe::Fixed(0.0), Keyframe::Fixed(0.0))
+    } else {
+        let x = next_keyframe(code.translation_fixed_x())?;
+        let y = next_keyframe(code.translation_fixed_y())?;
+        (x, y)
+    };
+
+    Ok(Srt0Texture { index, scale_x, scale_y, rotation, translation_x, translation_y })
+}
+
+/// Texture scale, rotation and translation animation, applied to the texture coordinates of materials
+#[derive(Clone, Debug)]
+pub struct Srt0 {
+    name: String,
+    num_frames: u16,
+    loop_value: bool,
+    matrix_mode: Srt0MatrixMode,
+    materials: Vec<Srt0Material>,
+    raw: RawBresChild,
+}
+
+impl Srt0 {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn num_frames(&self) -> u16 {
+        self.num_frames
+    }
+
+    pub fn loop_value(&self) -> bool {
+        self.loop_value
+    }
+
+    pub fn matrix_mode(&self) -> &Srt0MatrixMode {
+        &self.matrix_mode
+    }
+
+    pub fn materials(&self) -> &[Srt0Material] {
+        &self.materials
+    }
+
+    /// Returns the bytes the SRT0 was parsed from, only the names are rewritten to point into the new string table.
+    pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
+        self.raw.compile(bres_offset)
+    }
+}
+
+/// The 3D modelling software the texture matrices are calculated to match
+#[derive(Clone, Debug)]
+pub enum Srt0MatrixMode {
+    Maya,
+    SoftImage,
+    Max3ds,
+    Unknown(i32),
+}
+
+impl Srt0MatrixMode {
+    fn new(value: i32) -> Srt0MatrixMode {
+        match value {
+            0 => Srt0MatrixMode::Maya,
+            1 => Srt0MatrixMode::SoftImage,
+            2 => Srt0MatrixMode::Max3ds,
+            _ => Srt0MatrixMode::Unknown(value),
+        }
+    }
+}
+
+const SRT0_MATERIAL_SIZE: usize = 0xc;
+#[derive(Clone, Debug)]
+pub struct Srt0Material {
+    /// Name of the MDL0 material being animated
+    pub name: String,
+    pub textures: Vec<Srt0Texture>,
+    pub indirect_textures: Vec<Srt0Texture>,
+}
+
+const SRT0_TEXTURE_SIZE: usize = 0x4;
+#[derive(Clone, Debug)]
+pub struct Srt0Texture {
+    /// Index into the materials texture references or indirect texture matrices
+    pub index: u8,
+    pub scale_x: Keyframe,
+    pub scale_y: Keyframe,
+    /// In degrees
+    pub rotation: Keyframe,
+    pub translation_x: Keyframe,
+    pub translation_y: Keyframe,
+}
+
+impl Srt0Texture {
+    pub fn get_transform(&self, loop_value: bool, frame: i32) -> TextureTransform {
+        TextureTransform {
+            scale: Vector2::new(
+                self.scale_x.get_value(loop_value, frame),
+                self.scale_y.get_value(loop_value, frame),
+            ),
+            rotation: self.rotation.get_value(loop_value, frame),
+            translation: Vector2::new(
+                self.translation_x.get_value(loop_value, frame),
+                self.translation_y.get_value(loop_value, frame),
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TextureTransform {
+    pub scale: Vector2<f32>,
+    /// In degrees
+    pub rotation: f32,
+    pub translation: Vector2<f32>,
+}
+
+#[derive(Clone, Debug)]
+struct Srt0TextureCode {
+    value: u32,
+}
+
+#[rustfmt::skip]
+impl Srt0TextureCode {
+    fn new(value: u32) -> Srt0TextureCode {
+        Srt0TextureCode { value }
+    }
+
+    fn scale_one          (&self) -> bool { self.value & 0b00_0000_0010 != 0 }
+    fn rot_zero           (&self) -> bool { self.value & 0b00_0000_0100 != 0 }
+    fn translation_zero   (&self) -> bool { self.value & 0b00_0000_1000 != 0 }
+    fn scale_isotropic    (&self) -> bool { self.value & 0b00_0001_0000 != 0 }
+    fn scale_fixed_x      (&self) -> bool { self.value & 0b00_0010_0000 != 0 }
+    fn scale_fixed_y      (&self) -> bool { self.value & 0b00_0100_0000 != 0 }
+    fn rot_fixed          (&self) -> bool { self.value & 0b00_1000_0000 != 0 }
+    fn translation_fixed_x(&self) -> bool { self.value & 0b01_0000_0000 != 0 }
+    fn translation_fixed_y(&self) -> bool { self.value & 0b10_0000_0000 != 0 }
+}
@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::arc;
+use crate::arc::{Arc, ArcChildData};
+use crate::bres::{BresChild, BresChildData};
+use crate::coll::Coll;
+use crate::mdl0::Mdl0;
+use crate::wii_memory::WiiMemory;
+
+use fancy_slice::FancySlice;
+
+#[derive(Debug)]
+pub struct Stage {
+    /// The file name without the extension e.g. STGBATTLEFIELD
+    pub name: String,
+    /// File name of the .rel module containing the stages code e.g. st_battle.rel
+    /// None if the module could not be found in the module directory.
+    pub module_name: Option<String>,
+    pub arc: Arc,
+    /// true if the stage file was read from the mod instead of vanilla brawl
+    pub read_from_mod: bool,
+}
+
+impl Stage {
+    /// Call this function to get Stage structs that correspond to each STG*.pac file in the 'stage/melee' directory
+    ///
+    /// brawl_stage_dir must point at an exported Brawl 'stage/melee' directory.
+    /// mod_stage_dir may point at a brawl mod 'stage/melee' directory.
+    /// Individual files in mod_stage_dir will replace files in the brawl_stage_dir with the same name.
+    ///
+    /// `module_dirs` are the 'module' directories searched for the stages .rel file.
+    pub fn load(
+        brawl_stage_dir: &Path,
+        mod_stage_dir: Option<&Path>,
+        module_dirs: &[PathBuf],
+    ) -> Vec<Stage> {
+        let mut stage_datas: HashMap<String, StageData> = HashMap::new();
+        for (dir, read_from_mod) in
+            std::iter::once((brawl_stage_dir, false)).chain(mod_stage_dir.map(|x| (x, true)))
+        {
+            let Ok(dir) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in dir.flatten() {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let upper_name = file_name.to_uppercase();
+                if !upper_name.starts_with("STG") || !upper_name.ends_with(".PAC") {
+                    continue;
+                }
+
+                match fs::read(entry.path()) {
+                    Ok(data) => {
+                        let name = file_name[..file_name.len() - 4].to_string();
+                        stage_datas.insert(
+                            name.to_uppercase(),
+                            StageData {
+                                name,
+                                data,
+                                read_from_mod,
+                            },
+                        );
+                    }
+                    Err(err) => error!("Failed to read stage {:?}: {}", entry.path(), err),
+                }
+            }
+        }
+
+        let mut stages: Vec<Stage> = stage_datas
+            .into_par_iter()
+            .filter_map(|(_, stage_data)| Stage::load_single(stage_data, module_dirs))
+            .collect();
+        stages.sort_by(|a, b| a.name.cmp(&b.name));
+        stages
+    }
+
+    fn load_single(stage_data: StageData, module_dirs: &[PathBuf]) -> Option<Stage> {
+        info!("Parsing stage: {}", stage_data.name);
+        let data = FancySlice::new(&stage_data.data);
+        let arc = match arc::try_arc(data, &WiiMemory::new(), false) {
+            Ok(arc) => arc,
+            Err(err) => {
+                let file_name = format!("{}.pac", stage_data.name);
+                error!(
+                    "Failed to load stage {}, {}",
+                    stage_data.name,
+                    err.with_file(Path::new(&file_name))
+                );
+                return None;
+            }
+        };
+
+        let module_name = module_file_name(&stage_data.name);
+        let module_name = if module_dirs.iter().any(|x| x.join(&module_name).exists()) {
+            Some(module_name)
+        } else {
+            None
+        };
+
+        Some(Stage {
+            name: stage_data.name,
+            module_name,
+            arc,
+            read_from_mod: stage_data.read_from_mod,
+        })
+    }
+
+    /// Every COLL in the stage, a stage may have multiple e.g. for moving platforms
+    pub fn get_collisions(&self) -> Vec<&Coll> {
+        let mut collisions = vec![];
+        arc_collisions(&self.arc, &mut collisions);
+        collisions
+    }
+
+    /// Every MDL0 in the stage
+    pub fn get_models(&self) -> Vec<&Mdl0> {
+        let mut models = vec![];
+        arc_models(&self.arc, &mut models);
+        models
+    }
+
+    /// Reads the spawn points, blast zone and camera bounds from the position bones of the stage models.
+    pub fn get_positions(&self) -> StagePositions {
+        let mut points: Vec<(&str, f32, f32)> = vec![];
+        for model in self.get_models() {
+            for bone in model.bones.iter().flat_map(|x| x.flatten()) {
+                let bone = bone.bone;
+                if !points.iter().any(|x| x.0 == bone.name) {
+                    // the transform is independent of the parent bone so it is the position within the stage
+                    points.push((&bone.name, bone.transform.w.x, bone.transform.w.y));
+                }
+            }
+        }
+        points.sort_by(|a, b| a.0.cmp(b.0));
+
+        let point = |name: &str| {
+            points
+                .iter()
+                .find(|x| x.0 == name)
+                .map(|x| StagePoint { x: x.1, y: x.2 })
+        };
+        let bounds = |prefix: &str| {
+            let a = point(&format!("{prefix}0N"))?;
+            let b = point(&format!("{prefix}1N"))?;
+            Some(StageBounds {
+                left: a.x.min(b.x),
+                right: a.x.max(b.x),
+                top: a.y.max(b.y),
+                bottom: a.y.min(b.y),
+            })
+        };
+        let numbered = |prefix: &str| {
+            points
+                .iter()
+                .filter(|x| {
+                    x.0.strip_prefix(prefix)
+                        .and_then(|x| x.strip_suffix('N'))
+                        .map(|x| !x.is_empty() && x.chars().all(|x| x.is_ascii_digit()))
+                        .unwrap_or(false)
+                })
+                .map(|x| StagePoint { x: x.1, y: x.2 })
+                .collect()
+        };
+
+        StagePositions {
+            spawns: numbered("Player"),
+            respawns: numbered("Rebirth"),
+            blast_zone: bounds("Dead"),
+            camera_bounds: bounds("CamLimit"),
+        }
+    }
+}
+
+struct StageData {
+    name: String,
+    data: Vec<u8>,
+    read_from_mod: bool,
+}
+
+/// Brawl derives most module names from the stage name, the exceptions are listed here.
+///
+/// The exceptions come from comparing the STG*.pac files in the vanilla 'stage/melee' directory
+/// with the st_*.rel files in the vanilla 'module' directory.
+/// A mod that pairs a stage with a differently named module is not handled.
+fn module_file_name(stage_name: &str) -> String {
+    let name = stage_name.to_uppercase();
+    let name = name.trim_start_matches("STG");
+    // alternate layouts of the same stage e.g. STGEMBLEM_00 share the module
+    let name = name.split('_').next().unwrap_or_default();
+    // numbered levels of the same stage e.g. STGTARGETLV1 share the module
+    let module = match name.trim_end_matches(|x: char| x.is_ascii_digit()) {
+        "BATTLEFIELD" => "battle",
+        "ONLINETRAINING" => "otrain",
+        "TARGETLV" => "tbreak",
+        "EDIT" => "stageedit",
+        name => return format!("st_{}.rel", name.to_lowercase()),
+    };
+    format!("st_{module}.rel")
+}
+
+fn arc_collisions<'a>(arc: &'a Arc, collisions: &mut Vec<&'a Coll>) {
+    for child in &arc.children {
+        match &child.data {
+            ArcChildData::Arc(arc) => arc_collisions(arc, collisions),
+            ArcChildData::Coll(coll) => collisions.push(coll),
+            _ => {}
+        }
+    }
+}
+
+fn arc_models<'a>(arc: &'a Arc, models: &mut Vec<&'a Mdl0>) {
+    for child in &arc.children {
+        match &child.data {
+            ArcChildData::Arc(arc) => arc_models(arc, models),
+            ArcChildData::Bres(bres) => bres_models(&bres.children, models),
+            _ => {}
+        }
+    }
+}
+
+fn bres_models<'a>(children: &'a [BresChild], models: &mut Vec<&'a Mdl0>) {
+    for child in children {
+        match &child.data {
+            BresChildData::Bres(children) => bres_models(children, models),
+            BresChildData::Mdl0(mdl0) => models.push(mdl0),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StagePositions {
+    /// The starting position of each player
+    pub spawns: Vec<StagePoint>,
+    /// Where players reappear after losing a stock
+    pub respawns: Vec<StagePoint>,
+    /// A player is KO'd when they leave this area
+    pub blast_zone: Option<StageBounds>,
+    /// The camera is kept within this area
+    pub camera_bounds: Option<StageBounds>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StagePoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct StageBounds {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arc::tests::arc_fixture;
+    use crate::coll::tests::coll_fixture;
+
+    #[test]
+    fn module_names() {
+        assert_eq!(module_file_name("STGBATTLEFIELD"), "st_battle.rel");
+        assert_eq!(module_file_name("STGONLINETRAINING"), "st_otrain.rel");
+        assert_eq!(module_file_name("STGTARGETLV1"), "st_tbreak.rel");
+        assert_eq!(module_file_name("STGEDIT_0"), "st_stageedit.rel");
+        assert_eq!(module_file_name("STGEMBLEM_00"), "st_emblem.rel");
+        assert_eq!(module_file_name("stgFinal"), "st_final.rel");
+    }
+
+    #[test]
+    fn get_collisions() {
+        let coll = coll_fixture();
+        let inner = arc_fixture("STGTEST", &[&coll]);
+        let data = arc_fixture("STGTEST", &[&inner, &coll]);
+        let stage = Stage {
+            name: String::from("STGTEST"),
+            module_name: None,
+            arc: arc::try_arc(FancySlice::new(&data), &WiiMemory::new(), false).unwrap(),
+            read_from_mod: false,
+        };
+
+        let collisions = stage.get_collisions();
+        assert_eq!(collisions.len(), 2);
+        assert!(collisions.iter().all(|x| x.planes.len() == 3));
+    }
+}
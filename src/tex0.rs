@@ -0,0 +1,258 @@
+use anyhow::{Error, bail};
+use fancy_slice::FancySlice;
+
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_str};
+use crate::plt0::Plt0;
+use crate::resources::StringTable;
+use crate::wii_texture_formats::{self, WiiPaletteFormat, WiiPixelFormat};
+
+/// Parses a TEX0, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_tex0(data: FancySlice) -> Result<Tex0, ParseError> {
+    check_len(data, TEX0_HEADER_SIZE, "TEX0")?;
+    let size             = data.i32_be(0x4);
+    let version          = data.i32_be(0x8);
+    //let bres_offset    = data.i32_be(0xc);
+    let data_offset      = data.i32_be(0x10);
+    let string_offset    = data.u32_be(0x14);
+    //let has_palette    = data.u32_be(0x18);
+    let width            = data.u16_be(0x1c);
+    let height           = data.u16_be(0x1e);
+    let pixel_format     = data.u32_be(0x20);
+    let num_mipmaps      = data.u32_be(0x24);
+    let min_lod          = data.f32_be(0x28);
+    let max_lod          = data.f32_be(0x2c);
+    let orig_path_offset = data.i32_be(0x30);
+
+    let pixel_format = WiiPixelFormat::new(pixel_format);
+
+    let user_data_offset = match version {
+        3 => data.i32_be(0x34),
+        1 | 2 => 0,
+        _ => return Err(ParseError::new("TEX0", 0, ParseErrorKind::UnknownVersion(version))),
+    };
+
+    let name = try_str(&data, string_offset as usize, "TEX0 name")
+        .map_err(|err| err.offset_by(string_offset as usize))?
+        .to_string();
+
+    if size < TEX0_HEADER_SIZE as i32 || data_offset < 0 || data_offset > size {
+        return Err(ParseError::new("TEX0", 0, ParseErrorKind::Malformed(format!("size {size} or data offset {data_offset} is invalid"))));
+    }
+    check_len(data, size as usize, "TEX0")?;
+
+    // TODO: Parse the user data, until then it is kept as the raw bytes between the header and the image data.
+    let extra_data = data.relative_slice(TEX0_HEADER_SIZE.min(data_offset as usize)..data_offset as usize).to_vec();
+    let user_data_offset = if user_data_offset == 0 || (user_data_offset as usize >= TEX0_HEADER_SIZE && (user_data_offset as usize) < data_offset as usize) {
+        user_data_offset
+    } else {
+        warn!("TEX0 {name} has user data at 0x{user_data_offset:x} outside of the header, it will be lost when compiled");
+        0
+    };
+
+    let mut mipmaps = vec!();
+    let mut offset = data_offset as usize;
+    for i in 0..num_mipmaps as usize {
+        let (width, height) = mipmap_size(width, height, i);
+        // Unknown formats are kept as a single mipmap containing all the data
+        let mipmap_size = pixel_format.data_size(width, height).unwrap_or(size as usize - offset);
+        if offset + mipmap_size > size as usize {
+            return Err(ParseError::new("TEX0", offset, ParseErrorKind::UnexpectedEnd { needed: mipmap_size, len: size as usize - offset }));
+        }
+        mipmaps.push(data.relative_slice(offset..offset + mipmap_size).to_vec());
+        offset += mipmap_size;
+
+        if let WiiPixelFormat::Unknown (_) = pixel_format {
+            break;
+        }
+    }
+
+    Ok(Tex0 { name, width, height, pixel_format, min_lod, max_lod, mipmaps, orig_path_offset, version, user_data_offset, extra_data })
+}
+
+/// Each mipmap halves the dimensions of the previous one, down to a minimum of 1 pixel
+fn mipmap_size(width: u16, height: u16, mipmap: usize) -> (usize, usize) {
+    let width = (width as usize >> mipmap).max(1);
+    let height = (height as usize >> mipmap).max(1);
+    (width, height)
+}
+
+const TEX0_HEADER_SIZE: usize = 0x40;
+#[derive(Clone, Debug)]
+pub struct Tex0 {
+    pub name: String,
+    pub width: u16,
+    pub height: u16,
+    pub pixel_format: WiiPixelFormat,
+    pub min_lod: f32,
+    pub max_lod: f32,
+    /// The encoded image data, the first mipmap is the full size image.
+    pub mipmaps: Vec<Vec<u8>>,
+    // TODO: Calculate this, what is it even pointing to?
+    orig_path_offset: i32,
+    version: i32,
+    /// Relative to the start of the TEX0, 0 when there is no user data
+    user_data_offset: i32,
+    /// The bytes between the header and the image data, contains the user data of version 3 TEX0s
+    extra_data: Vec<u8>,
+}
+
+impl Tex0 {
+    /// Encodes an RGBA8 image, stored row by row starting from the top left, into a TEX0 with `num_mipmaps` mipmaps.
+    ///
+    /// The indexed formats also return a generated palette.
+    /// It needs to be added to the BRES alongside the TEX0, `Bres::insert_texture` does this.
+    pub fn encode(
+        name: &str,
+        rgba: &[u8],
+        width: usize,
+        height: usize,
+        pixel_format: WiiPixelFormat,
+        num_mipmaps: usize,
+    ) -> Result<(Tex0, Option<Plt0>), Error> {
+        if width == 0 || height == 0 || width > 1024 || height > 1024 {
+            bail!("Textures must be between 1x1 and 1024x1024 but was {width}x{height}");
+        }
+        if num_mipmaps == 0 {
+            bail!("Textures need at least one mipmap");
+        }
+        if rgba.len() != width * height * 4 {
+            bail!(
+                "A {}x{} image needs 0x{:x} bytes of RGBA8 data but 0x{:x} bytes were given",
+                width,
+                height,
+                width * height * 4,
+                rgba.len()
+            );
+        }
+
+        let palette = if pixel_format.is_indexed() {
+            let max_colors = match pixel_format {
+                WiiPixelFormat::CI4 => 0x10,
+                WiiPixelFormat::CI8 => 0x100,
+                _ => 0x4000,
+            };
+            let colors =
+                wii_texture_formats::quantize_palette(rgba, max_colors, &WiiPaletteFormat::RGB5A3);
+            Some(Plt0::new(name, WiiPaletteFormat::RGB5A3, &colors))
+        } else {
+            None
+        };
+        let colors = palette.as_ref().map(|x| x.colors()).unwrap_or_default();
+
+        let mut mipmaps = vec![];
+        let mut image = rgba.to_vec();
+        let (mut image_width, mut image_height) = (width, height);
+        for _ in 0..num_mipmaps {
+            mipmaps.push(pixel_format.encode(&image, image_width, image_height, &colors)?);
+            (image, image_width, image_height) = downsample(&image, image_width, image_height);
+        }
+
+        let tex0 = Tex0 {
+            name: name.to_string(),
+            width: width as u16,
+            height: height as u16,
+            pixel_format,
+            min_lod: 0.0,
+            max_lod: (num_mipmaps - 1) as f32,
+            mipmaps,
+            orig_path_offset: 0,
+            version: 1,
+            user_data_offset: 0,
+            extra_data: vec![],
+        };
+        Ok((tex0, palette))
+    }
+
+    pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
+        let mut output = vec![];
+        let mut strings = StringTable::new();
+
+        let data_offset = TEX0_HEADER_SIZE + self.extra_data.len();
+        let size = data_offset + self.mipmaps.iter().map(|x| x.len()).sum::<usize>();
+
+        // create TEX0 header
+        output.extend("TEX0".chars().map(|x| x as u8));
+        output.extend(i32::to_be_bytes(size as i32));
+        output.extend(i32::to_be_bytes(self.version));
+        output.extend(i32::to_be_bytes(bres_offset));
+        output.extend(i32::to_be_bytes(data_offset as i32));
+        strings.reference(output.len(), 0, &self.name);
+        output.extend(u32::to_be_bytes(0)); // string_offset
+        output.extend(u32::to_be_bytes(self.pixel_format.is_indexed() as u32)); // has_palette
+        output.extend(u16::to_be_bytes(self.width));
+        output.extend(u16::to_be_bytes(self.height));
+        output.extend(u32::to_be_bytes(self.pixel_format.value()));
+        output.extend(u32::to_be_bytes(self.mipmaps.len() as u32));
+        output.extend(f32::to_be_bytes(self.min_lod));
+        output.extend(f32::to_be_bytes(self.max_lod));
+        output.extend(i32::to_be_bytes(self.orig_path_offset));
+        if self.version == 3 {
+            output.extend(i32::to_be_bytes(self.user_data_offset));
+        }
+        output.resize(TEX0_HEADER_SIZE, 0x00);
+        output.extend(&self.extra_data);
+
+        // create image data
+        for mipmap in &self.mipmaps {
+            output.extend(mipmap);
+        }
+
+        // The strings are not included in the size, otherwise they would be read as image data.
+        strings.compile(&mut output);
+
+        output
+    }
+
+    /// The width and height of the mipmap
+    pub fn mipmap_size(&self, mipmap: usize) -> (usize, usize) {
+        mipmap_size(self.width, self.height, mipmap)
+    }
+
+    /// Decodes the mipmap into RGBA8 pixels, stored row by row starting from the top left.
+    ///
+    /// The indexed formats need a palette, this is the PLT0 with the same name as the TEX0.
+    pub fn decode(&self, mipmap: usize, palette: Option<&Plt0>) -> Result<Vec<u8>, Error> {
+        let data = match self.mipmaps.get(mipmap) {
+            Some(data) => data,
+            None => bail!(
+                "TEX0 {} has no mipmap {}, it only has {} mipmaps",
+                self.name,
+                mipmap,
+                self.mipmaps.len()
+            ),
+        };
+        let palette = match palette {
+            Some(palette) => palette.colors(),
+            None if self.pixel_format.is_indexed() => bail!(
+                "TEX0 {} uses the {:?} format which needs a palette",
+                self.name,
+                self.pixel_format
+            ),
+            None => vec![],
+        };
+        let (width, height) = self.mipmap_size(mipmap);
+        self.pixel_format.decode(data, width, height, &palette)
+    }
+}
+
+/// Halves the dimensions of an RGBA8 image by averaging each 2x2 block of pixels.
+fn downsample(rgba: &[u8], width: usize, height: usize) -> (Vec<u8>, usize, usize) {
+    let new_width = (width / 2).max(1);
+    let new_height = (height / 2).max(1);
+    let mut output = Vec::with_capacity(new_width * new_height * 4);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            for channel in 0..4 {
+                let mut sum = 0;
+                for (sample_x, sample_y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let sample_x = (x * 2 + sample_x).min(width - 1);
+                    let sample_y = (y * 2 + sample_y).min(height - 1);
+                    sum += rgba[(sample_y * width + sample_x) * 4 + channel] as u32;
+                }
+                output.push(((sum + 2) / 4) as u8);
+            }
+        }
+    }
+    (output, new_width, new_height)
+}
@@ -1,10 +1,42 @@
 use fancy_slice::FancySlice;
 
-pub(crate) fn list_offset(data: FancySlice) -> ListOffset {
-    ListOffset {
-        start_offset: data.i32_be(0x0),
-        count: data.i32_be(0x4),
+use crate::parse_error::{ParseError, ParseErrorKind, try_offset};
+
+/// Reads the `ListOffset` at `offset`
+pub(crate) fn list_offset(
+    data: &FancySlice,
+    offset: usize,
+    structure: &'static str,
+) -> Result<ListOffset, ParseError> {
+    let list = try_offset(data, offset as i64, LIST_OFFSET_SIZE, structure)?;
+    Ok(ListOffset {
+        start_offset: list.i32_be(0x0),
+        count: list.i32_be(0x4),
+    })
+}
+
+/// The offset of every item in the list, an error if the list does not fit in the data
+pub(crate) fn list_offsets(
+    data: FancySlice,
+    list: &ListOffset,
+    item_size: usize,
+    structure: &'static str,
+) -> Result<impl Iterator<Item = usize> + use<>, ParseError> {
+    let fits = list.start_offset >= 0
+        && list.count >= 0
+        && list.start_offset as usize + list.count as usize * item_size <= data.len();
+    if !fits {
+        return Err(ParseError::new(
+            structure,
+            list.start_offset.max(0) as usize,
+            ParseErrorKind::Malformed(format!(
+                "a list of {} items at offset {} does not fit in the data",
+                list.count, list.start_offset
+            )),
+        ));
     }
+    let start = list.start_offset as usize;
+    Ok((0..list.count as usize).map(move |i| start + i * item_size))
 }
 
 pub(crate) const LIST_OFFSET_SIZE: usize = 0x8;
@@ -25,3 +57,15 @@ pub fn parse_tag(data: &[u8]) -> String {
     }
     tag
 }
+
+/// Maps a frame onto an index into per frame animation data of length `len`.
+/// Looping animations wrap around, other animations hold their final value.
+pub(crate) fn frame_index(loop_value: bool, frame: i32, len: usize) -> usize {
+    if len == 0 {
+        0
+    } else if loop_value {
+        frame.rem_euclid(len as i32) as usize
+    } else {
+        frame.clamp(0, len as i32 - 1) as usize
+    }
+}
@@ -0,0 +1,131 @@
+use fancy_slice::FancySlice;
+
+use crate::bres::RawBresChild;
+use crate::parse_error::{ParseError, ParseErrorKind, check_len, try_offset, try_str};
+use crate::resources;
+use crate::util;
+
+/// Parses a VIS0, returning an error instead of panicking on malformed data.
+#[rustfmt::skip]
+pub fn try_vis0(data: FancySlice) -> Result<Vis0, ParseError> {
+    check_len(data, 0x14, "VIS0")?;
+    let _size             = data.i32_be(0x4);
+    let version           = data.i32_be(0x8);
+    let _bres_offset      = data.i32_be(0xc);
+    let resources_offset  = data.i32_be(0x10);
+    // version 4 adds a user data offset, shifting every following field along
+    let offset = match version {
+        3 => 0x14,
+        4 => 0x18,
+        _ => return Err(ParseError::new("VIS0", 0, ParseErrorKind::UnknownVersion(version))),
+    };
+    check_len(data, offset + 0x10, "VIS0")?;
+    let string_offset     = data.i32_be(offset);
+    let _orig_path_offset = data.i32_be(offset + 0x4);
+    let num_frames        = data.u16_be(offset + 0x8);
+    let _num_children     = data.u16_be(offset + 0xa);
+    let loop_value        = data.i32_be(offset + 0xc);
+
+    let name = try_str(&data, string_offset as usize, "VIS0 name")
+        .map_err(|err| err.offset_by(string_offset as usize))?
+        .to_string();
+
+    let resources_data = try_offset(&data, resources_offset as i64, 0, "VIS0")?;
+    let resources = resources::try_resources(resources_data)
+        .map_err(|err| err.offset_by(resources_offset as usize))?;
+
+    let mut bones = vec!();
+    for resource in resources {
+        let bone_offset = resources_offset as i64 + resource.data_offset as i64;
+        let bone_data = try_offset(&data, bone_offset, VIS0_BONE_SIZE, "VIS0 bone")?;
+        let flags    = bone_data.u32_be(0x4);
+        let enabled  = flags & 1 != 0;
+        let constant = flags & 2 != 0;
+
+        let visibility = if constant {
+            Vis0Visibility::Constant(enabled)
+        } else {
+            // Stored as a bit per frame, most significant bit first, packed into u32s
+            check_len(bone_data, VIS0_BONE_SIZE + (num_frames as usize).div_ceil(32) * 4, "VIS0 bone")
+                .map_err(|err| err.offset_by(bone_offset as usize))?;
+            let frames = (0..num_frames as usize)
+                .map(|i| bone_data.u32_be(VIS0_BONE_SIZE + i / 32 * 4) & (0x8000_0000 >> (i % 32)) != 0)
+                .collect();
+            Vis0Visibility::Animated(frames)
+        };
+
+        bones.push(Vis0Bone {
+            name: resource.string,
+            visibility,
+        });
+    }
+
+    let raw = RawBresChild::animation(data, resources_offset as usize, offset, 0x0, offset != 0x14, "VIS0")?;
+
+    Ok(Vis0 {
+        name,
+        num_frames,
+        loop_value: loop_value != 0,
+        bones,
+        raw,
+    })
+}
+
+/// Bone visibility animation, hiding the objects attached to the bone
+#[derive(Clone, Debug)]
+pub struct Vis0 {
+    name: String,
+    num_frames: u16,
+    loop_value: bool,
+    bones: Vec<Vis0Bone>,
+    raw: RawBresChild,
+}
+
+impl Vis0 {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn num_frames(&self) -> u16 {
+        self.num_frames
+    }
+
+    pub fn loop_value(&self) -> bool {
+        self.loop_value
+    }
+
+    pub fn bones(&self) -> &[Vis0Bone] {
+        &self.bones
+    }
+
+    /// Returns the bytes the VIS0 was parsed from, only the names are rewritten to point into the new string table.
+    pub fn compile(&self, bres_offset: i32) -> Vec<u8> {
+        self.raw.compile(bres_offset)
+    }
+}
+
+const VIS0_BONE_SIZE: usize = 0x8;
+#[derive(Clone, Debug)]
+pub struct Vis0Bone {
+    /// Name of the MDL0 bone being animated
+    pub name: String,
+    pub visibility: Vis0Visibility,
+}
+
+impl Vis0Bone {
+    pub fn is_visible(&self, loop_value: bool, frame: i32) -> bool {
+        match &self.visibility {
+            Vis0Visibility::Constant(visible) => *visible,
+            Vis0Visibility::Animated(frames) => {
+                frames[util::frame_index(loop_value, frame, frames.len())]
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Vis0Visibility {
+    Constant(bool),
+    /// Visibility for each frame
+    Animated(Vec<bool>),
+}
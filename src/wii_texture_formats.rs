@@ -1,3 +1,6 @@
+use anyhow::{Error, bail};
+use std::collections::HashMap;
+
 #[derive(Serialize, Clone, Debug, PartialEq)]
 pub enum WiiPixelFormat {
     I4,
@@ -9,12 +12,13 @@ pub enum WiiPixelFormat {
     RGBA8,
     CI4,
     CI8,
+    CI14X2,
     CMPR4,
     Unknown(u32),
 }
 
 impl WiiPixelFormat {
-    pub(crate) fn _new(value: u32) -> Self {
+    pub(crate) fn new(value: u32) -> Self {
         match value {
             0x0 => WiiPixelFormat::I4,
             0x1 => WiiPixelFormat::I8,
@@ -25,13 +29,14 @@ impl WiiPixelFormat {
             0x6 => WiiPixelFormat::RGBA8,
             0x8 => WiiPixelFormat::CI4,
             0x9 => WiiPixelFormat::CI8,
+            0xA => WiiPixelFormat::CI14X2,
             0xE => WiiPixelFormat::CMPR4,
             _ => WiiPixelFormat::Unknown(value),
         }
     }
 
     #[rustfmt::skip]
-    pub(crate) fn _value(&self) -> u32 {
+    pub(crate) fn value(&self) -> u32 {
         match self {
             WiiPixelFormat::I4              => 0x0,
             WiiPixelFormat::I8              => 0x1,
@@ -42,10 +47,405 @@ impl WiiPixelFormat {
             WiiPixelFormat::RGBA8           => 0x6,
             WiiPixelFormat::CI4             => 0x8,
             WiiPixelFormat::CI8             => 0x9,
+            WiiPixelFormat::CI14X2          => 0xA,
             WiiPixelFormat::CMPR4           => 0xE,
             WiiPixelFormat::Unknown (value) => *value,
         }
     }
+
+    /// Width and height in pixels of the blocks that the image data is split into.
+    #[rustfmt::skip]
+    pub fn block_size(&self) -> Option<(usize, usize)> {
+        match self {
+            WiiPixelFormat::I4     => Some((8, 8)),
+            WiiPixelFormat::I8     => Some((8, 4)),
+            WiiPixelFormat::IA4    => Some((8, 4)),
+            WiiPixelFormat::IA8    => Some((4, 4)),
+            WiiPixelFormat::RGB565 => Some((4, 4)),
+            WiiPixelFormat::RGB5A3 => Some((4, 4)),
+            WiiPixelFormat::RGBA8  => Some((4, 4)),
+            WiiPixelFormat::CI4    => Some((8, 8)),
+            WiiPixelFormat::CI8    => Some((8, 4)),
+            WiiPixelFormat::CI14X2 => Some((4, 4)),
+            WiiPixelFormat::CMPR4  => Some((8, 8)),
+            WiiPixelFormat::Unknown (_) => None,
+        }
+    }
+
+    #[rustfmt::skip]
+    pub fn bits_per_pixel(&self) -> Option<usize> {
+        match self {
+            WiiPixelFormat::I4     => Some(4),
+            WiiPixelFormat::I8     => Some(8),
+            WiiPixelFormat::IA4    => Some(8),
+            WiiPixelFormat::IA8    => Some(16),
+            WiiPixelFormat::RGB565 => Some(16),
+            WiiPixelFormat::RGB5A3 => Some(16),
+            WiiPixelFormat::RGBA8  => Some(32),
+            WiiPixelFormat::CI4    => Some(4),
+            WiiPixelFormat::CI8    => Some(8),
+            WiiPixelFormat::CI14X2 => Some(16),
+            WiiPixelFormat::CMPR4  => Some(4),
+            WiiPixelFormat::Unknown (_) => None,
+        }
+    }
+
+    /// Returns true if the pixels are indexes into a palette
+    pub fn is_indexed(&self) -> bool {
+        matches!(
+            self,
+            WiiPixelFormat::CI4 | WiiPixelFormat::CI8 | WiiPixelFormat::CI14X2
+        )
+    }
+
+    /// The number of bytes used by an image of this format with the given dimensions.
+    /// Images are padded out to a whole number of blocks.
+    pub fn data_size(&self, width: usize, height: usize) -> Option<usize> {
+        let (block_width, block_height) = self.block_size()?;
+        let padded_width = width.div_ceil(block_width) * block_width;
+        let padded_height = height.div_ceil(block_height) * block_height;
+        Some(padded_width * padded_height * self.bits_per_pixel()? / 8)
+    }
+
+    /// Decodes `data` into RGBA8 pixels, stored row by row starting from the top left.
+    ///
+    /// `palette` is only used by the indexed formats, where it must contain the colors returned by `Plt0::colors`.
+    /// Indexes outside of the palette are decoded as transparent black.
+    pub fn decode(
+        &self,
+        data: &[u8],
+        width: usize,
+        height: usize,
+        palette: &[[u8; 4]],
+    ) -> Result<Vec<u8>, Error> {
+        let (block_width, block_height) = match self.block_size() {
+            Some(block_size) => block_size,
+            None => bail!("Cannot decode unknown pixel format {:?}", self),
+        };
+        let size = self.data_size(width, height).unwrap();
+        if data.len() < size {
+            bail!(
+                "{:?} image of {}x{} needs 0x{:x} bytes but only 0x{:x} bytes were given",
+                self,
+                width,
+                height,
+                size,
+                data.len()
+            );
+        }
+        if self.is_indexed() && palette.is_empty() {
+            bail!("{:?} image needs a palette", self);
+        }
+
+        let blocks_wide = width.div_ceil(block_width);
+        let blocks_high = height.div_ceil(block_height);
+        let block_bytes = block_width * block_height * self.bits_per_pixel().unwrap() / 8;
+
+        let mut output = vec![0; width * height * 4];
+        for block_y in 0..blocks_high {
+            for block_x in 0..blocks_wide {
+                let block_offset = (block_y * blocks_wide + block_x) * block_bytes;
+                let block = &data[block_offset..block_offset + block_bytes];
+
+                for y in 0..block_height {
+                    for x in 0..block_width {
+                        let image_x = block_x * block_width + x;
+                        let image_y = block_y * block_height + y;
+                        if image_x < width && image_y < height {
+                            let color = self.decode_pixel(block, x, y, block_width, palette);
+                            let offset = (image_y * width + image_x) * 4;
+                            output[offset..offset + 4].copy_from_slice(&color);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Decodes the pixel at `x`, `y` within `block`
+    fn decode_pixel(
+        &self,
+        block: &[u8],
+        x: usize,
+        y: usize,
+        block_width: usize,
+        palette: &[[u8; 4]],
+    ) -> [u8; 4] {
+        let i = y * block_width + x;
+        let nibble = || {
+            if i % 2 == 0 {
+                block[i / 2] >> 4
+            } else {
+                block[i / 2] & 0xf
+            }
+        };
+        let u16 = || u16::from_be_bytes([block[i * 2], block[i * 2 + 1]]);
+        let palette_color = |index: usize| palette.get(index).cloned().unwrap_or([0; 4]);
+
+        match self {
+            WiiPixelFormat::I4 => {
+                let intensity = nibble() * 0x11;
+                [intensity, intensity, intensity, intensity]
+            }
+            WiiPixelFormat::I8 => {
+                let intensity = block[i];
+                [intensity, intensity, intensity, intensity]
+            }
+            WiiPixelFormat::IA4 => {
+                let alpha = (block[i] >> 4) * 0x11;
+                let intensity = (block[i] & 0xf) * 0x11;
+                [intensity, intensity, intensity, alpha]
+            }
+            WiiPixelFormat::IA8 => decode_ia8(u16()),
+            WiiPixelFormat::RGB565 => decode_rgb565(u16()),
+            WiiPixelFormat::RGB5A3 => decode_rgb5a3(u16()),
+            WiiPixelFormat::RGBA8 => {
+                // The alpha and red values of the block come first, followed by the green and blue values.
+                [
+                    block[i * 2 + 1],
+                    block[0x20 + i * 2],
+                    block[0x20 + i * 2 + 1],
+                    block[i * 2],
+                ]
+            }
+            WiiPixelFormat::CI4 => palette_color(nibble() as usize),
+            WiiPixelFormat::CI8 => palette_color(block[i] as usize),
+            WiiPixelFormat::CI14X2 => palette_color((u16() & 0x3fff) as usize),
+            WiiPixelFormat::CMPR4 => {
+                // Each block contains 2x2 DXT1 sub blocks
+                let sub_block = (y / 4 * 2 + x / 4) * 8;
+                let sub_block = &block[sub_block..sub_block + 8];
+                let colors = cmpr_colors(
+                    u16::from_be_bytes([sub_block[0], sub_block[1]]),
+                    u16::from_be_bytes([sub_block[2], sub_block[3]]),
+                );
+                let index = (sub_block[4 + y % 4] >> (6 - (x % 4) * 2)) & 0b11;
+                colors[index as usize]
+            }
+            WiiPixelFormat::Unknown(_) => unreachable!(),
+        }
+    }
+
+    /// Encodes RGBA8 pixels, stored row by row starting from the top left, into this format.
+    ///
+    /// `palette` is only used by the indexed formats, each pixel is encoded as the index of the closest color in the palette.
+    /// Use `quantize_palette` to create a palette for an image.
+    pub fn encode(
+        &self,
+        rgba: &[u8],
+        width: usize,
+        height: usize,
+        palette: &[[u8; 4]],
+    ) -> Result<Vec<u8>, Error> {
+        let (block_width, block_height) = match self.block_size() {
+            Some(block_size) => block_size,
+            None => bail!("Cannot encode unknown pixel format {:?}", self),
+        };
+        if rgba.len() != width * height * 4 {
+            bail!(
+                "A {}x{} image needs 0x{:x} bytes of RGBA8 data but 0x{:x} bytes were given",
+                width,
+                height,
+                width * height * 4,
+                rgba.len()
+            );
+        }
+        if self.is_indexed() && palette.is_empty() {
+            bail!("{:?} image needs a palette", self);
+        }
+        let max_palette_len = match self {
+            WiiPixelFormat::CI4 => 0x10,
+            WiiPixelFormat::CI8 => 0x100,
+            _ => 0x4000,
+        };
+        if self.is_indexed() && palette.len() > max_palette_len {
+            bail!(
+                "{:?} image can use at most {} palette colors but the palette has {} colors",
+                self,
+                max_palette_len,
+                palette.len()
+            );
+        }
+
+        let blocks_wide = width.div_ceil(block_width);
+        let blocks_high = height.div_ceil(block_height);
+        let palette = PaletteTree::new(palette);
+        let mut indexes = HashMap::new();
+        let mut output = vec![];
+        for block_y in 0..blocks_high {
+            for block_x in 0..blocks_wide {
+                // Pixels outside of the image repeat the closest edge pixel
+                let mut block = vec![];
+                for y in 0..block_height {
+                    for x in 0..block_width {
+                        let image_x = (block_x * block_width + x).min(width - 1);
+                        let image_y = (block_y * block_height + y).min(height - 1);
+                        let offset = (image_y * width + image_x) * 4;
+                        block.push([
+                            rgba[offset],
+                            rgba[offset + 1],
+                            rgba[offset + 2],
+                            rgba[offset + 3],
+                        ]);
+                    }
+                }
+                self.encode_block(&block, block_width, &palette, &mut indexes, &mut output);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Encodes the pixels of a block, stored row by row.
+    /// `indexes` caches the palette index of each color.
+    fn encode_block(
+        &self,
+        block: &[[u8; 4]],
+        block_width: usize,
+        palette: &PaletteTree,
+        indexes: &mut HashMap<[u8; 4], usize>,
+        output: &mut Vec<u8>,
+    ) {
+        let mut index = |color: [u8; 4]| {
+            *indexes
+                .entry(color)
+                .or_insert_with(|| palette.closest_color(color))
+        };
+
+        match self {
+            WiiPixelFormat::I4 => {
+                for pair in block.chunks(2) {
+                    output.push(
+                        (to_bits(intensity(pair[0]), 4) << 4) | to_bits(intensity(pair[1]), 4),
+                    );
+                }
+            }
+            WiiPixelFormat::I8 => {
+                output.extend(block.iter().map(|x| intensity(*x)));
+            }
+            WiiPixelFormat::IA4 => {
+                output.extend(
+                    block
+                        .iter()
+                        .map(|x| (to_bits(x[3], 4) << 4) | to_bits(intensity(*x), 4)),
+                );
+            }
+            WiiPixelFormat::IA8 => {
+                for color in block {
+                    output.extend(u16::to_be_bytes(encode_ia8(*color)));
+                }
+            }
+            WiiPixelFormat::RGB565 => {
+                for color in block {
+                    output.extend(u16::to_be_bytes(encode_rgb565(*color)));
+                }
+            }
+            WiiPixelFormat::RGB5A3 => {
+                for color in block {
+                    output.extend(u16::to_be_bytes(encode_rgb5a3(*color)));
+                }
+            }
+            WiiPixelFormat::RGBA8 => {
+                // The alpha and red values of the block come first, followed by the green and blue values.
+                for color in block {
+                    output.push(color[3]);
+                    output.push(color[0]);
+                }
+                for color in block {
+                    output.push(color[1]);
+                    output.push(color[2]);
+                }
+            }
+            WiiPixelFormat::CI4 => {
+                for pair in block.chunks(2) {
+                    output.push(((index(pair[0]) as u8) << 4) | index(pair[1]) as u8);
+                }
+            }
+            WiiPixelFormat::CI8 => {
+                output.extend(block.iter().map(|x| index(*x) as u8));
+            }
+            WiiPixelFormat::CI14X2 => {
+                for color in block {
+                    output.extend(u16::to_be_bytes(index(*color) as u16));
+                }
+            }
+            WiiPixelFormat::CMPR4 => {
+                // Each block contains 2x2 DXT1 sub blocks
+                for sub_block_y in 0..2 {
+                    for sub_block_x in 0..2 {
+                        let mut sub_block = [[0; 4]; 16];
+                        for y in 0..4 {
+                            for x in 0..4 {
+                                sub_block[y * 4 + x] = block
+                                    [(sub_block_y * 4 + y) * block_width + sub_block_x * 4 + x];
+                            }
+                        }
+                        output.extend(encode_cmpr_sub_block(&sub_block));
+                    }
+                }
+            }
+            WiiPixelFormat::Unknown(_) => unreachable!(),
+        }
+    }
+}
+
+/// The 4 colors a CMPR sub block can choose from.
+fn cmpr_colors(color0: u16, color1: u16) -> [[u8; 4]; 4] {
+    let c0 = decode_rgb565(color0);
+    let c1 = decode_rgb565(color1);
+    let mix = |weight0: u16, weight1: u16| {
+        let mut color = [0xff; 4];
+        for i in 0..3 {
+            color[i] =
+                ((c0[i] as u16 * weight0 + c1[i] as u16 * weight1) / (weight0 + weight1)) as u8;
+        }
+        color
+    };
+
+    if color0 > color1 {
+        [c0, c1, mix(2, 1), mix(1, 2)]
+    } else {
+        [c0, c1, mix(1, 1), [0, 0, 0, 0]]
+    }
+}
+
+fn decode_ia8(value: u16) -> [u8; 4] {
+    let alpha = (value >> 8) as u8;
+    let intensity = value as u8;
+    [intensity, intensity, intensity, alpha]
+}
+
+fn decode_rgb565(value: u16) -> [u8; 4] {
+    let r = ((value >> 11) & 0x1f) as u8;
+    let g = ((value >> 5) & 0x3f) as u8;
+    let b = (value & 0x1f) as u8;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+        0xff,
+    ]
+}
+
+/// The top bit selects between RGB555 and ARGB3444
+fn decode_rgb5a3(value: u16) -> [u8; 4] {
+    if value & 0x8000 != 0 {
+        let r = ((value >> 10) & 0x1f) as u8;
+        let g = ((value >> 5) & 0x1f) as u8;
+        let b = (value & 0x1f) as u8;
+        [
+            (r << 3) | (r >> 2),
+            (g << 3) | (g >> 2),
+            (b << 3) | (b >> 2),
+            0xff,
+        ]
+    } else {
+        let a = ((value >> 12) & 0x7) as u8;
+        let r = ((value >> 8) & 0xf) as u8;
+        let g = ((value >> 4) & 0xf) as u8;
+        let b = (value & 0xf) as u8;
+        [r * 0x11, g * 0x11, b * 0x11, (a << 5) | (a << 2) | (a >> 1)]
+    }
 }
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
@@ -180,4 +580,277 @@ impl WiiPaletteFormat {
             WiiPaletteFormat::Unknown(value) => *value,
         }
     }
+
+    /// Encodes an RGBA8 color into a palette color, unknown formats are encoded as 0.
+    pub fn encode_color(&self, color: [u8; 4]) -> u16 {
+        match self {
+            WiiPaletteFormat::IA8 => encode_ia8(color),
+            WiiPaletteFormat::RGB565 => encode_rgb565(color),
+            WiiPaletteFormat::RGB5A3 => encode_rgb5a3(color),
+            WiiPaletteFormat::Unknown(_) => 0,
+        }
+    }
+
+    /// Decodes a palette color into RGBA8, unknown formats are decoded as transparent black.
+    pub fn decode_color(&self, color: u16) -> [u8; 4] {
+        match self {
+            WiiPaletteFormat::IA8 => decode_ia8(color),
+            WiiPaletteFormat::RGB565 => decode_rgb565(color),
+            WiiPaletteFormat::RGB5A3 => decode_rgb5a3(color),
+            WiiPaletteFormat::Unknown(_) => [0; 4],
+        }
+    }
+}
+
+/// Encodes a 4x4 DXT1 block.
+///
+/// The two colors furthest from each other are used as the end points.
+/// If any pixel is mostly transparent the 3 color mode is used, so that those pixels can be fully transparent.
+fn encode_cmpr_sub_block(pixels: &[[u8; 4]; 16]) -> [u8; 8] {
+    let transparent = pixels.iter().any(|x| x[3] < 0x80);
+    let opaque: Vec<[u8; 4]> = pixels.iter().filter(|x| x[3] >= 0x80).cloned().collect();
+
+    let mut end_points = (0, 0);
+    let mut furthest = -1;
+    for a in &opaque {
+        for b in &opaque {
+            let distance = color_distance(*a, *b);
+            if distance > furthest {
+                furthest = distance;
+                end_points = (encode_rgb565(*a), encode_rgb565(*b));
+            }
+        }
+    }
+
+    let (mut color0, mut color1) = end_points;
+    if transparent == (color0 > color1) {
+        std::mem::swap(&mut color0, &mut color1);
+    }
+    // When the end points are equal the 3 color mode is used, which is fine as only the first color is needed.
+    let four_colors = color0 > color1;
+    let colors = cmpr_colors(color0, color1);
+
+    let mut output = [0; 8];
+    output[0..2].copy_from_slice(&u16::to_be_bytes(color0));
+    output[2..4].copy_from_slice(&u16::to_be_bytes(color1));
+    for (i, pixel) in pixels.iter().enumerate() {
+        let index = if pixel[3] < 0x80 {
+            3
+        } else if four_colors {
+            closest_color(&colors, *pixel)
+        } else {
+            closest_color(&colors[..3], *pixel)
+        };
+        output[4 + i / 4] |= (index as u8) << (6 - (i % 4) * 2);
+    }
+    output
+}
+
+/// Reduces the colors used by an RGBA8 image down to at most `max_colors` via median cut.
+/// The colors are chosen so that they survive being stored in `palette_format`.
+pub fn quantize_palette(
+    rgba: &[u8],
+    max_colors: usize,
+    palette_format: &WiiPaletteFormat,
+) -> Vec<[u8; 4]> {
+    let pixels: Vec<[u8; 4]> = rgba
+        .chunks_exact(4)
+        .map(|x| palette_format.decode_color(palette_format.encode_color([x[0], x[1], x[2], x[3]])))
+        .collect();
+
+    let mut unique = pixels.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    if unique.len() <= max_colors {
+        return unique;
+    }
+
+    // Each box is stored with its widest channel and the range of values along it, so they are only calculated once per box
+    let widest_channel = |colors: &[[u8; 4]]| {
+        (0..4)
+            .map(|channel| {
+                let min = colors.iter().map(|x| x[channel]).min().unwrap();
+                let max = colors.iter().map(|x| x[channel]).max().unwrap();
+                (channel, max - min)
+            })
+            .max_by_key(|x| x.1)
+            .unwrap()
+    };
+    let mut boxes = vec![(widest_channel(&pixels), pixels)];
+    while boxes.len() < max_colors {
+        // split the box with the largest range of values along its widest channel
+        let (i, ((channel, range), _)) = boxes
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, ((_, range), _))| *range)
+            .unwrap();
+        let (channel, range) = (*channel, *range);
+        if range == 0 {
+            break;
+        }
+
+        let (_, mut colors) = boxes.swap_remove(i);
+        colors.sort_unstable_by_key(|x| x[channel]);
+        let upper = colors.split_off(colors.len() / 2);
+        boxes.push((widest_channel(&colors), colors));
+        boxes.push((widest_channel(&upper), upper));
+    }
+
+    let mut palette: Vec<[u8; 4]> = boxes
+        .iter()
+        .map(|(_, colors)| {
+            let mut sum = [0usize; 4];
+            for color in colors {
+                for channel in 0..4 {
+                    sum[channel] += color[channel] as usize;
+                }
+            }
+            let average = sum.map(|x| (x / colors.len()) as u8);
+            palette_format.decode_color(palette_format.encode_color(average))
+        })
+        .collect();
+    palette.sort_unstable();
+    palette.dedup();
+    palette
+}
+
+/// A k-d tree of the colors in a palette, so that finding the closest color does not compare against every color.
+struct PaletteTree {
+    /// The colors and their palette index.
+    /// Each range of the tree is split at its middle color by the channel `depth % 4`, smaller values are stored before it and larger values after it.
+    colors: Vec<([u8; 4], usize)>,
+}
+
+impl PaletteTree {
+    fn new(palette: &[[u8; 4]]) -> PaletteTree {
+        let mut colors: Vec<_> = palette.iter().cloned().zip(0..).collect();
+        PaletteTree::build(&mut colors, 0);
+        PaletteTree { colors }
+    }
+
+    fn build(colors: &mut [([u8; 4], usize)], depth: usize) {
+        if colors.len() <= 1 {
+            return;
+        }
+        let middle = colors.len() / 2;
+        colors.select_nth_unstable_by_key(middle, |x| x.0[depth % 4]);
+        let (before, after) = colors.split_at_mut(middle);
+        PaletteTree::build(before, depth + 1);
+        PaletteTree::build(&mut after[1..], depth + 1);
+    }
+
+    /// Returns the same index as `closest_color` would, the first of the closest colors in the palette
+    fn closest_color(&self, color: [u8; 4]) -> usize {
+        let mut closest = (i32::MAX, 0);
+        PaletteTree::search(&self.colors, 0, color, &mut closest);
+        closest.1
+    }
+
+    fn search(
+        colors: &[([u8; 4], usize)],
+        depth: usize,
+        color: [u8; 4],
+        closest: &mut (i32, usize),
+    ) {
+        if colors.is_empty() {
+            return;
+        }
+        let middle = colors.len() / 2;
+        let (middle_color, index) = colors[middle];
+        let distance = color_distance(middle_color, color);
+        if (distance, index) < *closest {
+            *closest = (distance, index);
+        }
+
+        let channel = depth % 4;
+        let difference = color[channel] as i32 - middle_color[channel] as i32;
+        let (near, far) = if difference < 0 {
+            (&colors[..middle], &colors[middle + 1..])
+        } else {
+            (&colors[middle + 1..], &colors[..middle])
+        };
+        PaletteTree::search(near, depth + 1, color, closest);
+        // Every color in `far` is at least `difference` away along this channel
+        if difference * difference <= closest.0 {
+            PaletteTree::search(far, depth + 1, color, closest);
+        }
+    }
+}
+
+/// Returns the index of the color in `palette` closest to `color`
+fn closest_color(palette: &[[u8; 4]], color: [u8; 4]) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, x)| color_distance(**x, color))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+fn color_distance(a: [u8; 4], b: [u8; 4]) -> i32 {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| (*a as i32 - *b as i32).pow(2))
+        .sum()
+}
+
+fn intensity(color: [u8; 4]) -> u8 {
+    ((color[0] as u32 * 299 + color[1] as u32 * 587 + color[2] as u32 * 114) / 1000) as u8
+}
+
+/// Rounds an 8 bit value to the nearest value with the given number of bits
+fn to_bits(value: u8, bits: u32) -> u8 {
+    let max = (1 << bits) - 1;
+    ((value as u32 * max + 0x7f) / 0xff) as u8
+}
+
+fn encode_ia8(color: [u8; 4]) -> u16 {
+    ((color[3] as u16) << 8) | intensity(color) as u16
+}
+
+fn encode_rgb565(color: [u8; 4]) -> u16 {
+    ((to_bits(color[0], 5) as u16) << 11)
+        | ((to_bits(color[1], 6) as u16) << 5)
+        | to_bits(color[2], 5) as u16
+}
+
+/// Opaque colors are stored as RGB555, otherwise ARGB3444 is used
+fn encode_rgb5a3(color: [u8; 4]) -> u16 {
+    if color[3] == 0xff {
+        0x8000
+            | ((to_bits(color[0], 5) as u16) << 10)
+            | ((to_bits(color[1], 5) as u16) << 5)
+            | to_bits(color[2], 5) as u16
+    } else {
+        ((to_bits(color[3], 3) as u16) << 12)
+            | ((to_bits(color[0], 4) as u16) << 8)
+            | ((to_bits(color[1], 4) as u16) << 4)
+            | to_bits(color[2], 4) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_tree_matches_closest_color() {
+        // A simple xorshift so the colors cover every channel without needing a dependency
+        let mut state = 0x1234_5678u32;
+        let mut random_color = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state.to_be_bytes()
+        };
+        let mut palette: Vec<[u8; 4]> = (0..300).map(|_| random_color()).collect();
+        // duplicate colors must resolve to the first one, the same as closest_color
+        palette.extend_from_within(..20);
+
+        let tree = PaletteTree::new(&palette);
+        for _ in 0..2000 {
+            let color = random_color();
+            assert_eq!(tree.closest_color(color), closest_color(&palette, color));
+        }
+    }
 }